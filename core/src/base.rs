@@ -428,6 +428,13 @@ pub struct LogicalBlockAddress(u64);
 #[repr(transparent)]
 pub struct TaskPriorityLevel(usize);
 
+impl TaskPriorityLevel {
+    pub const APPLICATION: Self = Self(4);
+    pub const CALLBACK: Self = Self(8);
+    pub const NOTIFY: Self = Self(16);
+    pub const HIGH_LEVEL: Self = Self(31);
+}
+
 /// 32-byte buffer containing a MAC address
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(transparent)]
@@ -0,0 +1,568 @@
+//! EFI and Tiano decompression
+//!
+//! A pure Rust decoder for the compression format described in the
+//! [Compression Algorithm Specification][spec], as used by
+//! `EFI_DECOMPRESS_PROTOCOL`, and its Tiano variant produced by the EDK2
+//! `TianoCompress` tool.
+//!
+//! Both are LZ77 with per-block Huffman coding, and differ only in how many
+//! bits encode the match position table.
+//!
+//! This is useful when firmware does not provide the protocol,
+//! or for inspecting compressed sections from the host.
+//!
+//! # References
+//!
+//! - [Compression Algorithm Specification][spec]
+//!
+//! [spec]: <https://uefi.org/specs/UEFI/2.10/19_Protocols_Compression_Algorithm_Specification.html>
+use alloc::{vec, vec::Vec};
+
+use crate::{base::Status, error::Result};
+
+/// Size of the header, compressed size and original size
+const HEADER_SIZE: usize = 8;
+
+/// Bits in the bit buffer
+const BUF_BITS: u32 = 32;
+
+/// Longest match length
+const MAX_MATCH: usize = 256;
+
+/// Shortest match length
+const THRESHOLD: usize = 3;
+
+/// Number of char and length symbols
+const NC: usize = 0xFF + MAX_MATCH + 2 - THRESHOLD;
+
+/// Bits encoding the number of char and length code lengths
+const CBIT: u32 = 9;
+
+/// Largest supported position table bits, for [`Algorithm::Tiano`]
+const MAX_PBIT: u32 = 5;
+
+/// Bits encoding the number of code length code lengths
+const TBIT: u32 = 5;
+
+/// Maximum number of position symbols
+const MAX_NP: usize = (1 << MAX_PBIT) - 1;
+
+/// Number of code length symbols
+const NT: usize = 16 + 3;
+
+/// Size of the shared position and code length tables
+const NPT: usize = MAX_NP;
+
+/// Number of tree nodes available for codes longer than the lookup tables
+const NODES: usize = 2 * NC - 1;
+
+/// Bits in the char and length lookup table
+const C_TABLE_BITS: u32 = 12;
+
+/// Bits in the position and code length lookup table
+const PT_TABLE_BITS: u32 = 8;
+
+/// Compression format variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// The UEFI specification format, as used by `EFI_DECOMPRESS_PROTOCOL`
+    Efi,
+
+    /// The EDK2 Tiano format, which supports a larger window
+    Tiano,
+}
+
+impl Algorithm {
+    /// Bits encoding the number of position code lengths
+    const fn pbit(self) -> u32 {
+        match self {
+            Self::Efi => 4,
+            Self::Tiano => MAX_PBIT,
+        }
+    }
+}
+
+/// Returns the compressed and original sizes from the header of `src`
+fn header(src: &[u8]) -> Result<(usize, usize)> {
+    let Some((head, _)) = src.split_first_chunk::<HEADER_SIZE>() else {
+        return Err(Status::INVALID_PARAMETER.into());
+    };
+    let compressed = u32::from_le_bytes([head[0], head[1], head[2], head[3]]);
+    let original = u32::from_le_bytes([head[4], head[5], head[6], head[7]]);
+    Ok((compressed as usize, original as usize))
+}
+
+/// Size of the data `src` decompresses to
+///
+/// Only the header is checked, the data itself may still be invalid.
+///
+/// # Errors
+///
+/// - [`Status::INVALID_PARAMETER`] if `src` is too short for its header
+pub fn decompressed_size(src: &[u8]) -> Result<usize> {
+    let (compressed, original) = header(src)?;
+    if src.len() - HEADER_SIZE < compressed {
+        return Err(Status::INVALID_PARAMETER.into());
+    }
+    Ok(original)
+}
+
+/// Decompress `src`, compressed using `algorithm`
+///
+/// The decompressed size comes from the untrusted header of `src`,
+/// so it is checked against `max` before anything is allocated.
+///
+/// # Errors
+///
+/// - [`Status::INVALID_PARAMETER`] if `src` is truncated or corrupt
+/// - [`Status::BUFFER_TOO_SMALL`] if `src` decompresses to more than `max`
+///   bytes
+pub fn decompress(src: &[u8], algorithm: Algorithm, max: usize) -> Result<Vec<u8>> {
+    let original = decompressed_size(src)?;
+    if original > max {
+        return Err(Status::BUFFER_TOO_SMALL.into());
+    }
+    let (compressed, _) = header(src)?;
+    let data = &src[HEADER_SIZE..][..compressed];
+    let mut out = vec![0; original];
+    if original != 0 {
+        Decoder::new(data, algorithm).decode(&mut out)?;
+    }
+    Ok(out)
+}
+
+/// Where a code longer than its lookup table continues
+#[derive(Clone, Copy)]
+enum Slot {
+    Table(usize),
+    Left(usize),
+    Right(usize),
+}
+
+/// Build the lookup `table` for the canonical Huffman code `lens`
+///
+/// Codes up to `bits` long are looked up directly,
+/// longer codes continue as a tree in `left` and `right`.
+fn make_table(
+    lens: &[u8],
+    bits: u32,
+    table: &mut [u16],
+    left: &mut [u16; NODES],
+    right: &mut [u16; NODES],
+) -> Result<()> {
+    let bits = bits as usize;
+    let mut count = [0u32; 17];
+    for &len in lens {
+        let len = usize::from(len);
+        if len > 16 {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        count[len] += 1;
+    }
+
+    let mut start = [0u32; 18];
+    for i in 1..=16 {
+        start[i + 1] = start[i] + (count[i] << (16 - i));
+    }
+    // The code must be complete, or entirely empty
+    if start[17] != 1 << 16 && start[17] != 0 {
+        return Err(Status::INVALID_PARAMETER.into());
+    }
+
+    let shift = 16 - bits;
+    let mut weight = [0u32; 17];
+    for i in 1..=bits {
+        start[i] >>= shift;
+        weight[i] = 1 << (bits - i);
+    }
+    for (i, w) in weight.iter_mut().enumerate().skip(bits + 1) {
+        *w = 1 << (16 - i);
+    }
+
+    let first = (start[bits + 1] >> shift) as usize;
+    if first != 0 {
+        table[first..].fill(0);
+    }
+
+    let mut avail = lens.len();
+    let mask = 1 << (15 - bits);
+    for (ch, &len) in lens.iter().enumerate() {
+        let len = usize::from(len);
+        if len == 0 {
+            continue;
+        }
+        let next = start[len] + weight[len];
+        if len <= bits {
+            table
+                .get_mut(start[len] as usize..next as usize)
+                .ok_or(Status::INVALID_PARAMETER)?
+                .fill(ch as u16);
+        } else {
+            let mut code = start[len];
+            let mut slot = Slot::Table((code >> shift) as usize);
+            for _ in 0..len - bits {
+                let cell = match slot {
+                    Slot::Table(i) => table.get_mut(i),
+                    Slot::Left(i) => left.get_mut(i),
+                    Slot::Right(i) => right.get_mut(i),
+                }
+                .ok_or(Status::INVALID_PARAMETER)?;
+                let node = if *cell == 0 && avail < NODES {
+                    *cell = avail as u16;
+                    avail += 1;
+                    left[avail - 1] = 0;
+                    right[avail - 1] = 0;
+                    avail - 1
+                } else {
+                    usize::from(*cell)
+                };
+                if node < NODES {
+                    slot = if code & mask != 0 {
+                        Slot::Right(node)
+                    } else {
+                        Slot::Left(node)
+                    };
+                }
+                code <<= 1;
+            }
+            let cell = match slot {
+                Slot::Table(i) => table.get_mut(i),
+                Slot::Left(i) => left.get_mut(i),
+                Slot::Right(i) => right.get_mut(i),
+            }
+            .ok_or(Status::INVALID_PARAMETER)?;
+            *cell = ch as u16;
+        }
+        start[len] = next;
+    }
+    Ok(())
+}
+
+/// Decoder state
+///
+/// Bits are read most significant first.
+/// Reading past the end of the data yields zeros, as in the reference decoder.
+struct Decoder<'src> {
+    src: &'src [u8],
+
+    /// Next byte in `src`
+    pos: usize,
+
+    /// Next [`BUF_BITS`] bits of input
+    bit_buf: u32,
+
+    /// The most recently read byte
+    sub_buf: u32,
+
+    /// Unused bits in `sub_buf`
+    bit_count: u32,
+
+    /// Symbols left in the current block
+    block_size: u16,
+
+    pbit: u32,
+
+    left: [u16; NODES],
+    right: [u16; NODES],
+
+    c_len: [u8; NC],
+    c_table: [u16; 1 << C_TABLE_BITS],
+
+    pt_len: [u8; NPT],
+    pt_table: [u16; 1 << PT_TABLE_BITS],
+}
+
+impl<'src> Decoder<'src> {
+    fn new(src: &'src [u8], algorithm: Algorithm) -> Self {
+        let mut s = Self {
+            src,
+            pos: 0,
+            bit_buf: 0,
+            sub_buf: 0,
+            bit_count: 0,
+            block_size: 0,
+            pbit: algorithm.pbit(),
+            left: [0; NODES],
+            right: [0; NODES],
+            c_len: [0; NC],
+            c_table: [0; 1 << C_TABLE_BITS],
+            pt_len: [0; NPT],
+            pt_table: [0; 1 << PT_TABLE_BITS],
+        };
+        s.fill_buf(BUF_BITS);
+        s
+    }
+
+    /// Shift `n` bits out of the bit buffer, reading more input
+    fn fill_buf(&mut self, mut n: u32) {
+        self.bit_buf = self.bit_buf.checked_shl(n).unwrap_or(0);
+        while n > self.bit_count {
+            n -= self.bit_count;
+            self.bit_buf |= self.sub_buf.checked_shl(n).unwrap_or(0);
+            self.sub_buf = self.src.get(self.pos).copied().map_or(0, u32::from);
+            self.pos += 1;
+            self.bit_count = 8;
+        }
+        self.bit_count -= n;
+        self.bit_buf |= self.sub_buf >> self.bit_count;
+    }
+
+    /// Read the next `n` bits
+    fn get_bits(&mut self, n: u32) -> u32 {
+        let out = self.bit_buf.checked_shr(BUF_BITS - n).unwrap_or(0);
+        self.fill_buf(n);
+        out
+    }
+
+    /// Follow the tree for a code longer than a `bits` lookup table
+    fn walk(&self, mut sym: u16, limit: usize, bits: u32) -> Result<usize> {
+        let mut mask = 1u32 << (BUF_BITS - 1 - bits);
+        while usize::from(sym) >= limit {
+            let node = usize::from(sym);
+            if mask == 0 || node >= NODES {
+                return Err(Status::INVALID_PARAMETER.into());
+            }
+            sym = if self.bit_buf & mask != 0 {
+                self.right[node]
+            } else {
+                self.left[node]
+            };
+            mask >>= 1;
+        }
+        Ok(usize::from(sym))
+    }
+
+    /// Read the code lengths for the position or code length tables
+    ///
+    /// After the `special`th length, a 2-bit run of zero lengths follows.
+    fn read_pt_len(&mut self, nn: usize, nbit: u32, special: Option<usize>) -> Result<()> {
+        let number = self.get_bits(nbit) as usize;
+        if number == 0 {
+            let ch = self.get_bits(nbit) as usize;
+            if ch >= nn {
+                return Err(Status::INVALID_PARAMETER.into());
+            }
+            self.pt_table.fill(ch as u16);
+            self.pt_len[..nn].fill(0);
+            return Ok(());
+        }
+
+        let mut i = 0;
+        while i < number && i < NPT {
+            let mut len = self.bit_buf >> (BUF_BITS - 3);
+            if len == 7 {
+                let mut mask = 1 << (BUF_BITS - 1 - 3);
+                while mask & self.bit_buf != 0 {
+                    mask >>= 1;
+                    len += 1;
+                }
+            }
+            if len > 16 {
+                return Err(Status::INVALID_PARAMETER.into());
+            }
+            self.fill_buf(if len < 7 { 3 } else { len - 3 });
+            self.pt_len[i] = len as u8;
+            i += 1;
+
+            if Some(i) == special {
+                let zeros = self.get_bits(2) as usize;
+                let end = (i + zeros).min(NPT);
+                self.pt_len[i..end].fill(0);
+                i = end;
+            }
+        }
+        if i < nn {
+            self.pt_len[i..nn].fill(0);
+        }
+
+        make_table(
+            &self.pt_len[..nn],
+            PT_TABLE_BITS,
+            &mut self.pt_table,
+            &mut self.left,
+            &mut self.right,
+        )
+    }
+
+    /// Read the char and length code lengths, encoded with the code length
+    /// table
+    fn read_c_len(&mut self) -> Result<()> {
+        let number = self.get_bits(CBIT) as usize;
+        if number == 0 {
+            let ch = self.get_bits(CBIT) as usize;
+            if ch >= NC {
+                return Err(Status::INVALID_PARAMETER.into());
+            }
+            self.c_len.fill(0);
+            self.c_table.fill(ch as u16);
+            return Ok(());
+        }
+
+        let mut i = 0;
+        while i < number && i < NC {
+            let sym = self.pt_table[(self.bit_buf >> (BUF_BITS - PT_TABLE_BITS)) as usize];
+            let sym = self.walk(sym, NT, PT_TABLE_BITS)?;
+            self.fill_buf(self.pt_len[sym].into());
+            if sym <= 2 {
+                let zeros = match sym {
+                    0 => 1,
+                    1 => self.get_bits(4) as usize + 3,
+                    _ => self.get_bits(CBIT) as usize + 20,
+                };
+                let end = i + zeros;
+                self.c_len
+                    .get_mut(i..end)
+                    .ok_or(Status::INVALID_PARAMETER)?
+                    .fill(0);
+                i = end;
+            } else {
+                self.c_len[i] = (sym - 2) as u8;
+                i += 1;
+            }
+        }
+        self.c_len[i..].fill(0);
+
+        make_table(
+            &self.c_len,
+            C_TABLE_BITS,
+            &mut self.c_table,
+            &mut self.left,
+            &mut self.right,
+        )
+    }
+
+    /// Decode the next char or length symbol, starting a new block if needed
+    fn decode_c(&mut self) -> Result<usize> {
+        if self.block_size == 0 {
+            self.block_size = self.get_bits(16) as u16;
+            self.read_pt_len(NT, TBIT, Some(3))?;
+            self.read_c_len()?;
+            self.read_pt_len(MAX_NP, self.pbit, None)?;
+        }
+        self.block_size = self.block_size.wrapping_sub(1);
+
+        let sym = self.c_table[(self.bit_buf >> (BUF_BITS - C_TABLE_BITS)) as usize];
+        let sym = self.walk(sym, NC, C_TABLE_BITS)?;
+        self.fill_buf(self.c_len[sym].into());
+        Ok(sym)
+    }
+
+    /// Decode the next match position
+    fn decode_p(&mut self) -> Result<usize> {
+        let sym = self.pt_table[(self.bit_buf >> (BUF_BITS - PT_TABLE_BITS)) as usize];
+        let sym = self.walk(sym, MAX_NP, PT_TABLE_BITS)?;
+        self.fill_buf(self.pt_len[sym].into());
+        if sym > 1 {
+            let extra = sym as u32 - 1;
+            Ok((1 << extra) + self.get_bits(extra) as usize)
+        } else {
+            Ok(sym)
+        }
+    }
+
+    /// Decode until `out` is full
+    fn decode(&mut self, out: &mut [u8]) -> Result<()> {
+        let mut pos = 0;
+        while pos < out.len() {
+            let sym = self.decode_c()?;
+            if sym < 0x100 {
+                out[pos] = sym as u8;
+                pos += 1;
+            } else {
+                let len = sym - (0x100 - THRESHOLD);
+                let distance = self.decode_p()? + 1;
+                if distance > pos {
+                    return Err(Status::INVALID_PARAMETER.into());
+                }
+                for _ in 0..len {
+                    if pos >= out.len() {
+                        break;
+                    }
+                    out[pos] = out[pos - distance];
+                    pos += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = b"The quick brown fox jumps over the lazy dog. \
+        The quick brown fox jumps over the lazy dog again, and again, and again. \
+        0123456789 0123456789 abcabcabcabcabcabcabc!";
+
+    /// [`TEXT`] compressed as a single block
+    #[rustfmt::skip]
+    const EFI: &[u8] = &[
+        0x50, 0x00, 0x00, 0x00, 0xA2, 0x00, 0x00, 0x00, 0x00, 0x4A, 0x4C, 0x6E,
+        0xB5, 0x99, 0x4D, 0x06, 0x79, 0x1D, 0xCE, 0x00, 0x28, 0x19, 0x27, 0xE6,
+        0xCC, 0xC1, 0xB1, 0x8C, 0x0A, 0x71, 0x45, 0x41, 0x28, 0x10, 0xE0, 0x20,
+        0x92, 0xA6, 0xD3, 0x8C, 0xDA, 0x9A, 0xEF, 0x15, 0x31, 0x7B, 0xA8, 0x58,
+        0xBE, 0x05, 0xCD, 0xC7, 0x2D, 0x02, 0xF6, 0x3B, 0x06, 0xBD, 0xDD, 0x81,
+        0x75, 0xC8, 0x32, 0xD0, 0xF0, 0xFF, 0x60, 0x24, 0x12, 0x54, 0xE8, 0x25,
+        0x1B, 0xD5, 0x3C, 0x1F, 0x82, 0x18, 0xA3, 0x92, 0x59, 0xA7, 0xA3, 0xC5,
+        0x15, 0x55, 0xFC, 0x1C,
+    ];
+
+    /// [`TEXT`] compressed in blocks of 6 symbols
+    #[rustfmt::skip]
+    const TIANO: &[u8] = &[
+        0xC2, 0x00, 0x00, 0x00, 0xA2, 0x00, 0x00, 0x00, 0x00, 0x06, 0x33, 0x4D,
+        0x48, 0xED, 0xC1, 0x97, 0x0F, 0xC6, 0xDB, 0x42, 0xA0, 0x20, 0x05, 0xFA,
+        0x08, 0x00, 0x31, 0x9B, 0x4A, 0x47, 0x30, 0x19, 0x02, 0xDA, 0xE5, 0x67,
+        0x9A, 0x00, 0x7C, 0x4A, 0x80, 0x03, 0x18, 0x24, 0xA4, 0x78, 0x41, 0x9A,
+        0x31, 0xC4, 0xA1, 0x20, 0x01, 0x8D, 0xD0, 0x00, 0x63, 0x19, 0x16, 0x8F,
+        0x3E, 0x0C, 0xBC, 0x6B, 0x11, 0x70, 0xE3, 0x00, 0x0C, 0xA6, 0xE0, 0x00,
+        0xC6, 0x4D, 0xA9, 0x1D, 0xF8, 0x32, 0xE3, 0x0B, 0x34, 0x12, 0x08, 0x00,
+        0x4C, 0xDE, 0x00, 0x0C, 0x60, 0x92, 0x94, 0x05, 0x06, 0x68, 0xB3, 0x1F,
+        0x13, 0x0A, 0x4E, 0x30, 0x0B, 0x3B, 0xDA, 0x80, 0x00, 0xC6, 0x69, 0xA9,
+        0x1E, 0xB8, 0x32, 0x2A, 0xE2, 0x1B, 0x68, 0x44, 0x64, 0x00, 0x66, 0x3D,
+        0x00, 0x06, 0x28, 0x49, 0x32, 0x9C, 0x18, 0xC5, 0x88, 0x9A, 0xB0, 0x03,
+        0x1B, 0x06, 0x40, 0x01, 0x8C, 0x12, 0x52, 0x37, 0xA0, 0xCC, 0x8D, 0x10,
+        0x42, 0x40, 0xC0, 0x03, 0x7C, 0x40, 0x00, 0xC6, 0x6D, 0x29, 0x43, 0xC0,
+        0x65, 0xEA, 0xD4, 0x07, 0x91, 0x2A, 0x80, 0x82, 0xAC, 0xDC, 0x00, 0x18,
+        0xC0, 0x25, 0x11, 0xC4, 0x1E, 0x0F, 0x00, 0x25, 0xDC, 0x40, 0x01, 0x8C,
+        0x02, 0x51, 0x85, 0x42, 0x42, 0x09, 0x9C, 0x91, 0xC0, 0x49, 0x55, 0xB8,
+        0x00, 0x04, 0x40, 0x08, 0x62, 0x00, 0x6C, 0xD9, 0x80, 0x50,
+    ];
+
+    #[test]
+    fn efi() -> Result<()> {
+        assert_eq!(decompressed_size(EFI)?, TEXT.len());
+        assert_eq!(decompress(EFI, Algorithm::Efi, TEXT.len())?, TEXT);
+        Ok(())
+    }
+
+    #[test]
+    fn tiano() -> Result<()> {
+        assert_eq!(decompressed_size(TIANO)?, TEXT.len());
+        assert_eq!(decompress(TIANO, Algorithm::Tiano, TEXT.len())?, TEXT);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() -> Result<()> {
+        // Empty output needs no data
+        assert!(decompress(&[0; 8], Algorithm::Efi, 0)?.is_empty());
+
+        assert!(decompressed_size(&EFI[..7]).is_err());
+        assert!(decompress(&EFI[..EFI.len() - 1], Algorithm::Efi, TEXT.len()).is_err());
+
+        // Code lengths longer than 16 bits
+        let mut bad = [0xFF; 16];
+        bad[..8].copy_from_slice(&[8, 0, 0, 0, 16, 0, 0, 0]);
+        assert!(decompress(&bad, Algorithm::Efi, 16).is_err());
+
+        // Larger than the caller allows, without allocating it
+        let huge = [0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+        let e = decompress(&huge, Algorithm::Efi, 1 << 20).unwrap_err();
+        assert_eq!(e.status(), Status::BUFFER_TOO_SMALL);
+        let e = decompress(EFI, Algorithm::Efi, TEXT.len() - 1).unwrap_err();
+        assert_eq!(e.status(), Status::BUFFER_TOO_SMALL);
+        Ok(())
+    }
+}
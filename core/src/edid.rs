@@ -0,0 +1,604 @@
+//! Extended Display Identification Data
+//!
+//! A parser for [VESA EDID 1.3 and 1.4][edid] base blocks, as returned by the
+//! UEFI EDID protocols.
+//!
+//! The 128 byte base block is decoded in full.
+//! Of the extension blocks, only the detailed timings of CTA-861 extensions
+//! are decoded, see [`Extension::detailed_timings`].
+//! Everything else in an extension, such as CTA-861 data blocks, is only
+//! available as raw bytes from [`Extension::as_bytes`].
+//!
+//! # References
+//!
+//! - [EDID Protocols][spec]
+//! - [Extended Display Identification Data][edid]
+//!
+//! [spec]: <https://uefi.org/specs/UEFI/2.10/12_Protocols_Console_Support.html#edid-discovered-protocol>
+//! [edid]: <https://en.wikipedia.org/wiki/Extended_Display_Identification_Data>
+use core::str::from_utf8;
+
+use crate::{base::Status, error::Result};
+
+/// Size of the base block and every extension block
+pub const BLOCK_SIZE: usize = 128;
+
+/// Fixed header of every EDID
+const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+
+/// Offset of the first of the 4 descriptors
+const DESCRIPTORS: usize = 54;
+
+/// Size of a descriptor
+const DESCRIPTOR_SIZE: usize = 18;
+
+/// Offset of the number of extension blocks
+const EXTENSIONS: usize = 126;
+
+/// Tag of CTA-861 extension blocks
+const CTA_861: u8 = 0x02;
+
+/// Resolution and refresh rate of every established timing bit,
+/// starting from the high bit of the first byte
+const ESTABLISHED: [Timing; 17] = [
+    Timing::new(720, 400, 70),
+    Timing::new(720, 400, 88),
+    Timing::new(640, 480, 60),
+    Timing::new(640, 480, 67),
+    Timing::new(640, 480, 72),
+    Timing::new(640, 480, 75),
+    Timing::new(800, 600, 56),
+    Timing::new(800, 600, 60),
+    Timing::new(800, 600, 72),
+    Timing::new(800, 600, 75),
+    Timing::new(832, 624, 75),
+    // Interlaced
+    Timing::new(1024, 768, 87),
+    Timing::new(1024, 768, 60),
+    Timing::new(1024, 768, 70),
+    Timing::new(1024, 768, 75),
+    Timing::new(1280, 1024, 75),
+    Timing::new(1152, 870, 75),
+];
+
+/// A validated EDID
+#[derive(Debug, Clone, Copy)]
+pub struct Edid<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Edid<'a> {
+    /// Parse an EDID from `data`
+    ///
+    /// Only the base block is required to be present and valid,
+    /// `data` may include any number of extension blocks.
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if `data` is too short, or the
+    /// header or checksum are wrong.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let base = data.get(..BLOCK_SIZE).ok_or(Status::INVALID_PARAMETER)?;
+        if !base.starts_with(&HEADER) || !checksum(base) {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        Ok(Self { data })
+    }
+
+    /// The raw EDID
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// EDID version and revision, such as `(1, 4)`
+    pub fn version(&self) -> (u8, u8) {
+        (self.data[18], self.data[19])
+    }
+
+    /// Three letter PNP manufacturer ID, such as `*b"DEL"`
+    pub fn manufacturer(&self) -> [u8; 3] {
+        let id = u16::from_be_bytes([self.data[8], self.data[9]]);
+        let letter = |shift: u16| b'A' - 1 + ((id >> shift) & 0x1F) as u8;
+        [letter(10), letter(5), letter(0)]
+    }
+
+    /// Manufacturer product code
+    pub fn product(&self) -> u16 {
+        u16::from_le_bytes([self.data[10], self.data[11]])
+    }
+
+    /// Serial number, if the manufacturer set one
+    ///
+    /// Some displays instead use a [`Descriptor::Serial`]
+    pub fn serial(&self) -> Option<u32> {
+        let b = &self.data[12..16];
+        Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]])).filter(|s| *s != 0)
+    }
+
+    /// Week, if specified, and year of manufacture
+    ///
+    /// Week `0xFF` means the year is the model year instead.
+    pub fn manufactured(&self) -> (Option<u8>, u16) {
+        let week = Some(self.data[16]).filter(|w| *w != 0);
+        (week, 1990 + self.data[17] as u16)
+    }
+
+    /// Whether the display has a digital input
+    pub fn is_digital(&self) -> bool {
+        self.data[20] & 0x80 != 0
+    }
+
+    /// Physical width and height of the screen, in centimeters
+    ///
+    /// This is [`None`] for projectors, or displays that only specify an
+    /// aspect ratio.
+    pub fn size_cm(&self) -> Option<(u8, u8)> {
+        match (self.data[21], self.data[22]) {
+            (0, _) | (_, 0) => None,
+            size => Some(size),
+        }
+    }
+
+    /// Supported established timings
+    pub fn established_timings(&self) -> impl Iterator<Item = Timing> + 'a {
+        let bits = u32::from_be_bytes([self.data[35], self.data[36], self.data[37], 0]);
+        ESTABLISHED
+            .into_iter()
+            .enumerate()
+            .filter(move |(i, _)| bits & (1 << (31 - i)) != 0)
+            .map(|(_, t)| t)
+    }
+
+    /// Supported standard timings
+    pub fn standard_timings(&self) -> impl Iterator<Item = Timing> + 'a {
+        let version = self.version();
+        let (timings, _) = self.data[38..54].as_chunks::<2>();
+        timings
+            .iter()
+            .filter_map(move |t| Timing::from_standard(*t, version))
+    }
+
+    /// The four 18 byte descriptors
+    pub fn descriptors(&self) -> impl Iterator<Item = Descriptor<'a>> + 'a {
+        let (descriptors, _) = self.data[DESCRIPTORS..DESCRIPTORS + 4 * DESCRIPTOR_SIZE]
+            .as_chunks::<DESCRIPTOR_SIZE>();
+        descriptors.iter().map(|d| Descriptor::parse(d))
+    }
+
+    /// Supported detailed timings, in order of preference
+    pub fn detailed_timings(&self) -> impl Iterator<Item = DetailedTiming> + 'a {
+        self.descriptors().filter_map(|d| match d {
+            Descriptor::Timing(t) => Some(t),
+            _ => None,
+        })
+    }
+
+    /// The preferred timing, usually the native resolution of the display
+    ///
+    /// This is the first detailed timing, which is always preferred since
+    /// EDID 1.4.
+    pub fn preferred_timing(&self) -> Option<DetailedTiming> {
+        match self.descriptors().next()? {
+            Descriptor::Timing(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// The monitor name, if specified
+    pub fn name(&self) -> Option<&'a str> {
+        self.descriptors().find_map(|d| match d {
+            Descriptor::Name(s) => Some(s),
+            _ => None,
+        })
+    }
+
+    /// The monitor range limits, if specified
+    pub fn range_limits(&self) -> Option<RangeLimits> {
+        self.descriptors().find_map(|d| match d {
+            Descriptor::RangeLimits(r) => Some(r),
+            _ => None,
+        })
+    }
+
+    /// Number of extension blocks the base block says follow it
+    ///
+    /// These may not all be present, see [`Edid::extensions`].
+    pub fn extension_count(&self) -> usize {
+        self.data[EXTENSIONS] as usize
+    }
+
+    /// Extension blocks that are present and have a valid checksum
+    pub fn extensions(&self) -> impl Iterator<Item = Extension<'a>> + 'a {
+        let (blocks, _) = self.data[BLOCK_SIZE..].as_chunks::<BLOCK_SIZE>();
+        blocks
+            .iter()
+            .take(self.extension_count())
+            .filter(|b| checksum(&b[..]))
+            .map(|data| Extension { data })
+    }
+}
+
+/// Whether `block` sums to zero
+fn checksum(block: &[u8]) -> bool {
+    block.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) == 0
+}
+
+/// Resolution and refresh rate of an established or standard timing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timing {
+    /// Horizontal resolution, in pixels
+    pub width: u16,
+
+    /// Vertical resolution, in pixels
+    pub height: u16,
+
+    /// Refresh rate, in Hz
+    pub refresh: u8,
+}
+
+impl Timing {
+    /// Create a new [`Timing`]
+    pub const fn new(width: u16, height: u16, refresh: u8) -> Self {
+        Self {
+            width,
+            height,
+            refresh,
+        }
+    }
+
+    /// Decode a two byte standard timing, `None` if unused
+    fn from_standard(raw: [u8; 2], version: (u8, u8)) -> Option<Self> {
+        if raw == [0x01, 0x01] || raw[0] == 0 {
+            return None;
+        }
+        let width = (raw[0] as u16 + 31) * 8;
+        let height = match raw[1] >> 6 {
+            // 1:1 before EDID 1.3
+            0 if version < (1, 3) => width,
+            0 => width * 10 / 16,
+            1 => width * 3 / 4,
+            2 => width * 4 / 5,
+            _ => width * 9 / 16,
+        };
+        Some(Self::new(width, height, (raw[1] & 0x3F) + 60))
+    }
+}
+
+/// A detailed timing descriptor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DetailedTiming {
+    /// Pixel clock, in kHz
+    pub pixel_clock: u32,
+
+    /// Horizontal resolution, in pixels
+    pub width: u16,
+
+    /// Vertical resolution, in lines
+    pub height: u16,
+
+    /// Horizontal blanking, in pixels
+    pub h_blank: u16,
+
+    /// Vertical blanking, in lines
+    pub v_blank: u16,
+
+    /// Horizontal front porch, in pixels
+    pub h_front_porch: u16,
+
+    /// Horizontal sync pulse width, in pixels
+    pub h_sync: u16,
+
+    /// Vertical front porch, in lines
+    pub v_front_porch: u16,
+
+    /// Vertical sync pulse width, in lines
+    pub v_sync: u16,
+
+    /// Physical width, in millimeters
+    pub width_mm: u16,
+
+    /// Physical height, in millimeters
+    pub height_mm: u16,
+
+    /// Whether the timing is interlaced
+    pub interlaced: bool,
+}
+
+impl DetailedTiming {
+    /// Decode a detailed timing descriptor, which must be 18 bytes
+    /// with a non-zero pixel clock
+    fn parse(d: &[u8]) -> Self {
+        let hi = |b: u8, shift: u8| ((b >> shift) as u16 & 0xF) << 8;
+        Self {
+            pixel_clock: u16::from_le_bytes([d[0], d[1]]) as u32 * 10,
+            width: d[2] as u16 | hi(d[4], 4),
+            h_blank: d[3] as u16 | hi(d[4], 0),
+            height: d[5] as u16 | hi(d[7], 4),
+            v_blank: d[6] as u16 | hi(d[7], 0),
+            h_front_porch: d[8] as u16 | ((d[11] as u16 >> 6) & 0x3) << 8,
+            h_sync: d[9] as u16 | ((d[11] as u16 >> 4) & 0x3) << 8,
+            v_front_porch: (d[10] >> 4) as u16 | ((d[11] as u16 >> 2) & 0x3) << 4,
+            v_sync: (d[10] & 0xF) as u16 | (d[11] as u16 & 0x3) << 4,
+            width_mm: d[12] as u16 | hi(d[14], 4),
+            height_mm: d[13] as u16 | hi(d[14], 0),
+            interlaced: d[17] & 0x80 != 0,
+        }
+    }
+
+    /// Refresh rate, in mHz
+    pub fn refresh_mhz(&self) -> u32 {
+        let h = (self.width + self.h_blank) as u64;
+        let v = (self.height + self.v_blank) as u64;
+        match h * v {
+            0 => 0,
+            total => (self.pixel_clock as u64 * 1_000_000 / total) as u32,
+        }
+    }
+}
+
+/// Monitor range limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RangeLimits {
+    /// Minimum vertical rate, in Hz
+    pub min_v_rate: u16,
+
+    /// Maximum vertical rate, in Hz
+    pub max_v_rate: u16,
+
+    /// Minimum horizontal rate, in kHz
+    pub min_h_rate: u16,
+
+    /// Maximum horizontal rate, in kHz
+    pub max_h_rate: u16,
+
+    /// Maximum pixel clock, in MHz, if specified
+    pub max_pixel_clock: Option<u16>,
+}
+
+impl RangeLimits {
+    fn parse(d: &[u8]) -> Self {
+        // EDID 1.4 rate offsets
+        let offset = |flags: u8| match flags & 0x3 {
+            2 => (0, 255),
+            3 => (255, 255),
+            _ => (0, 0),
+        };
+        let (min_v, max_v) = offset(d[4]);
+        let (min_h, max_h) = offset(d[4] >> 2);
+        Self {
+            min_v_rate: d[5] as u16 + min_v,
+            max_v_rate: d[6] as u16 + max_v,
+            min_h_rate: d[7] as u16 + min_h,
+            max_h_rate: d[8] as u16 + max_h,
+            max_pixel_clock: Some(d[9] as u16 * 10).filter(|c| *c != 0),
+        }
+    }
+}
+
+/// One of the four 18 byte [`Edid`] descriptors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Descriptor<'a> {
+    /// A detailed timing
+    Timing(DetailedTiming),
+
+    /// Monitor name
+    Name(&'a str),
+
+    /// Monitor serial number
+    Serial(&'a str),
+
+    /// Unspecified text
+    Text(&'a str),
+
+    /// Monitor range limits
+    RangeLimits(RangeLimits),
+
+    /// Unused descriptor
+    Dummy,
+
+    /// Any other display descriptor, with its tag and raw contents
+    Other(u8, &'a [u8]),
+}
+
+impl<'a> Descriptor<'a> {
+    fn parse(d: &'a [u8]) -> Self {
+        if d[0] != 0 || d[1] != 0 {
+            return Self::Timing(DetailedTiming::parse(d));
+        }
+        // Text is ASCII, terminated by a newline and padded with spaces
+        let text = || {
+            let text = d[5..].split(|b| *b == b'\n').next().unwrap_or(&[]);
+            from_utf8(text).unwrap_or("").trim_end()
+        };
+        match d[3] {
+            0xFF => Self::Serial(text()),
+            0xFE => Self::Text(text()),
+            0xFD => Self::RangeLimits(RangeLimits::parse(d)),
+            0xFC => Self::Name(text()),
+            0x10 => Self::Dummy,
+            tag => Self::Other(tag, &d[5..]),
+        }
+    }
+}
+
+/// An [`Edid`] extension block
+#[derive(Debug, Clone, Copy)]
+pub struct Extension<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Extension<'a> {
+    /// Extension tag, such as `0x02` for CTA-861
+    pub fn tag(&self) -> u8 {
+        self.data[0]
+    }
+
+    /// The raw 128 byte block, including the tag and checksum
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Detailed timings in a CTA-861 extension, in order of preference
+    ///
+    /// This is empty for any other extension.
+    pub fn detailed_timings(&self) -> impl Iterator<Item = DetailedTiming> + 'a {
+        // Byte 2 is the offset of the detailed timings, or 0 if there are none
+        let start = match self.data[2] as usize {
+            start @ 4..=126 if self.tag() == CTA_861 => start,
+            _ => BLOCK_SIZE - 1,
+        };
+        // Detailed timings are followed by zero padding up to the checksum
+        let (timings, _) = self.data[start..BLOCK_SIZE - 1].as_chunks::<DESCRIPTOR_SIZE>();
+        timings
+            .iter()
+            .take_while(|d| d[0] != 0 || d[1] != 0)
+            .map(|d| DetailedTiming::parse(d))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    /// A 1920x1080 display, with a CTA-861 extension
+    fn edid() -> Vec<u8> {
+        let mut e = HEADER.to_vec();
+        // "DEL", product 0x4080, serial 12345, week 10 of 2020
+        e.extend([0x10, 0xAC, 0x80, 0x40, 0x39, 0x30, 0x00, 0x00, 10, 30]);
+        // EDID 1.4, digital, 53x30 cm
+        e.extend([1, 4, 0xA5, 53, 30, 0x78, 0x3A]);
+        // Chromaticity
+        e.extend([0; 10]);
+        // 640x480@60, 800x600@60, 1024x768@60
+        e.extend([0x21, 0x08, 0x00]);
+        // 1280x1024@60, 1920x1080@60 then unused
+        e.extend([0x81, 0x80, 0xD1, 0xC0]);
+        e.extend([0x01; 12]);
+        // 1920x1080@60, 148.5 MHz
+        e.extend([
+            0x02, 0x3A, 0x80, 0x18, 0x71, 0x38, 0x2D, 0x40, 0x58, 0x2C, 0x45, 0x00, 0x13, 0x2B,
+            0x21, 0x00, 0x00, 0x1E,
+        ]);
+        e.extend([0, 0, 0, 0xFC, 0]);
+        e.extend(b"DELL P2419H\n ");
+        e.extend([0, 0, 0, 0xFD, 0, 50, 76, 30, 83, 17, 0x00, 0x0A]);
+        e.extend(b"      ");
+        e.extend([0, 0, 0, 0xFF, 0]);
+        e.extend(b"ABC123\n      ");
+        e.push(1);
+        e.push(0u8.wrapping_sub(e.iter().fold(0u8, |s, b| s.wrapping_add(*b))));
+
+        // CTA-861 revision 3, with 1280x720@60, 74.25 MHz, and no data blocks
+        let mut ext = [0u8; BLOCK_SIZE];
+        ext[..4].copy_from_slice(&[0x02, 0x03, 0x04, 0x01]);
+        ext[4..22].copy_from_slice(&[
+            0x01, 0x1D, 0x00, 0x72, 0x51, 0xD0, 0x1E, 0x20, 0x6E, 0x28, 0x55, 0x00, 0xC4, 0x8E,
+            0x21, 0x00, 0x00, 0x1E,
+        ]);
+        ext[127] = 0u8.wrapping_sub(ext.iter().fold(0u8, |s, b| s.wrapping_add(*b)));
+        e.extend(ext);
+        e
+    }
+
+    #[test]
+    fn parse() -> Result<()> {
+        let data = edid();
+        let edid = Edid::parse(&data)?;
+        assert_eq!(edid.version(), (1, 4));
+        assert_eq!(&edid.manufacturer(), b"DEL");
+        assert_eq!(edid.product(), 0x4080);
+        assert_eq!(edid.serial(), Some(12345));
+        assert_eq!(edid.manufactured(), (Some(10), 2020));
+        assert!(edid.is_digital());
+        assert_eq!(edid.size_cm(), Some((53, 30)));
+        assert_eq!(edid.name(), Some("DELL P2419H"));
+
+        let est: Vec<_> = edid.established_timings().collect();
+        let expected = [(640, 480, 60), (800, 600, 60), (1024, 768, 60)];
+        assert_eq!(est, expected.map(|(w, h, r)| Timing::new(w, h, r)));
+
+        let std: Vec<_> = edid.standard_timings().collect();
+        let expected = [Timing::new(1280, 1024, 60), Timing::new(1920, 1080, 60)];
+        assert_eq!(std, expected);
+
+        let t = edid.preferred_timing().unwrap();
+        assert_eq!((t.width, t.height, t.pixel_clock), (1920, 1080, 148_500));
+        assert_eq!((t.h_blank, t.v_blank), (280, 45));
+        assert_eq!((t.h_front_porch, t.h_sync), (88, 44));
+        assert_eq!((t.v_front_porch, t.v_sync), (4, 5));
+        assert_eq!((t.width_mm, t.height_mm), (531, 299));
+        assert!(!t.interlaced);
+        assert_eq!(t.refresh_mhz(), 60_000);
+        assert_eq!(edid.detailed_timings().count(), 1);
+
+        let limits = RangeLimits {
+            min_v_rate: 50,
+            max_v_rate: 76,
+            min_h_rate: 30,
+            max_h_rate: 83,
+            max_pixel_clock: Some(170),
+        };
+        assert_eq!(edid.range_limits(), Some(limits));
+        assert!(edid
+            .descriptors()
+            .any(|d| d == Descriptor::Serial("ABC123")));
+
+        assert_eq!(edid.extension_count(), 1);
+        let ext: Vec<_> = edid.extensions().map(|e| e.tag()).collect();
+        assert_eq!(ext, [0x02]);
+        Ok(())
+    }
+
+    #[test]
+    fn cta_861() -> Result<()> {
+        let mut data = edid();
+        let edid = Edid::parse(&data)?;
+        let ext = edid.extensions().next().unwrap();
+        let timings: Vec<_> = ext.detailed_timings().collect();
+        assert_eq!(timings.len(), 1);
+        let t = timings[0];
+        assert_eq!((t.width, t.height, t.pixel_clock), (1280, 720, 74_250));
+        assert_eq!((t.h_blank, t.v_blank), (370, 30));
+        assert_eq!((t.h_front_porch, t.h_sync), (110, 40));
+        assert_eq!((t.v_front_porch, t.v_sync), (5, 5));
+        assert_eq!((t.width_mm, t.height_mm), (708, 398));
+        assert_eq!(t.refresh_mhz(), 60_000);
+
+        // Timings run up to the padding or the checksum
+        let block = &mut data[BLOCK_SIZE..];
+        for start in (22..BLOCK_SIZE - DESCRIPTOR_SIZE).step_by(DESCRIPTOR_SIZE) {
+            block.copy_within(4..22, start);
+        }
+        let ext = Extension { data: block };
+        assert_eq!(ext.detailed_timings().count(), 6);
+        assert!(ext.detailed_timings().all(|d| d == t));
+
+        // No timings, or not CTA-861
+        block[2] = 0;
+        assert_eq!(Extension { data: block }.detailed_timings().count(), 0);
+        block[2] = 4;
+        block[0] = 0x70;
+        assert_eq!(Extension { data: block }.detailed_timings().count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() -> Result<()> {
+        let mut data = edid();
+        assert!(Edid::parse(&data[..BLOCK_SIZE - 1]).is_err());
+
+        // Missing or corrupt extensions are ignored
+        let edid = Edid::parse(&data[..BLOCK_SIZE])?;
+        assert_eq!(edid.extensions().count(), 0);
+        data[BLOCK_SIZE + 5] = 1;
+        assert_eq!(Edid::parse(&data)?.extensions().count(), 0);
+
+        data[20] ^= 1;
+        assert!(Edid::parse(&data).is_err());
+        data[20] ^= 1;
+        data[0] = 1;
+        assert!(Edid::parse(&data).is_err());
+        Ok(())
+    }
+}
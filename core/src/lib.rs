@@ -19,6 +19,9 @@
 //! - [`error`] is our own addition, and provides a nice [`Result`]
 //! and Error type using [`base::Status`].
 //! - [`table`] contains the various System Tables
+//! - [`decompress`] contains a pure Rust EFI and Tiano decompressor
+//! - [`edid`] contains a parser for display EDID information
+//! - [`path`] contains pure Rust UEFI file paths
 //! - [`extra`] contains various "extra" things, types and trait implementations
 //!   that make working with UEFI nice, but are not part of UEFI
 //! - [`proto`] contains the various UEFI Protocols, organized roughly
@@ -39,7 +42,10 @@ pub mod error;
 // pub mod handlers;
 
 pub mod base;
+pub mod decompress;
+pub mod edid;
 pub mod extra;
+pub mod path;
 pub mod table;
 
 #[doc(inline)]
@@ -0,0 +1,606 @@
+//! UEFI file paths
+//!
+//! [`FilePath`] and [`FilePathBuf`] are pure Rust paths to entities on a UEFI
+//! filesystem, like [`str`] and [`String`].
+//!
+//! UEFI paths use `\` as the separator, but `/` is also accepted and
+//! converted by [`FilePathBuf`]. Like the FAT filesystems UEFI supports,
+//! paths compare case-insensitively.
+//!
+//! These are unrelated to device paths, except that a [`FilePath`] can be
+//! converted to and from a media file path node with
+//! [`FilePath::to_media_node`] and [`FilePathBuf::from_media_node`].
+//!
+//! # References
+//!
+//! - [File Path Media Device Path][media]
+//!
+//! [media]: <https://uefi.org/specs/UEFI/2.10/10_Protocols_Device_Path_Protocol.html#file-path-media-device-path>
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use crate::{
+    base::Status,
+    error::Result,
+    proto::device_path::{DevicePathHdr, DevicePathSubType, DevicePathType},
+};
+
+/// The UEFI path separator
+pub const SEPARATOR: char = '\\';
+
+/// Size of a [`DevicePathHdr`]
+const HEADER: usize = 4;
+
+/// Whether `c` separates components, accepting `/` as well
+fn is_separator(c: char) -> bool {
+    c == SEPARATOR || c == '/'
+}
+
+/// Compare `a` and `b` ignoring case
+fn cmp_ignore_case(a: &str, b: &str) -> Ordering {
+    let a = a.chars().flat_map(char::to_uppercase);
+    let b = b.chars().flat_map(char::to_uppercase);
+    a.cmp(b)
+}
+
+/// A borrowed UEFI file path, like [`str`]
+///
+/// See [the module][`self`] docs for details.
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct FilePath {
+    inner: str,
+}
+
+impl FilePath {
+    /// Wrap `s` as a [`FilePath`]
+    pub fn new<S: AsRef<str> + ?Sized>(s: &S) -> &FilePath {
+        // Safety: `FilePath` is `repr(transparent)` over `str`
+        unsafe { &*(s.as_ref() as *const str as *const FilePath) }
+    }
+
+    /// The path as a string, exactly as it was given
+    pub fn as_str(&self) -> &str {
+        &self.inner
+    }
+
+    /// Whether the path is empty
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Whether the path starts at the root of the volume
+    pub fn is_absolute(&self) -> bool {
+        self.inner.starts_with(is_separator)
+    }
+
+    /// Iterate over the [`Component`]s of the path
+    ///
+    /// Repeated and trailing separators are ignored.
+    pub fn components(&self) -> Components<'_> {
+        Components {
+            root: self.is_absolute(),
+            rest: &self.inner,
+        }
+    }
+
+    /// The path without its last component
+    ///
+    /// Returns [`None`] if the path is empty or only the root.
+    pub fn parent(&self) -> Option<&FilePath> {
+        let path = self.inner.trim_end_matches(is_separator);
+        if path.is_empty() {
+            return None;
+        }
+        let parent = match path.rfind(is_separator) {
+            Some(i) => match path[..i].trim_end_matches(is_separator) {
+                // Keep the root
+                "" => &path[..1],
+                parent => parent,
+            },
+            None => "",
+        };
+        Some(FilePath::new(parent))
+    }
+
+    /// The last component of the path, if it is a name
+    pub fn file_name(&self) -> Option<&str> {
+        match self.components().last()? {
+            Component::Normal(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// [`FilePath::file_name`] without its [`FilePath::extension`]
+    pub fn file_stem(&self) -> Option<&str> {
+        let name = self.file_name()?;
+        match name.rsplit_once('.') {
+            Some((stem, _)) if !stem.is_empty() => Some(stem),
+            _ => Some(name),
+        }
+    }
+
+    /// The extension of [`FilePath::file_name`], without the `.`
+    ///
+    /// Names starting with their only `.` have no extension.
+    pub fn extension(&self) -> Option<&str> {
+        match self.file_name()?.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => Some(ext),
+            _ => None,
+        }
+    }
+
+    /// A new path of `path` relative to this one
+    ///
+    /// See [`FilePathBuf::push`]
+    pub fn join<P: AsRef<FilePath>>(&self, path: P) -> FilePathBuf {
+        let mut buf = self.to_path_buf();
+        buf.push(path);
+        buf
+    }
+
+    /// A new normalized path
+    ///
+    /// Separators become `\`, repeated and trailing separators and `.` are
+    /// removed, and `..` removes the component before it.
+    /// `..` at the root is removed,
+    /// while leading `..` in a relative path are kept.
+    pub fn normalize(&self) -> FilePathBuf {
+        let absolute = self.is_absolute();
+        let mut parts: Vec<&str> = Vec::new();
+        for c in self.components() {
+            match c {
+                Component::Root | Component::CurDir => (),
+                Component::ParentDir => match parts.last() {
+                    Some(&p) if p != ".." => {
+                        parts.pop();
+                    }
+                    _ if !absolute => parts.push(".."),
+                    _ => (),
+                },
+                Component::Normal(name) => parts.push(name),
+            }
+        }
+
+        let mut out = String::new();
+        if absolute {
+            out.push(SEPARATOR);
+        }
+        for (i, part) in parts.iter().enumerate() {
+            if i != 0 {
+                out.push(SEPARATOR);
+            }
+            out.push_str(part);
+        }
+        FilePathBuf { inner: out }
+    }
+
+    /// Convert to an owned [`FilePathBuf`]
+    pub fn to_path_buf(&self) -> FilePathBuf {
+        FilePathBuf::from(self.as_str())
+    }
+
+    /// Encode the path as a media file path device path node,
+    /// without an end node
+    ///
+    /// Returns [`Status::BAD_BUFFER_SIZE`] if the path is too long for a node.
+    pub fn to_media_node(&self) -> Result<Vec<u8>> {
+        let path: Vec<u16> = self
+            .inner
+            .chars()
+            .map(|c| if c == '/' { SEPARATOR } else { c })
+            .collect::<String>()
+            .encode_utf16()
+            .chain([0])
+            .collect();
+        let len = u16::try_from(path.len() * 2)
+            .ok()
+            .filter(|len| len.checked_add(HEADER as u16).is_some())
+            .ok_or(Status::BAD_BUFFER_SIZE)?;
+
+        let hdr = DevicePathHdr::media_file(len);
+        let mut out = Vec::with_capacity(HEADER + len as usize);
+        out.extend([hdr.ty.value(), hdr.sub_ty.value()]);
+        out.extend(hdr.len);
+        out.extend(path.into_iter().flat_map(u16::to_le_bytes));
+        Ok(out)
+    }
+}
+
+impl PartialEq for FilePath {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FilePath {}
+
+impl PartialOrd for FilePath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compares [`Component`]s, ignoring case
+impl Ord for FilePath {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let mut a = self.components();
+        let mut b = other.components();
+        loop {
+            match (a.next(), b.next()) {
+                (Some(x), Some(y)) => match cmp_ignore_case(x.as_str(), y.as_str()) {
+                    Ordering::Equal => (),
+                    ord => return ord,
+                },
+                (x, y) => return x.is_some().cmp(&y.is_some()),
+            }
+        }
+    }
+}
+
+impl Hash for FilePath {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for c in self.components() {
+            for c in c.as_str().chars().flat_map(char::to_uppercase) {
+                state.write_u32(c as u32);
+            }
+            state.write_u8(0);
+        }
+    }
+}
+
+impl fmt::Display for FilePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.inner)
+    }
+}
+
+impl AsRef<FilePath> for FilePath {
+    fn as_ref(&self) -> &FilePath {
+        self
+    }
+}
+
+impl AsRef<FilePath> for str {
+    fn as_ref(&self) -> &FilePath {
+        FilePath::new(self)
+    }
+}
+
+impl AsRef<FilePath> for String {
+    fn as_ref(&self) -> &FilePath {
+        FilePath::new(self)
+    }
+}
+
+impl ToOwned for FilePath {
+    type Owned = FilePathBuf;
+
+    fn to_owned(&self) -> FilePathBuf {
+        self.to_path_buf()
+    }
+}
+
+/// An owned UEFI file path, like [`String`]
+///
+/// Any `/` given to a [`FilePathBuf`] are converted to `\`.
+///
+/// See [the module][`self`] docs for details.
+#[derive(Debug, Clone, Default)]
+pub struct FilePathBuf {
+    inner: String,
+}
+
+impl FilePathBuf {
+    /// A new empty path
+    pub const fn new() -> Self {
+        Self {
+            inner: String::new(),
+        }
+    }
+
+    /// Parse a media file path device path node from `node`
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if `node` is not a valid media
+    /// file path node.
+    pub fn from_media_node(node: &[u8]) -> Result<FilePathBuf> {
+        let (hdr, rest) = node
+            .split_first_chunk::<HEADER>()
+            .ok_or(Status::INVALID_PARAMETER)?;
+        if hdr[0] != DevicePathType::MEDIA.value()
+            || hdr[1] != DevicePathSubType::MEDIA_FILE.value()
+        {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let len = u16::from_le_bytes([hdr[2], hdr[3]]) as usize;
+        let path = len
+            .checked_sub(HEADER)
+            .and_then(|len| rest.get(..len))
+            .ok_or(Status::INVALID_PARAMETER)?;
+
+        let (path, _) = path.as_chunks::<2>();
+        let path = path
+            .iter()
+            .map(|c| u16::from_le_bytes(*c))
+            .take_while(|c| *c != 0);
+        let path = char::decode_utf16(path)
+            .collect::<core::result::Result<String, _>>()
+            .map_err(|_| Status::INVALID_PARAMETER)?;
+        Ok(FilePathBuf::from(path))
+    }
+
+    /// Borrow as a [`FilePath`]
+    pub fn as_path(&self) -> &FilePath {
+        FilePath::new(&self.inner)
+    }
+
+    /// Convert into the path [`String`]
+    pub fn into_string(self) -> String {
+        self.inner
+    }
+
+    /// Append `path` to this one, with a separator if needed
+    ///
+    /// If `path` is absolute it replaces this path.
+    pub fn push<P: AsRef<FilePath>>(&mut self, path: P) {
+        let path = path.as_ref();
+        if path.is_absolute() {
+            self.inner.clear();
+        } else if !self.inner.is_empty() && !self.inner.ends_with(SEPARATOR) {
+            self.inner.push(SEPARATOR);
+        }
+        self.push_str(path.as_str());
+    }
+
+    /// Remove the last component, see [`FilePath::parent`]
+    ///
+    /// Returns `false` and does nothing if there is no parent.
+    pub fn pop(&mut self) -> bool {
+        match self.parent() {
+            Some(parent) => {
+                let len = parent.as_str().len();
+                self.inner.truncate(len);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.inner
+            .extend(s.chars().map(|c| if c == '/' { SEPARATOR } else { c }));
+    }
+}
+
+impl Deref for FilePathBuf {
+    type Target = FilePath;
+
+    fn deref(&self) -> &FilePath {
+        self.as_path()
+    }
+}
+
+impl Borrow<FilePath> for FilePathBuf {
+    fn borrow(&self) -> &FilePath {
+        self.as_path()
+    }
+}
+
+impl AsRef<FilePath> for FilePathBuf {
+    fn as_ref(&self) -> &FilePath {
+        self.as_path()
+    }
+}
+
+impl From<&str> for FilePathBuf {
+    fn from(value: &str) -> Self {
+        let mut out = FilePathBuf::new();
+        out.push_str(value);
+        out
+    }
+}
+
+impl From<String> for FilePathBuf {
+    fn from(value: String) -> Self {
+        FilePathBuf::from(value.as_str())
+    }
+}
+
+impl From<&FilePath> for FilePathBuf {
+    fn from(value: &FilePath) -> Self {
+        value.to_path_buf()
+    }
+}
+
+impl PartialEq for FilePathBuf {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_path() == other.as_path()
+    }
+}
+
+impl Eq for FilePathBuf {}
+
+impl PartialOrd for FilePathBuf {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compares [`Component`]s, ignoring case
+impl Ord for FilePathBuf {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_path().cmp(other.as_path())
+    }
+}
+
+impl Hash for FilePathBuf {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_path().hash(state);
+    }
+}
+
+impl fmt::Display for FilePathBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.inner)
+    }
+}
+
+/// A single component of a [`FilePath`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Component<'a> {
+    /// The root of the volume, a leading `\`
+    Root,
+
+    /// The current directory, `.`
+    CurDir,
+
+    /// The parent directory, `..`
+    ParentDir,
+
+    /// A file or directory name
+    Normal(&'a str),
+}
+
+impl<'a> Component<'a> {
+    /// The component as a string
+    pub fn as_str(self) -> &'a str {
+        match self {
+            Component::Root => "\\",
+            Component::CurDir => ".",
+            Component::ParentDir => "..",
+            Component::Normal(name) => name,
+        }
+    }
+}
+
+/// Iterator over the [`Component`]s of a [`FilePath`]
+///
+/// See [`FilePath::components`]
+#[derive(Debug, Clone)]
+pub struct Components<'a> {
+    root: bool,
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Components<'a> {
+    type Item = Component<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.root {
+            self.root = false;
+            return Some(Component::Root);
+        }
+        let rest = self.rest.trim_start_matches(is_separator);
+        if rest.is_empty() {
+            self.rest = rest;
+            return None;
+        }
+        let (name, rest) = rest.split_at(rest.find(is_separator).unwrap_or(rest.len()));
+        self.rest = rest;
+        Some(match name {
+            "." => Component::CurDir,
+            ".." => Component::ParentDir,
+            name => Component::Normal(name),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn components() {
+        let path = FilePath::new("\\EFI\\/BOOT\\.\\..\\BOOTX64.EFI\\");
+        let names: Vec<_> = path.components().map(Component::as_str).collect();
+        assert_eq!(names, ["\\", "EFI", "BOOT", ".", "..", "BOOTX64.EFI"]);
+        assert!(path.is_absolute());
+
+        let path = FilePath::new("a\\b.tar.gz");
+        assert!(!path.is_absolute());
+        assert_eq!(path.file_name(), Some("b.tar.gz"));
+        assert_eq!(path.file_stem(), Some("b.tar"));
+        assert_eq!(path.extension(), Some("gz"));
+        assert_eq!(FilePath::new(".hidden").extension(), None);
+        assert_eq!(FilePath::new(".hidden").file_stem(), Some(".hidden"));
+        assert_eq!(FilePath::new("a\\..").file_name(), None);
+        assert_eq!(FilePath::new("\\").file_name(), None);
+    }
+
+    #[test]
+    fn parent() {
+        let parents = |path: &str| {
+            let mut path = FilePathBuf::from(path);
+            let mut out = vec![path.as_str().to_owned()];
+            while path.pop() {
+                out.push(path.as_str().to_owned());
+            }
+            out
+        };
+        assert_eq!(parents("\\EFI\\BOOT\\"), ["\\EFI\\BOOT\\", "\\EFI", "\\"]);
+        assert_eq!(parents("a/b"), ["a\\b", "a", ""]);
+        assert_eq!(FilePath::new("\\\\a").parent().unwrap().as_str(), "\\");
+        assert!(FilePath::new("").parent().is_none());
+    }
+
+    #[test]
+    fn join() {
+        let path = FilePath::new("\\EFI");
+        assert_eq!(
+            path.join("BOOT/BOOTX64.EFI").as_str(),
+            "\\EFI\\BOOT\\BOOTX64.EFI"
+        );
+        assert_eq!(path.join("\\other").as_str(), "\\other");
+        assert_eq!(FilePath::new("\\").join("a").as_str(), "\\a");
+        assert_eq!(FilePath::new("").join("a").as_str(), "a");
+    }
+
+    #[test]
+    fn normalize() {
+        let normal = |path: &str| FilePath::new(path).normalize().into_string();
+        assert_eq!(normal("/EFI//BOOT/./../nuefi/"), "\\EFI\\nuefi");
+        assert_eq!(normal("\\..\\a\\..\\..\\b"), "\\b");
+        assert_eq!(normal("..\\a\\..\\..\\b"), "..\\..\\b");
+        assert_eq!(normal("a\\.\\"), "a");
+        assert_eq!(normal("/"), "\\");
+        assert_eq!(normal("."), "");
+    }
+
+    #[test]
+    fn compare() {
+        let a = FilePathBuf::from("\\EFI\\Boot\\bootx64.efi");
+        assert_eq!(a.as_path(), FilePath::new("/efi/BOOT//BOOTX64.EFI"));
+        assert_ne!(a.as_path(), FilePath::new("\\efi\\boot"));
+        assert_ne!(a.as_path(), FilePath::new("EFI\\Boot\\bootx64.efi"));
+        assert!(FilePath::new("a") < FilePath::new("B"));
+        assert_eq!(a, FilePathBuf::from("/EFI/Boot/bootx64.efi"));
+    }
+
+    #[test]
+    fn media_node() -> Result<()> {
+        let path = FilePath::new("/EFI/a");
+        let node = path.to_media_node()?;
+        let mut expected = vec![0x04, 0x04, 18, 0];
+        for c in "\\EFI\\a\0".encode_utf16() {
+            expected.extend(c.to_le_bytes());
+        }
+        assert_eq!(node, expected);
+        assert_eq!(FilePathBuf::from_media_node(&node)?.as_str(), "\\EFI\\a");
+
+        assert!(FilePathBuf::from_media_node(&node[..10]).is_err());
+        let mut bad = node.clone();
+        bad[1] = 0x01;
+        assert!(FilePathBuf::from_media_node(&bad).is_err());
+        let long = "a".repeat(u16::MAX as usize);
+        assert!(FilePath::new(&long).to_media_node().is_err());
+        Ok(())
+    }
+}
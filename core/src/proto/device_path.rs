@@ -70,6 +70,11 @@ impl DevicePathType {
 
     /// Represents the end of the device path
     pub const END: Self = Self(0x7F);
+
+    /// Raw value
+    pub const fn value(self) -> u8 {
+        self.0
+    }
 }
 
 /// [`DevicePathHdr`] Sub Types
@@ -87,6 +92,11 @@ impl DevicePathSubType {
     /// Represents the end of this [`DevicePathHdr`] instance
     /// and the start of a new one
     pub const END_INSTANCE: Self = Self(0x01);
+
+    /// Raw value
+    pub const fn value(self) -> u8 {
+        self.0
+    }
 }
 
 /// Generic [`DevicePathHdr`] structure, and a
@@ -10,6 +10,7 @@ use crate::{base::*, error::Result};
 pub mod boot_fn;
 pub mod config;
 pub mod mem;
+pub mod run_fn;
 
 // FIXME: Hack
 type SimpleTextInput = c_void;
@@ -196,6 +197,20 @@ impl Header {
         }
         Ok(())
     }
+
+    /// Recalculate [`Header::crc32`] for `table`, after modifying it
+    ///
+    /// # Safety
+    ///
+    /// - `table` must contain a valid [`Header`]
+    /// - `table` must be valid for reads and writes of [`Header::size`] bytes
+    pub unsafe fn update_crc(table: *mut u8) {
+        let header = table as *mut Self;
+        (*header).crc32 = 0;
+        let bytes = core::slice::from_raw_parts(table, (*header).size as usize);
+        let crc = CRC.checksum(bytes);
+        (*header).crc32 = crc;
+    }
 }
 
 /// The EFI system table.
@@ -347,6 +362,44 @@ impl LocateSearch {
     pub const BY_PROTOCOL: Self = Self(2);
 }
 
+/// Type of event for [`BootServices::create_event`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct EventType(u32);
+
+impl EventType {
+    /// An event with no notification function
+    pub const NONE: Self = Self(0);
+
+    /// A timer event, see [`BootServices::set_timer`]
+    pub const TIMER: Self = Self(0x8000_0000);
+
+    /// Event is allocated from runtime memory
+    pub const RUNTIME: Self = Self(0x4000_0000);
+
+    /// Notification function is queued while the event is being waited on
+    pub const NOTIFY_WAIT: Self = Self(0x0000_0100);
+
+    /// Notification function is queued when the event is signaled
+    pub const NOTIFY_SIGNAL: Self = Self(0x0000_0200);
+}
+
+/// Type of timer for [`BootServices::set_timer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct TimerDelay(u32);
+
+impl TimerDelay {
+    /// Cancel the timer
+    pub const CANCEL: Self = Self(0);
+
+    /// Signal the event every trigger time
+    pub const PERIODIC: Self = Self(1);
+
+    /// Signal the event once, after the trigger time
+    pub const RELATIVE: Self = Self(2);
+}
+
 /// The UEFI Boot Services Table
 ///
 /// This is FFI-safe
@@ -371,12 +424,12 @@ pub struct BootServices {
     pub free_pool: Option<boot_fn::FreePool>,
 
     // Timers/Events
-    pub create_event: *mut c_void,
-    pub set_timer: *mut c_void,
-    pub wait_for_event: *mut c_void,
+    pub create_event: Option<boot_fn::CreateEvent>,
+    pub set_timer: Option<boot_fn::SetTimer>,
+    pub wait_for_event: Option<boot_fn::WaitForEvent>,
     pub signal_event: *mut c_void,
-    pub close_event: *mut c_void,
-    pub check_event: *mut c_void,
+    pub close_event: Option<boot_fn::CloseEvent>,
+    pub check_event: Option<boot_fn::CheckEvent>,
 
     // Protocols
     pub install_protocol_interface: Option<boot_fn::InstallProtocolInterface>,
@@ -442,12 +495,134 @@ impl BootServices {
     pub const REVISION: Revision = SystemTable::SPECIFICATION;
 }
 
+/// Reset type for [`RuntimeServices::reset_system`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct ResetType(u32);
+
+impl ResetType {
+    /// System-wide reset, all circuitry is reset
+    pub const COLD: Self = Self(0);
+
+    /// System-wide initialization, processors are reset
+    pub const WARM: Self = Self(1);
+
+    /// Enter a power state equivalent to ACPI G2/S5 or G3
+    pub const SHUTDOWN: Self = Self(2);
+
+    /// Platform specific reset, identified by a [`Guid`] in the reset data
+    pub const PLATFORM_SPECIFIC: Self = Self(3);
+}
+
+/// UEFI Time
+///
+/// See [`RuntimeServices::get_time`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Time {
+    /// 1900 - 9999
+    pub year: u16,
+
+    /// 1 - 12
+    pub month: u8,
+
+    /// 1 - 31
+    pub day: u8,
+
+    /// 0 - 23
+    pub hour: u8,
+
+    /// 0 - 59
+    pub minute: u8,
+
+    /// 0 - 59
+    pub second: u8,
+
+    pub _pad1: u8,
+
+    /// 0 - 999,999,999
+    pub nanosecond: u32,
+
+    /// Offset from UTC in minutes, -1440 to 1440,
+    /// or [`Time::UNSPECIFIED_TIMEZONE`]
+    pub time_zone: i16,
+
+    /// Daylight savings flags
+    pub daylight: u8,
+
+    pub _pad2: u8,
+}
+
+impl Time {
+    /// [`Time::time_zone`] is local time
+    pub const UNSPECIFIED_TIMEZONE: i16 = 0x07FF;
+
+    /// [`Time::daylight`] flag, time is affected by daylight savings time
+    pub const ADJUST_DAYLIGHT: u8 = 0x01;
+
+    /// [`Time::daylight`] flag, time has been adjusted for daylight savings
+    /// time
+    pub const IN_DAYLIGHT: u8 = 0x02;
+}
+
+/// Capabilities of the real time clock
+///
+/// See [`RuntimeServices::get_time`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct TimeCapabilities {
+    /// Resolution in counts per second
+    pub resolution: u32,
+
+    /// Accuracy in parts per million
+    pub accuracy: u32,
+
+    /// Whether setting the time clears time below the resolution
+    pub sets_to_zero: Boolean,
+}
+
 /// The UEFI Runtime Services Table
+///
+/// This is FFI-safe
 #[derive(Debug)]
 #[repr(C)]
 pub struct RuntimeServices {
     /// Table header
     pub header: Header,
+
+    // Time
+    pub get_time: Option<run_fn::GetTime>,
+
+    pub set_time: Option<run_fn::SetTime>,
+
+    pub get_wakeup_time: Option<run_fn::GetWakeupTime>,
+
+    pub set_wakeup_time: Option<run_fn::SetWakeupTime>,
+
+    // Virtual memory
+    pub set_virtual_address_map: Option<run_fn::SetVirtualAddressMap>,
+
+    pub convert_pointer: Option<run_fn::ConvertPointer>,
+
+    // Variables
+    pub get_variable: Option<run_fn::GetVariable>,
+
+    pub get_next_variable_name: Option<run_fn::GetNextVariableName>,
+
+    pub set_variable: Option<run_fn::SetVariable>,
+
+    // Misc
+    pub get_next_high_monotonic_count: Option<run_fn::GetNextHighMonotonicCount>,
+
+    pub reset_system: Option<run_fn::ResetSystem>,
+
+    // Capsules
+    pub update_capsule: Option<run_fn::UpdateCapsule>,
+
+    pub query_capsule_capabilities: Option<run_fn::QueryCapsuleCapabilities>,
+
+    // Variables again
+    pub query_variable_info: Option<run_fn::QueryVariableInfo>,
 }
 
 impl RuntimeServices {
@@ -465,4 +640,28 @@ mod tests {
         assert_eq!(rev.major(), 2);
         assert_eq!(rev.minor(), 70);
     }
+
+    #[test]
+    fn update_crc() {
+        // Safety: Every field of `SystemTable` is nullable
+        let mut table: SystemTable = unsafe { core::mem::zeroed() };
+        table.header = Header {
+            signature: SystemTable::SIGNATURE,
+            revision: SystemTable::REVISION,
+            size: size_of::<SystemTable>() as u32,
+            crc32: 0,
+            reserved: 0,
+        };
+        let ptr = |t: &mut SystemTable| t as *mut SystemTable as *mut u8;
+        // Safety: `table` is valid for its size
+        unsafe {
+            assert!(Header::validate(ptr(&mut table), SystemTable::SIGNATURE).is_err());
+            Header::update_crc(ptr(&mut table));
+            assert!(Header::validate(ptr(&mut table), SystemTable::SIGNATURE).is_ok());
+            table.firmware_revision = 1;
+            assert!(Header::validate(ptr(&mut table), SystemTable::SIGNATURE).is_err());
+            Header::update_crc(ptr(&mut table));
+            assert!(Header::validate(ptr(&mut table), SystemTable::SIGNATURE).is_ok());
+        }
+    }
 }
@@ -6,7 +6,7 @@
 //! - <https://uefi.org/specs/UEFI/2.10/07_Services_Boot_Services.html>
 use core::ffi::c_void;
 
-use super::{mem::*, LocateSearch};
+use super::{mem::*, EventType, LocateSearch, TimerDelay};
 use crate::base::*;
 
 // FIXME: Hack
@@ -43,6 +43,27 @@ pub type AllocatePool = unsafe extern "efiapi" fn(
 
 pub type FreePool = unsafe extern "efiapi" fn(mem: *mut c_void) -> Status;
 
+/// Notification function for an [`Event`]
+pub type EventNotify = unsafe extern "efiapi" fn(event: Event, context: *mut c_void);
+
+pub type CreateEvent = unsafe extern "efiapi" fn(
+    ty: EventType,
+    tpl: TaskPriorityLevel,
+    notify: Option<EventNotify>,
+    context: *mut c_void,
+    event: *mut Event,
+) -> Status;
+
+/// `trigger` is in 100ns units
+pub type SetTimer = unsafe extern "efiapi" fn(event: Event, ty: TimerDelay, trigger: u64) -> Status;
+
+pub type CloseEvent = unsafe extern "efiapi" fn(event: Event) -> Status;
+
+pub type WaitForEvent =
+    unsafe extern "efiapi" fn(events: usize, event: *const Event, index: *mut usize) -> Status;
+
+pub type CheckEvent = unsafe extern "efiapi" fn(event: Event) -> Status;
+
 pub type InstallProtocolInterface = unsafe extern "efiapi" fn(
     handle: *mut Handle,
     guid: *mut Guid,
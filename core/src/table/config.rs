@@ -9,27 +9,25 @@
 //! Several standard and vendor-specific tables are defined and known about
 //! here. Unknown tables can be used through [`GenericConfig`]
 #![allow(dead_code)]
-use core::{ffi::c_void, marker::PhantomData};
-
-use crate::{base::*, extra::Entity, GUID};
-
-mod imp {
-    use super::*;
-    pub trait Sealed {}
-    impl Sealed for AcpiTable10 {}
-    impl Sealed for AcpiTable20 {}
-    impl Sealed for RuntimeProperties {}
-    impl Sealed for SMBIOS {}
-    impl Sealed for SMBIOS3 {}
-    impl Sealed for SAL {}
-    impl Sealed for MPS {}
-    impl Sealed for JsonConfigData {}
-    impl Sealed for JsonCapsuleData {}
-    impl Sealed for JsonCapsuleResult {}
-    impl Sealed for DeviceTree {}
-    impl Sealed for MemoryAttributes {}
-    impl Sealed for ConformanceProfile {}
-}
+use core::{
+    ffi::c_void,
+    marker::PhantomData,
+    mem::size_of,
+    ptr::null_mut,
+    sync::atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+
+use crate::{
+    base::*,
+    error::Result,
+    extra::Entity,
+    table::mem::{MemoryDescriptor, MemoryDescriptors, MemoryFlags, MemoryMap},
+    GUID,
+};
+
+pub mod fdt;
+pub mod image;
+pub mod smbios;
 
 pub mod vendor {
     //! Vendor Specific Configuration tables
@@ -70,8 +68,6 @@ pub mod vendor {
         }
     }
 }
-use alloc::vec::Vec;
-
 use vendor::edk2::*;
 
 /// A generic UEFI Configuration table
@@ -84,16 +80,66 @@ pub struct ConfigurationTable {
 
 /// Identifies a UEFI Configuration Table
 ///
-/// Specifically, this is a sealed trait that identifies a table definition
+/// Specifically, this is a trait that identifies a table definition
 /// we statically *trust*. This has safety implications, an incorrect GUID
 /// can result in type confusion and thus unsoundness.
 ///
-/// Any type that implements this is thus guaranteed to be sound.
+/// All the tables defined here implement this, and you can implement it
+/// for your own vendor tables, using the [`GUID`][gm] macro to implement
+/// [`Entity`].
+///
+/// Register the name of your table with [`register_name`]
+/// for [`GenericConfig::name`].
 ///
 /// [`GenericConfig`] exposes a generic method for unsafely handling arbitrary
 /// configuration tables if you need this.
+///
+/// # Safety
+///
+/// - [`Entity::GUID`] must uniquely identify the table
+/// - [`ConfigTable::from_raw`] must be sound for any table with that GUID
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use core::ffi::c_void;
+///
+/// use nuefi::{
+///     table::config::{register_name, ConfigTable, GenericConfig, TableName},
+///     GUID,
+/// };
+///
+/// /// Vendor table with a single version field
+/// #[GUID("A46423E3-4617-49F1-B9FF-D1BFA9115839")]
+/// pub struct VendorTable<'tbl> {
+///     version: &'tbl u32,
+/// }
+///
+/// unsafe impl<'tbl> ConfigTable<'tbl> for VendorTable<'tbl> {
+///     type Out<'cfg> = Self where
+///         'tbl: 'cfg;
+///
+///     unsafe fn from_raw(raw: *const c_void) -> Self::Out<'tbl> {
+///         Self {
+///             version: &*raw.cast(),
+///         }
+///     }
+/// }
+///
+/// static VENDOR_NAME: TableName = TableName::new::<VendorTable>();
+///
+/// fn vendor_version(config: &GenericConfig<'_>) -> Option<u32> {
+///     register_name(&VENDOR_NAME);
+///     assert_eq!(config.name(), Some("VendorTable"));
+///
+///     // Or with the `SystemTable`, `table.config_table::<VendorTable>()`
+///     config.as_table::<VendorTable>().map(|t| *t.version)
+/// }
+/// ```
+///
+/// [gm]: crate::GUID
 // `'tbl` represents the lifetime of the [`SystemTable`]
-pub trait ConfigTable<'tbl>: Entity + imp::Sealed {
+pub unsafe trait ConfigTable<'tbl>: Entity {
     /// The lifetime `'cfg` represents the configuration table
     ///
     /// FIXME: I dont really get this honestly.
@@ -107,6 +153,69 @@ pub trait ConfigTable<'tbl>: Entity + imp::Sealed {
     unsafe fn from_raw(raw: *const c_void) -> Self::Out<'tbl>;
 }
 
+/// Name of your own [`ConfigTable`], for [`register_name`]
+///
+/// This is the storage for the registration, and lives in a `static`
+/// owned by you.
+#[derive(Debug)]
+pub struct TableName {
+    guid: Guid,
+    name: &'static str,
+
+    /// Whether this is already in [`USER_TABLES`]
+    registered: AtomicBool,
+
+    /// Next registered table
+    next: AtomicPtr<TableName>,
+}
+
+impl TableName {
+    /// Name of the table `T`
+    pub const fn new<T: Entity>() -> Self {
+        Self {
+            guid: T::GUID,
+            name: T::NAME,
+            registered: AtomicBool::new(false),
+            next: AtomicPtr::new(null_mut()),
+        }
+    }
+}
+
+/// Most recently registered [`TableName`], linked through [`TableName::next`]
+static USER_TABLES: AtomicPtr<TableName> = AtomicPtr::new(null_mut());
+
+/// Register your own [`ConfigTable`] so [`GenericConfig::name`] knows it
+///
+/// Registering the same `name` more than once does nothing.
+pub fn register_name(name: &'static TableName) {
+    if name.registered.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let new = name as *const TableName as *mut TableName;
+    let mut head = USER_TABLES.load(Ordering::Acquire);
+    loop {
+        name.next.store(head, Ordering::Release);
+        match USER_TABLES.compare_exchange_weak(head, new, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => return,
+            Err(now) => head = now,
+        }
+    }
+}
+
+/// Name of the table `guid` registered with [`register_name`]
+fn user_name(guid: Guid) -> Option<&'static str> {
+    let mut table = USER_TABLES.load(Ordering::Acquire);
+    while !table.is_null() {
+        // Safety: Only `&'static TableName`s are ever linked
+        let name = unsafe { &*table };
+        if name.guid == guid {
+            return Some(name.name);
+        }
+        table = name.next.load(Ordering::Acquire);
+    }
+    None
+}
+
 /// A generic UEFI configuration table, identified by a [`Guid`]
 #[derive(Debug)]
 #[repr(transparent)]
@@ -204,7 +313,7 @@ impl<'tbl> GenericConfig<'tbl> {
         } else if guid == MemoryStatus::GUID {
             Some(MemoryStatus::NAME)
         } else {
-            None
+            user_name(guid)
         }
     }
 
@@ -217,7 +326,8 @@ impl<'tbl> GenericConfig<'tbl> {
         if self.guid() == T::GUID {
             let raw = self.as_ptr();
             // Safety: We've just verified the GUID is correct
-            // `ConfigTable` is sealed and its trusted to have correct GUIDs and types
+            // `ConfigTable` is unsafe and its trusted to have correct GUIDs and
+            // types
             let o = unsafe { T::from_raw(raw) };
             Some(o)
         } else {
@@ -248,19 +358,49 @@ pub struct AcpiTable10 {
 }
 
 /// Table for SMBIOS 3
+///
+/// Points to the 64-bit `_SM3_` entry point. See [`smbios`]
 #[GUID("F2FD1544-9794-4A2C-992E-E5BBCF20E394", crate("crate"))]
 #[derive(Debug)]
 pub struct SMBIOS3 {
     table: *mut c_void,
 }
 
+impl SMBIOS3 {
+    /// The SMBIOS structure table
+    pub fn structures(&self) -> Result<smbios::StructureTable<'_>> {
+        // Safety: `table` is trusted to point to an `_SM3_` entry point,
+        // and UEFI identity maps the structure table.
+        let t = unsafe { smbios::StructureTable::from_ptr(self.table) }?;
+        match t.entry_point() {
+            smbios::EntryPoint::Bits64(_) => Ok(t),
+            smbios::EntryPoint::Bits32(_) => Err(Status::INVALID_PARAMETER.into()),
+        }
+    }
+}
+
 /// Table for SMBIOS
+///
+/// Points to the 32-bit `_SM_` entry point. See [`smbios`]
 #[GUID("EB9D2D31-2D88-11D3-9A16-0090273FC14D", crate("crate"))]
 #[derive(Debug)]
 pub struct SMBIOS {
     table: *mut c_void,
 }
 
+impl SMBIOS {
+    /// The SMBIOS structure table
+    pub fn structures(&self) -> Result<smbios::StructureTable<'_>> {
+        // Safety: `table` is trusted to point to an `_SM_` entry point,
+        // and UEFI identity maps the structure table.
+        let t = unsafe { smbios::StructureTable::from_ptr(self.table) }?;
+        match t.entry_point() {
+            smbios::EntryPoint::Bits32(_) => Ok(t),
+            smbios::EntryPoint::Bits64(_) => Err(Status::INVALID_PARAMETER.into()),
+        }
+    }
+}
+
 /// Table for SAL
 #[GUID("EB9D2D32-2D88-11D3-9A16-0090273FC14D", crate("crate"))]
 #[derive(Debug)]
@@ -275,7 +415,10 @@ pub struct MPS {
     table: *mut c_void,
 }
 
-/// Table for ACPI 2.0 and newer
+/// Table describing which UEFI Runtime Services are supported after
+/// ExitBootServices
+///
+/// If this table doesn't exist, all runtime services are supported.
 #[GUID("EB66918A-7EEF-402A-842E-931D21C38AE9", crate("crate"))]
 #[derive(Debug)]
 #[repr(C)]
@@ -283,6 +426,55 @@ pub struct RuntimeProperties {
     table: *mut c_void,
 }
 
+/// Raw [`RuntimeProperties`] table
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct RawRuntimeProperties {
+    version: u16,
+    length: u16,
+    supported: u32,
+}
+
+impl RuntimeProperties {
+    fn raw(&self) -> RawRuntimeProperties {
+        // Safety: `table` is trusted to point to the table
+        unsafe { self.table.cast::<RawRuntimeProperties>().read_unaligned() }
+    }
+
+    /// Table version
+    pub fn version(&self) -> u16 {
+        self.raw().version
+    }
+
+    /// Runtime services supported after ExitBootServices
+    pub fn supported(&self) -> RuntimeServicesSupported {
+        RuntimeServicesSupported::from_bits_truncate(self.raw().supported)
+    }
+}
+
+bitflags::bitflags! {
+    /// UEFI Runtime Services supported after ExitBootServices
+    ///
+    /// See [`RuntimeProperties`]
+    #[repr(transparent)]
+    pub struct RuntimeServicesSupported: u32 {
+        const GET_TIME = 0x0001;
+        const SET_TIME = 0x0002;
+        const GET_WAKEUP_TIME = 0x0004;
+        const SET_WAKEUP_TIME = 0x0008;
+        const GET_VARIABLE = 0x0010;
+        const GET_NEXT_VARIABLE_NAME = 0x0020;
+        const SET_VARIABLE = 0x0040;
+        const SET_VIRTUAL_ADDRESS_MAP = 0x0080;
+        const CONVERT_POINTER = 0x0100;
+        const GET_NEXT_HIGH_MONOTONIC_COUNT = 0x0200;
+        const RESET_SYSTEM = 0x0400;
+        const UPDATE_CAPSULE = 0x0800;
+        const QUERY_CAPSULE_CAPABILITIES = 0x1000;
+        const QUERY_VARIABLE_INFO = 0x2000;
+    }
+}
+
 /// Table for JSON Config Data
 #[GUID("87367F87-1119-41CE-AAEC-8BE0111F558A", crate("crate"))]
 #[derive(Debug)]
@@ -311,23 +503,156 @@ pub struct DeviceTree {
     table: *mut c_void,
 }
 
+impl DeviceTree {
+    /// The Flattened Device Tree blob
+    pub fn fdt(&self) -> Result<fdt::Fdt<'_>> {
+        // Safety: `table` is trusted to point to a DTB,
+        // which lives as long as the configuration table.
+        unsafe { fdt::Fdt::from_ptr(self.table) }
+    }
+}
+
+/// Memory attributes of UEFI Runtime Services memory
+///
+/// Describes the permissions of runtime code and data, as a set of
+/// [`MemoryDescriptor`]s which are sub-regions of the runtime entries of the
+/// [`MemoryMap`].
 #[GUID("DCFA911D-26EB-469F-A220-38B7DC461220", crate("crate"))]
 #[derive(Debug)]
 pub struct MemoryAttributes {
     table: *mut c_void,
 }
 
-/// UEFI Conformance profile
-#[derive(Debug)]
+/// Raw header of the [`MemoryAttributes`] table, followed by the descriptors
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct RawMemoryAttributes {
+    version: u32,
+    entries: u32,
+    descriptor_size: u32,
+    flags: u32,
+}
+
+impl MemoryAttributes {
+    /// Flag indicating runtime code is compatible with forward control flow
+    /// guards, such as CET-IBT or BTI.
+    pub const RT_FORWARD_CONTROL_FLOW_GUARD: u32 = 0x1;
+
+    fn header(&self) -> RawMemoryAttributes {
+        // Safety: `table` is trusted to point to the table
+        unsafe { self.table.cast::<RawMemoryAttributes>().read_unaligned() }
+    }
+
+    /// Table version
+    pub fn version(&self) -> u32 {
+        self.header().version
+    }
+
+    /// Number of descriptors
+    pub fn len(&self) -> usize {
+        self.header().entries as usize
+    }
+
+    /// Whether there are no descriptors
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Size in bytes of each descriptor
+    pub fn descriptor_size(&self) -> usize {
+        self.header().descriptor_size as usize
+    }
+
+    /// Table flags. Only meaningful for version 2 and later.
+    ///
+    /// See [`MemoryAttributes::RT_FORWARD_CONTROL_FLOW_GUARD`]
+    pub fn flags(&self) -> u32 {
+        let h = self.header();
+        if h.version >= 2 {
+            h.flags
+        } else {
+            0
+        }
+    }
+
+    /// Iterator over the descriptors in this table
+    ///
+    /// Each descriptor has the [`MemoryFlags::RUNTIME`] attribute, and
+    /// the [`MemoryFlags::RO`] and [`MemoryFlags::XP`] permission attributes
+    /// describing how it must be mapped.
+    ///
+    /// Returns [`None`] if the descriptor size is smaller than
+    /// [`MemoryDescriptor`]
+    pub fn entries(&self) -> Option<MemoryDescriptors<'_>> {
+        let h = self.header();
+        let stride = h.descriptor_size as usize;
+        if stride < size_of::<MemoryDescriptor>() {
+            return None;
+        }
+        let len = h.entries as usize * stride;
+        // Safety: `table` is trusted to be followed by `entries` descriptors
+        let buf = unsafe {
+            core::slice::from_raw_parts(
+                self.table
+                    .cast::<u8>()
+                    .add(size_of::<RawMemoryAttributes>()),
+                len,
+            )
+        };
+        MemoryDescriptors::new(buf, stride)
+    }
+
+    /// Cross-reference this table with the runtime entries of `map`
+    ///
+    /// Returns an iterator over the entries of this table,
+    /// with virtual addresses and cacheability attributes taken from the
+    /// runtime entry of `map` that contains them, and the permission
+    /// attributes from this table.
+    ///
+    /// Entries not contained by a runtime entry in `map` are skipped.
+    /// Runtime entries of `map` not described by this table,
+    /// such as MMIO, are not included.
+    ///
+    /// Yields nothing if [`MemoryAttributes::entries`] is [`None`]
+    pub fn runtime_regions<'a>(
+        &'a self,
+        map: &'a MemoryMap,
+    ) -> impl Iterator<Item = MemoryDescriptor> + 'a {
+        self.entries().into_iter().flatten().filter_map(|entry| {
+            let parent = map.runtime().find(|m| {
+                m.physical_start() <= entry.physical_start()
+                    && entry.physical_end() <= m.physical_end()
+            })?;
+            let offset = entry.physical_start() - parent.physical_start();
+            let virt = if parent.virtual_start() != 0 {
+                parent.virtual_start() + offset
+            } else {
+                0
+            };
+            let attribute = (parent.attribute() & !MemoryFlags::PERMISSIONS)
+                | (entry.attribute() & MemoryFlags::PERMISSIONS)
+                | MemoryFlags::RUNTIME;
+            Some(MemoryDescriptor::new(
+                entry.ty(),
+                entry.physical_start(),
+                virt,
+                entry.pages(),
+                attribute,
+            ))
+        })
+    }
+}
+
+/// Raw UEFI Conformance profile header
+///
+/// This is followed by `size` [`Guid`]s
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct RawConformanceProfile {
     ver: u16,
 
     /// Number of profiles
     size: u16,
-
-    /// Array of profiles
-    profiles: *const c_void,
 }
 
 /// UEFI Conformance profile
@@ -337,10 +662,85 @@ pub struct RawConformanceProfile {
 #[derive(Debug)]
 #[repr(C)]
 pub struct ConformanceProfile {
-    ver: u16,
-    profiles: Vec<Guid>,
+    table: *mut c_void,
+}
+
+impl ConformanceProfile {
+    fn raw(&self) -> RawConformanceProfile {
+        // Safety: `table` is trusted to point to the table
+        unsafe { self.table.cast::<RawConformanceProfile>().read_unaligned() }
+    }
+
+    /// Table version
+    pub fn version(&self) -> u16 {
+        self.raw().ver
+    }
+
+    /// Iterator over the profiles the platform conforms to
+    pub fn profiles(&self) -> impl Iterator<Item = Profile> + '_ {
+        let len = self.raw().size as usize;
+        // Safety: `table` is trusted to be followed by `size` GUIDs
+        let ptr = unsafe {
+            self.table
+                .cast::<u8>()
+                .add(size_of::<RawConformanceProfile>())
+        };
+        let ptr = ptr.cast::<Guid>();
+        // Safety: See above. The GUIDs are only 4 byte aligned.
+        (0..len).map(move |i| Profile(unsafe { ptr.add(i).read_unaligned() }))
+    }
 }
 
+/// A conformance profile from [`ConformanceProfile`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile(Guid);
+
+impl Profile {
+    /// Profile GUID
+    pub fn guid(&self) -> Guid {
+        self.0
+    }
+
+    /// Name of this profile, if known
+    pub fn name(&self) -> Option<&'static str> {
+        use profile::*;
+        let guid = self.0;
+        if guid == UefiSpec::GUID {
+            Some("UEFI Specification")
+        } else if guid == Ebbr21::GUID {
+            Some("EBBR 2.1")
+        } else if guid == Ebbr22::GUID {
+            Some("EBBR 2.2")
+        } else {
+            None
+        }
+    }
+}
+
+pub mod profile {
+    //! Known [`Profile`][super::Profile]s
+
+    use crate::GUID;
+
+    /// Conformance to the full UEFI specification
+    #[GUID("523C91AF-A195-4382-818D-295FE400640E", crate("crate"))]
+    #[derive(Debug)]
+    pub struct UefiSpec;
+
+    /// Conformance to Embedded Base Boot Requirements 2.1
+    #[GUID("CCE33C35-74AC-4087-BCE7-8B29B02EEB27", crate("crate"))]
+    #[derive(Debug)]
+    pub struct Ebbr21;
+
+    /// Conformance to Embedded Base Boot Requirements 2.2
+    #[GUID("9073EED4-E50D-11EE-B8B0-8B68DA62FCF3", crate("crate"))]
+    #[derive(Debug)]
+    pub struct Ebbr22;
+}
+
+/// Table of loaded images, for debuggers
+///
+/// See [`image`]
 #[GUID("49152E77-1ADA-4764-B7A2-7AFEFED95E8B", crate("crate"))]
 #[derive(Debug)]
 #[repr(C)]
@@ -348,6 +748,17 @@ pub struct DebugImageInfo {
     table: *mut c_void,
 }
 
+impl DebugImageInfo {
+    /// The debug image info table
+    pub fn images(&self) -> image::DebugImageTable<'_> {
+        // Safety: `table` is trusted to point to the table
+        unsafe { image::DebugImageTable::from_ptr(self.table) }
+    }
+}
+
+/// Table of image authentication results
+///
+/// See [`image`]
 #[GUID("D719B2CB-3D3A-4596-A3BC-DAD00E67656F", crate("crate"))]
 #[derive(Debug)]
 #[repr(C)]
@@ -355,6 +766,14 @@ pub struct ImageExecInfo {
     table: *mut c_void,
 }
 
+impl ImageExecInfo {
+    /// The image execution info table
+    pub fn images(&self) -> image::ImageExecTable<'_> {
+        // Safety: `table` is trusted to point to the table
+        unsafe { image::ImageExecTable::from_ptr(self.table) }
+    }
+}
+
 #[GUID("B122A263-3661-4F68-9929-78F8B0D62180", crate("crate"))]
 #[derive(Debug)]
 #[repr(C)]
@@ -423,7 +842,7 @@ pub struct MemoryStatus {
     table: *mut c_void,
 }
 
-impl<'tbl> ConfigTable<'tbl> for AcpiTable10 {
+unsafe impl<'tbl> ConfigTable<'tbl> for AcpiTable10 {
     type Out<'cfg> = Self where
         'tbl: 'cfg;
 
@@ -434,7 +853,7 @@ impl<'tbl> ConfigTable<'tbl> for AcpiTable10 {
     }
 }
 
-impl<'tbl> ConfigTable<'tbl> for AcpiTable20 {
+unsafe impl<'tbl> ConfigTable<'tbl> for AcpiTable20 {
     type Out<'cfg> = Self where
         'tbl: 'cfg;
 
@@ -445,7 +864,7 @@ impl<'tbl> ConfigTable<'tbl> for AcpiTable20 {
     }
 }
 
-impl<'tbl> ConfigTable<'tbl> for SMBIOS {
+unsafe impl<'tbl> ConfigTable<'tbl> for SMBIOS {
     type Out<'cfg> = Self where
         'tbl: 'cfg;
 
@@ -456,7 +875,7 @@ impl<'tbl> ConfigTable<'tbl> for SMBIOS {
     }
 }
 
-impl<'tbl> ConfigTable<'tbl> for SMBIOS3 {
+unsafe impl<'tbl> ConfigTable<'tbl> for SMBIOS3 {
     type Out<'cfg> = Self where
         'tbl: 'cfg;
 
@@ -467,7 +886,7 @@ impl<'tbl> ConfigTable<'tbl> for SMBIOS3 {
     }
 }
 
-impl<'tbl> ConfigTable<'tbl> for RuntimeProperties {
+unsafe impl<'tbl> ConfigTable<'tbl> for RuntimeProperties {
     type Out<'cfg> = Self where
         'tbl: 'cfg;
 
@@ -478,7 +897,7 @@ impl<'tbl> ConfigTable<'tbl> for RuntimeProperties {
     }
 }
 
-impl<'tbl> ConfigTable<'tbl> for JsonConfigData {
+unsafe impl<'tbl> ConfigTable<'tbl> for JsonConfigData {
     type Out<'cfg> = Self where
         'tbl: 'cfg;
 
@@ -489,7 +908,7 @@ impl<'tbl> ConfigTable<'tbl> for JsonConfigData {
     }
 }
 
-impl<'tbl> ConfigTable<'tbl> for JsonCapsuleData {
+unsafe impl<'tbl> ConfigTable<'tbl> for JsonCapsuleData {
     type Out<'cfg> = Self where
         'tbl: 'cfg;
 
@@ -500,7 +919,7 @@ impl<'tbl> ConfigTable<'tbl> for JsonCapsuleData {
     }
 }
 
-impl<'tbl> ConfigTable<'tbl> for JsonCapsuleResult {
+unsafe impl<'tbl> ConfigTable<'tbl> for JsonCapsuleResult {
     type Out<'cfg> = Self where
         'tbl: 'cfg;
 
@@ -511,7 +930,7 @@ impl<'tbl> ConfigTable<'tbl> for JsonCapsuleResult {
     }
 }
 
-impl<'tbl> ConfigTable<'tbl> for DeviceTree {
+unsafe impl<'tbl> ConfigTable<'tbl> for DeviceTree {
     type Out<'cfg> = Self where
         'tbl: 'cfg;
 
@@ -522,7 +941,7 @@ impl<'tbl> ConfigTable<'tbl> for DeviceTree {
     }
 }
 
-impl<'tbl> ConfigTable<'tbl> for MemoryAttributes {
+unsafe impl<'tbl> ConfigTable<'tbl> for MemoryAttributes {
     type Out<'cfg> = Self where
         'tbl: 'cfg;
 
@@ -533,22 +952,243 @@ impl<'tbl> ConfigTable<'tbl> for MemoryAttributes {
     }
 }
 
-#[cfg(no)]
-impl<'tbl> ConfigTable<'tbl> for ConformanceProfile {
-    type Out<'cfg> = Self  where
-    'tbl: 'cfg;
+unsafe impl<'tbl> ConfigTable<'tbl> for ConformanceProfile {
+    type Out<'cfg>
+        = Self
+    where
+        'tbl: 'cfg;
+
+    unsafe fn from_raw(raw: *const c_void) -> Self::Out<'tbl> {
+        Self {
+            table: raw.cast_mut(),
+        }
+    }
+}
+
+unsafe impl<'tbl> ConfigTable<'tbl> for DebugImageInfo {
+    type Out<'cfg>
+        = Self
+    where
+        'tbl: 'cfg;
+
+    unsafe fn from_raw(raw: *const c_void) -> Self::Out<'tbl> {
+        Self {
+            table: raw.cast_mut(),
+        }
+    }
+}
+
+unsafe impl<'tbl> ConfigTable<'tbl> for ImageExecInfo {
+    type Out<'cfg>
+        = Self
+    where
+        'tbl: 'cfg;
 
     unsafe fn from_raw(raw: *const c_void) -> Self::Out<'tbl> {
-        // let raw = &*raw.cast::<RawConformanceProfile>();
-        // let profiles = from_raw_parts(raw.profiles.cast::<Guid>(),
-        // raw.size.into()).to_vec();
-        ConformanceProfile {
-            ver: todo!(),
-            profiles: todo!(),
-            // inner: unsafe { &*raw },
-            // ver: raw.ver,
-            // profiles,
-            // phantom: PhantomData,
+        Self {
+            table: raw.cast_mut(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+    use core::ptr::{addr_of, addr_of_mut};
+
+    use super::*;
+    use crate::table::mem::MemoryType;
+
+    #[test]
+    fn memory_attributes() {
+        // Firmware descriptors may be larger than ours
+        let stride = size_of::<MemoryDescriptor>() + 8;
+        let words = stride / 8;
+        let code = MemoryDescriptor::new(
+            MemoryType::RUNTIME_CODE,
+            0x7000_0000,
+            0xFFFF_0000_0000,
+            4,
+            MemoryFlags::WB | MemoryFlags::RUNTIME,
+        );
+        let mmio = MemoryDescriptor::new(
+            MemoryType::MEMORY_MAPPED_IO,
+            0xFE00_0000,
+            0,
+            1,
+            MemoryFlags::UC | MemoryFlags::RUNTIME,
+        );
+        let conv = MemoryDescriptor::new(MemoryType::CONVENTIONAL, 0x1000, 0, 16, MemoryFlags::WB);
+
+        let mut buf = vec![0u64; words * 3];
+        for (i, d) in [conv, code, mmio].iter().enumerate() {
+            // Safety: In bounds of `buf`
+            unsafe {
+                buf.as_mut_ptr()
+                    .add(i * words)
+                    .cast::<MemoryDescriptor>()
+                    .write(*d)
+            };
+        }
+        let map = MemoryMap::new(buf, stride * 3, 0, stride, 1).unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.runtime().collect::<Vec<_>>(), [code, mmio]);
+
+        let text = MemoryDescriptor::new(
+            MemoryType::RUNTIME_CODE,
+            0x7000_1000,
+            0,
+            1,
+            MemoryFlags::RO | MemoryFlags::RUNTIME,
+        );
+        let data = MemoryDescriptor::new(
+            MemoryType::RUNTIME_DATA,
+            0x7000_2000,
+            0,
+            2,
+            MemoryFlags::XP | MemoryFlags::RUNTIME,
+        );
+        // Not within any runtime entry
+        let stray = MemoryDescriptor::new(
+            MemoryType::RUNTIME_DATA,
+            0x1000,
+            0,
+            1,
+            MemoryFlags::XP | MemoryFlags::RUNTIME,
+        );
+
+        let mut table = vec![0u64; 2 + words * 3];
+        table[0] = 2 | (3 << 32);
+        table[1] = stride as u64 | (1 << 32);
+        for (i, d) in [text, data, stray].iter().enumerate() {
+            // Safety: In bounds of `table`
+            unsafe {
+                table
+                    .as_mut_ptr()
+                    .add(2 + i * words)
+                    .cast::<MemoryDescriptor>()
+                    .write(*d)
+            };
+        }
+        // Safety: Valid table
+        let attrs = unsafe { MemoryAttributes::from_raw(table.as_ptr().cast()) };
+        assert_eq!(attrs.version(), 2);
+        assert_eq!(attrs.len(), 3);
+        assert_eq!(
+            attrs.flags(),
+            MemoryAttributes::RT_FORWARD_CONTROL_FLOW_GUARD
+        );
+        assert_eq!(
+            attrs.entries().unwrap().collect::<Vec<_>>(),
+            [text, data, stray]
+        );
+
+        let regions: Vec<_> = attrs.runtime_regions(&map).collect();
+        assert_eq!(
+            regions,
+            [
+                MemoryDescriptor::new(
+                    MemoryType::RUNTIME_CODE,
+                    0x7000_1000,
+                    0xFFFF_0000_1000,
+                    1,
+                    MemoryFlags::WB | MemoryFlags::RO | MemoryFlags::RUNTIME,
+                ),
+                MemoryDescriptor::new(
+                    MemoryType::RUNTIME_DATA,
+                    0x7000_2000,
+                    0xFFFF_0000_2000,
+                    2,
+                    MemoryFlags::WB | MemoryFlags::XP | MemoryFlags::RUNTIME,
+                ),
+            ]
+        );
+
+        // Descriptors smaller than `MemoryDescriptor` are rejected
+        table[1] = 8 | (1 << 32);
+        // Safety: Valid table
+        let attrs = unsafe { MemoryAttributes::from_raw(table.as_ptr().cast()) };
+        assert!(attrs.entries().is_none());
+        assert_eq!(attrs.runtime_regions(&map).count(), 0);
+
+        // Malformed descriptors don't overflow
+        let huge = MemoryDescriptor::new(
+            MemoryType::RUNTIME_DATA,
+            u64::MAX - 0xFFF,
+            0,
+            u64::MAX,
+            MemoryFlags::RUNTIME,
+        );
+        assert_eq!(huge.size(), u64::MAX);
+        assert_eq!(huge.physical_end(), u64::MAX);
+    }
+
+    #[test]
+    fn conformance_profile() {
+        let mut table = vec![1u8, 0, 3, 0];
+        table.extend(profile::Ebbr22::GUID.to_bytes());
+        table.extend(profile::UefiSpec::GUID.to_bytes());
+        table.extend([0xAA; 16]);
+        // Safety: Valid table
+        let conf = unsafe { ConformanceProfile::from_raw(table.as_ptr().cast()) };
+        assert_eq!(conf.version(), 1);
+        let names: Vec<_> = conf.profiles().map(|p| p.name()).collect();
+        assert_eq!(names, [Some("EBBR 2.2"), Some("UEFI Specification"), None]);
+
+        let table = [1u16, 8, 0x0451, 0x0000];
+        // Safety: Valid table
+        let props = unsafe { RuntimeProperties::from_raw(table.as_ptr().cast()) };
+        assert_eq!(
+            props.supported(),
+            RuntimeServicesSupported::GET_TIME
+                | RuntimeServicesSupported::GET_VARIABLE
+                | RuntimeServicesSupported::SET_VARIABLE
+                | RuntimeServicesSupported::RESET_SYSTEM
+        );
+    }
+
+    #[test]
+    fn user_table() {
+        #[GUID("A46423E3-4617-49F1-B9FF-D1BFA9115839", crate("crate"))]
+        struct VendorTable<'tbl> {
+            version: &'tbl u32,
+        }
+
+        unsafe impl<'tbl> ConfigTable<'tbl> for VendorTable<'tbl> {
+            type Out<'cfg>
+                = Self
+            where
+                'tbl: 'cfg;
+
+            unsafe fn from_raw(raw: *const c_void) -> Self::Out<'tbl> {
+                Self {
+                    version: &*raw.cast(),
+                }
+            }
+        }
+
+        let mut version = 7u32;
+        let table = GenericConfig::new(ConfigurationTable {
+            guid: VendorTable::GUID,
+            table: addr_of_mut!(version).cast(),
+        });
+        assert_eq!(table.name(), None);
+        static VENDOR_NAME: TableName = TableName::new::<VendorTable>();
+        register_name(&VENDOR_NAME);
+        register_name(&VENDOR_NAME);
+        assert_eq!(table.name(), Some("VendorTable"));
+        assert!(table.as_table::<SMBIOS>().is_none());
+        assert_eq!(table.as_table::<VendorTable>().map(|t| *t.version), Some(7));
+
+        let table = GenericConfig::new(ConfigurationTable {
+            guid: SMBIOS::GUID,
+            table: addr_of_mut!(version).cast(),
+        });
+        assert_eq!(table.name(), Some("SMBIOS"));
+
+        // Registering twice must not link the name to itself
+        let head = USER_TABLES.load(Ordering::Acquire);
+        assert_eq!(head, addr_of!(VENDOR_NAME).cast_mut());
+        assert!(VENDOR_NAME.next.load(Ordering::Acquire).is_null());
+    }
+}
@@ -0,0 +1,1026 @@
+//! Flattened Device Tree access
+//!
+//! The [`DeviceTree`][super::DeviceTree] configuration table points to a
+//! Flattened Device Tree blob, or DTB.
+//!
+//! [`Fdt`] is a zero-copy reader over an existing blob,
+//! and [`OwnedFdt`] is an owned, editable tree that can be serialized
+//! into a new blob, for example to update `/chosen` before reinstalling the
+//! table.
+//!
+//! # References
+//!
+//! - [Devicetree Specification v0.4][dt]
+//! - [Linux `/chosen` bindings][chosen]
+//!
+//! [dt]: <https://github.com/devicetree-org/devicetree-specification/releases/tag/v0.4>
+//! [chosen]: <https://www.kernel.org/doc/Documentation/devicetree/bindings/chosen.txt>
+use alloc::{string::String, vec::Vec};
+use core::{ffi::c_void, slice::from_raw_parts, str::from_utf8};
+
+use crate::{base::Status, error::Result};
+
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
+
+/// Size of the version 17 header
+const HEADER_SIZE: usize = 40;
+
+/// Size of the version 16 header, which lacks `size_dt_struct`
+const HEADER_SIZE_V16: usize = 36;
+
+/// Default `#address-cells` when a node doesn't specify it
+const DEFAULT_ADDRESS_CELLS: u32 = 2;
+
+/// Default `#size-cells` when a node doesn't specify it
+const DEFAULT_SIZE_CELLS: u32 = 1;
+
+/// A memory region, from a `reg` property or the memory reservation block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    /// Physical start address
+    pub address: u64,
+
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// The `#address-cells` and `#size-cells` used to decode a `reg` property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cells {
+    pub address: u32,
+    pub size: u32,
+}
+
+impl Default for Cells {
+    fn default() -> Self {
+        Self {
+            address: DEFAULT_ADDRESS_CELLS,
+            size: DEFAULT_SIZE_CELLS,
+        }
+    }
+}
+
+/// A zero-copy Flattened Device Tree reader
+#[derive(Debug, Clone, Copy)]
+pub struct Fdt<'fdt> {
+    /// The whole blob, `totalsize` bytes
+    data: &'fdt [u8],
+
+    /// The structure block
+    structs: &'fdt [u8],
+
+    /// The strings block
+    strings: &'fdt [u8],
+}
+
+impl<'fdt> Fdt<'fdt> {
+    /// Magic value at the start of every blob
+    pub const MAGIC: u32 = 0xD00D_FEED;
+
+    /// Version of the blobs we produce
+    pub const VERSION: u32 = 17;
+
+    /// Oldest version we can read
+    pub const LAST_COMP_VERSION: u32 = 16;
+
+    /// Parse and validate the header of `data`
+    ///
+    /// `data` may be larger than the blob.
+    pub fn from_bytes(data: &'fdt [u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE_V16 {
+            return Err(Status::BUFFER_TOO_SMALL.into());
+        }
+        if be_u32(data, 0) != Some(Self::MAGIC) {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let field = |n: usize| be_u32(data, n * 4).unwrap_or_default() as usize;
+        let (total, off_struct, off_strings, off_rsv) = (field(1), field(2), field(3), field(4));
+        let (version, last_comp) = (field(5) as u32, field(6) as u32);
+        let size_strings = field(8);
+
+        if version < Self::LAST_COMP_VERSION || last_comp > Self::VERSION {
+            return Err(Status::INCOMPATIBLE_VERSION.into());
+        }
+        // Version 16 has no `size_dt_struct`
+        let (header_size, size_struct) = if version < 17 {
+            (HEADER_SIZE_V16, None)
+        } else {
+            (HEADER_SIZE, Some(field(9)))
+        };
+        if total > data.len() || data.len() < header_size {
+            return Err(Status::BUFFER_TOO_SMALL.into());
+        }
+        if total < header_size || off_struct % 4 != 0 || off_rsv % 8 != 0 {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let data = &data[..total];
+        let structs = match size_struct {
+            Some(size) => off_struct
+                .checked_add(size)
+                .and_then(|end| data.get(off_struct..end)),
+            // Found by scanning to `FDT_END` below
+            None => data.get(off_struct..),
+        };
+        let structs = structs.ok_or(Status::INVALID_PARAMETER)?;
+        let strings = off_strings
+            .checked_add(size_strings)
+            .and_then(|end| data.get(off_strings..end))
+            .ok_or(Status::INVALID_PARAMETER)?;
+        if off_rsv < header_size || off_rsv > total {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+
+        let mut fdt = Self {
+            data,
+            structs,
+            strings,
+        };
+        if fdt.node_at(0, Cells::default()).is_none() {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        if size_struct.is_none() {
+            let len = fdt.structs_len().ok_or(Status::INVALID_PARAMETER)?;
+            fdt.structs = &structs[..len];
+        }
+        Ok(fdt)
+    }
+
+    /// Create from a pointer to a blob
+    ///
+    /// The size is taken from the blob header
+    ///
+    /// # Safety
+    ///
+    /// - `ptr` must point to a Flattened Device Tree blob, valid for `'fdt`
+    pub unsafe fn from_ptr(ptr: *const c_void) -> Result<Self> {
+        if ptr.is_null() {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let header = from_raw_parts(ptr as *const u8, HEADER_SIZE_V16);
+        if be_u32(header, 0) != Some(Self::MAGIC) {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let total = be_u32(header, 4).unwrap_or_default() as usize;
+        Self::from_bytes(from_raw_parts(ptr as *const u8, total.max(HEADER_SIZE_V16)))
+    }
+
+    /// The blob, `totalsize` bytes
+    pub fn as_bytes(&self) -> &'fdt [u8] {
+        self.data
+    }
+
+    /// Size of the blob in bytes
+    pub fn total_size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Blob version
+    pub fn version(&self) -> u32 {
+        self.header(5)
+    }
+
+    /// Oldest version this blob is compatible with
+    pub fn last_comp_version(&self) -> u32 {
+        self.header(6)
+    }
+
+    /// Physical ID of the boot CPU
+    pub fn boot_cpuid_phys(&self) -> u32 {
+        self.header(7)
+    }
+
+    /// Iterator over the memory reservation block
+    pub fn reservations(&self) -> impl Iterator<Item = Region> + 'fdt {
+        let data = self.data;
+        let mut off = self.header(4) as usize;
+        core::iter::from_fn(move || {
+            let address = be_u64(data, off)?;
+            let size = be_u64(data, off + 8)?;
+            if address == 0 && size == 0 {
+                return None;
+            }
+            off += 16;
+            Some(Region { address, size })
+        })
+    }
+
+    /// The root node
+    pub fn root(&self) -> Node<'fdt> {
+        // Validated in `from_bytes`, and the root name is always empty
+        self.node_at(0, Cells::default())
+            .expect("validated FDT root node was invalid")
+    }
+
+    /// Find a node by its absolute `path`, such as `/chosen`
+    ///
+    /// Path components without a unit address match any unit address,
+    /// so `/memory` matches `/memory@80000000`.
+    pub fn find(&self, path: &str) -> Option<Node<'fdt>> {
+        let mut node = self.root();
+        for part in path.split('/').filter(|p| !p.is_empty()) {
+            node = node.child(part)?;
+        }
+        Some(node)
+    }
+
+    /// The `/chosen` node
+    pub fn chosen(&self) -> Option<Chosen<'fdt>> {
+        self.find("/chosen").map(Chosen)
+    }
+
+    /// Iterator over all regions of all `/memory` nodes
+    ///
+    /// Memory nodes are children of the root with a `device_type` of
+    /// `memory`.
+    pub fn memory(&self) -> impl Iterator<Item = Region> + 'fdt {
+        self.root()
+            .children()
+            .filter(|n| {
+                n.property("device_type")
+                    .and_then(|p| p.as_str())
+                    .map_or(n.unit_name() == "memory", |t| t == "memory")
+            })
+            .flat_map(|n| n.reg())
+    }
+
+    /// Iterator over the children of `/reserved-memory`
+    pub fn reserved_memory(&self) -> impl Iterator<Item = ReservedMemory<'fdt>> + 'fdt {
+        self.find("/reserved-memory")
+            .into_iter()
+            .flat_map(|n| n.children())
+            .map(ReservedMemory)
+    }
+
+    fn header(&self, field: usize) -> u32 {
+        be_u32(self.data, field * 4).unwrap_or_default()
+    }
+
+    /// String at `off` in the strings block
+    fn string(&self, off: usize) -> Option<&'fdt str> {
+        cstr(self.strings.get(off..)?)
+    }
+
+    /// Node whose `FDT_BEGIN_NODE` token is at `off`
+    fn node_at(&self, off: usize, cells: Cells) -> Option<Node<'fdt>> {
+        if be_u32(self.structs, off)? != FDT_BEGIN_NODE {
+            return None;
+        }
+        let name = cstr(self.structs.get(off + 4..)?)?;
+        Some(Node {
+            fdt: *self,
+            name,
+            props: align4(off + 4 + name.len() + 1),
+            cells,
+        })
+    }
+
+    /// Token at `off`, and offset of the next token
+    ///
+    /// `FDT_NOP` is skipped. Properties and nodes are returned with
+    /// their payload offset, and skipped over.
+    fn token(&self, mut off: usize) -> Option<(Token<'fdt>, usize)> {
+        loop {
+            let tok = be_u32(self.structs, off)?;
+            return match tok {
+                FDT_NOP => {
+                    off += 4;
+                    continue;
+                }
+                FDT_BEGIN_NODE => Some((Token::Begin(off), off)),
+                FDT_END_NODE => Some((Token::End, off + 4)),
+                FDT_END => Some((Token::Finish, off)),
+                FDT_PROP => {
+                    let len = be_u32(self.structs, off + 4)? as usize;
+                    let name = self.string(be_u32(self.structs, off + 8)? as usize)?;
+                    let start = off + 12;
+                    let value = self.structs.get(start..start.checked_add(len)?)?;
+                    Some((Token::Prop(Property { name, value }), align4(start + len)))
+                }
+                _ => None,
+            };
+        }
+    }
+
+    /// Length of the structure block, up to and including `FDT_END`
+    fn structs_len(&self) -> Option<usize> {
+        let end = self.skip_node(0)?;
+        match self.token(end)? {
+            (Token::Finish, at) => Some(at + 4),
+            _ => None,
+        }
+    }
+
+    /// Skip the node whose `FDT_BEGIN_NODE` is at `off`,
+    /// returning the offset after its `FDT_END_NODE`
+    fn skip_node(&self, off: usize) -> Option<usize> {
+        let mut depth = 0usize;
+        let mut off = off;
+        loop {
+            match self.token(off)? {
+                (Token::Begin(at), _) => {
+                    let name = cstr(self.structs.get(at + 4..)?)?;
+                    depth += 1;
+                    off = align4(at + 4 + name.len() + 1);
+                }
+                // `FDT_END` before the node was closed
+                (Token::Finish, _) => return None,
+                (Token::End, next) => {
+                    depth -= 1;
+                    off = next;
+                    if depth == 0 {
+                        return Some(off);
+                    }
+                }
+                (Token::Prop(_), next) => off = next,
+            }
+        }
+    }
+}
+
+enum Token<'fdt> {
+    /// `FDT_BEGIN_NODE` at this offset
+    Begin(usize),
+
+    /// `FDT_END_NODE`
+    End,
+
+    /// `FDT_END`
+    Finish,
+
+    Prop(Property<'fdt>),
+}
+
+/// A node in an [`Fdt`]
+#[derive(Debug, Clone, Copy)]
+pub struct Node<'fdt> {
+    fdt: Fdt<'fdt>,
+    name: &'fdt str,
+
+    /// Offset of our first property token
+    props: usize,
+
+    /// Our parents address and size cells
+    cells: Cells,
+}
+
+impl<'fdt> Node<'fdt> {
+    /// Full node name, `node-name@unit-address`
+    pub fn name(&self) -> &'fdt str {
+        self.name
+    }
+
+    /// Node name without the unit address
+    pub fn unit_name(&self) -> &'fdt str {
+        self.name.split('@').next().unwrap_or_default()
+    }
+
+    /// Unit address, if any
+    pub fn unit_address(&self) -> Option<&'fdt str> {
+        self.name.split_once('@').map(|(_, a)| a)
+    }
+
+    /// Iterator over our properties
+    pub fn properties(&self) -> impl Iterator<Item = Property<'fdt>> + 'fdt {
+        let fdt = self.fdt;
+        let mut off = self.props;
+        core::iter::from_fn(move || match fdt.token(off)? {
+            (Token::Prop(p), next) => {
+                off = next;
+                Some(p)
+            }
+            _ => None,
+        })
+    }
+
+    /// Property called `name`
+    pub fn property(&self, name: &str) -> Option<Property<'fdt>> {
+        self.properties().find(|p| p.name == name)
+    }
+
+    /// Iterator over our child nodes
+    pub fn children(&self) -> impl Iterator<Item = Node<'fdt>> + 'fdt {
+        let fdt = self.fdt;
+        let cells = self.child_cells();
+        let mut off = self.props;
+        core::iter::from_fn(move || loop {
+            match fdt.token(off)? {
+                (Token::Prop(_), next) => off = next,
+                (Token::Begin(at), _) => {
+                    let node = fdt.node_at(at, cells)?;
+                    off = fdt.skip_node(at)?;
+                    return Some(node);
+                }
+                (Token::End | Token::Finish, _) => return None,
+            }
+        })
+    }
+
+    /// Child called `name`
+    ///
+    /// If `name` has no unit address, it matches any unit address.
+    pub fn child(&self, name: &str) -> Option<Node<'fdt>> {
+        self.children().find(|c| {
+            if name.contains('@') {
+                c.name == name
+            } else {
+                c.unit_name() == name
+            }
+        })
+    }
+
+    /// The cells our parent uses to describe our `reg` property
+    pub fn cells(&self) -> Cells {
+        self.cells
+    }
+
+    /// The cells our children use to describe their `reg` property
+    pub fn child_cells(&self) -> Cells {
+        let default = Cells::default();
+        Cells {
+            address: self
+                .property("#address-cells")
+                .and_then(|p| p.as_u32())
+                .unwrap_or(default.address),
+            size: self
+                .property("#size-cells")
+                .and_then(|p| p.as_u32())
+                .unwrap_or(default.size),
+        }
+    }
+
+    /// Iterator over the regions in our `reg` property
+    ///
+    /// Entries wider than 64-bits are skipped.
+    /// A `#size-cells` of 0, such as under i2c and spi buses,
+    /// gives regions with a size of 0.
+    pub fn reg(&self) -> impl Iterator<Item = Region> + 'fdt {
+        let cells = self.cells;
+        let value = self.property("reg").map(|p| p.value).unwrap_or_default();
+        // Only 1 or 2 cells are meaningful, anything else yields nothing.
+        let (addr, size) = (
+            cells.address.min(3) as usize * 4,
+            cells.size.min(3) as usize * 4,
+        );
+        let chunks = value.chunks_exact((addr + size).max(4));
+        chunks.filter_map(move |c| {
+            let (a, s) = c.get(..addr).zip(c.get(addr..))?;
+            Some(Region {
+                address: be_cells(a)?,
+                size: if size == 0 { 0 } else { be_cells(s)? },
+            })
+        })
+    }
+}
+
+/// A property of a [`Node`]
+#[derive(Debug, Clone, Copy)]
+pub struct Property<'fdt> {
+    name: &'fdt str,
+    value: &'fdt [u8],
+}
+
+impl<'fdt> Property<'fdt> {
+    /// Property name
+    pub fn name(&self) -> &'fdt str {
+        self.name
+    }
+
+    /// Raw property value
+    pub fn value(&self) -> &'fdt [u8] {
+        self.value
+    }
+
+    /// Value as a single nul terminated string
+    pub fn as_str(&self) -> Option<&'fdt str> {
+        match self.value.split_last() {
+            Some((0, s)) => from_utf8(s).ok(),
+            _ => None,
+        }
+    }
+
+    /// Value as a list of nul terminated strings
+    pub fn strings(&self) -> impl Iterator<Item = &'fdt str> + 'fdt {
+        let value = match self.value.split_last() {
+            Some((0, s)) => s,
+            _ => &[],
+        };
+        value
+            .split(|b| *b == 0)
+            .filter(move |_| !value.is_empty())
+            .filter_map(|s| from_utf8(s).ok())
+    }
+
+    /// Value as a single big-endian `u32`
+    pub fn as_u32(&self) -> Option<u32> {
+        self.value.try_into().ok().map(u32::from_be_bytes)
+    }
+
+    /// Value as a single big-endian `u64`
+    pub fn as_u64(&self) -> Option<u64> {
+        self.value.try_into().ok().map(u64::from_be_bytes)
+    }
+
+    /// Value as a single `u32` or `u64`
+    pub fn as_cells(&self) -> Option<u64> {
+        be_cells(self.value)
+    }
+}
+
+/// The `/chosen` node
+#[derive(Debug, Clone, Copy)]
+pub struct Chosen<'fdt>(Node<'fdt>);
+
+impl<'fdt> Chosen<'fdt> {
+    /// The underlying [`Node`]
+    pub fn node(&self) -> Node<'fdt> {
+        self.0
+    }
+
+    /// Kernel command line, `bootargs`
+    pub fn bootargs(&self) -> Option<&'fdt str> {
+        self.0.property("bootargs").and_then(|p| p.as_str())
+    }
+
+    /// Path to the console device, `stdout-path`
+    pub fn stdout_path(&self) -> Option<&'fdt str> {
+        self.0.property("stdout-path").and_then(|p| p.as_str())
+    }
+
+    /// Initrd location, from `linux,initrd-start` to `linux,initrd-end`
+    ///
+    /// Either property may be 32 or 64-bits.
+    pub fn initrd(&self) -> Option<(u64, u64)> {
+        let start = self.0.property("linux,initrd-start")?.as_cells()?;
+        let end = self.0.property("linux,initrd-end")?.as_cells()?;
+        Some((start, end))
+    }
+}
+
+/// A child of the `/reserved-memory` node
+#[derive(Debug, Clone, Copy)]
+pub struct ReservedMemory<'fdt>(Node<'fdt>);
+
+impl<'fdt> ReservedMemory<'fdt> {
+    /// The underlying [`Node`]
+    pub fn node(&self) -> Node<'fdt> {
+        self.0
+    }
+
+    /// Node name
+    pub fn name(&self) -> &'fdt str {
+        self.0.name()
+    }
+
+    /// Statically reserved regions, `reg`
+    ///
+    /// Dynamically allocated reservations have no regions
+    pub fn regions(&self) -> impl Iterator<Item = Region> + 'fdt {
+        self.0.reg()
+    }
+
+    /// Whether the OS must not map this region, `no-map`
+    pub fn no_map(&self) -> bool {
+        self.0.property("no-map").is_some()
+    }
+
+    /// Whether the OS may use this region, `reusable`
+    pub fn reusable(&self) -> bool {
+        self.0.property("reusable").is_some()
+    }
+
+    /// Size of a dynamic reservation, `size`
+    pub fn size(&self) -> Option<u64> {
+        self.0.property("size").and_then(|p| p.as_cells())
+    }
+}
+
+/// An owned, editable, device tree node
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OwnedNode {
+    name: String,
+    props: Vec<(String, Vec<u8>)>,
+    children: Vec<OwnedNode>,
+}
+
+impl OwnedNode {
+    /// Create a new empty node called `name`
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Copy `node` and all its children
+    pub fn from_node(node: &Node) -> Self {
+        Self {
+            name: node.name().into(),
+            props: node
+                .properties()
+                .map(|p| (p.name().into(), p.value().into()))
+                .collect(),
+            children: node.children().map(|c| Self::from_node(&c)).collect(),
+        }
+    }
+
+    /// Full node name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Property called `name`
+    pub fn property(&self, name: &str) -> Option<&[u8]> {
+        self.props
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| &v[..])
+    }
+
+    /// Add or replace the property `name`
+    pub fn set_property(&mut self, name: &str, value: &[u8]) {
+        match self.props.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value.into(),
+            None => self.props.push((name.into(), value.into())),
+        }
+    }
+
+    /// Add or replace the string property `name`
+    pub fn set_str(&mut self, name: &str, value: &str) {
+        let mut v = Vec::with_capacity(value.len() + 1);
+        v.extend_from_slice(value.as_bytes());
+        v.push(0);
+        self.set_property(name, &v);
+    }
+
+    /// Add or replace the `u32` property `name`
+    pub fn set_u32(&mut self, name: &str, value: u32) {
+        self.set_property(name, &value.to_be_bytes());
+    }
+
+    /// Add or replace the `u64` property `name`
+    pub fn set_u64(&mut self, name: &str, value: u64) {
+        self.set_property(name, &value.to_be_bytes());
+    }
+
+    /// Remove the property `name`, returning its value
+    pub fn remove_property(&mut self, name: &str) -> Option<Vec<u8>> {
+        let i = self.props.iter().position(|(n, _)| n == name)?;
+        Some(self.props.remove(i).1)
+    }
+
+    /// Child called exactly `name`
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut OwnedNode> {
+        self.children.iter_mut().find(|c| c.name == name)
+    }
+
+    /// Child called exactly `name`, created if it doesn't exist
+    pub fn child_or_insert(&mut self, name: &str) -> &mut OwnedNode {
+        match self.children.iter().position(|c| c.name == name) {
+            Some(i) => &mut self.children[i],
+            None => {
+                self.children.push(Self::new(name));
+                self.children.last_mut().unwrap()
+            }
+        }
+    }
+}
+
+/// An owned, editable, Flattened Device Tree
+///
+/// Serialize it with [`OwnedFdt::to_bytes`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OwnedFdt {
+    /// Memory reservation block
+    pub reservations: Vec<Region>,
+
+    /// Physical ID of the boot CPU
+    pub boot_cpuid_phys: u32,
+
+    /// The root node
+    pub root: OwnedNode,
+}
+
+impl OwnedFdt {
+    /// Copy `fdt`
+    pub fn from_fdt(fdt: &Fdt) -> Self {
+        Self {
+            reservations: fdt.reservations().collect(),
+            boot_cpuid_phys: fdt.boot_cpuid_phys(),
+            root: OwnedNode::from_node(&fdt.root()),
+        }
+    }
+
+    /// Find a node by its exact absolute `path`
+    pub fn find_mut(&mut self, path: &str) -> Option<&mut OwnedNode> {
+        let mut node = &mut self.root;
+        for part in path.split('/').filter(|p| !p.is_empty()) {
+            node = node.child_mut(part)?;
+        }
+        Some(node)
+    }
+
+    /// The `/chosen` node, created if it doesn't exist
+    pub fn chosen_mut(&mut self) -> &mut OwnedNode {
+        self.root.child_or_insert("chosen")
+    }
+
+    /// Set the kernel command line, `/chosen/bootargs`
+    pub fn set_bootargs(&mut self, args: &str) {
+        self.chosen_mut().set_str("bootargs", args);
+    }
+
+    /// Set the initrd location, `/chosen/linux,initrd-start` and
+    /// `/chosen/linux,initrd-end`
+    ///
+    /// `end` is exclusive
+    pub fn set_initrd(&mut self, start: u64, end: u64) {
+        let chosen = self.chosen_mut();
+        chosen.set_u64("linux,initrd-start", start);
+        chosen.set_u64("linux,initrd-end", end);
+    }
+
+    /// Serialize into a version 17 blob
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut structs = Vec::new();
+        let mut strings = Vec::new();
+        write_node(&self.root, &mut structs, &mut strings);
+        push_u32(&mut structs, FDT_END);
+
+        let off_rsv = HEADER_SIZE;
+        let off_struct = off_rsv + (self.reservations.len() + 1) * 16;
+        let off_strings = off_struct + structs.len();
+        let total = off_strings + strings.len();
+
+        let mut out = Vec::with_capacity(total);
+        for v in [
+            Fdt::MAGIC,
+            total as u32,
+            off_struct as u32,
+            off_strings as u32,
+            off_rsv as u32,
+            Fdt::VERSION,
+            Fdt::LAST_COMP_VERSION,
+            self.boot_cpuid_phys,
+            strings.len() as u32,
+            structs.len() as u32,
+        ] {
+            push_u32(&mut out, v);
+        }
+        for r in self.reservations.iter().chain([&Region {
+            address: 0,
+            size: 0,
+        }]) {
+            out.extend_from_slice(&r.address.to_be_bytes());
+            out.extend_from_slice(&r.size.to_be_bytes());
+        }
+        out.extend_from_slice(&structs);
+        out.extend_from_slice(&strings);
+        out
+    }
+}
+
+fn write_node(node: &OwnedNode, out: &mut Vec<u8>, strings: &mut Vec<u8>) {
+    push_u32(out, FDT_BEGIN_NODE);
+    out.extend_from_slice(node.name.as_bytes());
+    out.push(0);
+    pad4(out);
+    for (name, value) in &node.props {
+        push_u32(out, FDT_PROP);
+        push_u32(out, value.len() as u32);
+        push_u32(out, string_offset(strings, name) as u32);
+        out.extend_from_slice(value);
+        pad4(out);
+    }
+    for child in &node.children {
+        write_node(child, out, strings);
+    }
+    push_u32(out, FDT_END_NODE);
+}
+
+/// Offset of `name` in `strings`, adding it if needed
+fn string_offset(strings: &mut Vec<u8>, name: &str) -> usize {
+    let mut off = 0;
+    for s in strings.split(|b| *b == 0) {
+        if s == name.as_bytes() && off < strings.len() {
+            return off;
+        }
+        off += s.len() + 1;
+    }
+    let off = strings.len();
+    strings.extend_from_slice(name.as_bytes());
+    strings.push(0);
+    off
+}
+
+fn push_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn pad4(out: &mut Vec<u8>) {
+    out.resize(align4(out.len()), 0);
+}
+
+fn align4(off: usize) -> usize {
+    (off + 3) & !3
+}
+
+fn be_u32(b: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(b.get(off..off + 4)?.try_into().ok()?))
+}
+
+fn be_u64(b: &[u8], off: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(b.get(off..off + 8)?.try_into().ok()?))
+}
+
+/// One or two big-endian cells
+fn be_cells(b: &[u8]) -> Option<u64> {
+    match b.len() {
+        4 => be_u32(b, 0).map(u64::from),
+        8 => be_u64(b, 0),
+        _ => None,
+    }
+}
+
+/// Nul terminated UTF-8 string at the start of `b`
+fn cstr(b: &[u8]) -> Option<&str> {
+    let len = b.iter().position(|b| *b == 0)?;
+    from_utf8(&b[..len]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> OwnedFdt {
+        let mut fdt = OwnedFdt {
+            reservations: alloc::vec![Region {
+                address: 0x4000_0000,
+                size: 0x1000,
+            }],
+            ..Default::default()
+        };
+        fdt.root.set_u32("#address-cells", 2);
+        fdt.root.set_u32("#size-cells", 2);
+        fdt.root.set_str("compatible", "test,board\0test,soc");
+
+        let mem = fdt.root.child_or_insert("memory@80000000");
+        mem.set_str("device_type", "memory");
+        let mut reg = Vec::new();
+        for v in [0x8000_0000u64, 0x4000_0000, 0x1_0000_0000, 0x2000_0000] {
+            reg.extend_from_slice(&v.to_be_bytes());
+        }
+        mem.set_property("reg", &reg);
+
+        let rsv = fdt.root.child_or_insert("reserved-memory");
+        rsv.set_u32("#address-cells", 1);
+        rsv.set_u32("#size-cells", 1);
+        let fw = rsv.child_or_insert("firmware@9f000000");
+        fw.set_property("reg", &[0x9F, 0, 0, 0, 0, 0x10, 0, 0]);
+        fw.set_property("no-map", &[]);
+        let cma = rsv.child_or_insert("linux,cma");
+        cma.set_property("size", &0x0400_0000u32.to_be_bytes());
+        cma.set_property("reusable", &[]);
+        fdt
+    }
+
+    #[test]
+    fn version_16() -> Result<()> {
+        let v17 = tree().to_bytes();
+        let mut blob = v17.clone();
+        blob[20..28].copy_from_slice(&[0, 0, 0, 16, 0, 0, 0, 16]);
+        // Version 16 headers end before `size_dt_struct`
+        blob[36..40].copy_from_slice(&[0xFF; 4]);
+
+        let fdt = Fdt::from_bytes(&blob)?;
+        let fdt17 = Fdt::from_bytes(&v17)?;
+        assert_eq!(fdt.version(), 16);
+        assert_eq!(fdt.structs, fdt17.structs);
+        assert_eq!(fdt.memory().count(), 2);
+        assert_eq!(fdt.reserved_memory().count(), 2);
+
+        // Without `FDT_END`
+        let end = be_u32(&blob, 8).unwrap() as usize + fdt.structs.len();
+        blob[end - 4..end].copy_from_slice(&FDT_NOP.to_be_bytes());
+        let e = Fdt::from_bytes(&blob).unwrap_err();
+        assert_eq!(e.status(), Status::INVALID_PARAMETER);
+        Ok(())
+    }
+
+    #[test]
+    fn read() -> Result<()> {
+        let blob = tree().to_bytes();
+        let fdt = Fdt::from_bytes(&blob)?;
+        assert_eq!(fdt.total_size(), blob.len());
+        assert_eq!(fdt.version(), 17);
+        assert_eq!(
+            fdt.reservations().collect::<Vec<_>>(),
+            [Region {
+                address: 0x4000_0000,
+                size: 0x1000
+            }]
+        );
+
+        let root = fdt.root();
+        assert_eq!(root.name(), "");
+        let compat: Vec<_> = root.property("compatible").unwrap().strings().collect();
+        assert_eq!(compat, ["test,board", "test,soc"]);
+        assert!(fdt.chosen().is_none());
+
+        let mem = fdt.find("/memory").unwrap();
+        assert_eq!(mem.unit_address(), Some("80000000"));
+        assert_eq!(
+            fdt.memory().collect::<Vec<_>>(),
+            [
+                Region {
+                    address: 0x8000_0000,
+                    size: 0x4000_0000
+                },
+                Region {
+                    address: 0x1_0000_0000,
+                    size: 0x2000_0000
+                }
+            ]
+        );
+
+        let rsv: Vec<_> = fdt.reserved_memory().collect();
+        assert_eq!(rsv.len(), 2);
+        assert_eq!(rsv[0].name(), "firmware@9f000000");
+        assert!(rsv[0].no_map());
+        assert_eq!(
+            rsv[0].regions().collect::<Vec<_>>(),
+            [Region {
+                address: 0x9F00_0000,
+                size: 0x10_0000
+            }]
+        );
+        assert!(rsv[1].reusable());
+        assert_eq!(rsv[1].size(), Some(0x0400_0000));
+        assert_eq!(rsv[1].regions().count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn size_cells_zero() -> Result<()> {
+        let mut owned = tree();
+        let i2c = owned.root.child_or_insert("i2c@1000");
+        i2c.set_u32("#address-cells", 1);
+        i2c.set_u32("#size-cells", 0);
+        let eeprom = i2c.child_or_insert("eeprom@50");
+        eeprom.set_property("reg", &[0, 0, 0, 0x50, 0, 0, 0, 0x51]);
+        let blob = owned.to_bytes();
+        let fdt = Fdt::from_bytes(&blob)?;
+
+        let eeprom = fdt.find("/i2c@1000/eeprom@50").unwrap();
+        assert_eq!(
+            eeprom.reg().collect::<Vec<_>>(),
+            [
+                Region {
+                    address: 0x50,
+                    size: 0
+                },
+                Region {
+                    address: 0x51,
+                    size: 0
+                }
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn chosen() -> Result<()> {
+        let blob = tree().to_bytes();
+        let mut owned = OwnedFdt::from_fdt(&Fdt::from_bytes(&blob)?);
+        assert_eq!(owned, tree());
+
+        owned.set_bootargs("console=ttyS0");
+        owned.set_initrd(0x8800_0000, 0x8900_0000);
+        owned.set_bootargs("console=ttyAMA0 quiet");
+        let blob = owned.to_bytes();
+        let fdt = Fdt::from_bytes(&blob)?;
+
+        let chosen = fdt.chosen().unwrap();
+        assert_eq!(chosen.bootargs(), Some("console=ttyAMA0 quiet"));
+        assert_eq!(chosen.initrd(), Some((0x8800_0000, 0x8900_0000)));
+        assert_eq!(chosen.node().properties().count(), 3);
+
+        // Unrelated nodes survive
+        assert_eq!(fdt.memory().count(), 2);
+        assert_eq!(fdt.reserved_memory().count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() {
+        let mut blob = tree().to_bytes();
+        assert!(Fdt::from_bytes(&blob[..blob.len() - 1]).is_err());
+        assert!(Fdt::from_bytes(&blob[..8]).is_err());
+
+        blob[0] = 0;
+        assert!(Fdt::from_bytes(&blob).is_err());
+    }
+}
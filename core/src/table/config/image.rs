@@ -0,0 +1,487 @@
+//! Loaded image debugging and security tables
+//!
+//! The [`DebugImageInfo`][super::DebugImageInfo] configuration table lists
+//! every loaded image, so debuggers can find and load their symbols.
+//!
+//! The [`ImageExecInfo`][super::ImageExecInfo] configuration table records
+//! the result of authenticating every image that was loaded.
+//!
+//! # References
+//!
+//! - [UEFI Section 18.4. EFI Debug Support Table][s18_4]
+//! - [UEFI Section 32.5. Image Execution Information Table][s32_5]
+//!
+//! [s18_4]: <https://uefi.org/specs/UEFI/2.10/18_Protocols_Debugger_Support.html#efi-debug-support-table>
+//! [s32_5]: <https://uefi.org/specs/UEFI/2.10/32_Secure_Boot_and_Driver_Signing.html#image-execution-information-table>
+use alloc::string::String;
+use core::{
+    ffi::c_void,
+    mem::size_of,
+    ptr::{addr_of, read_volatile},
+    slice::from_raw_parts,
+};
+
+use crate::{
+    base::{Guid, Handle},
+    proto::device_path::{DevicePathSubType, DevicePathType},
+};
+
+/// Raw `EFI_DEBUG_IMAGE_INFO_TABLE_HEADER`
+#[derive(Debug)]
+#[repr(C)]
+pub struct RawDebugImageInfoHeader {
+    /// Volatile, updated by firmware as images are loaded and unloaded
+    pub update_status: u32,
+
+    /// Number of entries in `table`
+    pub size: u32,
+
+    /// Array of `size` pointers to entries. Unused entries are null.
+    pub table: *const *const RawDebugImageInfoNormal,
+}
+
+/// Raw `EFI_DEBUG_IMAGE_INFO_NORMAL`
+#[derive(Debug)]
+#[repr(C)]
+pub struct RawDebugImageInfoNormal {
+    pub ty: u32,
+
+    /// Pointer to the `EFI_LOADED_IMAGE_PROTOCOL` for this image
+    pub loaded_image: *mut c_void,
+
+    pub handle: Handle,
+}
+
+/// The debug image info table
+///
+/// Firmware updates this table in place as images are loaded and unloaded,
+/// check [`DebugImageTable::is_updating`] before trusting its contents.
+#[derive(Debug, Clone, Copy)]
+pub struct DebugImageTable<'tbl> {
+    header: &'tbl RawDebugImageInfoHeader,
+}
+
+impl<'tbl> DebugImageTable<'tbl> {
+    /// The table is being updated
+    pub const UPDATE_IN_PROGRESS: u32 = 0x1;
+
+    /// The table was modified. Debuggers clear this after reading it.
+    pub const MODIFIED: u32 = 0x2;
+
+    /// # Safety
+    ///
+    /// - `header` must point to a valid table, valid for `'tbl`
+    pub unsafe fn from_ptr(header: *const c_void) -> Self {
+        Self {
+            header: &*header.cast(),
+        }
+    }
+
+    /// Current update status flags
+    pub fn update_status(&self) -> u32 {
+        // Safety: Firmware writes this asynchronously to us
+        unsafe { read_volatile(addr_of!(self.header.update_status)) }
+    }
+
+    /// Whether firmware is in the middle of updating the table
+    pub fn is_updating(&self) -> bool {
+        self.update_status() & Self::UPDATE_IN_PROGRESS != 0
+    }
+
+    /// Whether the table has been modified
+    pub fn is_modified(&self) -> bool {
+        self.update_status() & Self::MODIFIED != 0
+    }
+
+    /// Number of entries, including unused ones
+    pub fn len(&self) -> usize {
+        self.header.size as usize
+    }
+
+    /// Whether there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterator over the loaded images
+    pub fn images(&self) -> impl Iterator<Item = DebugImage> + 'tbl {
+        let table = if self.header.table.is_null() {
+            &[]
+        } else {
+            // Safety: Trusted to be valid for `size` entries
+            unsafe { from_raw_parts(self.header.table, self.len()) }
+        };
+        table.iter().filter(|p| !p.is_null()).filter_map(|p| {
+            // Safety: Non-null entries are valid
+            let raw = unsafe { &**p };
+            (raw.ty == DebugImage::NORMAL).then_some(DebugImage {
+                loaded_image: raw.loaded_image,
+                handle: raw.handle,
+            })
+        })
+    }
+}
+
+/// An entry in the [`DebugImageTable`]
+#[derive(Debug, Clone, Copy)]
+pub struct DebugImage {
+    loaded_image: *mut c_void,
+    handle: Handle,
+}
+
+impl DebugImage {
+    /// Type of a normal image entry, the only defined type
+    pub const NORMAL: u32 = 0x1;
+
+    /// Pointer to the `EFI_LOADED_IMAGE_PROTOCOL` for this image
+    pub fn loaded_image(&self) -> *mut c_void {
+        self.loaded_image
+    }
+
+    /// Image handle
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+}
+
+/// The image execution info table
+#[derive(Debug, Clone, Copy)]
+pub struct ImageExecTable<'tbl> {
+    count: usize,
+
+    /// Start of the entries
+    entries: *const u8,
+
+    phantom: core::marker::PhantomData<&'tbl [u8]>,
+}
+
+impl<'tbl> ImageExecTable<'tbl> {
+    /// # Safety
+    ///
+    /// - `table` must point to a valid table, valid for `'tbl`
+    pub unsafe fn from_ptr(table: *const c_void) -> Self {
+        let count = table.cast::<usize>().read_unaligned();
+        Self {
+            count,
+            entries: table.cast::<u8>().add(size_of::<usize>()),
+            phantom: core::marker::PhantomData,
+        }
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Whether there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Iterator over the entries
+    ///
+    /// Stops at the first malformed entry
+    pub fn entries(&self) -> impl Iterator<Item = ImageExecution<'tbl>> + 'tbl {
+        let mut ptr = self.entries;
+        let mut remaining = self.count;
+        core::iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
+            }
+            // Safety: Trusted to be a valid entry
+            let size = unsafe { ptr.add(4).cast::<u32>().read_unaligned() } as usize;
+            if size < 8 {
+                remaining = 0;
+                return None;
+            }
+            // Safety: Entries are trusted to be valid for `size`
+            let bytes = unsafe { from_raw_parts(ptr, size) };
+            // Safety: See above
+            ptr = unsafe { ptr.add(size) };
+            remaining -= 1;
+            Some(ImageExecution { bytes })
+        })
+    }
+}
+
+/// Authentication result and state of an [`ImageExecution`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct ImageAction(u32);
+
+impl ImageAction {
+    pub const AUTH_UNTESTED: Self = Self(0x0);
+    pub const AUTH_SIG_FAILED: Self = Self(0x1);
+    pub const AUTH_SIG_PASSED: Self = Self(0x2);
+    pub const AUTH_SIG_NOT_FOUND: Self = Self(0x3);
+    pub const AUTH_SIG_FOUND: Self = Self(0x4);
+    pub const POLICY_FAILED: Self = Self(0x5);
+
+    /// Mask of the authentication result
+    pub const AUTHENTICATION: u32 = 0x7;
+
+    /// Flag indicating the image was initialized
+    pub const INITIALIZED: u32 = 0x8;
+
+    /// The authentication result, without flags
+    pub const fn authentication(self) -> Self {
+        Self(self.0 & Self::AUTHENTICATION)
+    }
+
+    /// Whether the image was initialized, it was allowed to run
+    pub const fn is_initialized(self) -> bool {
+        self.0 & Self::INITIALIZED != 0
+    }
+
+    /// Raw value
+    pub const fn value(self) -> u32 {
+        self.0
+    }
+}
+
+/// An entry in the [`ImageExecTable`]
+#[derive(Debug, Clone, Copy)]
+pub struct ImageExecution<'tbl> {
+    /// The entire entry, `InfoSize` bytes
+    bytes: &'tbl [u8],
+}
+
+impl<'tbl> ImageExecution<'tbl> {
+    /// Authentication result
+    pub fn action(&self) -> ImageAction {
+        ImageAction(le_u32(self.bytes, 0))
+    }
+
+    /// The raw entry, including the header
+    pub fn as_bytes(&self) -> &'tbl [u8] {
+        self.bytes
+    }
+
+    /// Offset of the end of the name, after its nul
+    fn name_end(&self) -> usize {
+        let name = self.bytes.get(8..).unwrap_or_default();
+        let len = name
+            .as_chunks::<2>()
+            .0
+            .iter()
+            .position(|c| *c == [0, 0])
+            .map_or(name.len(), |n| (n + 1) * 2);
+        8 + len
+    }
+
+    /// Image name, often empty
+    ///
+    /// Invalid characters are mapped to [`char::REPLACEMENT_CHARACTER`]
+    pub fn name(&self) -> String {
+        let name = &self.bytes[8..self.name_end()];
+        let chars = name
+            .as_chunks::<2>()
+            .0
+            .iter()
+            .map(|c| u16::from_le_bytes(*c))
+            .take_while(|c| *c != 0);
+        char::decode_utf16(chars)
+            .map(|r| r.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect()
+    }
+
+    /// Raw device path of the image, including the end node
+    pub fn device_path(&self) -> &'tbl [u8] {
+        let start = self.name_end();
+        let path = self.bytes.get(start..).unwrap_or_default();
+        let mut off = 0;
+        while let Some(node) = path.get(off..off + 4) {
+            let len = u16::from_le_bytes([node[2], node[3]]) as usize;
+            if len < 4 || off + len > path.len() {
+                break;
+            }
+            off += len;
+            if node[0] == DevicePathType::END.value()
+                && node[1] == DevicePathSubType::END_ENTIRE.value()
+            {
+                break;
+            }
+        }
+        &path[..off]
+    }
+
+    /// Signature list of the image, if any
+    pub fn signatures(&self) -> Option<SignatureList<'tbl>> {
+        let start = self.name_end() + self.device_path().len();
+        SignatureList::from_bytes(self.bytes.get(start..)?)
+    }
+}
+
+/// An `EFI_SIGNATURE_LIST`
+#[derive(Debug, Clone, Copy)]
+pub struct SignatureList<'tbl> {
+    bytes: &'tbl [u8],
+}
+
+impl<'tbl> SignatureList<'tbl> {
+    /// Size of the fixed list header
+    const HEADER: usize = 28;
+
+    /// Parse a signature list from the start of `bytes`
+    ///
+    /// Returns [`None`] if it is malformed
+    pub fn from_bytes(bytes: &'tbl [u8]) -> Option<Self> {
+        if bytes.len() < Self::HEADER {
+            return None;
+        }
+        let list_size = le_u32(bytes, 16) as usize;
+        let header_size = le_u32(bytes, 20) as usize;
+        let sig_size = le_u32(bytes, 24) as usize;
+        let bytes = bytes.get(..list_size)?;
+        if Self::HEADER + header_size > list_size || sig_size < size_of::<Guid>() {
+            return None;
+        }
+        Some(Self { bytes })
+    }
+
+    /// Type of signatures in this list
+    pub fn ty(&self) -> Guid {
+        Guid::new(self.bytes[..16].try_into().unwrap())
+    }
+
+    /// Size of each signature, including its owner [`Guid`]
+    pub fn signature_size(&self) -> usize {
+        le_u32(self.bytes, 24) as usize
+    }
+
+    /// Type specific header
+    pub fn header(&self) -> &'tbl [u8] {
+        let size = le_u32(self.bytes, 20) as usize;
+        &self.bytes[Self::HEADER..Self::HEADER + size]
+    }
+
+    /// Iterator over the signatures in this list
+    pub fn signatures(&self) -> impl Iterator<Item = Signature<'tbl>> + 'tbl {
+        let start = Self::HEADER + self.header().len();
+        self.bytes[start..]
+            .chunks_exact(self.signature_size())
+            .map(|s| Signature {
+                owner: Guid::new(s[..16].try_into().unwrap()),
+                data: &s[16..],
+            })
+    }
+}
+
+/// An `EFI_SIGNATURE_DATA` from a [`SignatureList`]
+#[derive(Debug, Clone, Copy)]
+pub struct Signature<'tbl> {
+    owner: Guid,
+    data: &'tbl [u8],
+}
+
+impl<'tbl> Signature<'tbl> {
+    /// Agent that added this signature
+    pub fn owner(&self) -> Guid {
+        self.owner
+    }
+
+    /// Signature data, such as a hash or certificate
+    pub fn data(&self) -> &'tbl [u8] {
+        self.data
+    }
+}
+
+fn le_u32(b: &[u8], off: usize) -> u32 {
+    b.get(off..off + 4)
+        .map_or(0, |b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn debug_images() {
+        let image = 0u8;
+        let normal = RawDebugImageInfoNormal {
+            ty: DebugImage::NORMAL,
+            loaded_image: addr_of!(image).cast_mut().cast(),
+            // Safety: Only compared, never used as a handle
+            handle: unsafe { Handle::new(0x1000 as *mut _) },
+        };
+        let entries = [addr_of!(normal), core::ptr::null()];
+        let mut header = RawDebugImageInfoHeader {
+            update_status: DebugImageTable::MODIFIED,
+            size: entries.len() as u32,
+            table: entries.as_ptr(),
+        };
+
+        // Safety: Valid table
+        let table = unsafe { DebugImageTable::from_ptr(addr_of!(header).cast()) };
+        assert_eq!(table.len(), 2);
+        assert!(table.is_modified() && !table.is_updating());
+        let images: Vec<_> = table.images().collect();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].loaded_image(), addr_of!(image).cast_mut().cast());
+        assert_eq!(images[0].handle(), normal.handle);
+
+        header.update_status |= DebugImageTable::UPDATE_IN_PROGRESS;
+        // Safety: Valid table
+        let table = unsafe { DebugImageTable::from_ptr(addr_of!(header).cast()) };
+        assert!(table.is_updating());
+
+        header.table = core::ptr::null();
+        // Safety: Valid table, a null `table` has no entries
+        let table = unsafe { DebugImageTable::from_ptr(addr_of!(header).cast()) };
+        assert_eq!(table.images().count(), 0);
+    }
+
+    #[test]
+    fn image_exec() {
+        let mut entry = Vec::new();
+        // Name
+        for c in "a.efi\0".encode_utf16() {
+            entry.extend(c.to_le_bytes());
+        }
+        // Device path, a media file node and end node
+        entry.extend([4, 4, 8, 0, b'a', 0, 0, 0]);
+        entry.extend([0x7F, 0xFF, 4, 0]);
+        // Signature list with one SHA256
+        let ty = [0x26, 0x16, 0xC4, 0xC1, 0x4C, 0x50, 0x92, 0x40];
+        entry.extend(ty.iter().chain(ty.iter()));
+        entry.extend((28u32 + 48).to_le_bytes());
+        entry.extend(0u32.to_le_bytes());
+        entry.extend(48u32.to_le_bytes());
+        entry.extend([0x11; 16]);
+        entry.extend([0x22; 32]);
+
+        let mut table = Vec::new();
+        table.extend(2usize.to_le_bytes());
+        for action in [ImageAction::AUTH_SIG_FAILED.0, ImageAction::INITIALIZED] {
+            table.extend(action.to_le_bytes());
+            table.extend((8 + entry.len() as u32).to_le_bytes());
+            table.extend(&entry);
+        }
+
+        // Safety: Valid table
+        let exec = unsafe { ImageExecTable::from_ptr(table.as_ptr().cast()) };
+        let entries: Vec<_> = exec.entries().collect();
+        assert_eq!(entries.len(), 2);
+
+        let e = entries[0];
+        assert_eq!(e.action().authentication(), ImageAction::AUTH_SIG_FAILED);
+        assert!(!e.action().is_initialized());
+        assert!(entries[1].action().is_initialized());
+        assert_eq!(
+            entries[1].action().authentication(),
+            ImageAction::AUTH_UNTESTED
+        );
+
+        assert_eq!(e.name(), "a.efi");
+        assert_eq!(e.device_path().len(), 12);
+        let sigs = e.signatures().unwrap();
+        assert_eq!(sigs.signature_size(), 48);
+        assert!(sigs.header().is_empty());
+        let sigs: Vec<_> = sigs.signatures().collect();
+        assert_eq!(sigs.len(), 1);
+        assert_eq!(sigs[0].owner(), Guid::new([0x11; 16]));
+        assert_eq!(sigs[0].data(), [0x22; 32]);
+    }
+}
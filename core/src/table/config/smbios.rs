@@ -0,0 +1,1049 @@
+//! SMBIOS structure table parsing
+//!
+//! The [`SMBIOS`][super::SMBIOS] and [`SMBIOS3`][super::SMBIOS3]
+//! configuration tables point to an SMBIOS entry point,
+//! which in turn describes the SMBIOS structure table.
+//!
+//! The structure table is a packed sequence of [`Structure`]s, each made of a
+//! formatted section followed by a set of nul terminated strings.
+//!
+//! Structures can be decoded into their typed form with [`Structure::info`].
+//!
+//! # References
+//!
+//! - [DMTF DSP0134 SMBIOS Reference Specification 3.6.0][dsp0134]
+//!
+//! [dsp0134]: <https://www.dmtf.org/sites/default/files/standards/documents/DSP0134_3.6.0.pdf>
+use core::{ffi::c_void, slice::from_raw_parts};
+
+use crate::{
+    base::{Guid, Status},
+    error::Result,
+};
+
+/// The SMBIOS 2.1 32-bit `_SM_` entry point
+#[derive(Debug, Clone, Copy)]
+pub struct EntryPoint32 {
+    major: u8,
+    minor: u8,
+    max_size: u16,
+    revision: u8,
+    table_len: u16,
+    table_address: u32,
+    count: u16,
+}
+
+impl EntryPoint32 {
+    /// Anchor string
+    pub const ANCHOR: &'static [u8; 4] = b"_SM_";
+
+    /// Intermediate anchor string
+    pub const DMI_ANCHOR: &'static [u8; 5] = b"_DMI_";
+
+    /// Parse and validate the entry point from `bytes`
+    ///
+    /// This verifies the anchors and both checksums.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 0x1F {
+            return Err(Status::BUFFER_TOO_SMALL.into());
+        }
+        if &bytes[..4] != Self::ANCHOR || &bytes[0x10..0x15] != Self::DMI_ANCHOR {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+
+        // SMBIOS 2.1 erroneously specified a length of `0x1E`
+        let len = bytes[5] as usize;
+        if !(0x1E..=0x1F).contains(&len) {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        if checksum(&bytes[..len]) != 0 || checksum(&bytes[0x10..0x1F]) != 0 {
+            return Err(Status::CRC_ERROR.into());
+        }
+
+        Ok(Self {
+            major: bytes[6],
+            minor: bytes[7],
+            max_size: le_u16(bytes, 8),
+            revision: bytes[0xA],
+            table_len: le_u16(bytes, 0x16),
+            table_address: le_u32(bytes, 0x18),
+            count: le_u16(bytes, 0x1C),
+        })
+    }
+
+    /// SMBIOS (Major, Minor) version
+    pub fn version(&self) -> (u8, u8) {
+        (self.major, self.minor)
+    }
+
+    /// Size of the largest structure in the table
+    pub fn max_size(&self) -> u16 {
+        self.max_size
+    }
+
+    /// Entry point revision
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    /// Total length of the structure table, in bytes
+    pub fn table_len(&self) -> u16 {
+        self.table_len
+    }
+
+    /// 32-bit physical address of the structure table
+    pub fn table_address(&self) -> u32 {
+        self.table_address
+    }
+
+    /// Number of structures in the table
+    pub fn count(&self) -> u16 {
+        self.count
+    }
+}
+
+/// The SMBIOS 3.0 64-bit `_SM3_` entry point
+#[derive(Debug, Clone, Copy)]
+pub struct EntryPoint64 {
+    major: u8,
+    minor: u8,
+    docrev: u8,
+    revision: u8,
+    max_len: u32,
+    table_address: u64,
+}
+
+impl EntryPoint64 {
+    /// Anchor string
+    pub const ANCHOR: &'static [u8; 5] = b"_SM3_";
+
+    /// Parse and validate the entry point from `bytes`
+    ///
+    /// This verifies the anchor and checksum.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 0x18 {
+            return Err(Status::BUFFER_TOO_SMALL.into());
+        }
+        if &bytes[..5] != Self::ANCHOR {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+
+        let len = bytes[6] as usize;
+        if len < 0x18 || len > bytes.len() {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        if checksum(&bytes[..len]) != 0 {
+            return Err(Status::CRC_ERROR.into());
+        }
+
+        Ok(Self {
+            major: bytes[7],
+            minor: bytes[8],
+            docrev: bytes[9],
+            revision: bytes[0xA],
+            max_len: le_u32(bytes, 0xC),
+            table_address: le_u64(bytes, 0x10),
+        })
+    }
+
+    /// SMBIOS (Major, Minor, Docrev) version
+    pub fn version(&self) -> (u8, u8, u8) {
+        (self.major, self.minor, self.docrev)
+    }
+
+    /// Entry point revision
+    pub fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    /// Maximum length of the structure table, in bytes
+    ///
+    /// The table is terminated by an end of table structure, type `127`,
+    /// and may be shorter than this.
+    pub fn max_len(&self) -> u32 {
+        self.max_len
+    }
+
+    /// 64-bit physical address of the structure table
+    pub fn table_address(&self) -> u64 {
+        self.table_address
+    }
+}
+
+/// Either SMBIOS entry point
+#[derive(Debug, Clone, Copy)]
+pub enum EntryPoint {
+    /// 32-bit `_SM_` entry point
+    Bits32(EntryPoint32),
+
+    /// 64-bit `_SM3_` entry point
+    Bits64(EntryPoint64),
+}
+
+impl EntryPoint {
+    /// Parse either entry point from `bytes`, based on the anchor
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(EntryPoint64::ANCHOR) {
+            EntryPoint64::from_bytes(bytes).map(Self::Bits64)
+        } else {
+            EntryPoint32::from_bytes(bytes).map(Self::Bits32)
+        }
+    }
+
+    /// SMBIOS (Major, Minor) version
+    pub fn version(&self) -> (u8, u8) {
+        match self {
+            Self::Bits32(e) => e.version(),
+            Self::Bits64(e) => (e.version().0, e.version().1),
+        }
+    }
+
+    /// Physical address of the structure table
+    pub fn table_address(&self) -> u64 {
+        match self {
+            Self::Bits32(e) => e.table_address().into(),
+            Self::Bits64(e) => e.table_address(),
+        }
+    }
+
+    /// Length, or maximum length, of the structure table, in bytes
+    pub fn table_len(&self) -> usize {
+        match self {
+            Self::Bits32(e) => e.table_len().into(),
+            Self::Bits64(e) => e.max_len() as usize,
+        }
+    }
+
+    /// Number of structures in the table, if known
+    pub fn count(&self) -> Option<u16> {
+        match self {
+            Self::Bits32(e) => Some(e.count()),
+            Self::Bits64(_) => None,
+        }
+    }
+}
+
+/// The SMBIOS structure table, and the [`EntryPoint`] that described it
+#[derive(Debug, Clone, Copy)]
+pub struct StructureTable<'tbl> {
+    entry: EntryPoint,
+    table: &'tbl [u8],
+}
+
+impl<'tbl> StructureTable<'tbl> {
+    /// Create a new [`StructureTable`] from `entry` and its table bytes
+    pub fn new(entry: EntryPoint, table: &'tbl [u8]) -> Self {
+        Self { entry, table }
+    }
+
+    /// Parse the output of `dmidecode --dump-bin`
+    ///
+    /// This format is the entry point, with the table address rewritten
+    /// to be an offset into the file, followed by the structure table.
+    pub fn from_dump(dump: &'tbl [u8]) -> Result<Self> {
+        let entry = EntryPoint::from_bytes(dump)?;
+        let start: usize = entry
+            .table_address()
+            .try_into()
+            .map_err(|_| Status::INVALID_PARAMETER)?;
+        let table = dump.get(start..).ok_or(Status::BUFFER_TOO_SMALL)?;
+        let len = entry.table_len().min(table.len());
+        Ok(Self::new(entry, &table[..len]))
+    }
+
+    /// Read the entry point and structure table from memory
+    ///
+    /// # Safety
+    ///
+    /// - `entry` must point to a valid SMBIOS entry point
+    /// - The structure table must be identity mapped and valid for `'tbl`
+    pub unsafe fn from_ptr(entry: *const c_void) -> Result<Self> {
+        if entry.is_null() {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let ptr = entry.cast::<u8>();
+
+        // Both entry points store their length before this
+        let len = if from_raw_parts(ptr, 5) == EntryPoint64::ANCHOR {
+            (*ptr.add(6)).into()
+        } else {
+            // SMBIOS 2.1 erroneously specified a length of `0x1E`,
+            // but the structure is always `0x1F` bytes
+            usize::from(*ptr.add(5)).max(0x1F)
+        };
+        let entry = EntryPoint::from_bytes(from_raw_parts(ptr, len))?;
+
+        let table = entry.table_address() as usize as *const u8;
+        if table.is_null() {
+            return Err(Status::NOT_FOUND.into());
+        }
+        Ok(Self::new(entry, from_raw_parts(table, entry.table_len())))
+    }
+
+    /// The [`EntryPoint`] for this table
+    pub fn entry_point(&self) -> EntryPoint {
+        self.entry
+    }
+
+    /// Raw bytes of the structure table
+    pub fn as_bytes(&self) -> &'tbl [u8] {
+        self.table
+    }
+
+    /// Iterate over every [`Structure`] in the table
+    pub fn structures(&self) -> Structures<'tbl> {
+        Structures {
+            data: self.table,
+            remaining: self.entry.count(),
+        }
+    }
+
+    /// Iterate over every [`Structure`] of type `ty`
+    pub fn structures_of(&self, ty: u8) -> impl Iterator<Item = Structure<'tbl>> {
+        self.structures().filter(move |s| s.ty() == ty)
+    }
+
+    /// Find the [`Structure`] identified by `handle`
+    pub fn find(&self, handle: u16) -> Option<Structure<'tbl>> {
+        self.structures().find(|s| s.handle() == handle)
+    }
+}
+
+/// Iterator over the [`Structure`]s in a [`StructureTable`]
+///
+/// Iteration stops at the end of table structure, type `127`,
+/// or at the first malformed structure.
+#[derive(Debug, Clone)]
+pub struct Structures<'tbl> {
+    data: &'tbl [u8],
+
+    /// Structures remaining, if known
+    remaining: Option<u16>,
+}
+
+impl<'tbl> Iterator for Structures<'tbl> {
+    type Item = Structure<'tbl>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+        let data = self.data;
+        let len = *data.get(1)? as usize;
+        if len < Structure::HEADER_LEN || len > data.len() {
+            self.data = &[];
+            return None;
+        }
+
+        // The string set ends with two nul bytes,
+        // even when there are no strings.
+        let end = data[len..]
+            .windows(2)
+            .position(|w| w == [0, 0])
+            .map(|p| len + p + 2);
+        let Some(end) = end else {
+            self.data = &[];
+            return None;
+        };
+
+        let s = Structure {
+            formatted: &data[..len],
+            strings: &data[len..end],
+        };
+        self.data = &data[end..];
+        self.remaining = self.remaining.map(|r| r - 1);
+
+        if s.ty() == Structure::END_OF_TABLE {
+            self.data = &[];
+            self.remaining = Some(0);
+        }
+        Some(s)
+    }
+}
+
+/// A single SMBIOS structure
+#[derive(Debug, Clone, Copy)]
+pub struct Structure<'tbl> {
+    /// Formatted section, including the header
+    formatted: &'tbl [u8],
+
+    /// String set, including the terminating double nul
+    strings: &'tbl [u8],
+}
+
+impl<'tbl> Structure<'tbl> {
+    /// Size of the structure header
+    pub const HEADER_LEN: usize = 4;
+
+    /// Type of the end of table structure
+    pub const END_OF_TABLE: u8 = 127;
+
+    /// Structure type
+    pub fn ty(&self) -> u8 {
+        self.formatted[0]
+    }
+
+    /// Structure handle
+    pub fn handle(&self) -> u16 {
+        le_u16(self.formatted, 2)
+    }
+
+    /// The formatted section, including the header
+    pub fn data(&self) -> &'tbl [u8] {
+        self.formatted
+    }
+
+    /// Iterate over the string set
+    ///
+    /// Strings are numbered starting at `1`
+    pub fn strings(&self) -> impl Iterator<Item = &'tbl [u8]> {
+        let s = &self.strings[..self.strings.len() - 1];
+        s.split(|b| *b == 0).filter(|s| !s.is_empty())
+    }
+
+    /// String number `n`, or [`None`] if `n` is `0`, doesn't exist,
+    /// or isn't valid UTF-8.
+    pub fn string(&self, n: u8) -> Option<&'tbl str> {
+        let n = usize::from(n).checked_sub(1)?;
+        let s = self.strings().nth(n)?;
+        core::str::from_utf8(s).ok()
+    }
+
+    /// Byte at `offset` into the formatted section, if present
+    pub fn byte(&self, offset: usize) -> Option<u8> {
+        self.formatted.get(offset).copied()
+    }
+
+    /// Word at `offset` into the formatted section, if present
+    pub fn word(&self, offset: usize) -> Option<u16> {
+        let b = self.formatted.get(offset..offset + 2)?;
+        Some(le_u16(b, 0))
+    }
+
+    /// Double word at `offset` into the formatted section, if present
+    pub fn dword(&self, offset: usize) -> Option<u32> {
+        let b = self.formatted.get(offset..offset + 4)?;
+        Some(le_u32(b, 0))
+    }
+
+    /// Quad word at `offset` into the formatted section, if present
+    pub fn qword(&self, offset: usize) -> Option<u64> {
+        let b = self.formatted.get(offset..offset + 8)?;
+        Some(le_u64(b, 0))
+    }
+
+    /// String referenced by the byte at `offset`
+    fn string_at(&self, offset: usize) -> Option<&'tbl str> {
+        self.string(self.byte(offset)?)
+    }
+
+    /// Decode this structure, if its type is known
+    pub fn info(&self) -> Option<Info<'tbl>> {
+        let s = *self;
+        Some(match self.ty() {
+            BiosInfo::TYPE => Info::Bios(BiosInfo(s)),
+            SystemInfo::TYPE => Info::System(SystemInfo(s)),
+            Baseboard::TYPE => Info::Baseboard(Baseboard(s)),
+            Chassis::TYPE => Info::Chassis(Chassis(s)),
+            Processor::TYPE => Info::Processor(Processor(s)),
+            MemoryDevice::TYPE => Info::MemoryDevice(MemoryDevice(s)),
+            _ => return None,
+        })
+    }
+}
+
+/// A decoded [`Structure`]
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum Info<'tbl> {
+    /// Type 0
+    Bios(BiosInfo<'tbl>),
+
+    /// Type 1
+    System(SystemInfo<'tbl>),
+
+    /// Type 2
+    Baseboard(Baseboard<'tbl>),
+
+    /// Type 3
+    Chassis(Chassis<'tbl>),
+
+    /// Type 4
+    Processor(Processor<'tbl>),
+
+    /// Type 17
+    MemoryDevice(MemoryDevice<'tbl>),
+}
+
+/// BIOS Information, type 0
+#[derive(Debug, Clone, Copy)]
+pub struct BiosInfo<'tbl>(Structure<'tbl>);
+
+impl<'tbl> BiosInfo<'tbl> {
+    pub const TYPE: u8 = 0;
+
+    /// BIOS vendor
+    pub fn vendor(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x4)
+    }
+
+    /// BIOS version
+    pub fn version(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x5)
+    }
+
+    /// Segment location of the BIOS starting address
+    pub fn starting_segment(&self) -> Option<u16> {
+        self.0.word(0x6)
+    }
+
+    /// BIOS release date, `mm/dd/yyyy`
+    pub fn release_date(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x8)
+    }
+
+    /// Size of the physical device containing the BIOS, in bytes
+    pub fn rom_size(&self) -> Option<u64> {
+        let size = self.0.byte(0x9)?;
+        if size != 0xFF {
+            return Some((u64::from(size) + 1) * 64 * 1024);
+        }
+        // Extended BIOS ROM size, SMBIOS 3.1
+        let ext = self.0.word(0x18)?;
+        let n = u64::from(ext & 0x3FFF);
+        match ext >> 14 {
+            0 => Some(n * 1024 * 1024),
+            1 => Some(n * 1024 * 1024 * 1024),
+            _ => None,
+        }
+    }
+
+    /// BIOS characteristics bit field
+    pub fn characteristics(&self) -> Option<u64> {
+        self.0.qword(0xA)
+    }
+
+    /// System BIOS (Major, Minor) release
+    pub fn bios_release(&self) -> Option<(u8, u8)> {
+        Some((self.0.byte(0x14)?, self.0.byte(0x15)?)).filter(|r| *r != (0xFF, 0xFF))
+    }
+
+    /// Embedded controller firmware (Major, Minor) release
+    pub fn ec_release(&self) -> Option<(u8, u8)> {
+        Some((self.0.byte(0x16)?, self.0.byte(0x17)?)).filter(|r| *r != (0xFF, 0xFF))
+    }
+}
+
+/// System Information, type 1
+#[derive(Debug, Clone, Copy)]
+pub struct SystemInfo<'tbl>(Structure<'tbl>);
+
+impl<'tbl> SystemInfo<'tbl> {
+    pub const TYPE: u8 = 1;
+
+    pub fn manufacturer(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x4)
+    }
+
+    pub fn product(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x5)
+    }
+
+    pub fn version(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x6)
+    }
+
+    pub fn serial(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x7)
+    }
+
+    /// System UUID
+    ///
+    /// Since SMBIOS 2.6 this is encoded the same as a [`Guid`],
+    /// with the first three fields little endian.
+    ///
+    /// Returns [`None`] if not present, or not set.
+    pub fn uuid(&self) -> Option<Guid> {
+        let b: [u8; 16] = self.0.formatted.get(0x8..0x18)?.try_into().ok()?;
+        if b == [0; 16] || b == [0xFF; 16] {
+            None
+        } else {
+            Some(Guid::new(b))
+        }
+    }
+
+    /// Event that caused the system to power up
+    pub fn wake_up_type(&self) -> Option<u8> {
+        self.0.byte(0x18)
+    }
+
+    pub fn sku(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x19)
+    }
+
+    pub fn family(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x1A)
+    }
+}
+
+/// Baseboard Information, type 2
+#[derive(Debug, Clone, Copy)]
+pub struct Baseboard<'tbl>(Structure<'tbl>);
+
+impl<'tbl> Baseboard<'tbl> {
+    pub const TYPE: u8 = 2;
+
+    pub fn manufacturer(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x4)
+    }
+
+    pub fn product(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x5)
+    }
+
+    pub fn version(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x6)
+    }
+
+    pub fn serial(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x7)
+    }
+
+    pub fn asset_tag(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x8)
+    }
+
+    /// Board feature flags
+    pub fn features(&self) -> Option<u8> {
+        self.0.byte(0x9)
+    }
+
+    /// Location of the board within the chassis
+    pub fn location(&self) -> Option<&'tbl str> {
+        self.0.string_at(0xA)
+    }
+
+    /// Handle of the [`Chassis`] containing this board
+    pub fn chassis(&self) -> Option<u16> {
+        self.0.word(0xB)
+    }
+
+    pub fn board_type(&self) -> Option<u8> {
+        self.0.byte(0xD)
+    }
+}
+
+/// System Enclosure or Chassis, type 3
+#[derive(Debug, Clone, Copy)]
+pub struct Chassis<'tbl>(Structure<'tbl>);
+
+impl<'tbl> Chassis<'tbl> {
+    pub const TYPE: u8 = 3;
+
+    pub fn manufacturer(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x4)
+    }
+
+    /// Chassis type, such as `0x03` for Desktop or `0x0A` for Notebook
+    pub fn chassis_type(&self) -> Option<u8> {
+        self.0.byte(0x5).map(|t| t & 0x7F)
+    }
+
+    /// Whether a chassis lock is present
+    pub fn lock(&self) -> Option<bool> {
+        self.0.byte(0x5).map(|t| t & 0x80 != 0)
+    }
+
+    pub fn version(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x6)
+    }
+
+    pub fn serial(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x7)
+    }
+
+    pub fn asset_tag(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x8)
+    }
+
+    /// State of the enclosure when last booted
+    pub fn boot_up_state(&self) -> Option<u8> {
+        self.0.byte(0x9)
+    }
+
+    /// Height of the enclosure, in rack units
+    pub fn height(&self) -> Option<u8> {
+        self.0.byte(0x11).filter(|h| *h != 0)
+    }
+}
+
+/// Processor Information, type 4
+#[derive(Debug, Clone, Copy)]
+pub struct Processor<'tbl>(Structure<'tbl>);
+
+impl<'tbl> Processor<'tbl> {
+    pub const TYPE: u8 = 4;
+
+    pub fn socket(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x4)
+    }
+
+    /// Processor type, such as `0x03` for Central Processor
+    pub fn processor_type(&self) -> Option<u8> {
+        self.0.byte(0x5)
+    }
+
+    /// Processor family
+    ///
+    /// This transparently uses the Processor Family 2 field if required.
+    pub fn family(&self) -> Option<u16> {
+        match self.0.byte(0x6)? {
+            0xFE => self.0.word(0x28),
+            f => Some(f.into()),
+        }
+    }
+
+    pub fn manufacturer(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x7)
+    }
+
+    /// Raw processor identification data
+    ///
+    /// On x86 this is the `CPUID` leaf 1 `EAX` and `EDX` values.
+    pub fn id(&self) -> Option<u64> {
+        self.0.qword(0x8)
+    }
+
+    pub fn version(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x10)
+    }
+
+    /// External clock frequency, in MHz
+    pub fn external_clock(&self) -> Option<u16> {
+        self.0.word(0x12).filter(|c| *c != 0)
+    }
+
+    /// Maximum supported speed, in MHz
+    pub fn max_speed(&self) -> Option<u16> {
+        self.0.word(0x14).filter(|c| *c != 0)
+    }
+
+    /// Speed at boot, in MHz
+    pub fn current_speed(&self) -> Option<u16> {
+        self.0.word(0x16).filter(|c| *c != 0)
+    }
+
+    /// Whether the socket is populated
+    pub fn populated(&self) -> Option<bool> {
+        self.0.byte(0x18).map(|s| s & 0x40 != 0)
+    }
+
+    pub fn serial(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x20)
+    }
+
+    pub fn asset_tag(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x21)
+    }
+
+    pub fn part_number(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x22)
+    }
+
+    /// Number of cores per socket
+    pub fn core_count(&self) -> Option<u16> {
+        self.count(0x23, 0x2A)
+    }
+
+    /// Number of enabled cores per socket
+    pub fn cores_enabled(&self) -> Option<u16> {
+        self.count(0x24, 0x2C)
+    }
+
+    /// Number of threads per socket
+    pub fn thread_count(&self) -> Option<u16> {
+        self.count(0x25, 0x2E)
+    }
+
+    /// A byte count at `offset`, using the word at `offset2` if required
+    fn count(&self, offset: usize, offset2: usize) -> Option<u16> {
+        match self.0.byte(offset)? {
+            0 => None,
+            0xFF => self.0.word(offset2).or(Some(0xFF)),
+            n => Some(n.into()),
+        }
+    }
+}
+
+/// Memory Device, type 17
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryDevice<'tbl>(Structure<'tbl>);
+
+impl<'tbl> MemoryDevice<'tbl> {
+    pub const TYPE: u8 = 17;
+
+    /// Handle of the physical memory array this device belongs to
+    pub fn array(&self) -> Option<u16> {
+        self.0.word(0x4)
+    }
+
+    /// Total width, in bits, including error correction
+    pub fn total_width(&self) -> Option<u16> {
+        self.0.word(0x8).filter(|w| *w != 0xFFFF)
+    }
+
+    /// Data width, in bits
+    pub fn data_width(&self) -> Option<u16> {
+        self.0.word(0xA).filter(|w| *w != 0xFFFF)
+    }
+
+    /// Size of the device in bytes
+    ///
+    /// Returns [`None`] if no device is installed or the size is unknown
+    pub fn size(&self) -> Option<u64> {
+        match self.0.word(0xC)? {
+            0 | 0xFFFF => None,
+            0x7FFF => {
+                let mib = self.0.dword(0x1C)? & 0x7FFF_FFFF;
+                Some(u64::from(mib) * 1024 * 1024)
+            }
+            s if s & 0x8000 != 0 => Some(u64::from(s & 0x7FFF) * 1024),
+            s => Some(u64::from(s) * 1024 * 1024),
+        }
+    }
+
+    /// Form factor, such as `0x09` for DIMM or `0x0D` for SODIMM
+    pub fn form_factor(&self) -> Option<u8> {
+        self.0.byte(0xE)
+    }
+
+    /// Physically labeled socket or board position
+    pub fn device_locator(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x10)
+    }
+
+    pub fn bank_locator(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x11)
+    }
+
+    /// Memory type, such as `0x1A` for DDR4
+    pub fn memory_type(&self) -> Option<u8> {
+        self.0.byte(0x12)
+    }
+
+    /// Maximum speed, in MT/s
+    pub fn speed(&self) -> Option<u32> {
+        match self.0.word(0x15)? {
+            0 => None,
+            0xFFFF => self.0.dword(0x54),
+            s => Some(s.into()),
+        }
+    }
+
+    pub fn manufacturer(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x17)
+    }
+
+    pub fn serial(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x18)
+    }
+
+    pub fn asset_tag(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x19)
+    }
+
+    pub fn part_number(&self) -> Option<&'tbl str> {
+        self.0.string_at(0x1A)
+    }
+
+    /// Configured speed, in MT/s
+    pub fn configured_speed(&self) -> Option<u32> {
+        match self.0.word(0x20)? {
+            0 => None,
+            0xFFFF => self.0.dword(0x58),
+            s => Some(s.into()),
+        }
+    }
+}
+
+/// Sum of `bytes`, which must be `0` for a valid entry point
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b))
+}
+
+fn le_u16(b: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes([b[off], b[off + 1]])
+}
+
+fn le_u32(b: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes([b[off], b[off + 1], b[off + 2], b[off + 3]])
+}
+
+fn le_u64(b: &[u8], off: usize) -> u64 {
+    let mut v = [0; 8];
+    v.copy_from_slice(&b[off..off + 8]);
+    u64::from_le_bytes(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    /// Append a structure of `ty` with `formatted` data after the header
+    fn push(table: &mut Vec<u8>, ty: u8, handle: u16, formatted: &[u8], strings: &[&str]) {
+        table.push(ty);
+        table.push((formatted.len() + 4) as u8);
+        table.extend_from_slice(&handle.to_le_bytes());
+        table.extend_from_slice(formatted);
+        for s in strings {
+            table.extend_from_slice(s.as_bytes());
+            table.push(0);
+        }
+        if strings.is_empty() {
+            table.push(0);
+        }
+        table.push(0);
+    }
+
+    /// Build a `dmidecode --dump-bin` style SMBIOS 3 dump
+    fn dump() -> Vec<u8> {
+        let mut table = Vec::new();
+
+        let mut bios = [0u8; 0x14];
+        bios[0] = 1;
+        bios[1] = 2;
+        bios[4] = 3;
+        bios[5] = 0x0F;
+        bios[0x10] = 1;
+        bios[0x11] = 2;
+        bios[0x12] = 0xFF;
+        bios[0x13] = 0xFF;
+        push(&mut table, 0, 0, &bios, &["Vendor", "1.0", "01/02/2023"]);
+
+        let mut sys = [0u8; 0x17];
+        sys[0] = 1;
+        sys[1] = 2;
+        sys[3] = 3;
+        sys[4..20].copy_from_slice(&[
+            0x44, 0x33, 0x22, 0x11, 0x66, 0x55, 0x88, 0x77, 0x99, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE,
+            0xFF, 0x00,
+        ]);
+        push(&mut table, 1, 1, &sys, &["Maker", "Product", "SERIAL123"]);
+
+        let mut mem = [0u8; 0x24];
+        mem[0x8] = 0x00;
+        mem[0x9] = 0x40;
+        mem[0xC] = 1;
+        mem[0xD] = 2;
+        push(&mut table, 17, 2, &mem, &["DIMM 0", "ACME"]);
+
+        push(&mut table, 127, 3, &[], &[]);
+
+        let mut dump = Vec::new();
+        dump.extend_from_slice(EntryPoint64::ANCHOR);
+        dump.extend_from_slice(&[0, 0x18, 3, 6, 0, 1, 0]);
+        dump.extend_from_slice(&(table.len() as u32).to_le_bytes());
+        dump.extend_from_slice(&0x20u64.to_le_bytes());
+        dump[5] = checksum(&dump).wrapping_neg();
+        dump.resize(0x20, 0);
+        dump.extend_from_slice(&table);
+        dump
+    }
+
+    #[test]
+    fn entry_point() {
+        let dump = dump();
+        let e = EntryPoint::from_bytes(&dump).unwrap();
+        assert_eq!(e.version(), (3, 6));
+        assert_eq!(e.table_address(), 0x20);
+
+        let mut bad = dump.clone();
+        bad[8] = 7;
+        let e = EntryPoint::from_bytes(&bad).unwrap_err();
+        assert_eq!(e.status(), Status::CRC_ERROR);
+    }
+
+    /// A 32-bit entry point of length `len`, with no structure table
+    fn entry32(len: u8) -> Vec<u8> {
+        let mut entry = [0u8; 0x1F];
+        entry[..4].copy_from_slice(EntryPoint32::ANCHOR);
+        entry[5] = len;
+        entry[6] = 2;
+        entry[7] = 1;
+        entry[0x10..0x15].copy_from_slice(EntryPoint32::DMI_ANCHOR);
+        entry[0x16] = 0x20;
+        entry[0x1C] = 1;
+        entry[0x1E] = 0x21;
+        entry[0x15] = checksum(&entry[0x10..0x1F]).wrapping_neg();
+        entry[4] = checksum(&entry[..len as usize]).wrapping_neg();
+        entry.to_vec()
+    }
+
+    #[test]
+    fn entry_point_32() {
+        for len in [0x1E, 0x1F] {
+            let entry = entry32(len);
+            let e = EntryPoint::from_bytes(&entry).unwrap();
+            assert_eq!(e.version(), (2, 1));
+            assert_eq!(e.table_len(), 0x20);
+
+            // Safety: `entry` is a valid entry point, without a table
+            let e = unsafe { StructureTable::from_ptr(entry.as_ptr().cast()) }.unwrap_err();
+            assert_eq!(e.status(), Status::NOT_FOUND, "length {len:#X}");
+        }
+    }
+
+    #[test]
+    fn structures() {
+        let dump = dump();
+        let table = StructureTable::from_dump(&dump).unwrap();
+        let structures: Vec<_> = table.structures().collect();
+        assert_eq!(structures.len(), 4);
+
+        let Some(Info::Bios(bios)) = structures[0].info() else {
+            panic!("Expected BIOS Information");
+        };
+        assert_eq!(bios.vendor(), Some("Vendor"));
+        assert_eq!(bios.version(), Some("1.0"));
+        assert_eq!(bios.release_date(), Some("01/02/2023"));
+        assert_eq!(bios.rom_size(), Some(1024 * 1024));
+        assert_eq!(bios.bios_release(), Some((1, 2)));
+        assert_eq!(bios.ec_release(), None);
+
+        let Some(Info::System(sys)) = table.find(1).and_then(|s| s.info()) else {
+            panic!("Expected System Information");
+        };
+        assert_eq!(sys.manufacturer(), Some("Maker"));
+        assert_eq!(sys.serial(), Some("SERIAL123"));
+        assert_eq!(sys.version(), None);
+        let mut buf = [0u8; 36];
+        let uuid = sys.uuid().unwrap();
+        let uuid = nuuid::Uuid::from_bytes_me(uuid.to_bytes());
+        assert_eq!(
+            uuid.to_str(&mut buf),
+            "11223344-5566-7788-99aa-bbccddeeff00"
+        );
+
+        let Some(Info::MemoryDevice(mem)) = table.structures_of(17).next().and_then(|s| s.info())
+        else {
+            panic!("Expected Memory Device");
+        };
+        assert_eq!(mem.size(), Some(16 * 1024 * 1024 * 1024));
+        assert_eq!(mem.device_locator(), Some("DIMM 0"));
+        assert_eq!(mem.bank_locator(), Some("ACME"));
+        assert_eq!(mem.manufacturer(), None);
+
+        assert_eq!(structures[3].ty(), Structure::END_OF_TABLE);
+        assert_eq!(structures[3].strings().count(), 0);
+    }
+
+    #[test]
+    fn truncated() {
+        let mut dump = dump();
+        dump.truncate(dump.len() - 10);
+        let table = StructureTable::from_dump(&dump).unwrap();
+        assert_eq!(table.structures().count(), 2);
+    }
+}
@@ -1,10 +1,14 @@
 //! UEFI Memory allocation related types
+use alloc::vec::Vec;
+use core::{mem::size_of, slice::from_raw_parts};
 
 /// UEFI Physical Address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct PhysicalAddress(u64);
 
 /// UEFI Virtual Address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct VirtualAddress(u64);
 
@@ -87,38 +91,260 @@ impl MemoryType {
     const _MAX: Self = Self(16);
 }
 
-/// UEFI Memory flags
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct MemoryFlags(u64);
-
-impl MemoryFlags {
-    pub const UC: Self = Self(0x0000000000000001);
-    pub const WC: Self = Self(0x0000000000000002);
-    pub const WT: Self = Self(0x0000000000000004);
-    pub const WB: Self = Self(0x0000000000000008);
-    pub const UCE: Self = Self(0x0000000000000010);
-    pub const WP: Self = Self(0x0000000000001000);
-    pub const RP: Self = Self(0x0000000000002000);
-    pub const XP: Self = Self(0x0000000000004000);
-    pub const NV: Self = Self(0x0000000000008000);
-    pub const MORE_RELIABLE: Self = Self(0x0000000000010000);
-    pub const RO: Self = Self(0x0000000000020000);
-    pub const SP: Self = Self(0x0000000000040000);
-    pub const CPU_CRYPTO: Self = Self(0x0000000000080000);
-    pub const RUNTIME: Self = Self(0x8000000000000000);
-    pub const ISA_VALID: Self = Self(0x4000000000000000);
-    pub const ISA_MASK: Self = Self(0x0FFFF00000000000);
+bitflags::bitflags! {
+    /// UEFI Memory flags
+    #[repr(transparent)]
+    pub struct MemoryFlags: u64 {
+        const UC = 0x0000000000000001;
+        const WC = 0x0000000000000002;
+        const WT = 0x0000000000000004;
+        const WB = 0x0000000000000008;
+        const UCE = 0x0000000000000010;
+        const WP = 0x0000000000001000;
+        const RP = 0x0000000000002000;
+        const XP = 0x0000000000004000;
+        const NV = 0x0000000000008000;
+        const MORE_RELIABLE = 0x0000000000010000;
+        const RO = 0x0000000000020000;
+        const SP = 0x0000000000040000;
+        const CPU_CRYPTO = 0x0000000000080000;
+        const RUNTIME = 0x8000000000000000;
+        const ISA_VALID = 0x4000000000000000;
+        const ISA_MASK = 0x0FFFF00000000000;
+
+        /// Cacheability attributes
+        const CACHEABILITY = Self::UC.bits
+            | Self::WC.bits
+            | Self::WT.bits
+            | Self::WB.bits
+            | Self::UCE.bits;
+
+        /// Access permission attributes
+        const PERMISSIONS = Self::WP.bits
+            | Self::RP.bits
+            | Self::XP.bits
+            | Self::RO.bits;
+    }
 }
 
 /// UEFI Memory Descriptor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 pub struct MemoryDescriptor {
     ty: MemoryType,
     start: PhysicalAddress,
+    virt: VirtualAddress,
     pages: u64,
     attribute: MemoryFlags,
 }
 
 impl MemoryDescriptor {
-    pub(crate) const _VERSION: u32 = 1;
+    /// Descriptor version we support
+    pub const VERSION: u32 = 1;
+
+    /// UEFI page size, which `pages` are counted in
+    pub const PAGE_SIZE: u64 = 4096;
+
+    /// Create a new [`MemoryDescriptor`]
+    pub const fn new(
+        ty: MemoryType,
+        start: u64,
+        virt: u64,
+        pages: u64,
+        attribute: MemoryFlags,
+    ) -> Self {
+        Self {
+            ty,
+            start: PhysicalAddress(start),
+            virt: VirtualAddress(virt),
+            pages,
+            attribute,
+        }
+    }
+
+    /// Type of memory
+    pub const fn ty(&self) -> MemoryType {
+        self.ty
+    }
+
+    /// Physical address of the first byte
+    pub const fn physical_start(&self) -> u64 {
+        self.start.0
+    }
+
+    /// Virtual address of the first byte
+    ///
+    /// Only meaningful for runtime memory after
+    /// `SetVirtualAddressMap` has been called.
+    pub const fn virtual_start(&self) -> u64 {
+        self.virt.0
+    }
+
+    /// Number of [`PAGE_SIZE`][`MemoryDescriptor::PAGE_SIZE`] pages
+    pub const fn pages(&self) -> u64 {
+        self.pages
+    }
+
+    /// Size in bytes
+    ///
+    /// Saturates at [`u64::MAX`] for malformed descriptors
+    pub const fn size(&self) -> u64 {
+        self.pages.saturating_mul(Self::PAGE_SIZE)
+    }
+
+    /// Physical address one past the last byte
+    ///
+    /// Saturates at [`u64::MAX`] for malformed descriptors
+    pub const fn physical_end(&self) -> u64 {
+        self.start.0.saturating_add(self.size())
+    }
+
+    /// Memory attributes
+    pub const fn attribute(&self) -> MemoryFlags {
+        self.attribute
+    }
+}
+
+/// Iterator over an array of [`MemoryDescriptor`]s
+///
+/// UEFI descriptors may be larger than [`MemoryDescriptor`],
+/// so they are read with a stride of the descriptor size firmware reports.
+#[derive(Debug, Clone)]
+pub struct MemoryDescriptors<'buf> {
+    buf: &'buf [u8],
+    stride: usize,
+}
+
+impl<'buf> MemoryDescriptors<'buf> {
+    /// Create from `buf`, containing descriptors `stride` bytes apart
+    ///
+    /// Returns [`None`] if `stride` is smaller than [`MemoryDescriptor`]
+    pub fn new(buf: &'buf [u8], stride: usize) -> Option<Self> {
+        if stride < size_of::<MemoryDescriptor>() {
+            return None;
+        }
+        Some(Self { buf, stride })
+    }
+}
+
+impl<'buf> Iterator for MemoryDescriptors<'buf> {
+    type Item = MemoryDescriptor;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.len() < size_of::<MemoryDescriptor>() {
+            return None;
+        }
+        // Safety: Checked there are enough bytes for a descriptor, and every
+        // bit pattern is valid for it.
+        let desc = unsafe {
+            self.buf
+                .as_ptr()
+                .cast::<MemoryDescriptor>()
+                .read_unaligned()
+        };
+        self.buf = self.buf.get(self.stride..).unwrap_or_default();
+        Some(desc)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self
+            .buf
+            .len()
+            .saturating_add(self.stride - size_of::<MemoryDescriptor>())
+            / self.stride;
+        (len, Some(len))
+    }
+}
+
+impl<'buf> ExactSizeIterator for MemoryDescriptors<'buf> {}
+
+/// A snapshot of the UEFI memory map
+#[derive(Debug, Clone)]
+pub struct MemoryMap {
+    /// `u64` for descriptor alignment
+    buf: Vec<u64>,
+
+    /// Size of the map in bytes
+    len: usize,
+
+    key: usize,
+    descriptor_size: usize,
+    descriptor_version: u32,
+}
+
+impl MemoryMap {
+    /// Create a new [`MemoryMap`] from the output of `GetMemoryMap`
+    ///
+    /// Returns [`None`] if `len` bytes do not fit in `buf`, or
+    /// `descriptor_size` is too small
+    pub fn new(
+        buf: Vec<u64>,
+        len: usize,
+        key: usize,
+        descriptor_size: usize,
+        descriptor_version: u32,
+    ) -> Option<Self> {
+        if len > buf.len() * size_of::<u64>() || descriptor_size < size_of::<MemoryDescriptor>() {
+            return None;
+        }
+        Some(Self {
+            buf,
+            len,
+            key,
+            descriptor_size,
+            descriptor_version,
+        })
+    }
+
+    /// Key identifying this version of the memory map,
+    /// as required by `ExitBootServices`
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    /// Size in bytes of each descriptor
+    pub fn descriptor_size(&self) -> usize {
+        self.descriptor_size
+    }
+
+    /// Version of each descriptor
+    pub fn descriptor_version(&self) -> u32 {
+        self.descriptor_version
+    }
+
+    /// Number of descriptors
+    pub fn len(&self) -> usize {
+        self.len / self.descriptor_size
+    }
+
+    /// Whether there are no descriptors
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterator over the descriptors in the map
+    pub fn iter(&self) -> MemoryDescriptors<'_> {
+        // Safety: `buf` is valid for `len` bytes, checked in `new`
+        let buf = unsafe { from_raw_parts(self.buf.as_ptr().cast::<u8>(), self.len) };
+        MemoryDescriptors {
+            buf,
+            stride: self.descriptor_size,
+        }
+    }
+
+    /// Iterator over the descriptors with the [`MemoryFlags::RUNTIME`]
+    /// attribute
+    pub fn runtime(&self) -> impl Iterator<Item = MemoryDescriptor> + '_ {
+        self.iter()
+            .filter(|d| d.attribute().contains(MemoryFlags::RUNTIME))
+    }
+}
+
+impl<'map> IntoIterator for &'map MemoryMap {
+    type Item = MemoryDescriptor;
+    type IntoIter = MemoryDescriptors<'map>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
@@ -0,0 +1,103 @@
+//! Function definitions for [`super::RuntimeServices`]
+//!
+//! # References
+//!
+//! - <https://uefi.org/specs/UEFI/2.10/04_EFI_System_Table.html#efi-runtime-services>
+//! - <https://uefi.org/specs/UEFI/2.10/08_Services_Runtime_Services.html>
+use core::ffi::c_void;
+
+use super::{mem::*, ResetType, Time, TimeCapabilities};
+use crate::base::*;
+
+// FIXME: Hack
+type CapsuleHeader = c_void;
+
+pub type GetTime = unsafe extern "efiapi" fn(
+    //
+    time: *mut Time,
+    capabilities: *mut TimeCapabilities,
+) -> Status;
+
+pub type SetTime = unsafe extern "efiapi" fn(
+    //
+    time: *mut Time,
+) -> Status;
+
+pub type GetWakeupTime = unsafe extern "efiapi" fn(
+    enabled: *mut Boolean,
+    pending: *mut Boolean,
+    time: *mut Time,
+) -> Status;
+
+pub type SetWakeupTime = unsafe extern "efiapi" fn(
+    //
+    enable: Boolean,
+    time: *mut Time,
+) -> Status;
+
+pub type SetVirtualAddressMap = unsafe extern "efiapi" fn(
+    map_size: usize,
+    descriptor_size: usize,
+    descriptor_version: u32,
+    virtual_map: *mut MemoryDescriptor,
+) -> Status;
+
+pub type ConvertPointer = unsafe extern "efiapi" fn(
+    //
+    debug_disposition: usize,
+    address: *mut *mut c_void,
+) -> Status;
+
+pub type GetVariable = unsafe extern "efiapi" fn(
+    name: *const Char16,
+    vendor: *const Guid,
+    attributes: *mut u32,
+    data_size: *mut usize,
+    data: *mut c_void,
+) -> Status;
+
+pub type GetNextVariableName = unsafe extern "efiapi" fn(
+    name_size: *mut usize,
+    name: *mut Char16,
+    vendor: *mut Guid,
+) -> Status;
+
+pub type SetVariable = unsafe extern "efiapi" fn(
+    name: *const Char16,
+    vendor: *const Guid,
+    attributes: u32,
+    data_size: usize,
+    data: *const c_void,
+) -> Status;
+
+pub type GetNextHighMonotonicCount = unsafe extern "efiapi" fn(
+    //
+    count: *mut u32,
+) -> Status;
+
+pub type ResetSystem = unsafe extern "efiapi" fn(
+    ty: ResetType,
+    status: Status,
+    data_size: usize,
+    data: *const c_void,
+) -> !;
+
+pub type UpdateCapsule = unsafe extern "efiapi" fn(
+    capsules: *mut *mut CapsuleHeader,
+    count: usize,
+    scatter_gather_list: PhysicalAddress,
+) -> Status;
+
+pub type QueryCapsuleCapabilities = unsafe extern "efiapi" fn(
+    capsules: *mut *mut CapsuleHeader,
+    count: usize,
+    max_size: *mut u64,
+    reset_type: *mut ResetType,
+) -> Status;
+
+pub type QueryVariableInfo = unsafe extern "efiapi" fn(
+    attributes: u32,
+    max_storage_size: *mut u64,
+    remaining_storage_size: *mut u64,
+    max_variable_size: *mut u64,
+) -> Status;
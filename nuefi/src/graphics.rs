@@ -0,0 +1,17 @@
+//! Drawing on the screen
+//!
+//! These work on top of the [`GraphicsOutput`] protocol types, but don't
+//! require firmware themselves, so they can be used in host tests.
+//!
+//! - [`draw`] contains 2D drawing primitives for any [`draw::Target`]
+//! - [`font`] contains PSF bitmap fonts, for drawing text
+//! - [`bmp`] contains a BMP image decoder
+//! - [`screenshot`] saves the screen to BMP files
+//! - [`console`] is a text console drawn with a [`font`]
+//!
+//! [`GraphicsOutput`]: crate::proto::graphics::GraphicsOutput
+pub mod bmp;
+pub mod console;
+pub mod draw;
+pub mod font;
+pub mod screenshot;
@@ -0,0 +1,460 @@
+//! BMP image decoding
+//!
+//! Supports 1, 4, 8, 24, and 32 bits per pixel, both bottom-up and top-down,
+//! uncompressed, RLE8, and bitfields, with any header version.
+//!
+//! Images can also be encoded, see [`Bmp::encode`].
+//!
+//! Images are decoded into a [`BltBuffer`], ready to be drawn with
+//! [`GraphicsOutput::draw_centered`], [`GraphicsOutput::draw_scaled`], or
+//! with a [`Target`][crate::graphics::draw::Target].
+//!
+//! [`GraphicsOutput::draw_centered`]: crate::proto::graphics::GraphicsOutput::draw_centered
+//! [`GraphicsOutput::draw_scaled`]: crate::proto::graphics::GraphicsOutput::draw_scaled
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    error::{Result, Status},
+    proto::graphics::{BltBuffer, Channel, Pixel},
+};
+
+const MAGIC: &[u8; 2] = b"BM";
+const FILE_HEADER_SIZE: usize = 14;
+const CORE_HEADER_SIZE: usize = 12;
+const INFO_HEADER_SIZE: usize = 40;
+
+const BI_RGB: u32 = 0;
+const BI_RLE8: u32 = 1;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+/// Largest image that will be decoded, in pixels
+const MAX_PIXELS: usize = 1 << 26;
+
+/// A decoded BMP image
+#[derive(Debug, Clone)]
+pub struct Bmp {
+    image: BltBuffer,
+    alpha: Option<Vec<u8>>,
+}
+
+impl Bmp {
+    /// Decode the BMP file `data`
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if `data` is malformed,
+    /// or [`Status::UNSUPPORTED`] if it uses an unsupported format.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        Decoder::new(data)?.decode()
+    }
+
+    /// The image pixels
+    pub fn image(&self) -> &BltBuffer {
+        &self.image
+    }
+
+    /// Opacity of each pixel, in rows, if the image has an alpha channel
+    ///
+    /// This is suitable for [`Target::image_masked`].
+    ///
+    /// [`Target::image_masked`]: crate::graphics::draw::Target::image_masked
+    pub fn alpha(&self) -> Option<&[u8]> {
+        self.alpha.as_deref()
+    }
+
+    /// The image pixels
+    pub fn into_image(self) -> BltBuffer {
+        self.image
+    }
+
+    /// Encode `image` as an uncompressed 24-bit BMP file
+    pub fn encode(image: &BltBuffer) -> Vec<u8> {
+        let (width, height) = image.size();
+        let stride = (width * 3).next_multiple_of(4);
+        let offset = FILE_HEADER_SIZE + INFO_HEADER_SIZE;
+        let size = offset + stride * height;
+
+        let mut out = Vec::with_capacity(size);
+        out.extend(MAGIC);
+        out.extend((size as u32).to_le_bytes());
+        out.extend([0; 4]);
+        out.extend((offset as u32).to_le_bytes());
+
+        out.extend((INFO_HEADER_SIZE as u32).to_le_bytes());
+        out.extend((width as u32).to_le_bytes());
+        out.extend((height as u32).to_le_bytes());
+        out.extend(1u16.to_le_bytes());
+        out.extend(24u16.to_le_bytes());
+        out.extend(BI_RGB.to_le_bytes());
+        out.extend(((stride * height) as u32).to_le_bytes());
+        // 72 DPI, and no palette
+        out.extend(2835u32.to_le_bytes());
+        out.extend(2835u32.to_le_bytes());
+        out.extend([0; 8]);
+
+        // Bottom-up
+        for y in (0..height).rev() {
+            let start = out.len();
+            for px in image.row(y) {
+                out.extend([px.blue(), px.green(), px.red()]);
+            }
+            out.resize(start + stride, 0);
+        }
+        out
+    }
+}
+
+/// Read a little endian `u16` at `at`
+fn u16_at(data: &[u8], at: usize) -> Result<u16> {
+    match data.get(at..at + 2) {
+        Some(b) => Ok(u16::from_le_bytes([b[0], b[1]])),
+        None => Err(Status::INVALID_PARAMETER.into()),
+    }
+}
+
+/// Read a little endian `u32` at `at`
+fn u32_at(data: &[u8], at: usize) -> Result<u32> {
+    match data.get(at..at + 4) {
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        None => Err(Status::INVALID_PARAMETER.into()),
+    }
+}
+
+/// Parsed BMP headers
+struct Decoder<'a> {
+    data: &'a [u8],
+    pixels: usize,
+    width: usize,
+    height: usize,
+    top_down: bool,
+    bpp: u16,
+    compression: u32,
+    palette: Vec<Pixel>,
+    masks: [Channel; 4],
+}
+
+impl<'a> Decoder<'a> {
+    fn new(data: &'a [u8]) -> Result<Self> {
+        if !data.starts_with(MAGIC) {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let pixels = u32_at(data, 10)? as usize;
+        let dib = FILE_HEADER_SIZE;
+        let header_size = u32_at(data, dib)? as usize;
+
+        let (width, height, bpp, compression, colors, entry) = if header_size == CORE_HEADER_SIZE {
+            let width = u16_at(data, dib + 4)? as i32;
+            let height = u16_at(data, dib + 6)? as i32;
+            (width, height, u16_at(data, dib + 10)?, BI_RGB, 0, 3)
+        } else if header_size >= INFO_HEADER_SIZE {
+            let width = u32_at(data, dib + 4)? as i32;
+            let height = u32_at(data, dib + 8)? as i32;
+            let bpp = u16_at(data, dib + 14)?;
+            let compression = u32_at(data, dib + 16)?;
+            let colors = u32_at(data, dib + 32)? as usize;
+            (width, height, bpp, compression, colors, 4)
+        } else {
+            return Err(Status::INVALID_PARAMETER.into());
+        };
+
+        let top_down = height < 0;
+        let width = usize::try_from(width).map_err(|_| Status::INVALID_PARAMETER)?;
+        let height = height.unsigned_abs() as usize;
+        if width == 0 || height == 0 {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        if width.checked_mul(height).is_none_or(|n| n > MAX_PIXELS) {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        // Top-down RLE is not allowed
+        if top_down && compression == BI_RLE8 {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+
+        let mut masks = [
+            Channel::from_mask(0x00FF_0000),
+            Channel::from_mask(0x0000_FF00),
+            Channel::from_mask(0x0000_00FF),
+            Channel::from_mask(0),
+        ];
+        let mut palette_at = dib + header_size;
+        match (compression, bpp) {
+            (BI_RGB, 1 | 4 | 8 | 24 | 32) | (BI_RLE8, 8) => (),
+            (BI_BITFIELDS | BI_ALPHABITFIELDS, 32) => {
+                // The masks are in the header since V2, and after it before
+                // then.
+                let count = if compression == BI_ALPHABITFIELDS || header_size >= 56 {
+                    4
+                } else {
+                    3
+                };
+                let at = dib + INFO_HEADER_SIZE;
+                for (i, mask) in masks.iter_mut().enumerate().take(count) {
+                    *mask = Channel::from_mask(u32_at(data, at + i * 4)?);
+                }
+                if header_size == INFO_HEADER_SIZE {
+                    palette_at += count * 4;
+                }
+            }
+            _ => return Err(Status::UNSUPPORTED.into()),
+        }
+
+        let palette = if bpp <= 8 {
+            let max = 1 << bpp;
+            let count = if colors == 0 { max } else { colors.min(max) };
+            let table = data
+                .get(palette_at..palette_at + count * entry)
+                .ok_or(Status::INVALID_PARAMETER)?;
+            table
+                .chunks(entry)
+                .map(|c| Pixel::new(c[2], c[1], c[0]))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            data,
+            pixels,
+            width,
+            height,
+            top_down,
+            bpp,
+            compression,
+            palette,
+            masks,
+        })
+    }
+
+    fn decode(&self) -> Result<Bmp> {
+        let mut image = BltBuffer::new(self.width, self.height);
+        let alpha = if self.compression == BI_RLE8 {
+            self.decode_rle8(&mut image)?;
+            None
+        } else {
+            self.decode_rows(&mut image)?
+        };
+        Ok(Bmp { image, alpha })
+    }
+
+    /// Convert between rows of the image, from the top, and rows of the file
+    fn row_of(&self, row: usize) -> usize {
+        if self.top_down {
+            row
+        } else {
+            self.height - 1 - row
+        }
+    }
+
+    /// Palette entry `index`
+    fn color(&self, index: u8) -> Result<Pixel> {
+        self.palette
+            .get(index as usize)
+            .copied()
+            .ok_or(Status::INVALID_PARAMETER.into())
+    }
+
+    fn decode_rows(&self, image: &mut BltBuffer) -> Result<Option<Vec<u8>>> {
+        let bits = self.width * self.bpp as usize;
+        let stride = bits.div_ceil(32) * 4;
+        let [red, green, blue, a] = self.masks;
+        let mut alpha = (a.width() != 0).then(|| vec![0; self.width * self.height]);
+
+        for y in 0..self.height {
+            let at = self.pixels + self.row_of(y) * stride;
+            let src = self
+                .data
+                .get(at..at + bits.div_ceil(8))
+                .ok_or(Status::INVALID_PARAMETER)?;
+            for x in 0..self.width {
+                let px = match self.bpp {
+                    1 | 4 | 8 => {
+                        let bpp = self.bpp as usize;
+                        let byte = src[x * bpp / 8];
+                        let shift = 8 - bpp - (x * bpp % 8);
+                        self.color((byte >> shift) & ((1 << bpp) - 1) as u8)?
+                    }
+                    24 => Pixel::new(src[x * 3 + 2], src[x * 3 + 1], src[x * 3]),
+                    _ => {
+                        let b = &src[x * 4..][..4];
+                        let raw = u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+                        if let Some(alpha) = &mut alpha {
+                            alpha[y * self.width + x] = a.decode(raw);
+                        }
+                        Pixel::new(red.decode(raw), green.decode(raw), blue.decode(raw))
+                    }
+                };
+                image[(x, y)] = px;
+            }
+        }
+        Ok(alpha)
+    }
+
+    fn decode_rle8(&self, image: &mut BltBuffer) -> Result<()> {
+        let mut src = self
+            .data
+            .get(self.pixels..)
+            .ok_or(Status::INVALID_PARAMETER)?;
+        let (mut x, mut row) = (0usize, 0usize);
+        let mut put = |x: usize, row: usize, px: Pixel| {
+            if x < self.width && row < self.height {
+                image[(x, self.row_of(row))] = px;
+            }
+        };
+        loop {
+            let (count, value) = match src {
+                [count, value, rest @ ..] => {
+                    src = rest;
+                    (*count as usize, *value)
+                }
+                // Missing end of bitmap marker
+                _ => return Ok(()),
+            };
+            match (count, value) {
+                (0, 0) => {
+                    x = 0;
+                    row += 1;
+                }
+                (0, 1) => return Ok(()),
+                (0, 2) => {
+                    let [dx, dy, rest @ ..] = src else {
+                        return Err(Status::INVALID_PARAMETER.into());
+                    };
+                    x += *dx as usize;
+                    row += *dy as usize;
+                    src = rest;
+                }
+                (0, n) => {
+                    let n = n as usize;
+                    let run = src.get(..n).ok_or(Status::INVALID_PARAMETER)?;
+                    for index in run {
+                        put(x, row, self.color(*index)?);
+                        x += 1;
+                    }
+                    src = src.get(n.next_multiple_of(2)..).unwrap_or(&[]);
+                }
+                (n, index) => {
+                    let px = self.color(index)?;
+                    for _ in 0..n {
+                        put(x, row, px);
+                        x += 1;
+                    }
+                }
+            }
+            if row >= self.height {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: usize = 5;
+    const H: usize = 3;
+
+    /// Color of palette entry `i` in the test images
+    fn color(i: usize) -> Pixel {
+        Pixel::new((i * 17) as u8, (255 - i * 17) as u8, (i * 40 % 256) as u8)
+    }
+
+    /// Check `bmp` is the test image, with colors from `f`
+    fn check(file: &[u8], f: impl Fn(usize, usize) -> Pixel) -> Result<Bmp> {
+        let bmp = Bmp::decode(file)?;
+        let image = bmp.image();
+        assert_eq!(image.size(), (W, H));
+        for y in 0..H {
+            for x in 0..W {
+                assert_eq!(image[(x, y)], f(x, y), "Pixel ({x}, {y})");
+            }
+        }
+        Ok(bmp)
+    }
+
+    fn index(x: usize, y: usize) -> usize {
+        y * W + x
+    }
+
+    #[test]
+    fn palette() -> Result<()> {
+        check(include_bytes!("../../testdata/bmp/pal1.bmp"), |x, y| {
+            color(index(x, y) % 2)
+        })?;
+        check(include_bytes!("../../testdata/bmp/pal4.bmp"), |x, y| {
+            color(index(x, y))
+        })?;
+        check(include_bytes!("../../testdata/bmp/pal8.bmp"), |x, y| {
+            color(index(x, y))
+        })?;
+        check(include_bytes!("../../testdata/bmp/core8.bmp"), |x, y| {
+            color(index(x, y))
+        })?;
+        Ok(())
+    }
+
+    #[test]
+    fn rle8() -> Result<()> {
+        let bmp = check(
+            include_bytes!("../../testdata/bmp/rle8.bmp"),
+            |x, y| match (x, y) {
+                (1, 0) => Pixel::default(),
+                _ => color(index(x, y)),
+            },
+        )?;
+        assert!(bmp.alpha().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn rgb() -> Result<()> {
+        let f = |x, y| color(index(x, y));
+        check(include_bytes!("../../testdata/bmp/rgb24.bmp"), f)?;
+        check(include_bytes!("../../testdata/bmp/rgb24_topdown.bmp"), f)?;
+        let bmp = check(include_bytes!("../../testdata/bmp/rgb32.bmp"), f)?;
+        assert!(bmp.alpha().is_none());
+
+        let bmp = check(include_bytes!("../../testdata/bmp/rgba32_v5.bmp"), f)?;
+        let alpha: Vec<u8> = (0..W * H).map(|i| (i * 17) as u8).collect();
+        assert_eq!(bmp.alpha(), Some(&alpha[..]));
+        Ok(())
+    }
+
+    #[test]
+    fn encode() -> Result<()> {
+        let data = include_bytes!("../../testdata/bmp/rgb24.bmp");
+        let original = Bmp::decode(data)?;
+        let file = Bmp::encode(original.image());
+        assert_eq!(file.len(), data.len());
+        // Pixel data is identical, the test file leaves the image size as 0
+        assert_eq!(file[54..], data[54..]);
+        let decoded = Bmp::decode(&file)?;
+        assert_eq!(decoded.image().pixels(), original.image().pixels());
+
+        let image = BltBuffer::new(0, 0);
+        assert_eq!(
+            Bmp::encode(&image).len(),
+            FILE_HEADER_SIZE + INFO_HEADER_SIZE
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid() {
+        let file = include_bytes!("../../testdata/bmp/rgb24.bmp");
+        let status = |data: &[u8]| Bmp::decode(data).err().map(|e| e.status());
+        assert_eq!(status(&file[..80]), Some(Status::INVALID_PARAMETER));
+        assert_eq!(status(&file[..20]), Some(Status::INVALID_PARAMETER));
+        assert_eq!(status(b"PNG"), Some(Status::INVALID_PARAMETER));
+
+        let mut jpeg = file.to_vec();
+        jpeg[FILE_HEADER_SIZE + 16] = 4;
+        assert_eq!(status(&jpeg), Some(Status::UNSUPPORTED));
+
+        let mut bad_index = include_bytes!("../../testdata/bmp/pal8.bmp").to_vec();
+        // First pixel of the top row, which is last
+        let top = bad_index.len() - 8;
+        bad_index[top] = 200;
+        assert_eq!(status(&bad_index), Some(Status::INVALID_PARAMETER));
+    }
+}
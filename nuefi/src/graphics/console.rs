@@ -0,0 +1,751 @@
+//! A text console drawn on a [`GraphicsOutput`]
+//!
+//! [`GraphicsConsole`] implements [`SimpleTextOutput`] by drawing a bitmap
+//! [`Font`], for firmware whose own console is too small or missing.
+//!
+//! Its modes are derived from the screen size, mode `0` being `80x25` and
+//! mode `1` being `80x50`, if they fit, and the last mode using the whole
+//! screen, which it starts in.
+//!
+//! It can be used directly with [`GraphicsConsole::output`], installed on a
+//! handle with [`GraphicsConsole::install`], or replace the firmware's
+//! console with [`GraphicsConsole::install_stdout`].
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{
+    char::{decode_utf16, REPLACEMENT_CHARACTER},
+    fmt,
+};
+
+use crate::{
+    error::{Result, Status},
+    graphics::font::Font,
+    nuefi_core::base::Char16,
+    proto::{
+        console::{
+            raw::{RawSimpleTextOutput, RawTextMode},
+            SimpleTextOutput,
+        },
+        graphics::{BltBuffer, GraphicsOutput, Pixel},
+        provider::Provider,
+    },
+    table::{Boot, BootServices, SystemTable},
+    EfiHandle,
+};
+
+/// The 16 text colors, indexed by attribute
+const PALETTE: [Pixel; 16] = [
+    Pixel::new(0x00, 0x00, 0x00),
+    Pixel::new(0x00, 0x00, 0x98),
+    Pixel::new(0x00, 0x98, 0x00),
+    Pixel::new(0x00, 0x98, 0x98),
+    Pixel::new(0x98, 0x00, 0x00),
+    Pixel::new(0x98, 0x00, 0x98),
+    Pixel::new(0x98, 0x98, 0x00),
+    Pixel::new(0x98, 0x98, 0x98),
+    Pixel::new(0x30, 0x30, 0x30),
+    Pixel::new(0x00, 0x00, 0xFF),
+    Pixel::new(0x00, 0xFF, 0x00),
+    Pixel::new(0x00, 0xFF, 0xFF),
+    Pixel::new(0xFF, 0x00, 0x00),
+    Pixel::new(0xFF, 0x00, 0xFF),
+    Pixel::new(0xFF, 0xFF, 0x00),
+    Pixel::new(0xFF, 0xFF, 0xFF),
+];
+
+/// Light gray on black
+const DEFAULT_ATTRIBUTE: u8 = 0x07;
+
+/// Height of the cursor, in pixels
+const CURSOR_HEIGHT: usize = 2;
+
+/// A [`SimpleTextOutput`] implementation drawing on a [`GraphicsOutput`]
+#[derive(Debug)]
+pub struct GraphicsConsole {
+    provider: Provider<RawSimpleTextOutput, State>,
+}
+
+impl GraphicsConsole {
+    /// Create a new console on `gop`, drawing with `font`, in the mode using
+    /// the whole screen.
+    ///
+    /// The screen is cleared.
+    ///
+    /// Returns [`Status::UNSUPPORTED`] if not even a single character fits
+    /// on the screen.
+    ///
+    /// # Safety
+    ///
+    /// - `gop` must be valid for as long as the console is used, which is
+    ///   forever once installed
+    pub unsafe fn new(gop: &GraphicsOutput, font: Font<'static>) -> Result<Self> {
+        // Safety: Caller ensures `gop` lives long enough
+        let gop: GraphicsOutput<'static> = unsafe { GraphicsOutput::new(gop.as_ptr()) };
+        let (width, height) = gop.mode().res();
+        let full = (
+            width as usize / font.width(),
+            height as usize / font.height(),
+        );
+        if full.0 == 0 || full.1 == 0 {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        let fits = |(cols, rows): (usize, usize)| cols <= full.0 && rows <= full.1;
+
+        let mut modes = vec![Some(Some((80, 25)).filter(|m| fits(*m)).unwrap_or(full))];
+        modes.push(Some((80, 50)).filter(|m| fits(*m)));
+        if !modes.contains(&Some(full)) {
+            modes.push(Some(full));
+        }
+
+        let mode = RawTextMode {
+            max_mode: modes.len() as i32,
+            mode: 0,
+            attribute: DEFAULT_ATTRIBUTE as i32,
+            cursor_column: 0,
+            cursor_row: 0,
+            cursor_visible: true,
+        };
+        let cell = BltBuffer::new(font.width(), font.height());
+        let state = State {
+            gop,
+            font,
+            modes,
+            mode: Box::into_raw(Box::new(mode)),
+            size: (0, 0),
+            origin: (0, 0),
+            cells: Vec::new(),
+            cell,
+        };
+        let raw = RawSimpleTextOutput {
+            reset: Some(reset),
+            output_string: Some(output_string),
+            test_string: Some(test_string),
+            query_mode: Some(query_mode),
+            set_mode: Some(set_mode),
+            set_attribute: Some(set_attribute),
+            clear_screen: Some(clear_screen),
+            set_cursor_position: Some(set_cursor_position),
+            enable_cursor: Some(enable_cursor),
+            mode: state.mode,
+        };
+        let this = Self {
+            provider: Provider::new(raw, state),
+        };
+        this.output().set_mode(this.max_mode())?;
+        Ok(this)
+    }
+
+    /// Use this console as a [`SimpleTextOutput`]
+    pub fn output(&self) -> SimpleTextOutput<'_> {
+        // Safety: `as_ptr` is a valid `RawSimpleTextOutput` as long as we are
+        unsafe { SimpleTextOutput::new(self.provider.as_ptr()) }
+    }
+
+    /// Install this console on `handle`, or a new handle if [`None`],
+    /// returning the handle
+    ///
+    /// This leaks the console, unless installing fails.
+    pub fn install(self, boot: &BootServices, handle: Option<EfiHandle>) -> Result<EfiHandle> {
+        self.provider.install::<SimpleTextOutput>(boot, handle)
+    }
+
+    /// Install this console on a new handle, and replace
+    /// [`SystemTable::stdout`] with it.
+    ///
+    /// This leaks the console, unless installing fails.
+    ///
+    /// # Safety
+    ///
+    /// - See [`SystemTable::set_stdout`]
+    pub unsafe fn install_stdout(self, table: &SystemTable<Boot>) -> Result<EfiHandle> {
+        let out = self.provider.as_ptr();
+        let handle = self.install(&table.boot(), None)?;
+        // Safety:
+        // - `out` was installed on `handle`, and leaked
+        // - Caller ensures nothing is using the old stdout
+        unsafe { table.set_stdout(handle, out) };
+        Ok(handle)
+    }
+
+    /// The last supported mode
+    fn max_mode(&self) -> u32 {
+        self.provider
+            .with_state(|s| s.modes.iter().rposition(Option::is_some))
+            .flatten()
+            .unwrap_or(0) as u32
+    }
+}
+
+/// Console state, shared by the protocol functions
+struct State {
+    gop: GraphicsOutput<'static>,
+    font: Font<'static>,
+
+    /// (cols, rows) of each mode, [`None`] if unsupported
+    modes: Vec<Option<(usize, usize)>>,
+
+    /// Our [`RawTextMode`], which firmware reads directly
+    mode: *mut RawTextMode,
+
+    /// (cols, rows) of the current mode
+    size: (usize, usize),
+
+    /// Top left pixel of the text, which is centered on the screen
+    origin: (usize, usize),
+
+    /// Character and attribute of every cell, row by row
+    cells: Vec<(char, u8)>,
+
+    /// Scratch buffer for drawing a cell
+    cell: BltBuffer,
+}
+
+impl State {
+    fn mode(&mut self) -> &mut RawTextMode {
+        // Safety: `mode` was leaked from a `Box` in `GraphicsConsole::new`,
+        // and is only freed when we are dropped
+        unsafe { &mut *self.mode }
+    }
+
+    fn attribute(&mut self) -> u8 {
+        self.mode().attribute as u8
+    }
+
+    fn cursor(&mut self) -> (usize, usize) {
+        let mode = self.mode();
+        (mode.cursor_column as usize, mode.cursor_row as usize)
+    }
+
+    fn set_mode(&mut self, mode: usize) -> Result<()> {
+        let size = self
+            .modes
+            .get(mode)
+            .copied()
+            .flatten()
+            .ok_or(Status::UNSUPPORTED)?;
+        let (width, height) = self.gop.mode().res();
+        let (fw, fh) = (self.font.width(), self.font.height());
+        // The screen may have shrunk since the modes were computed
+        let center = |screen: u32, used: usize| {
+            (screen as usize)
+                .checked_sub(used)
+                .map(|free| free / 2)
+                .ok_or(Status::UNSUPPORTED)
+        };
+        self.origin = (center(width, size.0 * fw)?, center(height, size.1 * fh)?);
+        self.size = size;
+        self.mode().mode = mode as i32;
+        self.clear()
+    }
+
+    fn set_attribute(&mut self, attribute: usize) -> Result<()> {
+        if attribute > 0x7F {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        self.mode().attribute = attribute as i32;
+        Ok(())
+    }
+
+    /// Clear the whole screen to the current background
+    fn clear(&mut self) -> Result<()> {
+        let attr = self.attribute();
+        let (width, height) = self.gop.mode().res();
+        let size = (width as usize, height as usize);
+        self.gop.fill(PALETTE[attr as usize >> 4], (0, 0), size)?;
+        self.cells = vec![(' ', attr); self.size.0 * self.size.1];
+        let mode = self.mode();
+        mode.cursor_column = 0;
+        mode.cursor_row = 0;
+        self.show_cursor()
+    }
+
+    fn set_cursor(&mut self, col: usize, row: usize) -> Result<()> {
+        if col >= self.size.0 || row >= self.size.1 {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        self.hide_cursor()?;
+        let mode = self.mode();
+        mode.cursor_column = col as i32;
+        mode.cursor_row = row as i32;
+        self.show_cursor()
+    }
+
+    fn enable_cursor(&mut self, visible: bool) -> Result<()> {
+        self.hide_cursor()?;
+        self.mode().cursor_visible = visible;
+        self.show_cursor()
+    }
+
+    /// Redraw the cell under the cursor without it
+    fn hide_cursor(&mut self) -> Result<()> {
+        let (col, row) = self.cursor();
+        self.draw_cell(col, row, false)
+    }
+
+    /// Redraw the cell under the cursor with it, if visible
+    fn show_cursor(&mut self) -> Result<()> {
+        let (col, row) = self.cursor();
+        let visible = self.mode().cursor_visible;
+        self.draw_cell(col, row, visible)
+    }
+
+    fn draw_cell(&mut self, col: usize, row: usize, cursor: bool) -> Result<()> {
+        let Some(&(c, attr)) = self.cells.get(row * self.size.0 + col) else {
+            return Ok(());
+        };
+        let fore = PALETTE[attr as usize & 0xF];
+        let back = PALETTE[attr as usize >> 4];
+        let glyph = self.font.glyph_or_fallback(c);
+        let (width, height) = self.cell.size();
+        for y in 0..height {
+            let underline = cursor && y >= height.saturating_sub(CURSOR_HEIGHT);
+            for (x, px) in self.cell.row_mut(y).iter_mut().enumerate() {
+                *px = if underline || glyph.get(x, y) {
+                    fore
+                } else {
+                    back
+                };
+            }
+        }
+        let dest = (self.origin.0 + col * width, self.origin.1 + row * height);
+        self.gop
+            .buffer_to_video(&self.cell, (0, 0), dest, (width, height))
+    }
+
+    /// Scroll the text up one row, clearing the last row
+    fn scroll(&mut self) -> Result<()> {
+        let (cols, rows) = self.size;
+        let (fw, fh) = (self.font.width(), self.font.height());
+        let (x, y) = self.origin;
+        let attr = self.attribute();
+
+        self.gop
+            .video_to_video((x, y + fh), (x, y), (cols * fw, (rows - 1) * fh))?;
+        self.gop.fill(
+            PALETTE[attr as usize >> 4],
+            (x, y + (rows - 1) * fh),
+            (cols * fw, fh),
+        )?;
+        self.cells.copy_within(cols.., 0);
+        let last = self.cells.len() - cols;
+        self.cells[last..].fill((' ', attr));
+        Ok(())
+    }
+
+    /// Move to the start of the next line, scrolling if needed
+    fn newline(&mut self) -> Result<()> {
+        let (_, row) = self.cursor();
+        if row + 1 >= self.size.1 {
+            self.scroll()?;
+        } else {
+            self.mode().cursor_row += 1;
+        }
+        Ok(())
+    }
+
+    fn output(&mut self, s: &[Char16]) -> Result<()> {
+        self.hide_cursor()?;
+        let mut unknown = false;
+        for c in decode_utf16(s.iter().copied()) {
+            let c = c.unwrap_or(REPLACEMENT_CHARACTER);
+            match c {
+                '\r' => self.mode().cursor_column = 0,
+                '\n' => self.newline()?,
+                '\x08' => {
+                    let mode = self.mode();
+                    mode.cursor_column = mode.cursor_column.saturating_sub(1);
+                }
+                c => {
+                    unknown |= self.font.glyph(c).is_none();
+                    let attr = self.attribute();
+                    let (col, row) = self.cursor();
+                    self.cells[row * self.size.0 + col] = (c, attr);
+                    self.draw_cell(col, row, false)?;
+                    if col + 1 >= self.size.0 {
+                        self.mode().cursor_column = 0;
+                        self.newline()?;
+                    } else {
+                        self.mode().cursor_column += 1;
+                    }
+                }
+            }
+        }
+        self.show_cursor()?;
+        if unknown {
+            return Err(Status::WARN_UNKNOWN_GLYPH.into());
+        }
+        Ok(())
+    }
+
+    /// Whether every character in `s` can be displayed
+    fn supports(&self, s: &[Char16]) -> bool {
+        decode_utf16(s.iter().copied()).all(|c| {
+            c.is_ok_and(|c| matches!(c, '\r' | '\n' | '\x08') || self.font.glyph(c).is_some())
+        })
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        // Safety: `mode` was leaked from a `Box` in `GraphicsConsole::new`
+        drop(unsafe { Box::from_raw(self.mode) });
+    }
+}
+
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("State")
+            .field("size", &self.size)
+            .field("origin", &self.origin)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Call `f` with the state for `this`
+///
+/// # Safety
+///
+/// - `this` must be a [`GraphicsConsole`]
+unsafe fn with<F>(this: *mut RawSimpleTextOutput, f: F) -> Status
+where
+    F: FnOnce(&mut State) -> Result<()>,
+{
+    if this.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    // Safety: Caller ensures `this` is from a `GraphicsConsole`
+    match unsafe { Provider::<RawSimpleTextOutput, State>::with(this, f) } {
+        Some(Ok(())) => Status::SUCCESS,
+        Some(Err(e)) => e.status(),
+        None => Status::NOT_READY,
+    }
+}
+
+/// The nul terminated `s` as a slice, without the nul
+///
+/// # Safety
+///
+/// - `s` must be a valid nul terminated string
+unsafe fn from_nul<'a>(s: *const Char16) -> &'a [Char16] {
+    let mut len = 0;
+    while *s.add(len) != 0 {
+        len += 1;
+    }
+    core::slice::from_raw_parts(s, len)
+}
+
+unsafe extern "efiapi" fn reset(this: *mut RawSimpleTextOutput, _extended: bool) -> Status {
+    with(this, |s| {
+        s.set_attribute(DEFAULT_ATTRIBUTE.into())?;
+        s.mode().cursor_visible = true;
+        s.clear()
+    })
+}
+
+unsafe extern "efiapi" fn output_string(
+    this: *mut RawSimpleTextOutput,
+    string: *const Char16,
+) -> Status {
+    if string.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    with(this, |s| s.output(from_nul(string)))
+}
+
+unsafe extern "efiapi" fn test_string(
+    this: *mut RawSimpleTextOutput,
+    string: *const Char16,
+) -> Status {
+    if string.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    with(this, |s| {
+        if s.supports(from_nul(string)) {
+            Ok(())
+        } else {
+            Err(Status::UNSUPPORTED.into())
+        }
+    })
+}
+
+unsafe extern "efiapi" fn query_mode(
+    this: *mut RawSimpleTextOutput,
+    mode: usize,
+    cols: *mut usize,
+    rows: *mut usize,
+) -> Status {
+    if cols.is_null() || rows.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    with(this, |s| {
+        let size = s.modes.get(mode).copied().flatten();
+        let (c, r) = size.ok_or(Status::UNSUPPORTED)?;
+        *cols = c;
+        *rows = r;
+        Ok(())
+    })
+}
+
+unsafe extern "efiapi" fn set_mode(this: *mut RawSimpleTextOutput, mode: usize) -> Status {
+    with(this, |s| s.set_mode(mode))
+}
+
+unsafe extern "efiapi" fn set_attribute(this: *mut RawSimpleTextOutput, attr: usize) -> Status {
+    with(this, |s| s.set_attribute(attr))
+}
+
+unsafe extern "efiapi" fn clear_screen(this: *mut RawSimpleTextOutput) -> Status {
+    with(this, |s| s.clear())
+}
+
+unsafe extern "efiapi" fn set_cursor_position(
+    this: *mut RawSimpleTextOutput,
+    cols: usize,
+    rows: usize,
+) -> Status {
+    with(this, |s| s.set_cursor(cols, rows))
+}
+
+unsafe extern "efiapi" fn enable_cursor(this: *mut RawSimpleTextOutput, visible: bool) -> Status {
+    with(this, |s| s.enable_cursor(visible))
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        ptr::addr_of_mut,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    use super::*;
+    use crate::{
+        graphics::font::tests::psf1,
+        proto::{
+            console::{TextBackground, TextForeground},
+            graphics::raw::{
+                RawBltOperation,
+                RawBltPixel,
+                RawGraphicsInfo,
+                RawGraphicsMode,
+                RawGraphicsOutput,
+                RawPixelFormat,
+                RawPixelMask,
+            },
+        },
+    };
+
+    const W: usize = 40;
+    const H: usize = 12;
+
+    static SCREEN: [AtomicU32; W * H] = [const { AtomicU32::new(0) }; W * H];
+
+    fn pixel(x: usize, y: usize) -> Pixel {
+        let raw = SCREEN[y * W + x].load(Ordering::Relaxed);
+        Pixel::from_bytes(raw.to_ne_bytes())
+    }
+
+    /// Mock `blt` drawing on [`SCREEN`]
+    unsafe extern "efiapi" fn blt(
+        this: *mut RawGraphicsOutput,
+        buffer: *mut RawBltPixel,
+        op: RawBltOperation,
+        src_x: usize,
+        src_y: usize,
+        dest_x: usize,
+        dest_y: usize,
+        width: usize,
+        height: usize,
+        delta: usize,
+    ) -> Status {
+        let buffer = buffer.cast::<Pixel>();
+        let delta = delta / size_of::<Pixel>();
+        let raw = |px: Pixel| u32::from_ne_bytes(*px.as_bytes());
+        // Copy from the top down, which is fine for scrolling up
+        for y in 0..height {
+            for x in 0..width {
+                let dest = &SCREEN[(dest_y + y) * W + dest_x + x];
+                let px = match op {
+                    RawBltOperation::VIDEO_FILL => raw(*buffer),
+                    RawBltOperation::BUFFER_TO_VIDEO => {
+                        raw(*buffer.add((src_y + y) * delta + src_x + x))
+                    }
+                    RawBltOperation::VIDEO_TO_VIDEO => {
+                        SCREEN[(src_y + y) * W + src_x + x].load(Ordering::Relaxed)
+                    }
+                    _ => return Status::UNSUPPORTED,
+                };
+                dest.store(px, Ordering::Relaxed);
+            }
+        }
+        Status::SUCCESS
+    }
+
+    /// Mock `blt` that draws nothing
+    unsafe extern "efiapi" fn blt_nop(
+        _: *mut RawGraphicsOutput,
+        _: *mut RawBltPixel,
+        _: RawBltOperation,
+        _: usize,
+        _: usize,
+        _: usize,
+        _: usize,
+        _: usize,
+        _: usize,
+        _: usize,
+    ) -> Status {
+        Status::SUCCESS
+    }
+
+    #[test]
+    fn console() -> Result<()> {
+        let mut info = RawGraphicsInfo {
+            version: 0,
+            horizontal: W as u32,
+            vertical: H as u32,
+            format: RawPixelFormat::BGR,
+            info: RawPixelMask {
+                red: 0,
+                green: 0,
+                blue: 0,
+                reserved: 0,
+            },
+            stride: W as u32,
+        };
+        let mut mode = RawGraphicsMode {
+            max_mode: 1,
+            mode: 0,
+            info: addr_of_mut!(info),
+            info_size: size_of::<RawGraphicsInfo>(),
+            fb_base: 0,
+            fb_size: 0,
+        };
+        let mut raw = RawGraphicsOutput {
+            query_mode: None,
+            set_mode: None,
+            blt: Some(blt),
+            mode: addr_of_mut!(mode),
+        };
+        // Safety: `raw` outlives `gop`
+        let gop = unsafe { GraphicsOutput::new(addr_of_mut!(raw)) };
+        let font = Font::parse(psf1().leak())?;
+        let gray = PALETTE[7];
+        let black = PALETTE[0];
+
+        // Safety: `gop` outlives `console`
+        let console = unsafe { GraphicsConsole::new(&gop, font)? };
+        let out = console.output();
+        // `SimpleTextOutput::output_string` needs firmware to allocate
+        let ucs2 = |s: &str| s.encode_utf16().chain([0]).collect::<Vec<u16>>();
+        let this = console.provider.as_ptr();
+        // Safety: `this` is our console, and strings are nul terminated
+        let print = |s: &str| unsafe { output_string(this, ucs2(s).as_ptr()) };
+        // Safety: Same as above
+        let supports = |s: &str| unsafe { test_string(this, ucs2(s).as_ptr()) };
+
+        // 8x4 glyphs, so 5x3 cells, which doesn't fit 80x25 or 80x50
+        assert_eq!(out.mode()?.size(), (5, 3));
+        assert_eq!(out.query_mode(0)?.size(), (5, 3));
+        assert!(out
+            .query_mode(1)
+            .is_err_and(|e| e.status() == Status::UNSUPPORTED));
+
+        // `A` is 0x41, so the second and last pixels of its first row are set
+        assert_eq!(print("AB"), Status::SUCCESS);
+        assert_eq!(out.cursor(), (2, 0));
+        assert_eq!((pixel(1, 0), pixel(7, 0), pixel(0, 0)), (gray, gray, black));
+
+        // Cursor underline
+        assert_eq!(pixel(17, 2), gray);
+        out.disable_cursor()?;
+        assert_eq!(pixel(17, 2), black);
+
+        out.set_attributes(TextForeground::WHITE, TextBackground::BLUE)?;
+        assert_eq!(print("C"), Status::SUCCESS);
+        assert_eq!((pixel(17, 0), pixel(16, 0)), (PALETTE[15], PALETTE[1]));
+
+        // Wraps, and scrolls `A` off the screen
+        assert_eq!(print("DE\r\n\r\nX"), Status::SUCCESS);
+        assert_eq!(out.cursor(), (1, 2));
+        assert_eq!(pixel(1, 0), black);
+        assert_eq!(pixel(1, 8), PALETTE[15]);
+
+        assert_eq!(supports("AB\r\n"), Status::SUCCESS);
+        assert_eq!(supports("€"), Status::UNSUPPORTED);
+        assert_eq!(print("€"), Status::WARN_UNKNOWN_GLYPH);
+        assert!(out
+            .set_cursor(5, 0)
+            .is_err_and(|e| e.status() == Status::UNSUPPORTED));
+
+        out.reset()?;
+        assert_eq!(out.cursor(), (0, 0));
+        assert!(out.cursor_visible());
+        assert_eq!(out.attributes().0, TextForeground::LIGHT_GRAY);
+        assert!(SCREEN[W * 4..]
+            .iter()
+            .all(|p| p.load(Ordering::Relaxed) == 0));
+        Ok(())
+    }
+
+    #[test]
+    fn shrunk_screen() -> Result<()> {
+        let mut info = RawGraphicsInfo {
+            version: 0,
+            horizontal: W as u32,
+            vertical: H as u32,
+            format: RawPixelFormat::BGR,
+            info: RawPixelMask {
+                red: 0,
+                green: 0,
+                blue: 0,
+                reserved: 0,
+            },
+            stride: W as u32,
+        };
+        let info_ptr = addr_of_mut!(info);
+        let mut mode = RawGraphicsMode {
+            max_mode: 1,
+            mode: 0,
+            info: info_ptr,
+            info_size: size_of::<RawGraphicsInfo>(),
+            fb_base: 0,
+            fb_size: 0,
+        };
+        let mut raw = RawGraphicsOutput {
+            query_mode: None,
+            set_mode: None,
+            blt: Some(blt_nop),
+            mode: addr_of_mut!(mode),
+        };
+        // Safety: `raw` outlives `gop`
+        let gop = unsafe { GraphicsOutput::new(addr_of_mut!(raw)) };
+        let font = Font::parse(psf1().leak())?;
+        // Safety: `gop` outlives `console`
+        let console = unsafe { GraphicsConsole::new(&gop, font)? };
+        let out = console.output();
+        assert_eq!(out.mode()?.size(), (5, 3));
+
+        // Safety: `info` is only read through `info_ptr` by `gop`
+        unsafe {
+            (*info_ptr).horizontal = 16;
+            (*info_ptr).stride = 16;
+        }
+        assert!(out
+            .set_mode(0)
+            .is_err_and(|e| e.status() == Status::UNSUPPORTED));
+        assert_eq!(out.mode()?.size(), (5, 3));
+
+        // Safety: See above
+        unsafe { (*info_ptr).vertical = 4 };
+        assert!(out
+            .set_mode(0)
+            .is_err_and(|e| e.status() == Status::UNSUPPORTED));
+
+        // Safety: See above
+        unsafe {
+            (*info_ptr).horizontal = W as u32;
+            (*info_ptr).stride = W as u32;
+            (*info_ptr).vertical = H as u32;
+        }
+        out.set_mode(0)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,583 @@
+//! 2D drawing primitives
+//!
+//! Lines, rectangles, circles, images, and [`Font`] text can be drawn on any
+//! [`Target`], such as the [`Framebuffer`], the [`Double`] buffer, or a
+//! [`BltBuffer`] in memory.
+//! A [`BltBuffer`] works anywhere, including host tests, so drawing can be
+//! tested by comparing it against an expected buffer.
+//!
+//! Positions are signed, and everything is clipped to the [`Target`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use nuefi::{graphics::{draw::Target, font::Font}, proto::graphics::{BltBuffer, Pixel, Rect}};
+//! # fn f(font: &Font) {
+//! let mut canvas = BltBuffer::new(320, 200);
+//! let white = Pixel::new(255, 255, 255);
+//! canvas.fill_rounded_rect(Rect::new(10, 10, 300, 180), 8, Pixel::new(0, 0, 128));
+//! canvas.text(20, 20, "Boot Menu", font, white, 2);
+//! # }
+//! ```
+use crate::{
+    graphics::font::Font,
+    proto::graphics::{BltBuffer, Double, Framebuffer, Pixel, Rect},
+};
+
+/// A signed (x, y) position, which may be off the [`Target`]
+pub type Point = (isize, isize);
+
+/// A surface of [`Pixel`]s that can be drawn on
+///
+/// Only [`Target::size`], [`Target::pixel`], and [`Target::set_pixel`] are
+/// required, everything else is drawn with them.
+pub trait Target {
+    /// Size in (Width, Height)
+    fn size(&self) -> (usize, usize);
+
+    /// Pixel at (`x`, `y`), or [`None`] if it is off the target
+    fn pixel(&self, x: usize, y: usize) -> Option<Pixel>;
+
+    /// Set the pixel at (`x`, `y`), if it is on the target
+    fn set_pixel(&mut self, x: usize, y: usize, color: Pixel);
+
+    /// Fill `rect` with `color`
+    fn fill_rect(&mut self, rect: Rect, color: Pixel) {
+        let rect = clip(self, rect);
+        for y in rect.y..rect.bottom() {
+            for x in rect.x..rect.right() {
+                self.set_pixel(x, y, color);
+            }
+        }
+    }
+
+    /// Set the pixel at (`x`, `y`), if it is on the target
+    fn plot(&mut self, x: isize, y: isize, color: Pixel) {
+        if let (Ok(x), Ok(y)) = (usize::try_from(x), usize::try_from(y)) {
+            self.set_pixel(x, y, color);
+        }
+    }
+
+    /// Blend `color` over the pixel at (`x`, `y`), with `alpha` opacity
+    fn blend(&mut self, x: isize, y: isize, color: Pixel, alpha: u8) {
+        let (Ok(x), Ok(y)) = (usize::try_from(x), usize::try_from(y)) else {
+            return;
+        };
+        match alpha {
+            0 => (),
+            255 => self.set_pixel(x, y, color),
+            _ => {
+                if let Some(under) = self.pixel(x, y) {
+                    self.set_pixel(x, y, mix(under, color, alpha));
+                }
+            }
+        }
+    }
+
+    /// Draw a line from `from` to `to`, including both ends
+    ///
+    /// Only the part of the line on the target is walked, so far off
+    /// endpoints cost no more than a line across the target.
+    fn line(&mut self, from: Point, to: Point, color: Pixel) {
+        let (width, height) = self.size();
+        let dx = to.0 as i128 - from.0 as i128;
+        let dy = to.1 as i128 - from.1 as i128;
+        // Step along the longer axis, which moves one pixel every step
+        if dx.abs() >= dy.abs() {
+            for (x, y) in line_steps(from, (dx, dy), width) {
+                self.plot(x, y, color);
+            }
+        } else {
+            for (y, x) in line_steps((from.1, from.0), (dy, dx), height) {
+                self.plot(x, y, color);
+            }
+        }
+    }
+
+    /// Draw a one pixel outline of `rect`
+    fn rect(&mut self, rect: Rect, color: Pixel) {
+        self.rounded_rect(rect, 0, color);
+    }
+
+    /// Draw a one pixel outline of `rect`, with corners of `radius`
+    fn rounded_rect(&mut self, rect: Rect, radius: usize, color: Pixel) {
+        let radius = radius.min(rect.width / 2).min(rect.height / 2);
+        let last = rect.height.saturating_sub(1);
+        for row in 0..rect.height {
+            let inset = corner_inset(row, rect.height, radius);
+            let span = rect.width - 2 * inset;
+            let edge = if row == 0 || row == last {
+                span
+            } else {
+                let above = corner_inset(row - 1, rect.height, radius);
+                let below = corner_inset(row + 1, rect.height, radius);
+                (above.max(below).saturating_sub(inset)).clamp(1, span.div_ceil(2))
+            };
+            let y = rect.y + row;
+            self.fill_rect(Rect::new(rect.x + inset, y, edge, 1), color);
+            self.fill_rect(Rect::new(rect.x + inset + span - edge, y, edge, 1), color);
+        }
+    }
+
+    /// Fill `rect` with `color`, with corners of `radius`
+    fn fill_rounded_rect(&mut self, rect: Rect, radius: usize, color: Pixel) {
+        let radius = radius.min(rect.width / 2).min(rect.height / 2);
+        for row in 0..rect.height {
+            let inset = corner_inset(row, rect.height, radius);
+            let span = Rect::new(rect.x + inset, rect.y + row, rect.width - 2 * inset, 1);
+            self.fill_rect(span, color);
+        }
+    }
+
+    /// Draw a one pixel outline of a circle
+    fn circle(&mut self, center: Point, radius: usize, color: Pixel) {
+        let (cx, cy) = center;
+        let mut x = radius as isize;
+        let mut y = 0;
+        let mut err = 1 - x;
+        while x >= y {
+            for (px, py) in [(x, y), (y, x), (-y, x), (-x, y)] {
+                self.plot(cx + px, cy + py, color);
+                self.plot(cx - px, cy - py, color);
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Fill a circle with `color`
+    fn fill_circle(&mut self, center: Point, radius: usize, color: Pixel) {
+        let (width, height) = self.size();
+        let (cx, cy) = (center.0 as i128, center.1 as i128);
+        let r = radius as i128;
+        // Only rows on the target, in wider arithmetic than any radius
+        let top = (cy - r).max(0);
+        let bottom = (cy + r).min(height as i128 - 1);
+        for y in top..=bottom {
+            let dy = (y - cy).unsigned_abs();
+            let half = (radius as u128 * radius as u128 - dy * dy).isqrt() as i128;
+            let left = (cx - half).max(0);
+            let right = (cx + half).min(width as i128 - 1);
+            if left <= right {
+                let len = (right - left + 1) as usize;
+                self.fill_rect(Rect::new(left as usize, y as usize, len, 1), color);
+            }
+        }
+    }
+
+    /// Draw `image` with its top left at (`x`, `y`)
+    fn image(&mut self, x: isize, y: isize, image: &BltBuffer) {
+        for row in 0..image.height() {
+            for (col, px) in image.row(row).iter().enumerate() {
+                self.plot(x + col as isize, y + row as isize, *px);
+            }
+        }
+    }
+
+    /// Draw `image` with its top left at (`x`, `y`), with `alpha` opacity
+    fn image_alpha(&mut self, x: isize, y: isize, image: &BltBuffer, alpha: u8) {
+        for row in 0..image.height() {
+            for (col, px) in image.row(row).iter().enumerate() {
+                self.blend(x + col as isize, y + row as isize, *px, alpha);
+            }
+        }
+    }
+
+    /// Draw `image` with its top left at (`x`, `y`), with each pixel's
+    /// opacity from `mask`
+    ///
+    /// `mask` is in rows of [`BltBuffer::width`], and pixels without an entry
+    /// are skipped.
+    fn image_masked(&mut self, x: isize, y: isize, image: &BltBuffer, mask: &[u8]) {
+        let mut alpha = mask.iter();
+        for row in 0..image.height() {
+            for (col, px) in image.row(row).iter().enumerate() {
+                let Some(a) = alpha.next() else {
+                    return;
+                };
+                self.blend(x + col as isize, y + row as isize, *px, *a);
+            }
+        }
+    }
+
+    /// Draw `s` in `font` with its top left at (`x`, `y`),
+    /// each glyph pixel `scale` pixels wide
+    ///
+    /// Newlines start a new line at `x`.
+    /// Characters without a glyph are drawn as in [`Font::glyph_or_fallback`].
+    ///
+    /// Returns the position after the last character.
+    fn text(
+        &mut self,
+        x: isize,
+        y: isize,
+        s: &str,
+        font: &Font,
+        color: Pixel,
+        scale: usize,
+    ) -> Point {
+        let scale = scale.max(1);
+        let (width, height) = (font.width() * scale, font.height() * scale);
+        let mut pos = (x, y);
+        for c in s.chars() {
+            if c == '\n' {
+                pos = (x, pos.1 + height as isize);
+                continue;
+            }
+            let glyph = font.glyph_or_fallback(c);
+            for gy in 0..glyph.height() {
+                for gx in (0..glyph.width()).filter(|gx| glyph.get(*gx, gy)) {
+                    let at = (pos.0 + (gx * scale) as isize, pos.1 + (gy * scale) as isize);
+                    self.fill_rect(signed_rect(at, scale, scale), color);
+                }
+            }
+            pos.0 += width as isize;
+        }
+        pos
+    }
+}
+
+/// Pixels of a line from `from`, `delta` long, with the major axis first,
+/// that are within `0..len` on the major axis
+///
+/// This matches the classic Bresenham line, but finds the minor axis position
+/// of each step directly rather than walking there from `from`.
+fn line_steps(from: Point, delta: (i128, i128), len: usize) -> impl Iterator<Item = Point> {
+    let (major, minor) = (delta.0.unsigned_abs(), delta.1.unsigned_abs());
+    let (start, last) = (from.0 as i128, len as i128 - 1);
+    let (first, end) = if delta.0 >= 0 {
+        (-start, last - start)
+    } else {
+        (start - last, start)
+    };
+    let steps = first.max(0)..=end.min(major as i128);
+    steps.map(move |step| {
+        // Round to nearest, with halves rounding up
+        let along = minor * step as u128;
+        let off = match major {
+            0 => 0,
+            _ => along / major + u128::from(2 * (along % major) >= major),
+        };
+        let x = start + delta.0.signum() * step;
+        let y = from.1 as i128 + delta.1.signum() * off as i128;
+        (x as isize, y as isize)
+    })
+}
+
+/// `rect`, clipped to `target`
+fn clip<T: Target + ?Sized>(target: &T, rect: Rect) -> Rect {
+    let (width, height) = target.size();
+    rect.intersect(&Rect::new(0, 0, width, height))
+}
+
+/// A `width` by `height` [`Rect`] at `pos`, without the negative parts
+fn signed_rect(pos: Point, width: usize, height: usize) -> Rect {
+    let clamp = |p: isize, len: usize| match usize::try_from(p) {
+        Ok(p) => (p, len),
+        Err(_) => (0, len.saturating_sub(p.unsigned_abs())),
+    };
+    let (x, width) = clamp(pos.0, width);
+    let (y, height) = clamp(pos.1, height);
+    Rect::new(x, y, width, height)
+}
+
+/// `over` blended onto `under` with `alpha` opacity
+fn mix(under: Pixel, over: Pixel, alpha: u8) -> Pixel {
+    let a = alpha as u16;
+    let c = |u: u8, o: u8| ((o as u16 * a + u as u16 * (255 - a) + 127) / 255) as u8;
+    Pixel::new(
+        c(under.red(), over.red()),
+        c(under.green(), over.green()),
+        c(under.blue(), over.blue()),
+    )
+}
+
+/// How far row `row` of a `height` tall rounded rectangle is inset by its
+/// corners of `radius`
+fn corner_inset(row: usize, height: usize, radius: usize) -> usize {
+    let edge = row.min(height - 1 - row);
+    if edge >= radius {
+        return 0;
+    }
+    // Pixel centers are tested against the corner circle, in half pixels
+    let dy = 2 * (radius - edge) - 1;
+    let r2 = 4 * radius * radius;
+    (0..radius)
+        .find(|col| {
+            let dx = 2 * (radius - col) - 1;
+            dx * dx + dy * dy <= r2
+        })
+        .unwrap_or(radius)
+}
+
+/// An in-memory canvas, useful for host tests
+impl Target for BltBuffer {
+    fn size(&self) -> (usize, usize) {
+        BltBuffer::size(self)
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Option<Pixel> {
+        self.get(x, y).copied()
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: Pixel) {
+        if let Some(p) = self.get_mut(x, y) {
+            *p = color;
+        }
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Pixel) {
+        let rect = clip(self, rect);
+        for y in rect.y..rect.bottom() {
+            self.row_mut(y)[rect.x..rect.right()].fill(color);
+        }
+    }
+}
+
+/// Drawing marks the changed areas dirty, for [`Double::flush`]
+impl<'gop> Target for Double<'gop> {
+    fn size(&self) -> (usize, usize) {
+        Double::size(self)
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Option<Pixel> {
+        self.buffer().get(x, y).copied()
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: Pixel) {
+        self.set(x, y, color);
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Pixel) {
+        self.fill(rect, color);
+    }
+}
+
+/// Pixels are written directly to the screen, with volatile accesses
+impl<'gop> Target for Framebuffer<'gop> {
+    fn size(&self) -> (usize, usize) {
+        let (w, h) = self.mode().res();
+        (w as usize, h as usize)
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Option<Pixel> {
+        let (w, h) = Target::size(self);
+        if x < w && y < h {
+            self.mode().decode(self.read(x as u32, y as u32))
+        } else {
+            None
+        }
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: Pixel) {
+        let (w, h) = Target::size(self);
+        if x < w && y < h {
+            if let Some(raw) = self.mode().encode(color) {
+                self.write(x as u32, y as u32, raw);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use super::*;
+    use crate::graphics::font::tests::{psf1, psf2};
+
+    const W: Pixel = Pixel::new(255, 255, 255);
+    const B: Pixel = Pixel::new(0, 0, 0);
+
+    /// Render `canvas` as text, `#` for white and `.` for black
+    fn render(canvas: &BltBuffer) -> Vec<String> {
+        (0..canvas.height())
+            .map(|y| {
+                let row = canvas.row(y).iter();
+                row.map(|p| if *p == W { '#' } else { '.' }).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lines() {
+        let mut canvas = BltBuffer::new(5, 5);
+        canvas.line((0, 0), (4, 4), W);
+        canvas.line((4, 0), (-10, 0), W);
+        canvas.line((0, 4), (0, 10), W);
+        assert_eq!(
+            render(&canvas),
+            ["#####", ".#...", "..#..", "...#.", "#...#"]
+        );
+
+        // Far off endpoints only walk the visible part
+        let mut canvas = BltBuffer::new(5, 5);
+        canvas.line((isize::MIN, isize::MIN), (isize::MAX, isize::MAX), W);
+        canvas.line((-1 << 40, 4), (1 << 40, 4), W);
+        canvas.line((-1 << 40, -1), (1 << 40, -1), W);
+        assert_eq!(
+            render(&canvas),
+            ["#....", ".#...", "..#..", "...#.", "#####"]
+        );
+    }
+
+    #[test]
+    fn clipped_lines() {
+        /// Unclipped Bresenham, walking every step
+        fn walk(canvas: &mut BltBuffer, from: Point, to: Point) {
+            let (mut x, mut y) = from;
+            let dx = (to.0 - x).abs();
+            let dy = -(to.1 - y).abs();
+            let sx = if x < to.0 { 1 } else { -1 };
+            let sy = if y < to.1 { 1 } else { -1 };
+            let mut err = dx + dy;
+            loop {
+                canvas.plot(x, y, W);
+                if (x, y) == to {
+                    break;
+                }
+                let e2 = 2 * err;
+                if e2 >= dy {
+                    err += dy;
+                    x += sx;
+                }
+                if e2 <= dx {
+                    err += dx;
+                    y += sy;
+                }
+            }
+        }
+
+        let range = -3..9;
+        for (x0, y0) in range
+            .clone()
+            .flat_map(|x| range.clone().map(move |y| (x, y)))
+        {
+            for (x1, y1) in range
+                .clone()
+                .flat_map(|x| range.clone().map(move |y| (x, y)))
+            {
+                let (from, to) = ((x0, y0), (x1, y1));
+                let mut expected = BltBuffer::new(6, 5);
+                walk(&mut expected, from, to);
+                let mut canvas = BltBuffer::new(6, 5);
+                canvas.line(from, to, W);
+                assert_eq!(render(&canvas), render(&expected), "{from:?} {to:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn rects() {
+        let mut canvas = BltBuffer::new(6, 5);
+        canvas.rect(Rect::new(0, 0, 6, 5), W);
+        canvas.fill_rect(Rect::new(2, 2, 10, 10), W);
+        assert_eq!(
+            render(&canvas),
+            ["######", "#....#", "#.####", "#.####", "######"]
+        );
+
+        let mut canvas = BltBuffer::new(8, 6);
+        canvas.fill_rounded_rect(Rect::new(0, 0, 8, 6), 3, W);
+        assert_eq!(
+            render(&canvas),
+            [".######.", "########", "########", "########", "########", ".######."]
+        );
+
+        let mut canvas = BltBuffer::new(10, 8);
+        canvas.rounded_rect(Rect::new(0, 0, 10, 8), 4, W);
+        let expected = [
+            "..######..",
+            ".#......#.",
+            "#........#",
+            "#........#",
+            "#........#",
+            "#........#",
+            ".#......#.",
+            "..######..",
+        ];
+        assert_eq!(render(&canvas), expected);
+    }
+
+    #[test]
+    fn circles() {
+        let mut canvas = BltBuffer::new(7, 7);
+        canvas.circle((3, 3), 3, W);
+        let expected = [
+            "..###..", ".#...#.", "#.....#", "#.....#", "#.....#", ".#...#.", "..###..",
+        ];
+        assert_eq!(render(&canvas), expected);
+
+        let mut canvas = BltBuffer::new(5, 5);
+        canvas.fill_circle((2, 2), 2, W);
+        canvas.fill_circle((0, 0), 1, W);
+        assert_eq!(
+            render(&canvas),
+            ["###..", "####.", "#####", ".###.", "..#.."]
+        );
+
+        // Huge circles only fill the target
+        let mut canvas = BltBuffer::new(3, 2);
+        canvas.fill_circle((1, 1), usize::MAX, W);
+        assert_eq!(render(&canvas), ["###", "###"]);
+        let mut canvas = BltBuffer::new(3, 2);
+        canvas.fill_circle((isize::MIN, 0), usize::MAX / 2, W);
+        assert_eq!(render(&canvas), ["...", "..."]);
+    }
+
+    #[test]
+    fn images() {
+        let mut canvas = BltBuffer::new(4, 2);
+        let image = BltBuffer::from_pixels([W, W, W, W].to_vec(), 2, 2).unwrap();
+        canvas.image(-1, 0, &image);
+        canvas.image(3, 1, &image);
+        assert_eq!(render(&canvas), ["#...", "#..#"]);
+
+        let mut canvas = BltBuffer::new(2, 2);
+        canvas.image_alpha(0, 0, &image, 0);
+        assert_eq!(canvas.pixel(0, 0), Some(B));
+        canvas.image_masked(0, 0, &image, &[255, 128, 0]);
+        assert_eq!(canvas.pixel(0, 0), Some(W));
+        assert_eq!(canvas.pixel(1, 0), Some(Pixel::new(128, 128, 128)));
+        assert_eq!(canvas.pixel(0, 1), Some(B));
+        assert_eq!(canvas.pixel(1, 1), Some(B));
+    }
+
+    #[test]
+    fn text() -> crate::error::Result<()> {
+        let data = psf1();
+        let font = Font::parse(&data)?;
+        let mut canvas = BltBuffer::new(16, 8);
+        let end = canvas.text(0, 0, "\u{3}\u{C0}\n\u{1}", &font, W, 1);
+        assert_eq!(end, (8, 4));
+        let expected = [
+            "......####......",
+            "#......##......#",
+            "#......##......#",
+            "################",
+            ".......#........",
+            "#......#........",
+            "#......#........",
+            "########........",
+        ];
+        assert_eq!(render(&canvas), expected);
+
+        let data = psf2();
+        let font = Font::parse(&data)?;
+        let mut canvas = BltBuffer::new(20, 4);
+        canvas.text(0, 0, "A", &font, W, 2);
+        let expected = [
+            "####################",
+            "####################",
+            "##................##",
+            "##................##",
+        ];
+        assert_eq!(render(&canvas), expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+//! PC Screen Font bitmap fonts
+//!
+//! Supports both [PSF1 and PSF2][psf], including their unicode tables.
+//!
+//! Fonts are not included, they must be supplied by the caller, such as with
+//! [`include_bytes!`] of a console font from the `kbd` project.
+//!
+//! [psf]: <https://www.win.tue.nl/~aeb/linux/kbd/font-formats-1.html>
+use alloc::collections::BTreeMap;
+use core::str::from_utf8;
+
+use crate::error::{Result, Status};
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE_512: u8 = 0x01;
+const PSF1_MODE_HAS_TAB: u8 = 0x02;
+const PSF1_MODE_SEQ: u8 = 0x04;
+const PSF1_SEPARATOR: u16 = 0xFFFF;
+const PSF1_START_SEQ: u16 = 0xFFFE;
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xB5, 0x4A, 0x86];
+/// Size of the fixed PSF2 header, which its header size field must cover
+const PSF2_HEADER_SIZE: usize = 32;
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xFF;
+const PSF2_START_SEQ: u8 = 0xFE;
+
+/// A PSF1 or PSF2 bitmap font
+#[derive(Debug, Clone)]
+pub struct Font<'a> {
+    glyphs: &'a [u8],
+    count: usize,
+    width: usize,
+    height: usize,
+    glyph_size: usize,
+    unicode: Option<BTreeMap<char, usize>>,
+}
+
+impl<'a> Font<'a> {
+    /// Parse a PSF1 or PSF2 font from `data`
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if `data` is not a valid font.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        if data.starts_with(&PSF1_MAGIC) {
+            Self::parse_psf1(data)
+        } else if data.starts_with(&PSF2_MAGIC) {
+            Self::parse_psf2(data)
+        } else {
+            Err(Status::INVALID_PARAMETER.into())
+        }
+    }
+
+    fn parse_psf1(data: &'a [u8]) -> Result<Self> {
+        let (mode, height) = match data {
+            [_, _, mode, height, ..] => (*mode, *height as usize),
+            _ => return Err(Status::INVALID_PARAMETER.into()),
+        };
+        let count = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+        let end = 4 + count * height;
+        let glyphs = data.get(4..end).ok_or(Status::INVALID_PARAMETER)?;
+
+        let unicode = if mode & (PSF1_MODE_HAS_TAB | PSF1_MODE_SEQ) != 0 {
+            let mut table = BTreeMap::new();
+            let mut glyph = 0;
+            let mut in_seq = false;
+            let (entries, _) = data[end..].as_chunks::<2>();
+            for c in entries {
+                match u16::from_le_bytes(*c) {
+                    PSF1_SEPARATOR => {
+                        glyph += 1;
+                        in_seq = false;
+                    }
+                    PSF1_START_SEQ => in_seq = true,
+                    c if !in_seq => {
+                        if let Some(c) = char::from_u32(c.into()) {
+                            table.entry(c).or_insert(glyph);
+                        }
+                    }
+                    _ => (),
+                }
+                if glyph >= count {
+                    break;
+                }
+            }
+            Some(table)
+        } else {
+            None
+        };
+
+        Self::new(glyphs, count, 8, height, unicode)
+    }
+
+    fn parse_psf2(data: &'a [u8]) -> Result<Self> {
+        let field = |i: usize| -> Result<usize> {
+            let b = data
+                .get(i * 4..i * 4 + 4)
+                .ok_or(Status::INVALID_PARAMETER)?;
+            Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        };
+        let (header, flags, count) = (field(2)?, field(3)?, field(4)?);
+        let (glyph_size, height, width) = (field(5)?, field(6)?, field(7)?);
+        if header < PSF2_HEADER_SIZE || glyph_size < width.div_ceil(8) * height {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let end = count
+            .checked_mul(glyph_size)
+            .and_then(|len| len.checked_add(header))
+            .ok_or(Status::INVALID_PARAMETER)?;
+        let glyphs = data.get(header..end).ok_or(Status::INVALID_PARAMETER)?;
+
+        let unicode = if flags as u32 & PSF2_HAS_UNICODE_TABLE != 0 {
+            let mut table = BTreeMap::new();
+            let entries = data[end..].split(|b| *b == PSF2_SEPARATOR);
+            for (glyph, entry) in entries.take(count).enumerate() {
+                // Sequences of multiple characters are not supported
+                let singles = entry.split(|b| *b == PSF2_START_SEQ).next();
+                let chars = singles.and_then(|s| from_utf8(s).ok()).unwrap_or("");
+                for c in chars.chars() {
+                    table.entry(c).or_insert(glyph);
+                }
+            }
+            Some(table)
+        } else {
+            None
+        };
+
+        Self::new(glyphs, count, width, height, unicode)
+    }
+
+    fn new(
+        glyphs: &'a [u8],
+        count: usize,
+        width: usize,
+        height: usize,
+        unicode: Option<BTreeMap<char, usize>>,
+    ) -> Result<Self> {
+        if count == 0 || width == 0 || height == 0 {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        Ok(Self {
+            glyph_size: glyphs.len() / count,
+            glyphs,
+            count,
+            width,
+            height,
+            unicode,
+        })
+    }
+
+    /// Width of every glyph, in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of every glyph, in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of glyphs
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Whether there are no glyphs, which is never true
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Glyph for `c`, if the font has one
+    ///
+    /// Without a unicode table, glyphs are indexed by code point.
+    pub fn glyph(&self, c: char) -> Option<Glyph<'a>> {
+        let index = match &self.unicode {
+            Some(table) => *table.get(&c)?,
+            None => c as usize,
+        };
+        self.glyph_at(index)
+    }
+
+    /// Glyph for `c`, falling back to `?` and then the first glyph
+    pub fn glyph_or_fallback(&self, c: char) -> Glyph<'a> {
+        self.glyph(c)
+            .or_else(|| self.glyph('?'))
+            .or_else(|| self.glyph_at(0))
+            .expect("Font had no glyphs")
+    }
+
+    /// Glyph number `index`
+    pub fn glyph_at(&self, index: usize) -> Option<Glyph<'a>> {
+        if index >= self.count {
+            return None;
+        }
+        let start = index * self.glyph_size;
+        Some(Glyph {
+            data: &self.glyphs[start..][..self.glyph_size],
+            width: self.width,
+            height: self.height,
+        })
+    }
+}
+
+/// A single bitmap [`Font`] glyph
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Glyph<'a> {
+    /// Width in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the pixel at (`x`, `y`) is set
+    ///
+    /// Pixels outside the glyph are never set.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let row = self.width.div_ceil(8) * y;
+        self.data[row + x / 8] & (0x80 >> (x % 8)) != 0
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    /// An 8x4 glyph for every byte, shaped like its top row
+    pub(crate) fn psf1() -> Vec<u8> {
+        let mut font = PSF1_MAGIC.to_vec();
+        font.extend([0, 4]);
+        for i in 0..=255u8 {
+            font.extend([i, 0x81, 0x81, 0xFF]);
+        }
+        font
+    }
+
+    /// A 10x2 font of `A` and a `?`, with a unicode table
+    pub(crate) fn psf2() -> Vec<u8> {
+        let mut font = PSF2_MAGIC.to_vec();
+        for field in [0, 32, PSF2_HAS_UNICODE_TABLE, 2, 4, 2, 10] {
+            font.extend(u32::to_le_bytes(field));
+        }
+        font.extend([0xFF, 0xC0, 0x80, 0x40]);
+        font.extend([0x0C, 0x00, 0x0C, 0x00]);
+        font.extend(b"A\xC3\x84\xFEA\xCC\x88\xFF?\xFF");
+        font
+    }
+
+    #[test]
+    fn psf1_font() -> Result<()> {
+        let data = psf1();
+        let font = Font::parse(&data)?;
+        assert_eq!((font.width(), font.height(), font.len()), (8, 4, 256));
+
+        let glyph = font.glyph('A').unwrap();
+        assert!(glyph.get(1, 0) && glyph.get(7, 0) && !glyph.get(0, 0));
+        assert!(glyph.get(0, 1) && !glyph.get(1, 1));
+        assert!(!glyph.get(8, 0) && !glyph.get(0, 4));
+        assert!(font.glyph('€').is_none());
+
+        assert!(Font::parse(&data[..100]).is_err());
+        assert!(Font::parse(b"nope").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn psf2_font() -> Result<()> {
+        let data = psf2();
+        let font = Font::parse(&data)?;
+        assert_eq!((font.width(), font.height(), font.len()), (10, 2, 2));
+
+        let glyph = font.glyph('Ä').unwrap();
+        assert!((0..10).all(|x| glyph.get(x, 0)));
+        assert!(glyph.get(0, 1) && glyph.get(9, 1) && !glyph.get(1, 1));
+
+        assert!(font.glyph('B').is_none());
+        let fallback = font.glyph_or_fallback('B');
+        assert!(fallback.get(4, 0) && !fallback.get(0, 0));
+
+        assert!(Font::parse(&data[..39]).is_err());
+
+        // The header size must cover the fixed header
+        let mut short = data.clone();
+        short[8..12].copy_from_slice(&u32::to_le_bytes(16));
+        assert!(Font::parse(&short).is_err());
+        Ok(())
+    }
+}
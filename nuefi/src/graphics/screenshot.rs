@@ -0,0 +1,394 @@
+//! Screenshots, saved as BMP files
+//!
+//! [`save_to_boot_volume`] saves the screen to the root of the volume this
+//! image was loaded from, usually the ESP, for example for bug reports.
+//!
+//! A hotkey can also be registered with [`register_hotkey`].
+//! Firmware calls key notifications at a raised task priority, where file
+//! access is not allowed, so the screenshot is only saved once the
+//! application calls [`save_if_requested`].
+use alloc::{format, string::String};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{
+    error::{Result, Status},
+    get_boot_table,
+    get_image_handle,
+    graphics::bmp::Bmp,
+    proto::{
+        console::{Key, KeyData, KeyNotify, ShiftState, SimpleTextInputEx},
+        graphics::GraphicsOutput,
+        loaded_image::LoadedImage,
+        media::{FsHandle, SimpleFileSystem},
+    },
+};
+
+/// Most screenshots [`save_to_boot_volume`] will name
+const MAX_SCREENSHOTS: usize = 1000;
+
+/// Set by the [`register_hotkey`] notification
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Save the screen to a new file `name` in `dir`
+///
+/// Returns [`Status::ACCESS_DENIED`] if `name` already exists.
+pub fn save(gop: &GraphicsOutput, dir: &FsHandle, name: &str) -> Result<()> {
+    if exists(dir, name)? {
+        return Err(Status::ACCESS_DENIED.into());
+    }
+    let file = Bmp::encode(&gop.capture()?);
+
+    let out = dir.create(name)?;
+    out.write_all(&file)?;
+    out.flush()?;
+    out.close()
+}
+
+/// Save the screen to the root of the volume this image was loaded from,
+/// as the first free `screenshot-NNN.bmp`
+///
+/// Returns the name of the new file.
+pub fn save_to_boot_volume(gop: &GraphicsOutput) -> Result<String> {
+    let table = get_boot_table().ok_or(Status::UNSUPPORTED)?;
+    let image = get_image_handle().ok_or(Status::UNSUPPORTED)?;
+    let boot = table.boot();
+
+    // Safety: Neither protocol is kept past this function
+    let fs = unsafe {
+        let loaded = boot
+            .handle_protocol::<LoadedImage>(image)?
+            .ok_or(Status::UNSUPPORTED)?;
+        let device = loaded.device().ok_or(Status::UNSUPPORTED)?;
+        boot.handle_protocol::<SimpleFileSystem>(device)?
+            .ok_or(Status::UNSUPPORTED)?
+    };
+    let root = fs.open_volume()?;
+    save_next(gop, &root)
+}
+
+/// Save the screen to `dir` as the first free `screenshot-NNN.bmp`,
+/// returning its name
+fn save_next(gop: &GraphicsOutput, dir: &FsHandle) -> Result<String> {
+    for i in 0..MAX_SCREENSHOTS {
+        let name = format!("screenshot-{i:03}.bmp");
+        if !exists(dir, &name)? {
+            save(gop, dir, &name)?;
+            return Ok(name);
+        }
+    }
+    Err(Status::VOLUME_FULL.into())
+}
+
+/// Whether `name` exists in `dir`
+///
+/// The handle opened to check is closed before returning.
+fn exists(dir: &FsHandle, name: &str) -> Result<bool> {
+    match dir.open(name) {
+        Ok(file) => {
+            file.close()?;
+            Ok(true)
+        }
+        Err(e) if e.status() == Status::NOT_FOUND => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Request a screenshot whenever `key` is pressed with `shift`, such as
+/// [`ScanCode::Function(12)`][crate::proto::console::ScanCode::Function]
+///
+/// The screenshot is taken by the next [`save_if_requested`].
+/// The hotkey stays registered until the returned [`KeyNotify`] is dropped.
+pub fn register_hotkey<'table>(
+    input: &SimpleTextInputEx<'table>,
+    key: Key,
+    shift: ShiftState,
+) -> Result<KeyNotify<'table>> {
+    input.register_key_notify(key, shift, request)
+}
+
+/// The [`register_hotkey`] notification
+fn request(_: KeyData) {
+    REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Whether the [`register_hotkey`] key was pressed since the last call
+pub fn requested() -> bool {
+    REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// Save a screenshot with [`save_to_boot_volume`] if one was requested,
+/// returning the name of the new file
+pub fn save_if_requested(gop: &GraphicsOutput) -> Result<Option<String>> {
+    if requested() {
+        save_to_boot_volume(gop).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{collections::BTreeMap, rc::Rc, vec::Vec};
+    use core::{cell::RefCell, mem::size_of, ptr::addr_of_mut};
+
+    use super::*;
+    use crate::proto::{
+        console::ToggleState,
+        graphics::{
+            raw::{
+                RawBltOperation,
+                RawBltPixel,
+                RawGraphicsInfo,
+                RawGraphicsMode,
+                RawGraphicsOutput,
+                RawPixelFormat,
+                RawPixelMask,
+            },
+            Pixel,
+        },
+        media::raw::RawFsHandle,
+        provider::Provider,
+    };
+
+    /// Mock screen size
+    const W: usize = 3;
+    const H: usize = 2;
+
+    /// Color of the mock screen at (`x`, `y`)
+    fn color(x: usize, y: usize) -> Pixel {
+        Pixel::new(0x10 + x as u8, 0x20 + y as u8, 0x30)
+    }
+
+    /// Mock `blt` reading [`color`]s
+    unsafe extern "efiapi" fn blt(
+        _: *mut RawGraphicsOutput,
+        buffer: *mut RawBltPixel,
+        op: RawBltOperation,
+        src_x: usize,
+        src_y: usize,
+        dest_x: usize,
+        dest_y: usize,
+        width: usize,
+        height: usize,
+        delta: usize,
+    ) -> Status {
+        if op != RawBltOperation::VIDEO_TO_BUFFER {
+            return Status::UNSUPPORTED;
+        }
+        let buffer = buffer.cast::<Pixel>();
+        let delta = delta / size_of::<Pixel>();
+        for y in 0..height {
+            for x in 0..width {
+                // Safety: `buffer` is valid for the block
+                unsafe {
+                    *buffer.add((dest_y + y) * delta + dest_x + x) = color(src_x + x, src_y + y);
+                }
+            }
+        }
+        Status::SUCCESS
+    }
+
+    /// Files in the mock directory, and the number of open handles
+    #[derive(Debug, Default)]
+    struct Volume {
+        files: BTreeMap<String, Vec<u8>>,
+        open: usize,
+        handles: Vec<MockFile>,
+    }
+
+    /// A mock file handle, and its name, or [`None`] for the directory
+    struct Mock {
+        volume: Rc<RefCell<Volume>>,
+        name: Option<String>,
+    }
+
+    type MockFile = Provider<RawFsHandle, Mock>;
+
+    /// Call `f` with the [`Mock`] for `this`
+    fn mock<U>(this: *mut RawFsHandle, f: impl FnOnce(&mut Mock) -> U) -> U {
+        // Safety: `this` is only ever from a `MockFile`
+        unsafe { MockFile::with(this, f) }.unwrap()
+    }
+
+    unsafe extern "efiapi" fn open(
+        this: *mut RawFsHandle,
+        new: *mut *mut RawFsHandle,
+        name: *const u16,
+        mode: u64,
+        _: u64,
+    ) -> Status {
+        let mut len = 0;
+        // Safety: `name` is nul terminated
+        while unsafe { *name.add(len) } != 0 {
+            len += 1;
+        }
+        // Safety: See above
+        let name = String::from_utf16(unsafe { core::slice::from_raw_parts(name, len) }).unwrap();
+        let volume = mock(this, |m| m.volume.clone());
+        let mut vol = volume.borrow_mut();
+        let create = mode & 0x8000_0000_0000_0000 != 0;
+        if !create && !vol.files.contains_key(&name) {
+            return Status::NOT_FOUND;
+        }
+        vol.files.entry(name.clone()).or_default();
+        let file = mock_file(volume.clone(), Some(name));
+        // Safety: `new` is valid
+        unsafe { *new = file.as_ptr() };
+        vol.open += 1;
+        vol.handles.push(file);
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn close(this: *mut RawFsHandle) -> Status {
+        mock(this, |m| {
+            // The directory isn't opened through `open`
+            if m.name.is_some() {
+                m.volume.borrow_mut().open -= 1;
+            }
+        });
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn write(
+        this: *mut RawFsHandle,
+        size: *mut usize,
+        buf: *const u8,
+    ) -> Status {
+        mock(this, |m| {
+            let name = m.name.as_ref().unwrap();
+            let mut vol = m.volume.borrow_mut();
+            // Safety: `buf` is valid for `size` bytes
+            let data = unsafe { core::slice::from_raw_parts(buf, *size) };
+            vol.files.get_mut(name).unwrap().extend_from_slice(data);
+        });
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn flush(_: *mut RawFsHandle) -> Status {
+        Status::SUCCESS
+    }
+
+    fn mock_file(volume: Rc<RefCell<Volume>>, name: Option<String>) -> MockFile {
+        let raw = RawFsHandle {
+            revision: 0x00010000,
+            open: Some(open),
+            close: Some(close),
+            delete: None,
+            read: None,
+            write: Some(write),
+            get_pos: None,
+            set_pos: None,
+            get_info: None,
+            set_info: None,
+            flush: Some(flush),
+            open_ex: core::ptr::null(),
+            read_ex: core::ptr::null(),
+            write_ex: core::ptr::null(),
+            flush_ex: core::ptr::null(),
+        };
+        Provider::new(raw, Mock { volume, name })
+    }
+
+    /// The BMP for the mock screen
+    fn expected_bmp() -> Vec<u8> {
+        // 14 byte file header, 40 byte info header, and 2 rows of 12 bytes
+        let mut bmp = Vec::new();
+        bmp.extend(b"BM");
+        bmp.extend(78u32.to_le_bytes());
+        bmp.extend([0; 4]);
+        bmp.extend(54u32.to_le_bytes());
+        bmp.extend(40u32.to_le_bytes());
+        bmp.extend((W as u32).to_le_bytes());
+        bmp.extend((H as u32).to_le_bytes());
+        bmp.extend(1u16.to_le_bytes());
+        bmp.extend(24u16.to_le_bytes());
+        bmp.extend(0u32.to_le_bytes());
+        bmp.extend(24u32.to_le_bytes());
+        bmp.extend(2835u32.to_le_bytes());
+        bmp.extend(2835u32.to_le_bytes());
+        bmp.extend([0; 8]);
+        // Bottom-up BGR rows, padded to 4 bytes
+        bmp.extend([
+            0x30, 0x21, 0x10, 0x30, 0x21, 0x11, 0x30, 0x21, 0x12, 0, 0, 0,
+        ]);
+        bmp.extend([
+            0x30, 0x20, 0x10, 0x30, 0x20, 0x11, 0x30, 0x20, 0x12, 0, 0, 0,
+        ]);
+        bmp
+    }
+
+    #[test]
+    fn save_screen() -> Result<()> {
+        let mut info = RawGraphicsInfo {
+            version: 0,
+            horizontal: W as u32,
+            vertical: H as u32,
+            format: RawPixelFormat::BLT_ONLY,
+            info: RawPixelMask {
+                red: 0,
+                green: 0,
+                blue: 0,
+                reserved: 0,
+            },
+            stride: W as u32,
+        };
+        let mut mode = RawGraphicsMode {
+            max_mode: 1,
+            mode: 0,
+            info: addr_of_mut!(info),
+            info_size: size_of::<RawGraphicsInfo>(),
+            fb_base: 0,
+            fb_size: 0,
+        };
+        let mut raw = RawGraphicsOutput {
+            query_mode: None,
+            set_mode: None,
+            blt: Some(blt),
+            mode: addr_of_mut!(mode),
+        };
+        // Safety: `raw` outlives `gop`
+        let gop = unsafe { GraphicsOutput::new(addr_of_mut!(raw)) };
+
+        let volume = Rc::new(RefCell::new(Volume::default()));
+        {
+            let mut vol = volume.borrow_mut();
+            vol.files.insert("screenshot-000.bmp".into(), Vec::new());
+            vol.files.insert("screenshot-001.bmp".into(), Vec::new());
+        }
+        let root = mock_file(volume.clone(), None);
+        // Safety: `root` outlives `dir`
+        let dir = unsafe { FsHandle::new(root.as_ptr()) };
+
+        save(&gop, &dir, "shot.bmp")?;
+        assert_eq!(volume.borrow().files["shot.bmp"], expected_bmp());
+        assert!(save(&gop, &dir, "shot.bmp").is_err_and(|e| e.status() == Status::ACCESS_DENIED));
+
+        assert_eq!(save_next(&gop, &dir)?, "screenshot-002.bmp");
+        {
+            let vol = volume.borrow();
+            assert_eq!(vol.files["screenshot-002.bmp"], expected_bmp());
+            assert!(vol.files["screenshot-000.bmp"].is_empty());
+            assert_eq!(vol.files.len(), 4);
+            // Every probe and file handle was closed
+            assert_eq!(vol.open, 0);
+        }
+
+        drop(dir);
+        volume.borrow_mut().handles.clear();
+        Ok(())
+    }
+
+    #[test]
+    fn hotkey() {
+        let key = KeyData {
+            key: Some(Key::Char('p')),
+            shift: ShiftState::empty(),
+            toggle: ToggleState::empty(),
+        };
+        assert!(!requested());
+        request(key);
+        request(key);
+        assert!(requested());
+        assert!(!requested());
+    }
+}
@@ -79,11 +79,13 @@ use table::raw::RawSystemTable;
 
 use crate::nuefi_core::base::Status;
 pub use crate::table::{Boot, SystemTable};
+pub mod graphics;
 pub mod logger;
 pub mod mem;
 pub mod proto;
 pub mod string;
 pub mod table;
+pub mod tui;
 mod util;
 
 /// UEFI Core types
@@ -527,6 +529,152 @@ mod tests {
             // See: The commit that added this comment for details
             // forget(_box);
         }
+        // Don't leave other tests the mock table, which is freed now
+        TABLE.store(core::ptr::null_mut(), Ordering::Release);
+        HANDLE.store(core::ptr::null_mut(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// [`config::RuntimeProperties`] only applies after ExitBootServices
+    ///
+    /// [`config::RuntimeProperties`]: nuefi_core::table::config::RuntimeProperties
+    #[test]
+    fn runtime_properties() -> Result<()> {
+        use nuefi_core::table::{
+            config::{ConfigurationTable, RuntimeProperties, RuntimeServicesSupported},
+            Time,
+            TimeCapabilities,
+        };
+
+        use crate::{proto::Entity, table::Runtime};
+
+        unsafe extern "efiapi" fn get_time(time: *mut Time, _: *mut TimeCapabilities) -> Status {
+            time.write(Time {
+                year: 2000,
+                ..Default::default()
+            });
+            Status::SUCCESS
+        }
+
+        let (mut st, _box) = mock();
+        // Version 1, length 8, and only SetTime
+        let mut properties: [u32; 2] = [0x0008_0001, RuntimeServicesSupported::SET_TIME.bits()];
+        let mut tables = [ConfigurationTable {
+            guid: RuntimeProperties::GUID,
+            table: properties.as_mut_ptr().cast(),
+        }];
+        st.configuration_table = tables.as_mut_ptr();
+        st.number_of_table_entries = tables.len();
+        // Safety: `runtime_services` is from `mock`
+        unsafe { (*st.runtime_services).get_time = Some(get_time) };
+        let st = (&mut *st) as *mut RawSystemTable;
+
+        // Safety: `st` is a valid mock table
+        let boot = unsafe { SystemTable::<Boot>::new(st) };
+        let runtime = boot.runtime();
+        assert_eq!(runtime.supported(), RuntimeServicesSupported::all());
+        assert_eq!(runtime.time()?.year, 2000);
+
+        // Safety: `st` is a valid mock table
+        let run = unsafe { SystemTable::<Runtime>::new(st) };
+        let runtime = run.runtime();
+        assert_eq!(runtime.supported(), RuntimeServicesSupported::SET_TIME);
+        let e = runtime.time().unwrap_err();
+        assert_eq!(e.status(), Status::UNSUPPORTED);
+        Ok(())
+    }
+
+    /// [`SystemTable::debug_images`] skips null entries, and the gdb command
+    /// offsets every section, such as `.text`, to where the image was loaded
+    #[test]
+    fn debug_images() -> Result<()> {
+        use core::{mem::zeroed, ptr::null};
+
+        use nuefi_core::table::config::{
+            image::{
+                DebugImage,
+                DebugImageTable,
+                RawDebugImageInfoHeader,
+                RawDebugImageInfoNormal,
+            },
+            ConfigurationTable,
+            DebugImageInfo,
+        };
+
+        use crate::proto::{loaded_image::raw::RawLoadedImage, Entity};
+
+        // Linked at the usual PE base, with `.text` in the first page after
+        // the headers.
+        let link_base = 0x1_4000_0000u64;
+        let text = 0x1000;
+        let mut code = alloc::vec![0u8; 0x2000];
+
+        // Safety: All zeros is valid for `RawLoadedImage`
+        let mut image: RawLoadedImage = unsafe { zeroed() };
+        image.image_base = code.as_mut_ptr();
+        image.image_size = code.len() as u64;
+        let normal = RawDebugImageInfoNormal {
+            ty: DebugImage::NORMAL,
+            loaded_image: (&mut image as *mut RawLoadedImage).cast(),
+            handle: EfiHandle::null(),
+        };
+        let entries = [&normal as *const RawDebugImageInfoNormal, null()];
+        let mut header = RawDebugImageInfoHeader {
+            update_status: 0,
+            size: entries.len() as u32,
+            table: entries.as_ptr(),
+        };
+        let header = &mut header as *mut RawDebugImageInfoHeader;
+        let mut tables = [ConfigurationTable {
+            guid: DebugImageInfo::GUID,
+            table: header.cast(),
+        }];
+
+        let (mut st, _box) = mock();
+        st.configuration_table = tables.as_mut_ptr();
+        st.number_of_table_entries = tables.len();
+        let st = (&mut *st) as *mut RawSystemTable;
+        // Safety: `st` is a valid mock table
+        let table = unsafe { SystemTable::<Boot>::new(st) };
+
+        let images = table.debug_images()?;
+        assert_eq!(images.len(), 1);
+        let loaded = &images[0];
+        assert_eq!(loaded.image_base(), code.as_mut_ptr());
+        assert_eq!(loaded.image_size(), 0x2000);
+
+        let offset = (code.as_ptr() as u64).wrapping_sub(link_base);
+        assert_eq!(
+            loaded.gdb_add_symbol_file("app.debug", link_base),
+            alloc::format!("add-symbol-file app.debug -o {offset:#x}")
+        );
+        // `.text` lands where it was actually loaded
+        assert_eq!(
+            (link_base + text).wrapping_add(offset),
+            code[text as usize..].as_ptr() as u64
+        );
+
+        // Safety: `header` is valid
+        unsafe { (*header).update_status = DebugImageTable::UPDATE_IN_PROGRESS };
+        let e = table.debug_images().unwrap_err();
+        assert_eq!(e.status(), Status::NOT_READY);
+        Ok(())
+    }
+
+    #[test]
+    fn consoles_fallback() -> Result<()> {
+        let (mut st, _box) = mock();
+        let st = (&mut *st) as *mut RawSystemTable;
+        // Safety: `st` is a valid mock table
+        let table = unsafe { SystemTable::<Boot>::new(st) };
+
+        let consoles = table.consoles()?;
+        assert_eq!(consoles.len(), 1, "Consoles should fall back to stdout");
+        let (handle, out) = consoles.iter().next().unwrap();
+        assert_eq!(out.as_ptr(), table.stdout().as_ptr());
+        // Safety: `st` is valid
+        assert_eq!(handle, unsafe { (*st).console_out_handle });
+        consoles.clear()?;
         Ok(())
     }
 }
@@ -12,7 +12,9 @@ const POOL_ALIGN: usize = 8;
 pub use nuefi_core::table::mem::{
     AllocateType,
     MemoryDescriptor,
+    MemoryDescriptors,
     MemoryFlags,
+    MemoryMap,
     MemoryType,
     PhysicalAddress,
     VirtualAddress,
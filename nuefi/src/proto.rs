@@ -40,17 +40,22 @@
 //! # fn main() {}
 //! ```
 
-use core::{marker::PhantomData, ops::Deref};
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
 
 use crate::{get_boot_table, EfiHandle};
 
 pub mod console;
+pub mod decompress;
 pub mod device_path;
 pub mod edid;
 pub mod graphics;
 pub mod loaded_image;
 pub mod media;
 pub mod platform_init;
+pub mod provider;
 pub mod vendor;
 
 /// A scope around a [Protocol] that will call
@@ -101,6 +106,13 @@ impl<'table, Proto: Protocol<'table>> Deref for Scope<'table, Proto> {
     }
 }
 
+impl<'table, Proto: Protocol<'table>> DerefMut for Scope<'table, Proto> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.proto
+    }
+}
+
 impl<'table, Proto: Protocol<'table>> Drop for Scope<'table, Proto> {
     fn drop(&mut self) {
         if let Some(table) = get_boot_table() {
@@ -1,24 +1,40 @@
 //! UEFI Console related protocols
+use alloc::{boxed::Box, fmt::format, string::String, vec::Vec};
 use core::{
+    ffi::c_void,
     fmt::{self, Write},
-    mem::size_of,
+    marker::PhantomData,
+    mem::{size_of, ManuallyDrop},
+    ptr::null_mut,
     slice::from_raw_parts_mut,
+    sync::atomic::{AtomicPtr, AtomicU32, Ordering},
 };
 
 use crate::{
     error::{Result, Status},
+    get_boot_table,
     string::UefiString,
+    table::Event,
     util::interface,
+    EfiHandle,
 };
 
 pub mod raw;
 
-use raw::RawSimpleTextOutput;
+use raw::{
+    RawInputKey,
+    RawKeyData,
+    RawKeyNotify,
+    RawKeyState,
+    RawSimpleTextInput,
+    RawSimpleTextInputEx,
+    RawSimpleTextOutput,
+};
 
 use crate::Protocol;
 
 /// Text foreground attributes for [SimpleTextOutput]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
 // Uefi wants them as usize but SetAttributes wants u32 but actually its all one
 // byte
@@ -47,7 +63,7 @@ impl TextForeground {
 }
 
 /// Text background attributes for [SimpleTextOutput]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
 // Uefi wants them as usize but SetAttributes wants u32 but actually its all one
 // byte??
@@ -64,7 +80,648 @@ impl TextBackground {
     pub const LIGHT_GRAY: Self = Self(0x07);
 }
 
-// interface!(SimpleTextInput(RawSimpleTextInput));
+/// A non-printable key, from the UEFI scan code tables
+///
+/// The function keys past F12, and the media keys,
+/// are generally only reported by [`SimpleTextInput`] implementations that
+/// also support the extended protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScanCode {
+    Up,
+    Down,
+    Right,
+    Left,
+    Home,
+    End,
+    Insert,
+    Delete,
+    PageUp,
+    PageDown,
+
+    /// Function key `F1` through `F24`
+    Function(u8),
+
+    Escape,
+    Mute,
+    VolumeUp,
+    VolumeDown,
+    BrightnessUp,
+    BrightnessDown,
+    Suspend,
+    Hibernate,
+    ToggleDisplay,
+    Recovery,
+    Eject,
+
+    /// Any other scan code, such as the OEM reserved range `0x8000..`
+    Other(u16),
+}
+
+impl ScanCode {
+    /// The [`ScanCode`] for the raw UEFI scan code, or [`None`] for the null
+    /// scan code
+    pub const fn from_raw(code: u16) -> Option<Self> {
+        Some(match code {
+            0x00 => return None,
+            0x01 => Self::Up,
+            0x02 => Self::Down,
+            0x03 => Self::Right,
+            0x04 => Self::Left,
+            0x05 => Self::Home,
+            0x06 => Self::End,
+            0x07 => Self::Insert,
+            0x08 => Self::Delete,
+            0x09 => Self::PageUp,
+            0x0A => Self::PageDown,
+            0x0B..=0x16 => Self::Function((code - 0x0B + 1) as u8),
+            0x17 => Self::Escape,
+            0x68..=0x73 => Self::Function((code - 0x68 + 13) as u8),
+            0x7F => Self::Mute,
+            0x80 => Self::VolumeUp,
+            0x81 => Self::VolumeDown,
+            0x100 => Self::BrightnessUp,
+            0x101 => Self::BrightnessDown,
+            0x102 => Self::Suspend,
+            0x103 => Self::Hibernate,
+            0x104 => Self::ToggleDisplay,
+            0x105 => Self::Recovery,
+            0x106 => Self::Eject,
+            _ => Self::Other(code),
+        })
+    }
+
+    /// The raw UEFI scan code, or [`None`] for a function key outside
+    /// `F1` through `F24`, or [`ScanCode::Other`] with the null scan code
+    pub const fn to_raw(self) -> Option<u16> {
+        Some(match self {
+            Self::Up => 0x01,
+            Self::Down => 0x02,
+            Self::Right => 0x03,
+            Self::Left => 0x04,
+            Self::Home => 0x05,
+            Self::End => 0x06,
+            Self::Insert => 0x07,
+            Self::Delete => 0x08,
+            Self::PageUp => 0x09,
+            Self::PageDown => 0x0A,
+            Self::Function(n @ 1..=12) => 0x0B + (n as u16 - 1),
+            Self::Function(n @ 13..=24) => 0x68 + (n as u16 - 13),
+            Self::Function(_) | Self::Other(0) => return None,
+            Self::Escape => 0x17,
+            Self::Mute => 0x7F,
+            Self::VolumeUp => 0x80,
+            Self::VolumeDown => 0x81,
+            Self::BrightnessUp => 0x100,
+            Self::BrightnessDown => 0x101,
+            Self::Suspend => 0x102,
+            Self::Hibernate => 0x103,
+            Self::ToggleDisplay => 0x104,
+            Self::Recovery => 0x105,
+            Self::Eject => 0x106,
+            Self::Other(code) => code,
+        })
+    }
+}
+
+/// A key press from [`SimpleTextInput`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// A printable character, or a control character such as `'\r'` for
+    /// Enter, `'\u{8}'` for Backspace, or `'\t'` for Tab.
+    Char(char),
+
+    /// A non-printable key
+    Scan(ScanCode),
+}
+
+impl Key {
+    /// The [`Key`] for `key`, or [`None`] if it has neither a scan code nor
+    /// a character
+    ///
+    /// Unpaired surrogates become [`char::REPLACEMENT_CHARACTER`]
+    pub fn from_raw(key: RawInputKey) -> Option<Self> {
+        if let Some(scan) = ScanCode::from_raw(key.scan_code) {
+            Some(Self::Scan(scan))
+        } else if key.unicode_char != 0 {
+            let c = char::from_u32(key.unicode_char.into()).unwrap_or(char::REPLACEMENT_CHARACTER);
+            Some(Self::Char(c))
+        } else {
+            None
+        }
+    }
+
+    /// The raw UEFI key, or [`None`] if this is a character outside the
+    /// Basic Multilingual Plane, or [`ScanCode::to_raw`] is [`None`]
+    pub fn to_raw(self) -> Option<RawInputKey> {
+        match self {
+            Self::Char(c) => Some(RawInputKey {
+                scan_code: 0,
+                unicode_char: u16::try_from(u32::from(c)).ok()?,
+            }),
+            Self::Scan(s) => Some(RawInputKey {
+                scan_code: s.to_raw()?,
+                unicode_char: 0,
+            }),
+        }
+    }
+}
+
+interface!(
+    #[Protocol("387477C1-69C7-11D2-8E39-00A0C969723B", crate("crate"))]
+    SimpleTextInput(RawSimpleTextInput)
+);
+
+impl<'table> SimpleTextInput<'table> {
+    /// Reset the input device, discarding any pending keys
+    pub fn reset(&self) -> Result<()> {
+        // Safety: Construction ensures these are valid
+        unsafe { (self.interface().reset.ok_or(Status::UNSUPPORTED)?)(self.interface, false) }
+            .into()
+    }
+
+    /// The next pending [`Key`], or [`None`] if no key is pending
+    ///
+    /// This does not wait, see [`SimpleTextInput::read_key`].
+    pub fn read_key_stroke(&self) -> Result<Option<Key>> {
+        let mut key = RawInputKey::default();
+        let read = self
+            .interface()
+            .read_key_stroke
+            .ok_or(Status::UNSUPPORTED)?;
+
+        // Safety: Construction ensures these are valid
+        let ret = unsafe { (read)(self.interface, &mut key) };
+        if ret.is_success() {
+            Ok(Key::from_raw(key))
+        } else if ret == Status::NOT_READY {
+            Ok(None)
+        } else {
+            Err(ret.into())
+        }
+    }
+
+    /// Event signaled when a key is available
+    ///
+    /// Use with [`BootServices::wait_for_event`][wait]
+    ///
+    /// [wait]: crate::table::BootServices::wait_for_event
+    pub fn wait_for_key(&self) -> Event {
+        self.interface().wait_for_key
+    }
+
+    /// Wait for and return the next [`Key`]
+    pub fn read_key(&self) -> Result<Key> {
+        let table = get_boot_table();
+        loop {
+            if let Some(table) = &table {
+                table.boot().wait_for_event(&[self.wait_for_key()])?;
+            }
+            if let Some(key) = self.read_key_stroke()? {
+                return Ok(key);
+            }
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// Modifier keys held during a key press, from [`SimpleTextInputEx`]
+    ///
+    /// This is only meaningful if [`ShiftState::VALID`] is set.
+    #[repr(transparent)]
+    pub struct ShiftState: u32 {
+        const VALID = 0x80000000;
+        const RIGHT_SHIFT = 0x00000001;
+        const LEFT_SHIFT = 0x00000002;
+        const RIGHT_CONTROL = 0x00000004;
+        const LEFT_CONTROL = 0x00000008;
+        const RIGHT_ALT = 0x00000010;
+        const LEFT_ALT = 0x00000020;
+        const RIGHT_LOGO = 0x00000040;
+        const LEFT_LOGO = 0x00000080;
+        const MENU = 0x00000100;
+        const SYS_REQ = 0x00000200;
+    }
+}
+
+impl ShiftState {
+    /// Either shift key is held
+    pub const fn shift(self) -> bool {
+        self.intersects(Self::LEFT_SHIFT.union(Self::RIGHT_SHIFT))
+    }
+
+    /// Either control key is held
+    pub const fn ctrl(self) -> bool {
+        self.intersects(Self::LEFT_CONTROL.union(Self::RIGHT_CONTROL))
+    }
+
+    /// Either alt key is held
+    pub const fn alt(self) -> bool {
+        self.intersects(Self::LEFT_ALT.union(Self::RIGHT_ALT))
+    }
+
+    /// Either logo key is held
+    pub const fn logo(self) -> bool {
+        self.intersects(Self::LEFT_LOGO.union(Self::RIGHT_LOGO))
+    }
+}
+
+bitflags::bitflags! {
+    /// Lock key state, from [`SimpleTextInputEx`]
+    ///
+    /// This is only meaningful if [`ToggleState::VALID`] is set.
+    #[repr(transparent)]
+    pub struct ToggleState: u8 {
+        const VALID = 0x80;
+
+        /// Report partial keystrokes, such as modifier keys on their own
+        const KEY_STATE_EXPOSED = 0x40;
+        const SCROLL_LOCK = 0x01;
+        const NUM_LOCK = 0x02;
+        const CAPS_LOCK = 0x04;
+    }
+}
+
+/// A key press with modifier and lock state, from [`SimpleTextInputEx`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyData {
+    /// The key, or [`None`] for a partial keystroke
+    ///
+    /// Partial keystrokes, such as a modifier key pressed on its own, are only
+    /// reported with [`ToggleState::KEY_STATE_EXPOSED`].
+    pub key: Option<Key>,
+
+    /// Modifier keys held
+    pub shift: ShiftState,
+
+    /// Lock key state
+    pub toggle: ToggleState,
+}
+
+impl KeyData {
+    fn from_raw(data: RawKeyData) -> Self {
+        Self {
+            key: Key::from_raw(data.key),
+            shift: ShiftState::from_bits_truncate(data.state.shift_state),
+            toggle: ToggleState::from_bits_truncate(data.state.toggle_state),
+        }
+    }
+}
+
+/// Maximum number of concurrent [`SimpleTextInputEx::register_key_notify`]
+/// registrations
+const MAX_KEY_NOTIFY: usize = 16;
+
+type KeyCallback = Box<dyn FnMut(KeyData)>;
+
+/// Callbacks for each registration slot
+///
+/// Firmware gives notification functions no context, so each slot has its own
+/// [`key_notify`] instantiation.
+static KEY_NOTIFY: [AtomicPtr<KeyCallback>; MAX_KEY_NOTIFY] =
+    [const { AtomicPtr::new(null_mut()) }; MAX_KEY_NOTIFY];
+
+/// Notification function for registration `SLOT`
+unsafe extern "efiapi" fn key_notify<const SLOT: usize>(key: *mut RawKeyData) -> Status {
+    let f = KEY_NOTIFY[SLOT].load(Ordering::Acquire);
+    if !f.is_null() && !key.is_null() {
+        // Safety:
+        // - `f` was created by `register_key_notify` and is only freed after
+        //   firmware stops calling us.
+        // - Notifications can't interrupt themselves
+        // - `key` is valid from firmware
+        unsafe { (*f)(KeyData::from_raw(*key)) };
+    }
+    Status::SUCCESS
+}
+
+const KEY_NOTIFY_FN: [RawKeyNotify; MAX_KEY_NOTIFY] = [
+    key_notify::<0>,
+    key_notify::<1>,
+    key_notify::<2>,
+    key_notify::<3>,
+    key_notify::<4>,
+    key_notify::<5>,
+    key_notify::<6>,
+    key_notify::<7>,
+    key_notify::<8>,
+    key_notify::<9>,
+    key_notify::<10>,
+    key_notify::<11>,
+    key_notify::<12>,
+    key_notify::<13>,
+    key_notify::<14>,
+    key_notify::<15>,
+];
+
+/// A key notification registered with
+/// [`SimpleTextInputEx::register_key_notify`]
+///
+/// The callback stays registered until this is dropped or passed to
+/// [`SimpleTextInputEx::unregister_key_notify`].
+///
+/// If firmware fails to unregister it, the callback and its slot are leaked,
+/// because firmware may still call it.
+#[derive(Debug)]
+#[must_use = "dropping a `KeyNotify` unregisters it"]
+pub struct KeyNotify<'table> {
+    interface: *mut RawSimpleTextInputEx,
+    handle: *mut c_void,
+    slot: usize,
+    phantom: PhantomData<&'table mut RawSimpleTextInputEx>,
+}
+
+impl<'table> KeyNotify<'table> {
+    /// Unregister from firmware, then free the callback and its slot
+    fn unregister(&self) -> Result<()> {
+        // Safety: `interface` is valid for `'table`
+        let unregister =
+            unsafe { (*self.interface).unregister_key_notify }.ok_or(Status::UNSUPPORTED)?;
+
+        // Safety: Construction ensures these are valid
+        let ret = unsafe { (unregister)(self.interface, self.handle) };
+        if !ret.is_success() {
+            return Err(ret.into());
+        }
+        let cb = KEY_NOTIFY[self.slot].swap(null_mut(), Ordering::AcqRel);
+        if !cb.is_null() {
+            // Safety: Firmware will no longer call this slot
+            drop(unsafe { Box::from_raw(cb) });
+        }
+        Ok(())
+    }
+}
+
+impl<'table> Drop for KeyNotify<'table> {
+    fn drop(&mut self) {
+        let _ = self.unregister();
+    }
+}
+
+interface!(
+    #[Protocol("DD9E7534-7762-4698-8C14-F58517A625AA", crate("crate"))]
+    SimpleTextInputEx(RawSimpleTextInputEx)
+);
+
+impl<'table> SimpleTextInputEx<'table> {
+    /// Reset the input device, discarding any pending keys
+    pub fn reset(&self) -> Result<()> {
+        // Safety: Construction ensures these are valid
+        unsafe { (self.interface().reset.ok_or(Status::UNSUPPORTED)?)(self.interface, false) }
+            .into()
+    }
+
+    /// The next pending [`KeyData`], or [`None`] if no key is pending
+    ///
+    /// This does not wait, see [`SimpleTextInputEx::read_key`].
+    pub fn read_key_stroke(&self) -> Result<Option<KeyData>> {
+        let mut key = RawKeyData::default();
+        let read = self
+            .interface()
+            .read_key_stroke_ex
+            .ok_or(Status::UNSUPPORTED)?;
+
+        // Safety: Construction ensures these are valid
+        let ret = unsafe { (read)(self.interface, &mut key) };
+        if ret.is_success() {
+            Ok(Some(KeyData::from_raw(key)))
+        } else if ret == Status::NOT_READY {
+            Ok(None)
+        } else {
+            Err(ret.into())
+        }
+    }
+
+    /// Event signaled when a key is available
+    ///
+    /// Use with [`BootServices::wait_for_event`][wait]
+    ///
+    /// [wait]: crate::table::BootServices::wait_for_event
+    pub fn wait_for_key(&self) -> Event {
+        self.interface().wait_for_key_ex
+    }
+
+    /// Wait for and return the next [`KeyData`]
+    pub fn read_key(&self) -> Result<KeyData> {
+        let table = get_boot_table();
+        loop {
+            if let Some(table) = &table {
+                table.boot().wait_for_event(&[self.wait_for_key()])?;
+            }
+            if let Some(key) = self.read_key_stroke()? {
+                return Ok(key);
+            }
+        }
+    }
+
+    /// Set the lock key state
+    ///
+    /// [`ToggleState::VALID`] is always set.
+    ///
+    /// Setting [`ToggleState::KEY_STATE_EXPOSED`] enables partial keystrokes,
+    /// for example to detect Shift being held during boot.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::UNSUPPORTED`] if the device doesn't support setting `state`
+    pub fn set_state(&self, state: ToggleState) -> Result<()> {
+        let state = (state | ToggleState::VALID).bits();
+        // Safety: Construction ensures these are valid
+        unsafe { (self.interface().set_state.ok_or(Status::UNSUPPORTED)?)(self.interface, &state) }
+            .into()
+    }
+
+    /// Call `f` whenever `key` is pressed with the modifiers in `shift`
+    ///
+    /// An empty `shift` matches any modifiers.
+    ///
+    /// `f` is called from a firmware notification at raised task priority,
+    /// and so should be short and not wait on events.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::INVALID_PARAMETER`] if `key` can't be represented in UEFI
+    /// - [`Status::OUT_OF_RESOURCES`] if too many notifications are registered
+    pub fn register_key_notify<F>(
+        &self,
+        key: Key,
+        shift: ShiftState,
+        f: F,
+    ) -> Result<KeyNotify<'table>>
+    where
+        F: FnMut(KeyData) + 'static,
+    {
+        let key = key.to_raw().ok_or(Status::INVALID_PARAMETER)?;
+        let shift = if shift.is_empty() {
+            shift
+        } else {
+            shift | ShiftState::VALID
+        };
+        let data = RawKeyData {
+            key,
+            state: RawKeyState {
+                shift_state: shift.bits(),
+                toggle_state: 0,
+            },
+        };
+        let register = self
+            .interface()
+            .register_key_notify
+            .ok_or(Status::UNSUPPORTED)?;
+
+        let cb: *mut KeyCallback = Box::into_raw(Box::new(Box::new(f)));
+        let Some(slot) = KEY_NOTIFY.iter().position(|s| {
+            s.compare_exchange(null_mut(), cb, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        }) else {
+            // Safety: `cb` was never shared
+            drop(unsafe { Box::from_raw(cb) });
+            return Err(Status::OUT_OF_RESOURCES.into());
+        };
+
+        let mut handle = null_mut();
+        // Safety: Construction ensures these are valid
+        let ret = unsafe { (register)(self.interface, &data, KEY_NOTIFY_FN[slot], &mut handle) };
+        if ret.is_success() {
+            return Ok(KeyNotify {
+                interface: self.interface,
+                handle,
+                slot,
+                phantom: PhantomData,
+            });
+        }
+        KEY_NOTIFY[slot].store(null_mut(), Ordering::Release);
+        // Safety: Registration failed, so firmware never saw `cb`
+        drop(unsafe { Box::from_raw(cb) });
+        Err(ret.into())
+    }
+
+    /// Unregister a notification from
+    /// [`SimpleTextInputEx::register_key_notify`]
+    ///
+    /// This is the same as dropping `notify`, but reports errors.
+    pub fn unregister_key_notify(&self, notify: KeyNotify<'table>) -> Result<()> {
+        // Don't unregister again on drop, even if this fails
+        ManuallyDrop::new(notify).unregister()
+    }
+}
+
+bitflags::bitflags! {
+    /// Output processing options for [`SimpleTextOutput`]
+    ///
+    /// See [`SimpleTextOutput::set_options`]
+    #[repr(transparent)]
+    pub struct OutputOptions: u32 {
+        /// Replace characters the console can't display with ASCII
+        /// approximations, for example box-drawing characters with `+`, `-`
+        /// and `|`.
+        const ASCII_FALLBACK = 1 << 0;
+
+        /// Interpret ANSI escape sequences
+        ///
+        /// SGR colors are mapped to [`TextForeground`] and [`TextBackground`],
+        /// cursor movement to [`SimpleTextOutput::set_cursor`],
+        /// erase display to [`SimpleTextOutput::clear`],
+        /// and cursor visibility to [`SimpleTextOutput::enable_cursor`].
+        ///
+        /// All other escape sequences are stripped.
+        const ANSI = 1 << 1;
+    }
+}
+
+/// Split `s`, which starts with an escape, into the escape sequence and the
+/// rest of the string
+///
+/// Incomplete sequences are consumed up to the end of `s`.
+fn split_escape(s: &str) -> (&str, &str) {
+    let b = s.as_bytes();
+    let end = match b.get(1) {
+        // Control Sequence Introducer
+        Some(b'[') => {
+            let params = b[2..]
+                .iter()
+                .position(|c| !(0x20..=0x3F).contains(c))
+                .map_or(b.len(), |i| i + 2);
+            match b.get(params) {
+                Some(0x40..=0x7E) => params + 1,
+                _ => params,
+            }
+        }
+        // Operating System Command, terminated by BEL or ST
+        Some(b']') => {
+            let bel = s.find('\x07').map(|i| i + 1);
+            let st = s[2..].find("\x1b\\").map(|i| i + 4);
+            match (bel, st) {
+                (Some(bel), Some(st)) => bel.min(st),
+                (bel, st) => bel.or(st).unwrap_or(s.len()),
+            }
+        }
+        Some(c) if c.is_ascii() => 2,
+        _ => 1,
+    };
+    s.split_at(end)
+}
+
+/// The UEFI color for ANSI color `n`
+const fn ansi_color(n: u16) -> usize {
+    const COLORS: [usize; 8] = [0x00, 0x04, 0x02, 0x06, 0x01, 0x05, 0x03, 0x07];
+    COLORS[n as usize % 8]
+}
+
+/// Most consoles [`SimpleTextOutput::set_options`] can configure at once
+const MAX_OUTPUT_OPTIONS: usize = 16;
+
+/// The console each [`OUTPUT_OPTIONS`] slot belongs to
+static OUTPUT_CONSOLES: [AtomicPtr<RawSimpleTextOutput>; MAX_OUTPUT_OPTIONS] =
+    [const { AtomicPtr::new(null_mut()) }; MAX_OUTPUT_OPTIONS];
+
+/// Options for the console in the same [`OUTPUT_CONSOLES`] slot
+static OUTPUT_OPTIONS: [AtomicU32; MAX_OUTPUT_OPTIONS] =
+    [const { AtomicU32::new(0) }; MAX_OUTPUT_OPTIONS];
+
+/// An ASCII approximation of `c`
+fn ascii_fallback(c: char) -> &'static str {
+    match c {
+        '─' | '━' | '═' | '╌' | '╍' | '┄' | '┅' | '┈' | '┉' | '‐' | '‑' | '‒' | '–' | '—' | '―'
+        | '−' => "-",
+        '│' | '┃' | '║' | '╎' | '╏' | '┆' | '┇' | '┊' | '┋' => "|",
+        '\u{250C}'..='\u{254B}' | '\u{2552}'..='\u{256C}' | '╭' | '╮' | '╯' | '╰' => "+",
+        '╱' => "/",
+        '╲' => "\\",
+        '╳' => "X",
+        '█' | '▓' | '▒' | '░' | '■' | '▀' | '▄' | '▌' | '▐' => "#",
+        '←' => "<",
+        '→' => ">",
+        '↑' | '▲' => "^",
+        '↓' | '▼' => "v",
+        '◄' | '◀' => "<",
+        '►' | '▶' => ">",
+        '•' | '·' | '●' | '○' | '◦' | '∙' => "*",
+        '✓' | '✔' => "v",
+        '✗' | '✘' | '×' => "x",
+        '…' => "...",
+        '‘' | '’' | '‚' | '′' => "'",
+        '“' | '”' | '„' | '″' => "\"",
+        '«' => "<<",
+        '»' => ">>",
+        '\u{A0}' | '\u{2000}'..='\u{200A}' => " ",
+        _ => "?",
+    }
+}
+
+/// `s` with every non-ASCII character `supports` rejects replaced by its
+/// [`ascii_fallback`]
+fn with_fallback<F: FnMut(&str) -> bool>(s: &str, mut supports: F) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut buf = [0; 4];
+    for c in s.chars() {
+        if c.is_ascii() || supports(c.encode_utf8(&mut buf)) {
+            out.push(c);
+        } else {
+            out.push_str(ascii_fallback(c));
+        }
+    }
+    out
+}
 
 interface!(
     #[Protocol("387477C2-69C7-11D2-8E39-00A0C969723B", crate("crate"))]
@@ -228,6 +885,113 @@ impl<'table> SimpleTextOutput<'table> {
         Ok(TextMode::new(mode, info.size()))
     }
 
+    /// Move the cursor to column `col` and row `row`
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::UNSUPPORTED`] if the position is outside the current
+    ///   [`TextMode`]
+    pub fn set_cursor(&self, col: usize, row: usize) -> Result<()> {
+        // Safety: Construction ensures these are valid
+        unsafe {
+            (self
+                .interface()
+                .set_cursor_position
+                .ok_or(Status::UNSUPPORTED)?)(self.interface, col, row)
+        }
+        .into()
+    }
+
+    /// Current cursor position, (Col, Row)
+    pub fn cursor(&self) -> (usize, usize) {
+        // Safety: Construction ensures these are valid
+        let mode = unsafe { *self.interface().mode };
+        (mode.cursor_column as usize, mode.cursor_row as usize)
+    }
+
+    /// Whether the cursor is visible
+    pub fn cursor_visible(&self) -> bool {
+        // Safety: Construction ensures these are valid
+        unsafe { (*self.interface().mode).cursor_visible }
+    }
+
+    /// Whether the console can display every character in `s`
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::INVALID_PARAMETER`] if `s` contains a nul
+    pub fn supports(&self, s: &str) -> Result<bool> {
+        if s.contains('\0') {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let test = self.interface().test_string.ok_or(Status::UNSUPPORTED)?;
+        let s = UefiString::new(s);
+
+        // Safety: s is a nul terminated string
+        let ret = unsafe { test(self.interface, s.as_ptr()) };
+        if ret.is_success() {
+            Ok(true)
+        } else if ret == Status::UNSUPPORTED {
+            Ok(false)
+        } else {
+            Err(ret.into())
+        }
+    }
+
+    /// Set the [`OutputOptions`] used by the [`Write`] implementation
+    ///
+    /// Options belong to this console's interface, so they apply to every
+    /// [`SimpleTextOutput`] for the same device, such as the one used by
+    /// [`crate::logger`] for stdout, but not to other consoles.
+    ///
+    /// Setting empty options releases this console's slot.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::OUT_OF_RESOURCES`] if options are already set on too many
+    ///   other consoles
+    pub fn set_options(&self, options: OutputOptions) -> Result<()> {
+        if let Some(slot) = self.options_slot() {
+            OUTPUT_OPTIONS[slot].store(options.bits(), Ordering::Relaxed);
+            if options.is_empty() {
+                OUTPUT_CONSOLES[slot].store(null_mut(), Ordering::Release);
+            }
+            return Ok(());
+        }
+        if options.is_empty() {
+            return Ok(());
+        }
+        for (slot, console) in OUTPUT_CONSOLES.iter().enumerate() {
+            if console
+                .compare_exchange(
+                    null_mut(),
+                    self.interface,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                )
+                .is_ok()
+            {
+                OUTPUT_OPTIONS[slot].store(options.bits(), Ordering::Relaxed);
+                return Ok(());
+            }
+        }
+        Err(Status::OUT_OF_RESOURCES.into())
+    }
+
+    /// The current [`OutputOptions`] for this console
+    pub fn options(&self) -> OutputOptions {
+        self.options_slot().map_or(OutputOptions::empty(), |slot| {
+            OutputOptions::from_bits_truncate(OUTPUT_OPTIONS[slot].load(Ordering::Relaxed))
+        })
+    }
+
+    /// This console's slot in [`OUTPUT_CONSOLES`]
+    fn options_slot(&self) -> Option<usize> {
+        OUTPUT_CONSOLES
+            .iter()
+            .position(|console| console.load(Ordering::Acquire) == self.interface)
+    }
+
     fn max_mode(&self) -> i32 {
         // Safety: Type system
         unsafe { (*self.interface().mode).max_mode }
@@ -236,13 +1000,141 @@ impl<'table> SimpleTextOutput<'table> {
 
 // Internal
 impl<'table> SimpleTextOutput<'table> {
+    /// [`SimpleTextOutput::output_string`], applying
+    /// [`OutputOptions::ASCII_FALLBACK`]
+    fn output_fallback(&self, s: &str) -> Result<()> {
+        let fallback = self.options().contains(OutputOptions::ASCII_FALLBACK);
+        if !fallback || s.is_ascii() || self.supports(s).unwrap_or(true) {
+            return self.output_string(s);
+        }
+        let out = with_fallback(s, |c| self.supports(c).unwrap_or(true));
+        self.output_string(&out)
+    }
+
+    /// Apply a complete escape sequence from [`split_escape`]
+    fn apply_escape(&self, seq: &str) -> Result<()> {
+        let Some(body) = seq.strip_prefix("\x1b[") else {
+            return Ok(());
+        };
+        let Some(cmd) = body
+            .chars()
+            .last()
+            .filter(|c| ('\x40'..='\x7E').contains(c))
+        else {
+            return Ok(());
+        };
+        let params = &body[..body.len() - 1];
+        let (private, params) = match params.strip_prefix('?') {
+            Some(p) => (true, p),
+            None => (false, params),
+        };
+
+        // Missing or empty parameters are 0
+        let mut args = [0u16; 16];
+        let mut len = 0;
+        for (arg, p) in args.iter_mut().zip(params.split(';')) {
+            *arg = p.parse().unwrap_or(0);
+            len += 1;
+        }
+        let args = &args[..len.max(1)];
+        // Movement parameters are at least 1
+        let arg = |i: usize| args.get(i).copied().unwrap_or(0).max(1) as usize;
+
+        match (private, cmd) {
+            (false, 'm') => self.apply_sgr(args),
+            (false, 'H' | 'f') => self.set_cursor(arg(1) - 1, arg(0) - 1),
+            (false, 'A') => {
+                let (col, row) = self.cursor();
+                self.set_cursor(col, row.saturating_sub(arg(0)))
+            }
+            (false, 'B') => {
+                let (col, row) = self.cursor();
+                self.set_cursor(col, row + arg(0))
+            }
+            (false, 'C') => {
+                let (col, row) = self.cursor();
+                self.set_cursor(col + arg(0), row)
+            }
+            (false, 'D') => {
+                let (col, row) = self.cursor();
+                self.set_cursor(col.saturating_sub(arg(0)), row)
+            }
+            (false, 'J') if matches!(args[0], 2 | 3) => self.clear(),
+            (true, 'h') if args[0] == 25 => self.enable_cursor(),
+            (true, 'l') if args[0] == 25 => self.disable_cursor(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Apply ANSI Select Graphic Rendition parameters
+    fn apply_sgr(&self, args: &[u16]) -> Result<()> {
+        let (mut fore, mut back) = self.attributes();
+        let bright = TextForeground::BRIGHT.0;
+        let mut args = args.iter().copied();
+        while let Some(arg) = args.next() {
+            match arg {
+                0 => {
+                    fore = TextForeground::LIGHT_GRAY;
+                    back = TextBackground::BLACK;
+                }
+                1 => fore.0 |= bright,
+                22 => fore.0 &= !bright,
+                30..=37 => fore.0 = ansi_color(arg - 30) | (fore.0 & bright),
+                39 => fore = TextForeground::LIGHT_GRAY,
+                40..=47 => back.0 = ansi_color(arg - 40),
+                49 => back = TextBackground::BLACK,
+                90..=97 => fore.0 = ansi_color(arg - 90) | bright,
+                100..=107 => back.0 = ansi_color(arg - 100),
+                // Extended colors, only the first 16 have an equivalent
+                38 | 48 => match args.next() {
+                    Some(5) => match args.next() {
+                        Some(n @ 0..=15) if arg == 38 => {
+                            fore.0 = ansi_color(n) | if n >= 8 { bright } else { 0 };
+                        }
+                        Some(n @ 0..=15) => back.0 = ansi_color(n),
+                        _ => (),
+                    },
+                    Some(2) => {
+                        args.nth(2);
+                    }
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+        self.set_attributes(fore, back)
+    }
+
+    /// Write `s`, applying [`OutputOptions::ANSI`]
     fn write_str_impl(&self, s: &str) -> fmt::Result {
+        if !self.options().contains(OutputOptions::ANSI) {
+            return self.write_text(s);
+        }
+        let mut rest = s;
+        while let Some(i) = rest.find('\x1b') {
+            if i != 0 {
+                self.write_text(&rest[..i])?;
+            }
+            let (seq, tail) = split_escape(&rest[i..]);
+            // Unsupported sequences are stripped, and failures are not fatal
+            let _ = self.apply_escape(seq);
+            rest = tail;
+        }
+        if rest.is_empty() {
+            Ok(())
+        } else {
+            self.write_text(rest)
+        }
+    }
+
+    /// Write `s`, which contains no escape sequences
+    fn write_text(&self, s: &str) -> fmt::Result {
         // If the input contains a nul byte, write up to the nul and then return an
         // error.
         let nul = s.split_once('\0');
         let s = if let Some((s, _)) = nul { s } else { s };
 
-        let ret = match self.output_string(s) {
+        let ret = match self.output_fallback(s) {
             Ok(()) => Ok(()),
             Err(e) if e.status() == Status::WARN_UNKNOWN_GLYPH => Ok(()),
             Err(_) => Err(fmt::Error),
@@ -268,17 +1160,210 @@ impl<'table> SimpleTextOutput<'table> {
 ///
 /// Warnings are ignored. Ending Newlines are turned into \n\r.
 /// Interior newlines are not.
-// #[cfg(no)]
+///
+/// With [`OutputOptions::ANSI`], each [`write!`] is formatted in full before
+/// being written, so that escape sequences aren't split.
 impl<'t> Write for SimpleTextOutput<'t> {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         self.write_str_impl(s)
     }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        (&*self).write_fmt(args)
+    }
 }
 
 impl<'t> Write for &SimpleTextOutput<'t> {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         self.write_str_impl(s)
     }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        if self.options().contains(OutputOptions::ANSI) {
+            match args.as_str() {
+                Some(s) => self.write_str_impl(s),
+                None => self.write_str_impl(&format(args)),
+            }
+        } else {
+            fmt::write(self, args)
+        }
+    }
+}
+
+/// Every console output device, from [`SystemTable::consoles`][consoles]
+///
+/// Writes, attributes, and clears are broadcast to every device.
+/// A failure on one device doesn't stop output to the others,
+/// the first error is returned after every device was tried.
+///
+/// Modes are per-device, see [`Consoles::set_mode`] and
+/// [`Consoles::select_mode`].
+///
+/// [consoles]: crate::table::SystemTable::consoles
+#[derive(Debug)]
+pub struct Consoles<'table> {
+    outputs: Vec<(EfiHandle, SimpleTextOutput<'table>)>,
+}
+
+impl<'table> Consoles<'table> {
+    pub(crate) fn new(outputs: Vec<(EfiHandle, SimpleTextOutput<'table>)>) -> Self {
+        Self { outputs }
+    }
+
+    /// Number of devices
+    pub fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Whether there are no devices
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+
+    /// Device `index`
+    pub fn get(&self, index: usize) -> Option<&SimpleTextOutput<'table>> {
+        self.outputs.get(index).map(|(_, o)| o)
+    }
+
+    /// Every device, and the handle it is installed on
+    pub fn iter(&self) -> impl Iterator<Item = (EfiHandle, &SimpleTextOutput<'table>)> + '_ {
+        self.outputs.iter().map(|(h, o)| (*h, o))
+    }
+
+    pub fn output_string(&self, string: &str) -> Result<()> {
+        self.each(|o| o.output_string(string))
+    }
+
+    pub fn set_attributes(&self, fore: TextForeground, back: TextBackground) -> Result<()> {
+        self.each(|o| o.set_attributes(fore, back))
+    }
+
+    /// Reset every device
+    ///
+    /// Clears the screen, resets cursor position.
+    pub fn reset(&self) -> Result<()> {
+        self.each(|o| o.reset())
+    }
+
+    /// Clears the screen, resets cursor position.
+    pub fn clear(&self) -> Result<()> {
+        self.each(|o| o.clear())
+    }
+
+    /// Enables the cursor
+    pub fn enable_cursor(&self) -> Result<()> {
+        self.each(|o| o.enable_cursor())
+    }
+
+    /// Disables the cursor
+    pub fn disable_cursor(&self) -> Result<()> {
+        self.each(|o| o.disable_cursor())
+    }
+
+    /// Set the cursor position to column `col` and row `row`
+    pub fn set_cursor(&self, col: usize, row: usize) -> Result<()> {
+        self.each(|o| o.set_cursor(col, row))
+    }
+
+    /// Set device `index` to `mode`
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if there is no device `index`
+    pub fn set_mode(&self, index: usize, mode: u32) -> Result<()> {
+        self.get(index)
+            .ok_or(Status::INVALID_PARAMETER)?
+            .set_mode(mode)
+    }
+
+    /// Set the mode of each device to the one `f` returns for it,
+    /// or leave it alone on [`None`]
+    ///
+    /// # Example
+    ///
+    /// Use the largest mode on every device
+    ///
+    /// ```rust,no_run
+    /// # use nuefi::proto::console::Consoles;
+    /// # fn f(consoles: &Consoles) -> nuefi::error::Result<()> {
+    /// consoles.select_mode(|out| {
+    ///     out.modes()
+    ///         .flatten()
+    ///         .max_by_key(|m| m.size().0 * m.size().1)
+    ///         .map(|m| m.mode())
+    /// })?;
+    /// # Ok(()) }
+    /// ```
+    pub fn select_mode<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&SimpleTextOutput<'table>) -> Option<u32>,
+    {
+        self.each(|o| match f(o) {
+            Some(mode) => o.set_mode(mode),
+            None => Ok(()),
+        })
+    }
+
+    /// Size in (Cols, Rows) that fits on every device
+    pub fn size(&self) -> (usize, usize) {
+        self.outputs
+            .iter()
+            .filter_map(|(_, o)| o.mode().ok())
+            .map(|m| m.size())
+            .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1)))
+            .unwrap_or((80, 25))
+    }
+
+    /// Call `f` for every device, returning the first error
+    fn each<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&SimpleTextOutput<'table>) -> Result<()>,
+    {
+        let mut ret = Ok(());
+        for (_, o) in &self.outputs {
+            let r = f(o);
+            if ret.is_ok() {
+                ret = r;
+            }
+        }
+        ret
+    }
+
+    /// Write `s` to every device, see [`SimpleTextOutput`]'s [`Write`]
+    fn write_str_impl(&self, s: &str) -> fmt::Result {
+        let mut ret = Ok(());
+        for (_, o) in &self.outputs {
+            let r = o.write_str_impl(s);
+            if ret.is_ok() {
+                ret = r;
+            }
+        }
+        ret
+    }
+}
+
+/// Writes are broadcast to every device, with the same behavior as
+/// [`SimpleTextOutput`]
+impl<'t> Write for Consoles<'t> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_str_impl(s)
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        (&*self).write_fmt(args)
+    }
+}
+
+impl<'t> Write for &Consoles<'t> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_str_impl(s)
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        // Formatted once, rather than once per device
+        match args.as_str() {
+            Some(s) => self.write_str_impl(s),
+            None => self.write_str_impl(&format(args)),
+        }
+    }
 }
 
 /// UEFI Text Mode Information
@@ -306,3 +1391,640 @@ impl TextMode {
         self.size
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use core::{
+        cell::{Cell, RefCell},
+        mem::zeroed,
+        ptr::addr_of_mut,
+    };
+
+    use super::{raw::RawTextMode, *};
+
+    /// [`RawSimpleTextOutput`] for escape sequences
+    ///
+    /// The screen is 80x25, and clears are counted.
+    #[repr(C)]
+    struct MockOutput {
+        raw: RawSimpleTextOutput,
+        mode: RawTextMode,
+        clears: Cell<usize>,
+    }
+
+    impl MockOutput {
+        fn new() -> Box<Self> {
+            let mut mock = Box::new(Self {
+                raw: RawSimpleTextOutput {
+                    reset: None,
+                    output_string: None,
+                    test_string: None,
+                    query_mode: None,
+                    set_mode: None,
+                    set_attribute: Some(set_attribute),
+                    clear_screen: Some(clear_screen),
+                    set_cursor_position: Some(set_cursor_position),
+                    enable_cursor: Some(enable_cursor),
+                    mode: null_mut(),
+                },
+                mode: RawTextMode {
+                    max_mode: 1,
+                    mode: 0,
+                    attribute: 0x07,
+                    cursor_column: 0,
+                    cursor_row: 0,
+                    cursor_visible: true,
+                },
+                clears: Cell::new(0),
+            });
+            mock.raw.mode = addr_of_mut!(mock.mode);
+            mock
+        }
+    }
+
+    unsafe extern "efiapi" fn set_attribute(this: *mut RawSimpleTextOutput, attr: usize) -> Status {
+        // Safety: Only ever called through a `MockOutput`
+        unsafe { (*(*this).mode).attribute = attr as i32 };
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn clear_screen(this: *mut RawSimpleTextOutput) -> Status {
+        // Safety: Only ever called through a `MockOutput`
+        let mock = unsafe { &*this.cast::<MockOutput>() };
+        mock.clears.set(mock.clears.get() + 1);
+        // Safety: See above
+        unsafe { set_cursor_position(this, 0, 0) }
+    }
+
+    unsafe extern "efiapi" fn set_cursor_position(
+        this: *mut RawSimpleTextOutput,
+        col: usize,
+        row: usize,
+    ) -> Status {
+        if col >= 80 || row >= 25 {
+            return Status::UNSUPPORTED;
+        }
+        // Safety: Only ever called through a `MockOutput`
+        let mode = unsafe { &mut *(*this).mode };
+        mode.cursor_column = col as i32;
+        mode.cursor_row = row as i32;
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn enable_cursor(
+        this: *mut RawSimpleTextOutput,
+        visible: bool,
+    ) -> Status {
+        // Safety: Only ever called through a `MockOutput`
+        unsafe { (*(*this).mode).cursor_visible = visible };
+        Status::SUCCESS
+    }
+
+    /// [`RawSimpleTextInputEx`] that records key notifications
+    #[repr(C)]
+    struct MockInputEx {
+        raw: RawSimpleTextInputEx,
+        notify: RefCell<Vec<Option<(RawKeyData, RawKeyNotify)>>>,
+    }
+
+    impl MockInputEx {
+        fn new() -> Self {
+            // Safety: Every field of the raw protocol is nullable
+            let mut raw: RawSimpleTextInputEx = unsafe { zeroed() };
+            raw.register_key_notify = Some(register_key_notify);
+            raw.unregister_key_notify = Some(unregister_key_notify);
+            Self {
+                raw,
+                notify: RefCell::new(Vec::new()),
+            }
+        }
+
+        /// Call every notification registered for `key`
+        fn press(&self, key: RawKeyData) {
+            let notify: Vec<_> = self.notify.borrow().iter().flatten().copied().collect();
+            for (data, f) in notify {
+                if data.key == key.key {
+                    let mut key = key;
+                    // Safety: `key` is valid
+                    unsafe { f(&mut key) };
+                }
+            }
+        }
+    }
+
+    unsafe extern "efiapi" fn register_key_notify(
+        this: *mut RawSimpleTextInputEx,
+        key: *const RawKeyData,
+        notify: RawKeyNotify,
+        handle: *mut *mut c_void,
+    ) -> Status {
+        // Safety: Only ever called through a `MockInputEx`, with valid pointers
+        let (mock, key) = unsafe { (&*this.cast::<MockInputEx>(), *key) };
+        let mut list = mock.notify.borrow_mut();
+        list.push(Some((key, notify)));
+        // Safety: `handle` is valid
+        unsafe { *handle = list.len() as *mut c_void };
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn unregister_key_notify(
+        this: *mut RawSimpleTextInputEx,
+        handle: *mut c_void,
+    ) -> Status {
+        // Safety: Only ever called through a `MockInputEx`
+        let mock = unsafe { &*this.cast::<MockInputEx>() };
+        let mut list = mock.notify.borrow_mut();
+        match (handle as usize)
+            .checked_sub(1)
+            .and_then(|i| list.get_mut(i))
+        {
+            Some(n @ Some(_)) => {
+                *n = None;
+                Status::SUCCESS
+            }
+            _ => Status::INVALID_PARAMETER,
+        }
+    }
+
+    #[test]
+    fn scan_codes() {
+        assert_eq!(ScanCode::from_raw(0x00), None);
+        for code in (0x01..=0x200).chain([0x7FFF, 0x8000, 0xFFFF]) {
+            let scan = ScanCode::from_raw(code).unwrap();
+            assert_eq!(scan.to_raw(), Some(code), "{scan:?}");
+        }
+
+        assert_eq!(ScanCode::from_raw(0x01), Some(ScanCode::Up));
+        assert_eq!(ScanCode::from_raw(0x0A), Some(ScanCode::PageDown));
+        assert_eq!(ScanCode::from_raw(0x17), Some(ScanCode::Escape));
+        assert_eq!(ScanCode::from_raw(0x106), Some(ScanCode::Eject));
+
+        for n in 1..=24 {
+            let scan = ScanCode::Function(n);
+            assert_eq!(ScanCode::from_raw(scan.to_raw().unwrap()), Some(scan));
+        }
+        assert_eq!(ScanCode::from_raw(0x0B), Some(ScanCode::Function(1)));
+        assert_eq!(ScanCode::from_raw(0x16), Some(ScanCode::Function(12)));
+        assert_eq!(ScanCode::from_raw(0x68), Some(ScanCode::Function(13)));
+        assert_eq!(ScanCode::from_raw(0x73), Some(ScanCode::Function(24)));
+        assert_eq!(ScanCode::Function(0).to_raw(), None);
+        assert_eq!(ScanCode::Function(25).to_raw(), None);
+        assert_eq!(ScanCode::Other(0).to_raw(), None);
+
+        assert_eq!(ScanCode::from_raw(0x18), Some(ScanCode::Other(0x18)));
+        assert_eq!(ScanCode::from_raw(0x74), Some(ScanCode::Other(0x74)));
+        assert_eq!(ScanCode::from_raw(0x8000), Some(ScanCode::Other(0x8000)));
+    }
+
+    #[test]
+    fn keys() {
+        let raw = |scan_code, unicode_char| RawInputKey {
+            scan_code,
+            unicode_char,
+        };
+
+        assert_eq!(Key::from_raw(raw(0, 0)), None);
+        assert_eq!(Key::from_raw(raw(0, 0x61)), Some(Key::Char('a')));
+        assert_eq!(Key::from_raw(raw(0, 0x0D)), Some(Key::Char('\r')));
+        assert_eq!(Key::from_raw(raw(0, 0xE9)), Some(Key::Char('é')));
+        assert_eq!(Key::from_raw(raw(0, 0x263A)), Some(Key::Char('☺')));
+        assert_eq!(
+            Key::from_raw(raw(0, 0xD800)),
+            Some(Key::Char(char::REPLACEMENT_CHARACTER))
+        );
+        // Scan codes take priority
+        assert_eq!(
+            Key::from_raw(raw(0x17, 0x1B)),
+            Some(Key::Scan(ScanCode::Escape))
+        );
+        assert_eq!(
+            Key::from_raw(raw(0x0C, 0)),
+            Some(Key::Scan(ScanCode::Function(2)))
+        );
+        assert_eq!(
+            Key::from_raw(raw(0x9000, 0)),
+            Some(Key::Scan(ScanCode::Other(0x9000)))
+        );
+
+        for key in [
+            Key::Char('a'),
+            Key::Char('\u{8}'),
+            Key::Char('é'),
+            Key::Char('\u{FFFD}'),
+            Key::Scan(ScanCode::Up),
+            Key::Scan(ScanCode::Function(1)),
+            Key::Scan(ScanCode::Function(12)),
+            Key::Scan(ScanCode::Function(13)),
+            Key::Scan(ScanCode::Function(24)),
+            Key::Scan(ScanCode::Eject),
+            Key::Scan(ScanCode::Other(0x8000)),
+        ] {
+            let raw = key.to_raw().unwrap();
+            assert_eq!(Key::from_raw(raw), Some(key));
+        }
+        assert_eq!(Key::Char('😀').to_raw(), None);
+        assert_eq!(Key::Scan(ScanCode::Function(0)).to_raw(), None);
+        assert_eq!(Key::Scan(ScanCode::Function(25)).to_raw(), None);
+    }
+
+    #[test]
+    fn key_data() {
+        let data = |shift_state, toggle_state| RawKeyData {
+            key: RawInputKey {
+                scan_code: 0,
+                unicode_char: 0x61,
+            },
+            state: RawKeyState {
+                shift_state,
+                toggle_state,
+            },
+        };
+
+        let key = KeyData::from_raw(data(0, 0));
+        assert_eq!(key.key, Some(Key::Char('a')));
+        assert!(key.shift.is_empty());
+        assert!(key.toggle.is_empty());
+
+        let key = KeyData::from_raw(data(0x8000_0001, 0x80 | 0x04));
+        assert_eq!(key.shift, ShiftState::VALID | ShiftState::RIGHT_SHIFT);
+        assert!(key.shift.shift() && !key.shift.ctrl() && !key.shift.alt() && !key.shift.logo());
+        assert_eq!(key.toggle, ToggleState::VALID | ToggleState::CAPS_LOCK);
+
+        let key = KeyData::from_raw(data(0x8000_0008 | 0x20 | 0x40, 0x80 | 0x40 | 0x03));
+        assert!(!key.shift.shift() && key.shift.ctrl() && key.shift.alt() && key.shift.logo());
+        assert!(key
+            .shift
+            .contains(ShiftState::LEFT_CONTROL | ShiftState::LEFT_ALT));
+        assert_eq!(
+            key.toggle,
+            ToggleState::VALID
+                | ToggleState::KEY_STATE_EXPOSED
+                | ToggleState::SCROLL_LOCK
+                | ToggleState::NUM_LOCK
+        );
+
+        // Unknown bits are dropped
+        let key = KeyData::from_raw(data(0x8000_0400 | 0x100, 0x80 | 0x08));
+        assert_eq!(key.shift, ShiftState::VALID | ShiftState::MENU);
+        assert_eq!(key.toggle, ToggleState::VALID);
+
+        // Partial keystroke
+        let mut raw = data(0x8000_0002, 0x80 | 0x40);
+        raw.key.unicode_char = 0;
+        let key = KeyData::from_raw(raw);
+        assert_eq!(key.key, None);
+        assert!(key.shift.shift());
+    }
+
+    #[test]
+    fn key_notify() -> Result<()> {
+        let mut mock = MockInputEx::new();
+        // Safety: `mock` outlives `input`
+        let input = unsafe { SimpleTextInputEx::new(addr_of_mut!(mock).cast()) };
+        let a = RawKeyData {
+            key: Key::Char('a').to_raw().unwrap(),
+            state: RawKeyState {
+                shift_state: (ShiftState::VALID | ShiftState::LEFT_CONTROL).bits(),
+                toggle_state: 0,
+            },
+        };
+
+        let pressed = Rc::new(Cell::new(None));
+        let p = pressed.clone();
+        let notify = input.register_key_notify(
+            Key::Char('a'),
+            ShiftState::LEFT_CONTROL,
+            move |k: KeyData| p.set(Some(k)),
+        )?;
+        {
+            let list = mock.notify.borrow();
+            let (data, _) = list[0].unwrap();
+            assert_eq!(data.key, a.key);
+            assert_eq!(data.state.shift_state, a.state.shift_state);
+        }
+        mock.press(a);
+        let key = pressed.take().unwrap();
+        assert_eq!(key.key, Some(Key::Char('a')));
+        assert!(key.shift.ctrl());
+
+        input.unregister_key_notify(notify)?;
+        mock.press(a);
+        assert_eq!(pressed.take(), None);
+        assert!(KEY_NOTIFY
+            .iter()
+            .all(|s| s.load(Ordering::Relaxed).is_null()));
+
+        assert!(input
+            .register_key_notify(
+                Key::Scan(ScanCode::Function(0)),
+                ShiftState::empty(),
+                |_| ()
+            )
+            .is_err_and(|e| e.status() == Status::INVALID_PARAMETER));
+
+        // Every slot gets its own callback
+        let count = Rc::new(Cell::new(0));
+        let mut notify = Vec::new();
+        for i in 0..MAX_KEY_NOTIFY {
+            let count = count.clone();
+            let key = Key::Scan(ScanCode::Function(i as u8 + 1));
+            notify.push(
+                input.register_key_notify(key, ShiftState::empty(), move |_| {
+                    count.set(count.get() + i + 1)
+                })?,
+            );
+        }
+        assert!(input
+            .register_key_notify(Key::Char('b'), ShiftState::empty(), |_| ())
+            .is_err_and(|e| e.status() == Status::OUT_OF_RESOURCES));
+        for i in 0..MAX_KEY_NOTIFY {
+            mock.press(RawKeyData {
+                key: Key::Scan(ScanCode::Function(i as u8 + 1)).to_raw().unwrap(),
+                state: RawKeyState::default(),
+            });
+        }
+        assert_eq!(count.get(), (1..=MAX_KEY_NOTIFY).sum());
+
+        // Dropping unregisters
+        drop(notify.remove(5));
+        assert!(KEY_NOTIFY[5].load(Ordering::Relaxed).is_null());
+        assert!(mock.notify.borrow()[6].is_none());
+        count.set(0);
+        mock.press(RawKeyData {
+            key: Key::Scan(ScanCode::Function(6)).to_raw().unwrap(),
+            state: RawKeyState::default(),
+        });
+        assert_eq!(count.get(), 0);
+
+        // Unregistering frees the slot
+        input.unregister_key_notify(notify.remove(3))?;
+        notify.push(input.register_key_notify(Key::Char('b'), ShiftState::empty(), |_| ())?);
+        notify.push(input.register_key_notify(Key::Char('b'), ShiftState::empty(), |_| ())?);
+        assert!(input
+            .register_key_notify(Key::Char('c'), ShiftState::empty(), |_| ())
+            .is_err_and(|e| e.status() == Status::OUT_OF_RESOURCES));
+        for n in notify {
+            input.unregister_key_notify(n)?;
+        }
+        assert!(KEY_NOTIFY
+            .iter()
+            .all(|s| s.load(Ordering::Relaxed).is_null()));
+        Ok(())
+    }
+
+    #[test]
+    fn ascii_fallback() {
+        for (c, ascii) in [
+            ('─', "-"),
+            ('━', "-"),
+            ('═', "-"),
+            ('—', "-"),
+            ('−', "-"),
+            ('│', "|"),
+            ('║', "|"),
+            ('┌', "+"),
+            ('┼', "+"),
+            ('╋', "+"),
+            ('╔', "+"),
+            ('╬', "+"),
+            ('╭', "+"),
+            ('╰', "+"),
+            ('╱', "/"),
+            ('╲', "\\"),
+            ('╳', "X"),
+            ('█', "#"),
+            ('░', "#"),
+            ('←', "<"),
+            ('→', ">"),
+            ('↑', "^"),
+            ('▼', "v"),
+            ('▶', ">"),
+            ('•', "*"),
+            ('✔', "v"),
+            ('✘', "x"),
+            ('…', "..."),
+            ('’', "'"),
+            ('“', "\""),
+            ('«', "<<"),
+            ('»', ">>"),
+            ('\u{A0}', " "),
+            ('\u{2003}', " "),
+            ('é', "?"),
+            ('😀', "?"),
+            ('\u{FFFD}', "?"),
+        ] {
+            assert_eq!(super::ascii_fallback(c), ascii, "{c:?}");
+            assert!(ascii.is_ascii());
+        }
+    }
+
+    #[test]
+    fn fallback_text() {
+        let supports = |s: &str| s == "é";
+        for (text, out) in [
+            ("", ""),
+            ("plain", "plain"),
+            ("é", "é"),
+            ("┌─┐\n│é│\n└─┘", "+-+\n|é|\n+-+"),
+            ("→ 😀…", "> ?..."),
+        ] {
+            assert_eq!(with_fallback(text, supports), out, "{text:?}");
+        }
+
+        // ASCII is never tested
+        let out = with_fallback("a─b", |s| {
+            assert!(!s.is_ascii());
+            false
+        });
+        assert_eq!(out, "a-b");
+    }
+
+    #[test]
+    fn split_escapes() {
+        for (s, seq, rest) in [
+            // CSI
+            ("\x1b[31mred", "\x1b[31m", "red"),
+            ("\x1b[1;32;40mX", "\x1b[1;32;40m", "X"),
+            ("\x1b[mX", "\x1b[m", "X"),
+            ("\x1b[?25lX", "\x1b[?25l", "X"),
+            ("\x1b[5Z\x1b[0m", "\x1b[5Z", "\x1b[0m"),
+            // Incomplete or split sequences are consumed to the end
+            ("\x1b", "\x1b", ""),
+            ("\x1b[", "\x1b[", ""),
+            ("\x1b[31", "\x1b[31", ""),
+            ("\x1b[3\x01X", "\x1b[3", "\x01X"),
+            ("\x1b[3é", "\x1b[3", "é"),
+            // OSC
+            ("\x1b]0;title\x07rest", "\x1b]0;title\x07", "rest"),
+            ("\x1b]0;title\x1b\\rest", "\x1b]0;title\x1b\\", "rest"),
+            ("\x1b]0;a\x07b\x1b\\c", "\x1b]0;a\x07", "b\x1b\\c"),
+            ("\x1b]0;title", "\x1b]0;title", ""),
+            // Two byte sequences
+            ("\x1bcX", "\x1bc", "X"),
+            ("\x1bé", "\x1b", "é"),
+        ] {
+            assert_eq!(split_escape(s), (seq, rest), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn ansi_escapes() -> Result<()> {
+        let mut mock = MockOutput::new();
+        // Safety: `mock` outlives `out`
+        let out = unsafe { SimpleTextOutput::new(addr_of_mut!(mock.raw)) };
+        let colors = |seq: &str| -> Result<(usize, usize)> {
+            out.apply_escape(seq)?;
+            let (fore, back) = out.attributes();
+            Ok((fore.0, back.0))
+        };
+
+        assert_eq!(colors("\x1b[31m")?, (0x04, 0x00));
+        assert_eq!(colors("\x1b[1m")?, (0x0C, 0x00));
+        assert_eq!(colors("\x1b[34m")?, (0x09, 0x00));
+        assert_eq!(colors("\x1b[22m")?, (0x01, 0x00));
+        assert_eq!(colors("\x1b[92;44m")?, (0x0A, 0x01));
+        assert_eq!(colors("\x1b[33;103m")?, (0x0E, 0x06));
+        assert_eq!(colors("\x1b[0m")?, (0x07, 0x00));
+        assert_eq!(colors("\x1b[37;1;46m")?, (0x0F, 0x03));
+        assert_eq!(colors("\x1b[39;49m")?, (0x07, 0x00));
+        assert_eq!(colors("\x1b[35;41m")?, (0x05, 0x04));
+        assert_eq!(colors("\x1b[m")?, (0x07, 0x00));
+
+        // Extended colors
+        assert_eq!(colors("\x1b[38;5;1m")?, (0x04, 0x00));
+        assert_eq!(colors("\x1b[38;5;12m")?, (0x09, 0x00));
+        assert_eq!(colors("\x1b[48;5;3m")?, (0x09, 0x06));
+        assert_eq!(colors("\x1b[38;5;200;48;5;16m")?, (0x09, 0x06));
+        assert_eq!(colors("\x1b[38;2;1;2;3;32m")?, (0x0A, 0x06));
+
+        // Unknown and incomplete sequences do nothing
+        for seq in [
+            "\x1b[5m",
+            "\x1b[5Z",
+            "\x1b[?1049h",
+            "\x1b[31",
+            "\x1b[",
+            "\x1b]0;title\x07",
+            "\x1bc",
+        ] {
+            assert_eq!(colors(seq)?, (0x0A, 0x06), "{seq:?}");
+        }
+        assert_eq!(out.cursor(), (0, 0));
+        assert_eq!(mock.clears.get(), 0);
+
+        // Cursor
+        out.apply_escape("\x1b[5;10H")?;
+        assert_eq!(out.cursor(), (9, 4));
+        out.apply_escape("\x1b[2A")?;
+        assert_eq!(out.cursor(), (9, 2));
+        out.apply_escape("\x1b[B")?;
+        assert_eq!(out.cursor(), (9, 3));
+        out.apply_escape("\x1b[3C")?;
+        assert_eq!(out.cursor(), (12, 3));
+        out.apply_escape("\x1b[20D")?;
+        assert_eq!(out.cursor(), (0, 3));
+        out.apply_escape("\x1b[9A")?;
+        assert_eq!(out.cursor(), (0, 0));
+        out.apply_escape("\x1b[3;4f")?;
+        assert_eq!(out.cursor(), (3, 2));
+        assert!(out
+            .apply_escape("\x1b[99;99H")
+            .is_err_and(|e| e.status() == Status::UNSUPPORTED));
+        out.apply_escape("\x1b[H")?;
+        assert_eq!(out.cursor(), (0, 0));
+
+        out.apply_escape("\x1b[?25l")?;
+        assert!(!out.cursor_visible());
+        out.apply_escape("\x1b[?25h")?;
+        assert!(out.cursor_visible());
+
+        out.apply_escape("\x1b[1J")?;
+        assert_eq!(mock.clears.get(), 0);
+        out.apply_escape("\x1b[2J")?;
+        out.apply_escape("\x1b[3J")?;
+        assert_eq!(mock.clears.get(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn consoles_broadcast() -> Result<()> {
+        let mut a = MockOutput::new();
+        let mut b = MockOutput::new();
+        a.raw.clear_screen = None;
+        // Safety: `a` and `b` outlive `consoles`
+        let consoles = unsafe {
+            Consoles::new(alloc::vec![
+                (
+                    EfiHandle::null(),
+                    SimpleTextOutput::new(addr_of_mut!(a.raw))
+                ),
+                (
+                    EfiHandle::null(),
+                    SimpleTextOutput::new(addr_of_mut!(b.raw))
+                ),
+            ])
+        };
+        assert_eq!(consoles.len(), 2);
+
+        consoles.set_attributes(TextForeground::YELLOW, TextBackground::BLUE)?;
+        consoles.set_cursor(3, 4)?;
+        consoles.disable_cursor()?;
+        for out in [consoles.get(0).unwrap(), consoles.get(1).unwrap()] {
+            assert_eq!(
+                out.attributes(),
+                (TextForeground::YELLOW, TextBackground::BLUE)
+            );
+            assert_eq!(out.cursor(), (3, 4));
+            assert!(!out.cursor_visible());
+        }
+
+        // A failing device doesn't stop the others
+        assert!(consoles
+            .clear()
+            .is_err_and(|e| e.status() == Status::UNSUPPORTED));
+        assert_eq!(a.clears.get(), 0);
+        assert_eq!(b.clears.get(), 1);
+        assert_eq!(consoles.get(1).unwrap().cursor(), (0, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn output_options() -> Result<()> {
+        let mut a = MockOutput::new();
+        let mut b = MockOutput::new();
+        // Safety: `a` and `b` outlive the outputs
+        let (a1, a2, b) = unsafe {
+            (
+                SimpleTextOutput::new(addr_of_mut!(a.raw)),
+                SimpleTextOutput::new(addr_of_mut!(a.raw)),
+                SimpleTextOutput::new(addr_of_mut!(b.raw)),
+            )
+        };
+
+        // Options follow the interface, not the wrapper
+        a1.set_options(OutputOptions::ANSI)?;
+        assert_eq!(a2.options(), OutputOptions::ANSI);
+        assert_eq!(b.options(), OutputOptions::empty());
+
+        b.set_options(OutputOptions::ASCII_FALLBACK)?;
+        a2.set_options(OutputOptions::all())?;
+        assert_eq!(a1.options(), OutputOptions::all());
+        assert_eq!(b.options(), OutputOptions::ASCII_FALLBACK);
+
+        // Only the ANSI console interprets escapes, the other writes them
+        // as text, which the mock doesn't support
+        assert!(write!(&a1, "\x1b[31m").is_ok());
+        assert!(write!(&b, "\x1b[31m").is_err());
+        assert_eq!(a1.attributes().0, TextForeground::RED);
+        assert_eq!(b.attributes().0, TextForeground::LIGHT_GRAY);
+
+        a1.set_options(OutputOptions::empty())?;
+        b.set_options(OutputOptions::empty())?;
+        assert_eq!(a2.options(), OutputOptions::empty());
+        assert!(!OUTPUT_CONSOLES
+            .iter()
+            .any(|c| c.load(Ordering::Acquire) == a1.interface));
+        Ok(())
+    }
+}
@@ -1,13 +1,103 @@
 //! Raw UEFI data types
 
-use core::{fmt, ptr::null_mut};
+use core::{ffi::c_void, fmt, ptr::null_mut};
 
-use crate::nuefi_core::base::{Char16, Status};
+use crate::nuefi_core::base::{Char16, Event, Status};
 
-#[derive(Debug)]
+/// Raw EFI_INPUT_KEY
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct RawInputKey {
+    pub scan_code: u16,
+    pub unicode_char: Char16,
+}
+
+/// Raw EFI_SIMPLE_TEXT_INPUT_PROTOCOL struct
+///
+/// <https://uefi.org/specs/UEFI/2.10/12_Protocols_Console_Support.html#simple-text-input-protocol>
 #[repr(C)]
 pub struct RawSimpleTextInput {
-    //
+    pub reset: Option<unsafe extern "efiapi" fn(this: *mut Self, extended: bool) -> Status>,
+
+    pub read_key_stroke:
+        Option<unsafe extern "efiapi" fn(this: *mut Self, key: *mut RawInputKey) -> Status>,
+
+    pub wait_for_key: Event,
+}
+
+impl fmt::Debug for RawSimpleTextInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawSimpleTextInput")
+            .field("reset", &{ &self.reset as *const _ })
+            .field("read_key_stroke", &{ &self.read_key_stroke as *const _ })
+            .field("wait_for_key", &self.wait_for_key)
+            .finish()
+    }
+}
+
+/// Raw EFI_KEY_STATE
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct RawKeyState {
+    pub shift_state: u32,
+    pub toggle_state: u8,
+}
+
+/// Raw EFI_KEY_DATA
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct RawKeyData {
+    pub key: RawInputKey,
+    pub state: RawKeyState,
+}
+
+/// Raw EFI_KEY_NOTIFY_FUNCTION
+pub type RawKeyNotify = unsafe extern "efiapi" fn(key: *mut RawKeyData) -> Status;
+
+/// Raw EFI_SIMPLE_TEXT_INPUT_EX_PROTOCOL struct
+///
+/// <https://uefi.org/specs/UEFI/2.10/12_Protocols_Console_Support.html#efi-simple-text-input-ex-protocol>
+#[repr(C)]
+pub struct RawSimpleTextInputEx {
+    pub reset: Option<unsafe extern "efiapi" fn(this: *mut Self, extended: bool) -> Status>,
+
+    pub read_key_stroke_ex:
+        Option<unsafe extern "efiapi" fn(this: *mut Self, key: *mut RawKeyData) -> Status>,
+
+    pub wait_for_key_ex: Event,
+
+    pub set_state: Option<unsafe extern "efiapi" fn(this: *mut Self, state: *const u8) -> Status>,
+
+    pub register_key_notify: Option<
+        unsafe extern "efiapi" fn(
+            this: *mut Self,
+            key: *const RawKeyData,
+            notify: RawKeyNotify,
+            handle: *mut *mut c_void,
+        ) -> Status,
+    >,
+
+    pub unregister_key_notify:
+        Option<unsafe extern "efiapi" fn(this: *mut Self, handle: *mut c_void) -> Status>,
+}
+
+impl fmt::Debug for RawSimpleTextInputEx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawSimpleTextInputEx")
+            .field("reset", &{ &self.reset as *const _ })
+            .field("read_key_stroke_ex", &{
+                &self.read_key_stroke_ex as *const _
+            })
+            .field("wait_for_key_ex", &self.wait_for_key_ex)
+            .field("set_state", &{ &self.set_state as *const _ })
+            .field("register_key_notify", &{
+                &self.register_key_notify as *const _
+            })
+            .field("unregister_key_notify", &{
+                &self.unregister_key_notify as *const _
+            })
+            .finish()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
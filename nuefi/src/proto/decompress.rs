@@ -0,0 +1,103 @@
+//! UEFI Decompress Protocol
+//!
+//! See [`nuefi_core::decompress`] for a pure Rust implementation
+//! that doesn't depend on firmware support.
+use alloc::{vec, vec::Vec};
+use core::ffi::c_void;
+
+use raw::RawDecompress;
+
+use crate::{
+    error::{Result, Status},
+    util::interface,
+    Protocol,
+};
+
+pub mod raw;
+
+interface!(
+    #[Protocol("D8117CFE-94A6-11D4-9A3A-0090273FC14D", crate("crate"))]
+    Decompress(RawDecompress)
+);
+
+impl<'table> Decompress<'table> {
+    /// Returns the decompressed size of `source`, and the scratch size
+    /// [`Decompress::decompress_into`] requires.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::INVALID_PARAMETER`] if `source` is larger than [`u32::MAX`]
+    ///   or is too small to contain the sizes
+    pub fn info(&self, source: &[u8]) -> Result<(usize, usize)> {
+        let size: u32 = source
+            .len()
+            .try_into()
+            .map_err(|_| Status::INVALID_PARAMETER)?;
+        let get_info = self.interface().get_info.ok_or(Status::UNSUPPORTED)?;
+        let mut dest = 0;
+        let mut scratch = 0;
+
+        // Safety:
+        // - `get_info` checked above
+        // - `source` is valid for `size` bytes
+        let ret = unsafe {
+            (get_info)(
+                self.interface,
+                source.as_ptr().cast(),
+                size,
+                &mut dest,
+                &mut scratch,
+            )
+        };
+        if ret.is_success() {
+            return Ok((dest as usize, scratch as usize));
+        }
+        Err(ret.into())
+    }
+
+    /// Decompress `source` into `dest`, using `scratch` as working memory
+    ///
+    /// `dest` and `scratch` must be at least the sizes returned by
+    /// [`Decompress::info`]
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::INVALID_PARAMETER`] if `source` is corrupt, or any buffer is
+    ///   larger than [`u32::MAX`]
+    pub fn decompress_into(
+        &self,
+        source: &[u8],
+        dest: &mut [u8],
+        scratch: &mut [u8],
+    ) -> Result<()> {
+        let len = |b: usize| -> Result<u32> {
+            b.try_into().map_err(|_| Status::INVALID_PARAMETER.into())
+        };
+        let decompress = self.interface().decompress.ok_or(Status::UNSUPPORTED)?;
+
+        // Safety:
+        // - `decompress` checked above
+        // - All buffers are valid for their sizes
+        unsafe {
+            (decompress)(
+                self.interface,
+                source.as_ptr().cast(),
+                len(source.len())?,
+                dest.as_mut_ptr().cast::<c_void>(),
+                len(dest.len())?,
+                scratch.as_mut_ptr().cast::<c_void>(),
+                len(scratch.len())?,
+            )
+        }
+        .into()
+    }
+
+    /// Decompress `source`, allocating the output and scratch buffers
+    pub fn decompress(&self, source: &[u8]) -> Result<Vec<u8>> {
+        let (size, scratch) = self.info(source)?;
+        let mut dest = vec![0; size];
+        let mut scratch = vec![0; scratch];
+        self.decompress_into(source, &mut dest, &mut scratch)?;
+        Ok(dest)
+    }
+}
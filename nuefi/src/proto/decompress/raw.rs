@@ -0,0 +1,42 @@
+//! Raw UEFI Decompress Protocol types
+
+use core::{ffi::c_void, fmt};
+
+use crate::nuefi_core::base::Status;
+
+/// Raw EFI_DECOMPRESS_PROTOCOL struct
+///
+/// <https://uefi.org/specs/UEFI/2.10/19_Protocols_Compression_Algorithm_Specification.html#decompress-protocol>
+#[repr(C)]
+pub struct RawDecompress {
+    pub get_info: Option<
+        unsafe extern "efiapi" fn(
+            this: *mut Self,
+            source: *const c_void,
+            source_size: u32,
+            destination_size: *mut u32,
+            scratch_size: *mut u32,
+        ) -> Status,
+    >,
+
+    pub decompress: Option<
+        unsafe extern "efiapi" fn(
+            this: *mut Self,
+            source: *const c_void,
+            source_size: u32,
+            destination: *mut c_void,
+            destination_size: u32,
+            scratch: *mut c_void,
+            scratch_size: u32,
+        ) -> Status,
+    >,
+}
+
+impl fmt::Debug for RawDecompress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawDecompress")
+            .field("get_info", &{ &self.get_info as *const _ })
+            .field("decompress", &{ &self.decompress as *const _ })
+            .finish()
+    }
+}
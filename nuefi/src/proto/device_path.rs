@@ -31,6 +31,7 @@ use crate::{
     error::{Result, Status},
     get_boot_table,
     mem::MemoryType,
+    nuefi_core::path::FilePath,
     string::UefiString,
     table::BootServices,
     util::interface,
@@ -141,46 +142,33 @@ impl<'table> DevicePath<'table> {
 
     /// Append the UEFI file path, returning the new device path
     // FIXME: These leak memory.
-    pub fn append_file_path(&self, path: &str) -> Result<DevicePath<'table>> {
+    pub fn append_file_path<P: AsRef<FilePath>>(&self, path: P) -> Result<DevicePath<'table>> {
         let table = get_boot_table().ok_or(Status::UNSUPPORTED)?;
         let boot = table.boot();
-        // log::trace!("Path: {path}");
 
         let hdr_size = size_of::<RawDevicePath>();
-        let path: Vec<u16> = path.encode_utf16().chain([0]).collect();
-        let path_len = path.len() * 2;
+        let media = path.as_ref().to_media_node()?;
+        let end = RawDevicePath::end();
 
-        let cap = path_len + hdr_size + hdr_size;
-        // log::trace!("Capacity: {cap} - {path_len}");
+        let cap = media.len() + hdr_size;
 
         let data = boot
             .allocate_pool(MemoryType::LOADER_DATA, cap)?
             .cast::<u8>();
 
-        let path_len = path_len.try_into().map_err(|_| Status::BAD_BUFFER_SIZE)?;
-
-        let media = RawDevicePath::media_file(path_len);
-        let end = RawDevicePath::end();
-
         // Safety: `data` is valid for `cap`, which is all we write
         unsafe {
             // Write Media file node
-            let ptr = &media as *const _ as *const u8;
-            data.as_ptr().copy_from_nonoverlapping(ptr, hdr_size);
-
-            // Write name
-            let ptr = path.as_ptr() as *const u8;
-            let name = data.as_ptr().add(hdr_size);
-            name.copy_from_nonoverlapping(ptr, path_len.into());
+            data.as_ptr()
+                .copy_from_nonoverlapping(media.as_ptr(), media.len());
 
             // Write end of structure node
             let ptr = &end as *const _ as *const u8;
-            let eos = data.as_ptr().add(hdr_size + path_len as usize);
+            let eos = data.as_ptr().add(media.len());
             eos.copy_from_nonoverlapping(ptr, hdr_size);
 
             // We've ensured this is a valid `DevicePath` structure
             let node = unsafe { DevicePath::new(data.as_ptr() as *mut _) };
-            // log::trace!("Node: {:#?}", node.to_string());
 
             // Append it
             let ret = self.append(&node)?;
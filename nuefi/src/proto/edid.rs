@@ -1,12 +1,36 @@
 //! UEFI EDID Protocol services
+//!
+//! See [`nuefi_core::edid`] for parsing EDID information
 
-use core::slice::from_raw_parts;
+use alloc::boxed::Box;
+use core::{ptr::null_mut, slice::from_raw_parts};
 
-use crate::{interface, Protocol};
+use crate::{
+    error::{Result, Status},
+    interface,
+    nuefi_core::edid::Edid,
+    proto::provider::Provider,
+    table::BootServices,
+    EfiHandle,
+    Protocol,
+};
 
 pub mod raw;
 use raw::*;
 
+/// EDID bytes from the protocol fields `size` and `edid`, or [`None`]
+///
+/// # Safety
+///
+/// - `edid` must be valid for `size` bytes if it is not null
+unsafe fn edid_from_raw<'a>(size: u32, edid: *mut u8) -> Option<&'a [u8]> {
+    if size != 0 && !edid.is_null() {
+        Some(from_raw_parts(edid, size as usize))
+    } else {
+        None
+    }
+}
+
 interface!(
     #[Protocol("BD8C1056-9F36-44EC-92A8-A6337F817986", crate("crate"))]
     ActiveEdid(RawEdidActive)
@@ -16,15 +40,17 @@ impl<'boot> ActiveEdid<'boot> {
     /// EDID information from the active display, or [`None`]
     pub fn edid(&self) -> Option<&[u8]> {
         let i = self.interface();
-        let size = i.size as usize;
-        let ptr = i.edid;
-        if size != 0 && !ptr.is_null() {
-            // Safety:
-            // - EDID information is valid from firmware and read only.
-            unsafe { Some(from_raw_parts(ptr, size)) }
-        } else {
-            None
-        }
+        // Safety:
+        // - EDID information is valid from firmware and read only.
+        unsafe { edid_from_raw(i.size, i.edid) }
+    }
+
+    /// Parsed EDID information from the active display
+    ///
+    /// Returns [`Status::UNSUPPORTED`] if there is no EDID information,
+    /// and [`Status::INVALID_PARAMETER`] if it is invalid.
+    pub fn parse(&self) -> Result<Edid<'_>> {
+        Edid::parse(self.edid().ok_or(Status::UNSUPPORTED)?)
     }
 }
 
@@ -35,3 +61,170 @@ interface!(
     #[Protocol("1C0C34F6-D380-41FA-A049-8AD06C1A66AA", crate("crate"))]
     DiscoveredEdid(RawEdidDiscovered)
 );
+
+impl<'boot> DiscoveredEdid<'boot> {
+    /// EDID information read from the display, or [`None`]
+    ///
+    /// This is unmodified by any [`EdidOverride`], see [`ActiveEdid`].
+    pub fn edid(&self) -> Option<&[u8]> {
+        let i = self.interface();
+        // Safety:
+        // - EDID information is valid from firmware and read only.
+        unsafe { edid_from_raw(i.size, i.edid) }
+    }
+
+    /// Parsed EDID information read from the display
+    ///
+    /// Returns [`Status::UNSUPPORTED`] if there is no EDID information,
+    /// and [`Status::INVALID_PARAMETER`] if it is invalid.
+    pub fn parse(&self) -> Result<Edid<'_>> {
+        Edid::parse(self.edid().ok_or(Status::UNSUPPORTED)?)
+    }
+}
+
+bitflags::bitflags! {
+    /// How an [`EdidOverride`] applies to a display
+    #[repr(transparent)]
+    pub struct OverrideAttributes: u32 {
+        /// Use the discovered EDID, ignoring the override
+        const DONT_OVERRIDE = 1 << 0;
+
+        /// Allow the display to be hot plugged, even if it is not
+        /// normally supported
+        const ENABLE_HOT_PLUG = 1 << 1;
+    }
+}
+
+/// Callback deciding the override for a display, see
+/// [`EdidOverride::install`]
+type GetEdid = Box<dyn Fn(EfiHandle) -> Option<(OverrideAttributes, &'static [u8])>>;
+
+unsafe extern "efiapi" fn get_edid(
+    this: *mut RawEdidOverride,
+    child: *mut EfiHandle,
+    attributes: *mut u32,
+    size: *mut usize,
+    edid: *mut *mut u8,
+) -> Status {
+    let args = [child.cast(), attributes.cast(), size.cast(), edid.cast()];
+    if this.is_null() || args.iter().any(|p: &*mut u8| p.is_null()) {
+        return Status::INVALID_PARAMETER;
+    }
+    // Safety:
+    // - `this` is only ever installed from `EdidOverride::install`
+    let ret = unsafe { Provider::<RawEdidOverride, GetEdid>::with(this, |get| get(*child)) };
+    match ret {
+        Some(Some((attr, data))) => {
+            *attributes = attr.bits();
+            *size = data.len();
+            // Firmware only reads the EDID
+            *edid = if data.is_empty() {
+                null_mut()
+            } else {
+                data.as_ptr().cast_mut()
+            };
+            Status::SUCCESS
+        }
+        Some(None) => Status::UNSUPPORTED,
+        None => Status::NOT_READY,
+    }
+}
+
+interface!(
+    /// Lets platform drivers override the EDID of a display,
+    /// for example to force a resolution for broken monitors.
+    #[Protocol("48ECB431-FB72-45C0-A922-F458FE040BD5", crate("crate"))]
+    EdidOverride(RawEdidOverride)
+);
+
+impl<'boot> EdidOverride<'boot> {
+    /// The override for the display on handle `child`, and how to apply it,
+    /// or [`None`] if there is no override for it.
+    ///
+    /// The EDID may be empty, such as with
+    /// [`OverrideAttributes::DONT_OVERRIDE`].
+    pub fn edid(&self, child: EfiHandle) -> Result<Option<(OverrideAttributes, &[u8])>> {
+        let get = self.interface().get_edid.ok_or(Status::UNSUPPORTED)?;
+        let mut child = child;
+        let mut attributes = 0;
+        let mut size = 0;
+        let mut edid = null_mut();
+
+        // Safety: Construction ensures these are valid
+        let ret = unsafe {
+            (get)(
+                self.interface,
+                &mut child,
+                &mut attributes,
+                &mut size,
+                &mut edid,
+            )
+        };
+        if ret == Status::UNSUPPORTED {
+            return Ok(None);
+        } else if !ret.is_success() {
+            return Err(ret.into());
+        }
+        let attributes = OverrideAttributes::from_bits_truncate(attributes);
+        let edid = if size != 0 && !edid.is_null() {
+            // Safety: Firmware promises `edid` is valid for `size` bytes
+            unsafe { from_raw_parts(edid, size) }
+        } else {
+            &[]
+        };
+        Ok(Some((attributes, edid)))
+    }
+
+    /// Install an [`EdidOverride`] on `handle`, such as a drivers image
+    /// handle.
+    ///
+    /// The graphics driver calls `f` with the handle of each display it
+    /// finds, and uses the returned EDID instead of the displays own.
+    /// Returning [`None`] leaves the display alone.
+    ///
+    /// Only one override is used by the graphics driver, and it must be
+    /// installed before the driver starts on the display, so this is only
+    /// useful from a driver loaded early.
+    ///
+    /// The override is never uninstalled.
+    pub fn install<F>(boot: &BootServices, handle: EfiHandle, f: F) -> Result<()>
+    where
+        F: Fn(EfiHandle) -> Option<(OverrideAttributes, &'static [u8])> + 'static,
+    {
+        let raw = RawEdidOverride {
+            get_edid: Some(get_edid),
+        };
+        let get: GetEdid = Box::new(f);
+        Provider::new(raw, get)
+            .install::<EdidOverride>(boot, Some(handle))
+            .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edid_override() -> Result<()> {
+        static EDID: [u8; 4] = [1, 2, 3, 4];
+        let raw = RawEdidOverride {
+            get_edid: Some(get_edid),
+        };
+        let get: GetEdid = Box::new(|child| {
+            let attr = OverrideAttributes::ENABLE_HOT_PLUG;
+            (child != EfiHandle::null()).then_some((attr, &EDID[..]))
+        });
+        let provider = Provider::new(raw, get);
+        // Safety: `provider` outlives `proto`
+        let proto = unsafe { EdidOverride::new(provider.as_ptr()) };
+
+        // Safety: The handle is only compared to null
+        let child = unsafe { EfiHandle::new(1 as _) };
+        let (attr, edid) = proto.edid(child)?.unwrap();
+        assert_eq!(attr, OverrideAttributes::ENABLE_HOT_PLUG);
+        assert_eq!(edid, EDID);
+        assert!(proto.edid(EfiHandle::null())?.is_none());
+        Ok(())
+    }
+}
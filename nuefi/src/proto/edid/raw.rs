@@ -1,5 +1,9 @@
 //! Raw UEFI EDID Protocol types
 
+use core::fmt;
+
+use crate::{nuefi_core::base::Status, EfiHandle};
+
 /// Raw EDID_ACTIVE_PROTOCOL struct
 #[derive(Debug)]
 #[repr(C)]
@@ -15,3 +19,27 @@ pub struct RawEdidDiscovered {
     pub size: u32,
     pub edid: *mut u8,
 }
+
+pub type RawGetEdid = unsafe extern "efiapi" fn(
+    this: *mut RawEdidOverride,
+    child: *mut EfiHandle,
+    attributes: *mut u32,
+    size: *mut usize,
+    edid: *mut *mut u8,
+) -> Status;
+
+/// Raw EFI_EDID_OVERRIDE_PROTOCOL struct
+///
+/// <https://uefi.org/specs/UEFI/2.10/12_Protocols_Console_Support.html#edid-override-protocol>
+#[repr(C)]
+pub struct RawEdidOverride {
+    pub get_edid: Option<RawGetEdid>,
+}
+
+impl fmt::Debug for RawEdidOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawEdidOverride")
+            .field("get_edid", &{ &self.get_edid as *const _ })
+            .finish()
+    }
+}
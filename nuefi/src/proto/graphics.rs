@@ -1,6 +1,7 @@
 //! UEFI Graphics related protocols
 use alloc::{vec, vec::Vec};
 use core::{
+    cmp::Reverse,
     ffi::c_void,
     fmt::{self, Write},
     iter::once,
@@ -10,12 +11,21 @@ use core::{
     slice::{from_raw_parts, from_raw_parts_mut},
 };
 
-use raw::{RawBltOperation, RawBltPixel, RawGraphicsInfo, RawGraphicsOutput, RawPixelFormat};
+use raw::{
+    RawBltOperation,
+    RawBltPixel,
+    RawGraphicsInfo,
+    RawGraphicsOutput,
+    RawPixelFormat,
+    RawPixelMask,
+};
 
 use self::raw::RawGraphicsMode;
 use crate::{
     error::{Result, Status},
     get_boot_table,
+    proto::edid::ActiveEdid,
+    table::BootServices,
     util::interface,
     Protocol,
 };
@@ -29,20 +39,43 @@ interface!(
 
 impl<'table> GraphicsOutput<'table> {
     /// Set the graphic mode to number `mode`
-    // FIXME: This needs to invalidate the `Framebuffer`
-    pub fn set_mode(&self, mode: u32) -> Result<()> {
+    ///
+    /// This takes `&mut self` so that no [`Framebuffer`] or [`Double`] from
+    /// this [`GraphicsOutput`] can outlive the mode they were created for.
+    ///
+    /// This only covers this instance. Another [`GraphicsOutput`] for the same
+    /// handle, such as from calling
+    /// [`BootServices::handle_protocol`][hp] again, or anything else using the
+    /// protocol, can still change the mode and invalidate them.
+    ///
+    /// [hp]: crate::table::BootServices::handle_protocol
+    pub fn set_mode(&mut self, mode: u32) -> Result<()> {
+        let set_mode = self.interface().set_mode.ok_or(Status::UNSUPPORTED)?;
         // Safety: Construction ensures these are valid
-        unsafe { (self.interface().set_mode.unwrap())(self.interface, mode) }.into()
+        unsafe { (set_mode)(self.interface, mode) }.into()
+    }
+
+    /// Pick a mode according to `policy` and set it, returning the new mode
+    ///
+    /// If setting the best mode fails, the next best is tried, and so on.
+    /// If none can be set, the last error is returned and the current mode
+    /// is kept.
+    /// If the current mode is the best one that works, it is kept.
+    ///
+    /// Returns [`Status::NOT_FOUND`] if no modes could be queried.
+    pub fn select_mode(&mut self, policy: ModePolicy) -> Result<GraphicsMode> {
+        match get_boot_table() {
+            Some(table) => self.select_mode_with(policy, Some(&table.boot())),
+            None => self.select_mode_with(policy, None),
+        }
     }
 
     pub fn query_mode(&self, mode: u32) -> Result<GraphicsMode> {
+        let query_mode = self.interface().query_mode.ok_or(Status::UNSUPPORTED)?;
         let mut size = 0;
         let mut info = core::ptr::null();
         // Safety: Construction ensures these are valid
-        let ret = unsafe {
-            //
-            (self.interface().query_mode.unwrap())(self.interface, mode, &mut size, &mut info)
-        };
+        let ret = unsafe { (query_mode)(self.interface, mode, &mut size, &mut info) };
         if ret.is_success() && !info.is_null() && size >= size_of::<RawGraphicsInfo>() {
             let mode = GraphicsMode::new(
                 mode,
@@ -66,7 +99,7 @@ impl<'table> GraphicsOutput<'table> {
     pub fn modes(&self) -> impl Iterator<Item = Result<GraphicsMode>> + '_ {
         let mut mode = 0;
         core::iter::from_fn(move || {
-            if mode >= self.max_mode() {
+            if mode >= self.mode_raw().max_mode {
                 return None;
             }
             let m = self.query_mode(mode);
@@ -89,19 +122,222 @@ impl<'table> GraphicsOutput<'table> {
         GraphicsMode::new(mode, info)
     }
 
+    /// Fill the `size` (width, height) block at `dest` with `color`
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if the block doesn't fit in
+    /// [`GraphicsMode::res`].
+    /// Empty blocks do nothing.
+    pub fn fill(&self, color: Pixel, dest: (usize, usize), size: (usize, usize)) -> Result<()> {
+        check_block(dest, size, self.res())?;
+        if size.0 == 0 || size.1 == 0 {
+            return Ok(());
+        }
+        let mut color = color;
+        // Safety:
+        // - VideoFill only reads the 0th pixel, which is valid
+        // - The block was checked against the screen
+        unsafe {
+            self.blt_raw(
+                (&mut color as *mut Pixel).cast(),
+                BltOperation::VideoFill,
+                (0, 0),
+                dest,
+                size,
+                0,
+            )
+        }
+    }
+
+    /// Read the `size` (width, height) block at `src` on screen into `buffer`
+    /// at `dest`
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if the block doesn't fit in
+    /// [`GraphicsMode::res`] or `buffer`.
+    /// Empty blocks do nothing.
+    pub fn video_to_buffer(
+        &self,
+        buffer: &mut BltBuffer,
+        src: (usize, usize),
+        dest: (usize, usize),
+        size: (usize, usize),
+    ) -> Result<()> {
+        check_block(src, size, self.res())?;
+        check_block(dest, size, buffer.size())?;
+        if size.0 == 0 || size.1 == 0 {
+            return Ok(());
+        }
+        let delta = buffer.delta();
+        // Safety:
+        // - `buffer` is valid for writes of its width, height, and delta
+        // - Both blocks were checked above
+        unsafe {
+            self.blt_raw(
+                buffer.pixels_mut().as_mut_ptr().cast(),
+                BltOperation::VideoToBuffer,
+                src,
+                dest,
+                size,
+                delta,
+            )
+        }
+    }
+
+    /// Write the `size` (width, height) block at `src` in `buffer` to the
+    /// screen at `dest`
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if the block doesn't fit in
+    /// `buffer` or [`GraphicsMode::res`].
+    /// Empty blocks do nothing.
+    pub fn buffer_to_video(
+        &self,
+        buffer: &BltBuffer,
+        src: (usize, usize),
+        dest: (usize, usize),
+        size: (usize, usize),
+    ) -> Result<()> {
+        check_block(src, size, buffer.size())?;
+        check_block(dest, size, self.res())?;
+        if size.0 == 0 || size.1 == 0 {
+            return Ok(());
+        }
+        // Safety:
+        // - `buffer` is valid for its width, height, and delta
+        // - BufferToVideo does not write to `buffer`
+        // - Both blocks were checked above
+        unsafe {
+            self.blt_raw(
+                buffer.pixels().as_ptr().cast_mut().cast(),
+                BltOperation::BufferToVideo,
+                src,
+                dest,
+                size,
+                buffer.delta(),
+            )
+        }
+    }
+
+    /// Copy the `size` (width, height) block at `src` on screen to `dest`
+    ///
+    /// The blocks may overlap.
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if either block doesn't fit in
+    /// [`GraphicsMode::res`].
+    /// Empty blocks do nothing.
+    pub fn video_to_video(
+        &self,
+        src: (usize, usize),
+        dest: (usize, usize),
+        size: (usize, usize),
+    ) -> Result<()> {
+        let res = self.res();
+        check_block(src, size, res)?;
+        check_block(dest, size, res)?;
+        if size.0 == 0 || size.1 == 0 {
+            return Ok(());
+        }
+        // Safety:
+        // - VideoToVideo does not use the buffer
+        // - Both blocks were checked above
+        unsafe {
+            self.blt_raw(
+                core::ptr::null_mut(),
+                BltOperation::VideoToVideo,
+                src,
+                dest,
+                size,
+                0,
+            )
+        }
+    }
+
+    /// Read the whole screen into a new [`BltBuffer`]
+    pub fn capture(&self) -> Result<BltBuffer> {
+        let size = self.res();
+        let mut image = BltBuffer::new(size.0, size.1);
+        self.video_to_buffer(&mut image, (0, 0), (0, 0), size)?;
+        Ok(image)
+    }
+
+    /// Draw `image` centered on the screen
+    ///
+    /// Images larger than the screen are cropped to their center.
+    pub fn draw_centered(&self, image: &BltBuffer) -> Result<()> {
+        let (sw, sh) = self.res();
+        let (iw, ih) = image.size();
+        let offset = |screen: usize, image: usize| {
+            if image > screen {
+                ((image - screen) / 2, 0)
+            } else {
+                (0, (screen - image) / 2)
+            }
+        };
+        let (src_x, dest_x) = offset(sw, iw);
+        let (src_y, dest_y) = offset(sh, ih);
+        let size = (iw.min(sw), ih.min(sh));
+        self.buffer_to_video(image, (src_x, src_y), (dest_x, dest_y), size)
+    }
+
+    /// Draw `image` scaled to fit the screen and centered, keeping its
+    /// aspect ratio
+    pub fn draw_scaled(&self, image: &BltBuffer) -> Result<()> {
+        let (sw, sh) = self.res();
+        let (iw, ih) = image.size();
+        if iw == 0 || ih == 0 {
+            return Ok(());
+        }
+        // Whichever of width or height fills the screen first
+        let (w, h) = if sw * ih <= sh * iw {
+            (sw, (ih * sw / iw).max(1))
+        } else {
+            ((iw * sh / ih).max(1), sh)
+        };
+        self.draw_centered(&image.scaled(w, h))
+    }
+
+    /// Get the current framebuffer
+    ///
+    /// Note that each pixel `(x, y)`
+    /// is at `y *`[`GraphicsMode::stride`]` + x` pixels
+    ///
+    /// Returns [`Status::UNSUPPORTED`] if the mode has no framebuffer,
+    /// such as [`PixelFormat::BltOnly`].
+    /// Use [`GraphicsOutput::buffer_to_video`] and friends instead.
+    pub fn framebuffer(&self) -> Result<Framebuffer<'_>> {
+        if matches!(
+            self.mode().format(),
+            PixelFormat::BltOnly | PixelFormat::Unknown
+        ) || self.mode_raw().fb_base == 0
+        {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        // Safety:
+        unsafe {
+            let mode = self.mode_raw();
+            let ptr = mode.fb_base as *mut u8;
+            let size = mode.fb_size;
+            let fb = Framebuffer::new(ptr, size, self.mode());
+            Ok(fb)
+        }
+    }
+
     /// Blt, or BLock Transfer
     ///
     /// (x, y)
     /// (width, height)
     ///
-    /// `buffer` must be at least `width * height`
-    /// or else `INVALID_PARAMETER` will be returned.
+    /// `delta` is the width in pixels of each `buffer` row,
+    /// or `0` if rows are `width` pixels.
     ///
-    /// If the width in `buffer` is not the same as the display then
-    /// `delta` must contain the data width (pixels) or else output will be
-    /// garbled.
+    /// Returns [`Status::INVALID_PARAMETER`] if the block doesn't fit in
+    /// `buffer` or [`GraphicsMode::res`], or for
+    /// [`BltOperation::VideoToBuffer`], which can't write to a shared
+    /// `buffer`.
     ///
     /// Buffer is BGR formatted 32-bit pixels
+    #[deprecated(
+        note = "use `GraphicsOutput::fill`, `video_to_buffer`, `buffer_to_video`, \
+                or `video_to_video`"
+    )]
     pub fn blt(
         &self,
         buffer: &[Pixel],
@@ -111,50 +347,136 @@ impl<'table> GraphicsOutput<'table> {
         res: (usize, usize),
         delta: usize,
     ) -> Result<()> {
-        if buffer.len() < (res.0 * res.1) {
-            return Err(Status::INVALID_PARAMETER.into());
+        let screen = self.res();
+        match op {
+            BltOperation::VideoFill => {
+                check_block(dest, res, screen)?;
+                if buffer.is_empty() {
+                    return Err(Status::INVALID_PARAMETER.into());
+                }
+            }
+            BltOperation::BufferToVideo => {
+                let delta = if delta == 0 { res.0 } else { delta };
+                check_block(dest, res, screen)?;
+                check_block(src, res, (delta, usize::MAX))?;
+                // Index one past the last pixel read from `buffer`
+                let end = src
+                    .1
+                    .checked_add(res.1.saturating_sub(1))
+                    .and_then(|y| y.checked_mul(delta))
+                    .and_then(|y| y.checked_add(src.0 + res.0))
+                    .ok_or(Status::INVALID_PARAMETER)?;
+                if res.1 != 0 && end > buffer.len() {
+                    return Err(Status::INVALID_PARAMETER.into());
+                }
+            }
+            BltOperation::VideoToVideo => {
+                check_block(src, res, screen)?;
+                check_block(dest, res, screen)?;
+            }
+            BltOperation::VideoToBuffer => return Err(Status::INVALID_PARAMETER.into()),
         }
-        // Safety: Construction ensures these are valid
+        if res.0 == 0 || res.1 == 0 {
+            return Ok(());
+        }
+        let delta = if delta == 0 { res.0 } else { delta };
+        // Safety:
+        // - Only VideoToBuffer writes to the buffer, and it was rejected
+        // - `buffer` and the screen were checked above
+        unsafe { self.blt_raw(buffer.as_ptr().cast_mut().cast(), op, src, dest, res, delta) }
+    }
+
+    /// Blt, or BLock Transfer
+    ///
+    /// `size` is (width, height), and `delta` is the width in pixels of
+    /// each `buffer` row.
+    ///
+    /// # Safety
+    ///
+    /// - `buffer` must be valid for `op`, `src`, `dest`, `size`, and `delta`
+    /// - `src` and `dest` must be within the screen for `op`
+    unsafe fn blt_raw(
+        &self,
+        buffer: *mut RawBltPixel,
+        op: BltOperation,
+        src: (usize, usize),
+        dest: (usize, usize),
+        size: (usize, usize),
+        delta: usize,
+    ) -> Result<()> {
+        let blt = self.interface().blt.ok_or(Status::UNSUPPORTED)?;
+        // Safety: Caller ensures the arguments are valid
         unsafe {
-            (self.interface().blt.unwrap())(
+            (blt)(
                 self.interface,
-                buffer.as_ptr() as *mut RawBltPixel,
+                buffer,
                 op.into(),
                 src.0,
                 src.1,
                 dest.0,
                 dest.1,
-                res.0,
-                res.1,
+                size.0,
+                size.1,
                 delta * size_of::<RawBltPixel>(),
             )
         }
         .into()
     }
 
-    /// Get a mutable byte slice to the current framebuffer
-    ///
-    /// Note that each pixel `(x, y)`
-    /// is at the `<size of a pixel> *`[`GraphicsMode::stride`]
-    pub fn framebuffer(&self) -> Result<Framebuffer<'_>> {
-        // FIXME: Volatile?
-        // Safety:
-        unsafe {
-            let mode = self.mode_raw();
-            let ptr = mode.fb_base as *mut u8;
-            let size = mode.fb_size;
-            let fb = Framebuffer::new(ptr, size, self.mode().stride());
-            Ok(fb)
+    /// Set the first of `modes` that works, stopping at the current mode
+    fn set_first(&mut self, modes: &[GraphicsMode]) -> Result<GraphicsMode> {
+        let current = self.mode();
+        let mut err = Status::NOT_FOUND;
+        for mode in modes {
+            if mode.mode() == current.mode() {
+                return Ok(current);
+            }
+            match self.set_mode(mode.mode()) {
+                Ok(()) => return Ok(self.mode()),
+                Err(e) => err = e.status(),
+            }
         }
+        Err(err.into())
     }
 
-    /// Max supported mode
-    ///
-    /// # Note
-    ///
-    /// Unlike the raw structure, the return value is the last valid mode.
-    fn max_mode(&self) -> u32 {
-        self.mode_raw().max_mode - 1
+    /// [`GraphicsOutput::select_mode`], finding the native resolution
+    /// through `boot`
+    fn select_mode_with(
+        &mut self,
+        policy: ModePolicy,
+        boot: Option<&BootServices>,
+    ) -> Result<GraphicsMode> {
+        let mut modes: Vec<GraphicsMode> = self.modes().filter_map(Result::ok).collect();
+        let native = match (policy, boot) {
+            (ModePolicy::Native, Some(boot)) => self.native_res(boot),
+            _ => None,
+        };
+        policy.sort(&mut modes, native);
+        self.set_first(&modes)
+    }
+
+    /// (width, height) of the preferred timing in our displays
+    /// [`ActiveEdid`], if it has one
+    fn native_res(&self, boot: &BootServices) -> Option<(u32, u32)> {
+        let handles = boot.handles_for_protocol::<GraphicsOutput>().ok()?;
+        handles.into_iter().find_map(|handle| {
+            // Safety: Neither protocol is used beyond this closure
+            unsafe {
+                let gop = boot.handle_protocol::<GraphicsOutput>(handle).ok()??;
+                if gop.as_ptr() != self.interface {
+                    return None;
+                }
+                let edid = boot.handle_protocol::<ActiveEdid>(handle).ok()??;
+                let timing = edid.parse().ok()?.preferred_timing()?;
+                Some((timing.width.into(), timing.height.into()))
+            }
+        })
+    }
+
+    /// Current (width, height)
+    fn res(&self) -> (usize, usize) {
+        let (w, h) = self.mode().res();
+        (w as usize, h as usize)
     }
 
     fn mode_raw(&self) -> &RawGraphicsMode {
@@ -168,8 +490,55 @@ impl<'table> GraphicsOutput<'table> {
     }
 }
 
+/// How [`GraphicsOutput::select_mode`] chooses a mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ModePolicy {
+    /// The highest resolution, by number of pixels
+    Highest,
+
+    /// The resolution closest to (width, height)
+    Closest(u32, u32),
+
+    /// The native resolution of the display, from the preferred timing of
+    /// its [`ActiveEdid`]
+    ///
+    /// This is the resolution closest to it, if there's no exact match, or
+    /// [`ModePolicy::Highest`] without EDID information.
+    Native,
+
+    /// The highest resolution with the aspect ratio width:height, such as
+    /// `Aspect(16, 9)`, or [`ModePolicy::Highest`] if there are none
+    Aspect(u32, u32),
+}
+
+impl ModePolicy {
+    /// Sort `modes` best first, with `native` the (width, height) for
+    /// [`ModePolicy::Native`]
+    fn sort(self, modes: &mut [GraphicsMode], native: Option<(u32, u32)>) {
+        let area = |m: &GraphicsMode| {
+            let (w, h) = m.res();
+            Reverse(w as u64 * h as u64)
+        };
+        let distance = |m: &GraphicsMode, (tw, th): (u32, u32)| {
+            let (w, h) = m.res();
+            w.abs_diff(tw) as u64 + h.abs_diff(th) as u64
+        };
+        match (self, native) {
+            (Self::Closest(w, h), _) | (Self::Native, Some((w, h))) => {
+                modes.sort_by_key(|m| (distance(m, (w, h)), area(m)))
+            }
+            (Self::Aspect(aw, ah), _) => modes.sort_by_key(|m| {
+                let (w, h) = m.res();
+                (w as u64 * ah as u64 != h as u64 * aw as u64, area(m))
+            }),
+            _ => modes.sort_by_key(area),
+        }
+    }
+}
+
 /// UEFI Graphics Mode Information
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct GraphicsMode {
     /// Mode number
     mode: u32,
@@ -203,6 +572,158 @@ impl GraphicsMode {
     pub fn format(&self) -> PixelFormat {
         self.info.format.into()
     }
+
+    /// Channel layout, only for [`PixelFormat::BitMask`]
+    pub fn mask(&self) -> Option<PixelMask> {
+        match self.format() {
+            PixelFormat::BitMask => Some(PixelMask::from_raw(self.info.info)),
+            _ => None,
+        }
+    }
+
+    /// Encode `pixel` as it would be stored in the framebuffer
+    ///
+    /// Returns [`None`] for formats without a framebuffer,
+    /// [`PixelFormat::BltOnly`] and [`PixelFormat::Unknown`].
+    pub fn encode(&self, pixel: Pixel) -> Option<u32> {
+        let (r, g, b) = (
+            pixel.red() as u32,
+            pixel.green() as u32,
+            pixel.blue() as u32,
+        );
+        match self.format() {
+            PixelFormat::RGB => Some(r | g << 8 | b << 16),
+            PixelFormat::BGR => Some(b | g << 8 | r << 16),
+            PixelFormat::BitMask => self.mask().map(|m| m.encode(pixel)),
+            _ => None,
+        }
+    }
+
+    /// Decode `raw`, as stored in the framebuffer, into a [`Pixel`]
+    ///
+    /// Returns [`None`] for formats without a framebuffer,
+    /// [`PixelFormat::BltOnly`] and [`PixelFormat::Unknown`].
+    pub fn decode(&self, raw: u32) -> Option<Pixel> {
+        let [x, g, y, _] = raw.to_le_bytes();
+        match self.format() {
+            PixelFormat::RGB => Some(Pixel::new(x, g, y)),
+            PixelFormat::BGR => Some(Pixel::new(y, g, x)),
+            PixelFormat::BitMask => self.mask().map(|m| m.decode(raw)),
+            _ => None,
+        }
+    }
+}
+
+/// One color channel of a [`PixelMask`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Channel {
+    shift: u32,
+    width: u32,
+}
+
+impl Channel {
+    /// Channel for the bits set in `mask`
+    ///
+    /// Only the lowest contiguous run of bits is used.
+    pub const fn from_mask(mask: u32) -> Self {
+        if mask == 0 {
+            return Self { shift: 0, width: 0 };
+        }
+        let shift = mask.trailing_zeros();
+        let width = (mask >> shift).trailing_ones();
+        Self { shift, width }
+    }
+
+    /// Position of the lowest bit
+    pub const fn shift(&self) -> u32 {
+        self.shift
+    }
+
+    /// Number of bits
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Bits of this channel
+    pub const fn mask(&self) -> u32 {
+        self.max() << self.shift
+    }
+
+    /// Scale the 8-bit `value` to this channel, in position
+    pub const fn encode(&self, value: u8) -> u32 {
+        let max = self.max() as u64;
+        (((value as u64 * max + 127) / 255) as u32) << self.shift
+    }
+
+    /// Scale this channel in `raw` to 8 bits
+    pub const fn decode(&self, raw: u32) -> u8 {
+        let max = self.max() as u64;
+        if max == 0 {
+            return 0;
+        }
+        let value = ((raw >> self.shift) as u64) & max;
+        ((value * 255 + max / 2) / max) as u8
+    }
+
+    const fn max(&self) -> u32 {
+        match self.width {
+            0 => 0,
+            w => u32::MAX >> (32 - w),
+        }
+    }
+}
+
+/// Decoded [`raw::RawPixelMask`], for [`PixelFormat::BitMask`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PixelMask {
+    red: Channel,
+    green: Channel,
+    blue: Channel,
+    reserved: Channel,
+}
+
+impl PixelMask {
+    pub const fn from_raw(mask: RawPixelMask) -> Self {
+        Self {
+            red: Channel::from_mask(mask.red),
+            green: Channel::from_mask(mask.green),
+            blue: Channel::from_mask(mask.blue),
+            reserved: Channel::from_mask(mask.reserved),
+        }
+    }
+
+    pub const fn red(&self) -> Channel {
+        self.red
+    }
+
+    pub const fn green(&self) -> Channel {
+        self.green
+    }
+
+    pub const fn blue(&self) -> Channel {
+        self.blue
+    }
+
+    /// Bits not used for color
+    pub const fn reserved(&self) -> Channel {
+        self.reserved
+    }
+
+    /// Encode `pixel` with this layout, leaving reserved bits zero
+    pub fn encode(&self, pixel: Pixel) -> u32 {
+        self.red.encode(pixel.red())
+            | self.green.encode(pixel.green())
+            | self.blue.encode(pixel.blue())
+    }
+
+    /// Decode `raw` with this layout
+    pub fn decode(&self, raw: u32) -> Pixel {
+        Pixel::new(
+            self.red.decode(raw),
+            self.green.decode(raw),
+            self.blue.decode(raw),
+        )
+    }
 }
 
 /// UEFI Framebuffer pixel format
@@ -215,11 +736,14 @@ pub enum PixelFormat {
     /// BGR Pixels
     BGR,
 
-    /// Pixels defined by [`raw::RawPixelMask`]
+    /// Pixels defined by [`GraphicsMode::mask`]
     BitMask,
 
     /// Only blt supported, no framebuffer
     BltOnly,
+
+    /// Unknown format, treated like [`PixelFormat::BltOnly`]
+    Unknown,
 }
 
 impl From<RawPixelFormat> for PixelFormat {
@@ -229,7 +753,7 @@ impl From<RawPixelFormat> for PixelFormat {
             RawPixelFormat::BGR => PixelFormat::BGR,
             RawPixelFormat::BIT_MASK => PixelFormat::BitMask,
             RawPixelFormat::BLT_ONLY => PixelFormat::BltOnly,
-            _ => unimplemented!(),
+            _ => PixelFormat::Unknown,
         }
     }
 }
@@ -252,14 +776,17 @@ pub enum BltOperation {
     VideoToVideo,
 }
 
-impl From<RawBltOperation> for BltOperation {
-    fn from(value: RawBltOperation) -> Self {
+/// Unknown operations are [`Status::INVALID_PARAMETER`]
+impl TryFrom<RawBltOperation> for BltOperation {
+    type Error = Status;
+
+    fn try_from(value: RawBltOperation) -> core::result::Result<Self, Self::Error> {
         match value {
-            RawBltOperation::VIDEO_FILL => BltOperation::VideoFill,
-            RawBltOperation::VIDEO_TO_BUFFER => BltOperation::VideoToBuffer,
-            RawBltOperation::BUFFER_TO_VIDEO => BltOperation::BufferToVideo,
-            RawBltOperation::VIDEO_TO_VIDEO => BltOperation::VideoToVideo,
-            _ => unimplemented!(),
+            RawBltOperation::VIDEO_FILL => Ok(BltOperation::VideoFill),
+            RawBltOperation::VIDEO_TO_BUFFER => Ok(BltOperation::VideoToBuffer),
+            RawBltOperation::BUFFER_TO_VIDEO => Ok(BltOperation::BufferToVideo),
+            RawBltOperation::VIDEO_TO_VIDEO => Ok(BltOperation::VideoToVideo),
+            _ => Err(Status::INVALID_PARAMETER),
         }
     }
 }
@@ -270,6 +797,155 @@ impl From<BltOperation> for RawBltOperation {
     }
 }
 
+/// Whether the `size` (width, height) block at `pos` fits in `bounds`
+fn check_block(pos: (usize, usize), size: (usize, usize), bounds: (usize, usize)) -> Result<()> {
+    let fits = |p: usize, s: usize, b: usize| p.checked_add(s).is_some_and(|end| end <= b);
+    if fits(pos.0, size.0, bounds.0) && fits(pos.1, size.1, bounds.1) {
+        Ok(())
+    } else {
+        Err(Status::INVALID_PARAMETER.into())
+    }
+}
+
+/// A buffer of [`Pixel`]s for [`GraphicsOutput`] block transfers
+///
+/// Rows are `delta` pixels apart, which is at least `width`.
+#[derive(Debug, Clone)]
+pub struct BltBuffer {
+    pixels: Vec<Pixel>,
+    width: usize,
+    height: usize,
+    delta: usize,
+}
+
+impl BltBuffer {
+    /// A black `width` by `height` buffer
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::with_delta(width, height, width)
+    }
+
+    /// A black `width` by `height` buffer, with rows `delta` pixels apart
+    ///
+    /// # Panics
+    ///
+    /// - If `delta` is less than `width`
+    pub fn with_delta(width: usize, height: usize, delta: usize) -> Self {
+        assert!(delta >= width, "BltBuffer delta was less than its width");
+        Self {
+            pixels: vec![Pixel::default(); delta * height],
+            width,
+            height,
+            delta,
+        }
+    }
+
+    /// A `width` by `height` buffer of `pixels`, in rows
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if `pixels` is not
+    /// `width * height` long.
+    pub fn from_pixels(pixels: Vec<Pixel>, width: usize, height: usize) -> Result<Self> {
+        if width.checked_mul(height) != Some(pixels.len()) {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        Ok(Self {
+            pixels,
+            width,
+            height,
+            delta: width,
+        })
+    }
+
+    /// Width in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// (Width, Height)
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Distance between rows, in pixels
+    pub fn delta(&self) -> usize {
+        self.delta
+    }
+
+    /// Pixel at (`x`, `y`), or [`None`] if out of bounds
+    pub fn get(&self, x: usize, y: usize) -> Option<&Pixel> {
+        if x < self.width && y < self.height {
+            self.pixels.get(y * self.delta + x)
+        } else {
+            None
+        }
+    }
+
+    /// Mutable pixel at (`x`, `y`), or [`None`] if out of bounds
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut Pixel> {
+        if x < self.width && y < self.height {
+            self.pixels.get_mut(y * self.delta + x)
+        } else {
+            None
+        }
+    }
+
+    /// Every pixel, including padding between rows
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.pixels
+    }
+
+    /// Every pixel, including padding between rows
+    pub fn pixels_mut(&mut self) -> &mut [Pixel] {
+        &mut self.pixels
+    }
+
+    /// Row `y`, without padding
+    pub fn row(&self, y: usize) -> &[Pixel] {
+        let start = y * self.delta;
+        &self.pixels[start..][..self.width]
+    }
+
+    /// Mutable row `y`, without padding
+    pub fn row_mut(&mut self, y: usize) -> &mut [Pixel] {
+        let start = y * self.delta;
+        &mut self.pixels[start..][..self.width]
+    }
+
+    /// A copy of this resized to `width` by `height`, using the nearest
+    /// pixel
+    pub fn scaled(&self, width: usize, height: usize) -> BltBuffer {
+        let mut out = BltBuffer::new(width, height);
+        if self.width == 0 || self.height == 0 {
+            return out;
+        }
+        for y in 0..height {
+            let src = self.row(y * self.height / height);
+            for (x, px) in out.row_mut(y).iter_mut().enumerate() {
+                *px = src[x * self.width / width];
+            }
+        }
+        out
+    }
+}
+
+impl Index<(usize, usize)> for BltBuffer {
+    type Output = Pixel;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        self.get(x, y).expect("BltBuffer index out of bounds")
+    }
+}
+
+impl IndexMut<(usize, usize)> for BltBuffer {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+        self.get_mut(x, y).expect("BltBuffer index out of bounds")
+    }
+}
+
 /// UEFI Framebuffer
 #[derive(Debug)]
 pub struct Framebuffer<'gop> {
@@ -279,9 +955,12 @@ pub struct Framebuffer<'gop> {
     /// Size of the framebuffer in bytes
     size: usize,
 
-    /// Stride of the framebuffer in bytes
+    /// Stride of the framebuffer in pixels
     stride: u32,
 
+    /// Mode the framebuffer is for
+    mode: GraphicsMode,
+
     /// Holds the lifetime of our parent [`GraphicsOutput`]
     phantom: PhantomData<&'gop u8>,
 }
@@ -290,15 +969,21 @@ impl<'gop> Framebuffer<'gop> {
     /// Create new Framebuffer wrapper
     ///
     /// - `ptr` MUST be valid for `size` bytes
-    unsafe fn new(ptr: *mut u8, size: usize, stride: u32) -> Self {
+    unsafe fn new(ptr: *mut u8, size: usize, mode: GraphicsMode) -> Self {
         Self {
             ptr,
             size,
-            stride,
+            stride: mode.stride(),
+            mode,
             phantom: PhantomData,
         }
     }
 
+    /// Every pixel, including those past the edge of the screen in each
+    /// [`Framebuffer::stride`]
+    ///
+    /// These are not volatile accesses, see [`Framebuffer::read`] and
+    /// [`Framebuffer::write`].
     pub fn pixels(&self) -> &'gop [Pixel] {
         let ptr = self.ptr as *mut Pixel;
         let len = self.size / size_of::<Pixel>();
@@ -306,6 +991,13 @@ impl<'gop> Framebuffer<'gop> {
         unsafe { from_raw_parts(ptr, len) }
     }
 
+    /// Every pixel, mutably
+    ///
+    /// Writes through this slice are not volatile, and so may be reordered,
+    /// merged, or removed by the compiler, which is wrong for video memory.
+    ///
+    /// See [`Framebuffer::pixels`]
+    #[deprecated(note = "writes to video memory must be volatile, use `Framebuffer::write`")]
     pub fn pixels_mut(&mut self) -> &'gop mut [Pixel] {
         let ptr = self.ptr as *mut Pixel;
         let len = self.size / size_of::<Pixel>();
@@ -317,18 +1009,65 @@ impl<'gop> Framebuffer<'gop> {
     pub fn size(&self) -> usize {
         self.size
     }
+
+    /// Pixels per scan line, see [`GraphicsMode::stride`]
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// Mode this framebuffer is for
+    pub fn mode(&self) -> &GraphicsMode {
+        &self.mode
+    }
+
+    /// Volatile read of the raw pixel at (`x`, `y`)
+    ///
+    /// Use [`GraphicsMode::decode`] to turn this into a [`Pixel`].
+    ///
+    /// # Panics
+    ///
+    /// - If (`x`, `y`) is outside the framebuffer
+    pub fn read(&self, x: u32, y: u32) -> u32 {
+        let ptr = self.offset(x, y);
+        // Safety: `offset` ensures this is in bounds
+        unsafe { ptr.read_volatile() }
+    }
+
+    /// Volatile write of the raw pixel `raw` at (`x`, `y`)
+    ///
+    /// Use [`GraphicsMode::encode`] to get `raw` from a [`Pixel`].
+    ///
+    /// # Panics
+    ///
+    /// - If (`x`, `y`) is outside the framebuffer
+    pub fn write(&mut self, x: u32, y: u32, raw: u32) {
+        let ptr = self.offset(x, y);
+        // Safety: `offset` ensures this is in bounds
+        unsafe { ptr.write_volatile(raw) }
+    }
+
+    /// Pointer to the pixel at (`x`, `y`), asserting its in bounds
+    fn offset(&self, x: u32, y: u32) -> *mut u32 {
+        let index = (y as usize)
+            .checked_mul(self.stride as usize)
+            .and_then(|i| i.checked_add(x as usize))
+            .filter(|i| *i < self.size / size_of::<u32>())
+            .expect("Framebuffer index out of bounds");
+        // Safety:
+        // - We checked `index` is within range
+        // - Pixels are 32-bits in every framebuffer format
+        unsafe { self.ptr.cast::<u32>().add(index) }
+    }
 }
 
 impl<'gop> Index<(u32, u32)> for Framebuffer<'gop> {
     type Output = Pixel;
 
     fn index(&self, (x, y): (u32, u32)) -> &Self::Output {
-        let index = ((y * self.stride) + x) as usize;
-        assert!(index <= self.size, "Framebuffer index out of bounds");
         // Safety:
-        // - We assert `index` is within range
+        // - `offset` asserts the index is within range
         // - The type here is a `Pixel`
-        unsafe { &*self.ptr.add(index).cast::<Pixel>() }
+        unsafe { &*self.offset(x, y).cast::<Pixel>() }
     }
 }
 
@@ -337,7 +1076,7 @@ impl<'gop> Index<(u32, u32)> for Framebuffer<'gop> {
 /// 32-bits in size, 24-bits usable
 ///
 /// ABI Compatible with `[u8; 4]`
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 #[repr(transparent)]
 pub struct Pixel {
     data: [u8; 4],
@@ -349,7 +1088,7 @@ impl Pixel {
     /// # Note
     ///
     /// Takes arguments in RGB order for convenience
-    pub fn new(r: u8, g: u8, b: u8) -> Self {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
         Self { data: [b, g, r, 0] }
     }
 
@@ -392,20 +1131,953 @@ impl Coord {
     }
 }
 
-/// A double buffer for the framebuffer
+/// A rectangle of pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Whether this contains no pixels
+    pub const fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// One past the right edge
+    pub const fn right(&self) -> usize {
+        self.x.saturating_add(self.width)
+    }
+
+    /// One past the bottom edge
+    pub const fn bottom(&self) -> usize {
+        self.y.saturating_add(self.height)
+    }
+
+    /// Whether (`x`, `y`) is inside this
+    pub const fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// The smallest rectangle containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(
+            x,
+            y,
+            self.right().max(other.right()) - x,
+            self.bottom().max(other.bottom()) - y,
+        )
+    }
+
+    /// The overlap of both, which may be empty
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        Rect::new(x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+    }
+
+    /// Whether this overlaps or shares an edge with `other`
+    fn touches(&self, other: &Rect) -> bool {
+        self.x <= other.right()
+            && other.x <= self.right()
+            && self.y <= other.bottom()
+            && other.y <= self.bottom()
+    }
+}
+
+/// Where [`Double::flush`] sends pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flush {
+    /// [`GraphicsOutput::buffer_to_video`], which works in every mode
+    Blt,
+
+    /// Volatile [`Framebuffer`] writes, encoded for the mode
+    Framebuffer,
+}
+
+/// Most dirty rectangles tracked before they are merged into one
+const MAX_DIRTY: usize = 16;
+
+/// A double buffer for the screen
+///
+/// Drawing happens on a [`BltBuffer`] in memory, and only the dirty parts are
+/// copied to the screen by [`Double::flush`], avoiding tearing.
+///
+/// Drawing through [`Double::buffer_mut`] must be followed by
+/// [`Double::mark_dirty`] for the changes to be flushed.
 #[derive(Debug)]
-struct Double<'table> {
-    fb: Framebuffer<'table>,
-    buf: Vec<u8>,
+pub struct Double<'gop> {
+    gop: &'gop GraphicsOutput<'gop>,
+    mode: GraphicsMode,
+    fb: Option<Framebuffer<'gop>>,
+    buf: BltBuffer,
+    dirty: Vec<Rect>,
 }
 
-impl<'table> Double<'table> {
-    pub fn new(fb: Framebuffer<'table>) -> Self {
+impl<'gop> Double<'gop> {
+    /// A black back buffer for the current mode, flushed with
+    /// [`Flush::Blt`]
+    pub fn new(gop: &'gop GraphicsOutput<'gop>) -> Self {
+        let mode = gop.mode();
+        let (w, h) = mode.res();
         Self {
-            buf: vec![0; fb.size()],
-            fb,
+            gop,
+            mode,
+            fb: None,
+            buf: BltBuffer::new(w as usize, h as usize),
+            dirty: Vec::new(),
+        }
+    }
+
+    /// A black back buffer for the current mode, flushed with `flush`
+    ///
+    /// Returns [`Status::UNSUPPORTED`] for [`Flush::Framebuffer`] if the
+    /// mode has no framebuffer, see [`GraphicsOutput::framebuffer`].
+    pub fn with_flush(gop: &'gop GraphicsOutput<'gop>, flush: Flush) -> Result<Self> {
+        let mut this = Self::new(gop);
+        if flush == Flush::Framebuffer {
+            this.fb = Some(gop.framebuffer()?);
+        }
+        Ok(this)
+    }
+
+    /// How this is flushed
+    pub fn flush_mode(&self) -> Flush {
+        match self.fb {
+            Some(_) => Flush::Framebuffer,
+            None => Flush::Blt,
         }
     }
 
-    // pub fn swap(&self)
+    /// (Width, Height)
+    pub fn size(&self) -> (usize, usize) {
+        self.buf.size()
+    }
+
+    /// The back buffer
+    pub fn buffer(&self) -> &BltBuffer {
+        &self.buf
+    }
+
+    /// The back buffer, mutably
+    ///
+    /// Changes must be marked with [`Double::mark_dirty`]
+    pub fn buffer_mut(&mut self) -> &mut BltBuffer {
+        &mut self.buf
+    }
+
+    /// Set the pixel at (`x`, `y`), if it is on screen
+    pub fn set(&mut self, x: usize, y: usize, color: Pixel) {
+        if let Some(p) = self.buf.get_mut(x, y) {
+            *p = color;
+            self.mark_dirty(Rect::new(x, y, 1, 1));
+        }
+    }
+
+    /// Fill the on screen part of `rect` with `color`
+    pub fn fill(&mut self, rect: Rect, color: Pixel) {
+        let rect = self.clip(rect);
+        for y in rect.y..rect.bottom() {
+            self.buf.row_mut(y)[rect.x..rect.right()].fill(color);
+        }
+        self.mark_dirty(rect);
+    }
+
+    /// Copy every pixel from `src` to (`x`, `y`), clipped to the screen
+    pub fn draw(&mut self, x: usize, y: usize, src: &BltBuffer) {
+        let rect = self.clip(Rect::new(x, y, src.width(), src.height()));
+        for row in 0..rect.height {
+            let from = &src.row(row)[..rect.width];
+            self.buf.row_mut(rect.y + row)[rect.x..rect.right()].copy_from_slice(from);
+        }
+        self.mark_dirty(rect);
+    }
+
+    /// Mark `rect` as needing a flush
+    ///
+    /// Touching rectangles are merged, and if too many accumulate
+    /// they are all merged into one.
+    pub fn mark_dirty(&mut self, rect: Rect) {
+        let mut rect = self.clip(rect);
+        if rect.is_empty() {
+            return;
+        }
+        while let Some(i) = self.dirty.iter().position(|d| d.touches(&rect)) {
+            rect = rect.union(&self.dirty.swap_remove(i));
+        }
+        self.dirty.push(rect);
+        if self.dirty.len() > MAX_DIRTY {
+            let all = self
+                .dirty
+                .drain(..)
+                .fold(Rect::default(), |a, b| a.union(&b));
+            self.dirty.push(all);
+        }
+    }
+
+    /// Mark the whole screen as needing a flush
+    pub fn mark_all(&mut self) {
+        let (width, height) = self.size();
+        self.dirty.clear();
+        self.mark_dirty(Rect::new(0, 0, width, height));
+    }
+
+    /// Rectangles that will be written by the next [`Double::flush`]
+    pub fn dirty(&self) -> &[Rect] {
+        &self.dirty
+    }
+
+    /// Copy every dirty rectangle to the screen
+    ///
+    /// Returns [`Status::ABORTED`] if the mode changed since this was created.
+    /// On errors, the dirty rectangles are kept.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.gop.mode().mode() != self.mode.mode() {
+            return Err(Status::ABORTED.into());
+        }
+        while let Some(rect) = self.dirty.last().copied() {
+            match &mut self.fb {
+                Some(fb) => {
+                    for y in rect.y..rect.bottom() {
+                        let row = &self.buf.row(y)[rect.x..rect.right()];
+                        for (x, px) in (rect.x..).zip(row) {
+                            let raw = self.mode.encode(*px).unwrap_or(0);
+                            fb.write(x as u32, y as u32, raw);
+                        }
+                    }
+                }
+                None => {
+                    let pos = (rect.x, rect.y);
+                    let size = (rect.width, rect.height);
+                    self.gop.buffer_to_video(&self.buf, pos, pos, size)?;
+                }
+            }
+            self.dirty.pop();
+        }
+        Ok(())
+    }
+
+    /// `rect`, clipped to the screen
+    fn clip(&self, rect: Rect) -> Rect {
+        let (width, height) = self.size();
+        rect.intersect(&Rect::new(0, 0, width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use core::{
+        cell::RefCell,
+        mem::zeroed,
+        ptr::{addr_of_mut, null_mut},
+        sync::atomic::{AtomicPtr, Ordering},
+    };
+
+    use super::*;
+    use crate::{
+        proto::{edid::raw::RawEdidActive, Guid, Protocol as _},
+        table::raw::{LocateSearch, RawBootServices},
+        EfiHandle,
+    };
+
+    /// Arguments to one [`RawGraphicsOutput::blt`] call
+    ///
+    /// For [`RawBltOperation::VIDEO_FILL`] the fill color is read instead of
+    /// keeping the `buffer`, which only lives for the call.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct BltCall {
+        buffer: *mut RawBltPixel,
+        color: Option<Pixel>,
+        op: RawBltOperation,
+        src: (usize, usize),
+        dest: (usize, usize),
+        size: (usize, usize),
+        delta: usize,
+    }
+
+    /// [`RawGraphicsOutput`] that records its blt calls
+    #[repr(C)]
+    struct MockGop {
+        raw: RawGraphicsOutput,
+        blts: RefCell<Vec<BltCall>>,
+    }
+
+    impl MockGop {
+        fn new(mode: *mut RawGraphicsMode) -> Self {
+            Self {
+                raw: RawGraphicsOutput {
+                    query_mode: None,
+                    set_mode: None,
+                    blt: Some(blt),
+                    mode,
+                },
+                blts: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    unsafe extern "efiapi" fn blt(
+        this: *mut RawGraphicsOutput,
+        buffer: *mut RawBltPixel,
+        op: RawBltOperation,
+        src_x: usize,
+        src_y: usize,
+        dest_x: usize,
+        dest_y: usize,
+        width: usize,
+        height: usize,
+        delta: usize,
+    ) -> Status {
+        // Safety: Only ever called through a `MockGop`
+        let mock = unsafe { &*this.cast::<MockGop>() };
+        let (buffer, color) = if op == RawBltOperation::VIDEO_FILL {
+            // Safety: VideoFill buffers hold at least the one color
+            (null_mut(), Some(unsafe { *buffer.cast::<Pixel>() }))
+        } else {
+            (buffer, None)
+        };
+        mock.blts.borrow_mut().push(BltCall {
+            buffer,
+            color,
+            op,
+            src: (src_x, src_y),
+            dest: (dest_x, dest_y),
+            size: (width, height),
+            delta,
+        });
+        Status::SUCCESS
+    }
+
+    #[test]
+    fn blt_buffer() -> Result<()> {
+        let mut buf = BltBuffer::with_delta(3, 2, 4);
+        assert_eq!(buf.pixels().len(), 8);
+        buf[(2, 1)] = Pixel::new(1, 2, 3);
+        assert_eq!(buf.pixels()[6], Pixel::new(1, 2, 3));
+        assert_eq!(
+            buf.row(1),
+            &[Pixel::default(), Pixel::default(), Pixel::new(1, 2, 3)]
+        );
+        assert!(buf.get(3, 0).is_none());
+        assert!(buf.get(0, 2).is_none());
+
+        assert!(BltBuffer::from_pixels(vec![Pixel::default(); 5], 3, 2).is_err());
+        let buf = BltBuffer::from_pixels(vec![Pixel::default(); 6], 3, 2)?;
+        assert_eq!(buf.delta(), 3);
+
+        let (a, b) = (Pixel::new(1, 0, 0), Pixel::new(2, 0, 0));
+        let buf = BltBuffer::from_pixels(vec![a, b], 2, 1)?;
+        let big = buf.scaled(4, 2);
+        assert_eq!(big.row(0), &[a, a, b, b]);
+        assert_eq!(big.row(1), &[a, a, b, b]);
+        assert_eq!(big.scaled(2, 1).pixels(), buf.pixels());
+        Ok(())
+    }
+
+    fn info(format: RawPixelFormat, mask: RawPixelMask) -> RawGraphicsInfo {
+        RawGraphicsInfo {
+            version: 0,
+            horizontal: 640,
+            vertical: 480,
+            format,
+            info: mask,
+            stride: 640,
+        }
+    }
+
+    const NO_MASK: RawPixelMask = RawPixelMask {
+        red: 0,
+        green: 0,
+        blue: 0,
+        reserved: 0,
+    };
+
+    #[test]
+    fn pixel_formats() {
+        let px = Pixel::new(0x12, 0x34, 0x56);
+
+        let rgb = GraphicsMode::new(0, info(RawPixelFormat::RGB, NO_MASK));
+        assert_eq!(rgb.encode(px), Some(0x563412));
+        assert_eq!(rgb.decode(0x563412), Some(px));
+
+        let bgr = GraphicsMode::new(0, info(RawPixelFormat::BGR, NO_MASK));
+        assert_eq!(bgr.encode(px), Some(0x123456));
+        assert_eq!(bgr.decode(0x123456), Some(px));
+        assert!(bgr.mask().is_none());
+
+        let blt = GraphicsMode::new(0, info(RawPixelFormat::BLT_ONLY, NO_MASK));
+        assert_eq!(blt.encode(px), None);
+        let unknown = GraphicsMode::new(0, info(RawPixelFormat::FORMAT_MAX, NO_MASK));
+        assert_eq!(unknown.format(), PixelFormat::Unknown);
+        assert_eq!(unknown.decode(0), None);
+
+        // RGB565
+        let mask = RawPixelMask {
+            red: 0xF800,
+            green: 0x07E0,
+            blue: 0x001F,
+            reserved: 0xFFFF_0000,
+        };
+        let m565 = GraphicsMode::new(0, info(RawPixelFormat::BIT_MASK, mask));
+        let mask = m565.mask().unwrap();
+        assert_eq!((mask.red().shift(), mask.red().width()), (11, 5));
+        assert_eq!((mask.green().shift(), mask.green().width()), (5, 6));
+        assert_eq!((mask.blue().shift(), mask.blue().width()), (0, 5));
+        assert_eq!(mask.reserved().mask(), 0xFFFF_0000);
+        assert_eq!(m565.encode(Pixel::new(255, 255, 255)), Some(0xFFFF));
+        assert_eq!(m565.encode(Pixel::new(255, 0, 0)), Some(0xF800));
+        assert_eq!(m565.decode(0x07E0), Some(Pixel::new(0, 255, 0)));
+
+        // 10 bits per channel
+        let mask = RawPixelMask {
+            red: 0x3FF0_0000,
+            green: 0x000F_FC00,
+            blue: 0x0000_03FF,
+            reserved: 0xC000_0000,
+        };
+        let m10 = GraphicsMode::new(0, info(RawPixelFormat::BIT_MASK, mask));
+        assert_eq!(m10.encode(Pixel::new(255, 0, 255)), Some(0x3FF0_03FF));
+        assert_eq!(m10.decode(0x3FF0_03FF), Some(Pixel::new(255, 0, 255)));
+        assert_eq!(m10.decode(m10.encode(px).unwrap()), Some(px));
+    }
+
+    #[test]
+    fn double() -> Result<()> {
+        let mut info = info(RawPixelFormat::RGB, NO_MASK);
+        info.horizontal = 6;
+        info.vertical = 4;
+        info.stride = 8;
+        let mut fb = vec![0u32; 8 * 4];
+        let mut mode = RawGraphicsMode {
+            max_mode: 1,
+            mode: 0,
+            info: addr_of_mut!(info),
+            info_size: size_of::<RawGraphicsInfo>(),
+            fb_base: fb.as_mut_ptr() as u64,
+            fb_size: fb.len() * size_of::<u32>(),
+        };
+        let mut raw = RawGraphicsOutput {
+            query_mode: None,
+            set_mode: None,
+            blt: None,
+            mode: addr_of_mut!(mode),
+        };
+        // Safety: `raw` outlives `gop`
+        let gop = unsafe { GraphicsOutput::new(addr_of_mut!(raw)) };
+        let red = Pixel::new(255, 0, 0);
+        let blue = Pixel::new(0, 0, 255);
+
+        let mut double = Double::new(&gop);
+        double.set(0, 0, red);
+        assert!(double
+            .flush()
+            .is_err_and(|e| e.status() == Status::UNSUPPORTED));
+        assert_eq!(double.dirty(), &[Rect::new(0, 0, 1, 1)]);
+
+        let mut double = Double::with_flush(&gop, Flush::Framebuffer)?;
+        double.set(0, 0, red);
+        double.set(1, 0, red);
+        assert_eq!(double.dirty(), &[Rect::new(0, 0, 2, 1)]);
+        double.fill(Rect::new(4, 2, 10, 10), blue);
+        assert_eq!(double.dirty().len(), 2);
+        double.set(6, 0, red);
+        assert_eq!(double.dirty().len(), 2);
+
+        double.flush()?;
+        assert!(double.dirty().is_empty());
+        assert_eq!(&fb[..3], &[0xFF, 0xFF, 0]);
+        assert_eq!(&fb[2 * 8..3 * 8], &[0, 0, 0, 0, 0xFF0000, 0xFF0000, 0, 0]);
+        assert_eq!(&fb[3 * 8 + 4..3 * 8 + 8], &[0xFF0000, 0xFF0000, 0, 0]);
+
+        for i in 0..MAX_DIRTY + 1 {
+            double.set((i % 3) * 2, (i / 3) % 4, red);
+        }
+        assert!(double.dirty().len() <= MAX_DIRTY);
+        double.mark_all();
+        assert_eq!(double.dirty(), &[Rect::new(0, 0, 6, 4)]);
+        Ok(())
+    }
+
+    /// Mock `set_mode` that fails for mode 0
+    unsafe extern "efiapi" fn set_mode(this: *mut RawGraphicsOutput, mode: u32) -> Status {
+        if mode == 0 {
+            return Status::DEVICE_ERROR;
+        }
+        (*(*this).mode).mode = mode;
+        Status::SUCCESS
+    }
+
+    #[test]
+    fn select_mode() -> Result<()> {
+        let res = [
+            (1920, 1200),
+            (1024, 768),
+            (1920, 1080),
+            (800, 600),
+            (1280, 720),
+        ];
+        let mut modes: Vec<GraphicsMode> = res
+            .iter()
+            .enumerate()
+            .map(|(i, (w, h))| {
+                let mut info = info(RawPixelFormat::BGR, NO_MASK);
+                (info.horizontal, info.vertical) = (*w, *h);
+                GraphicsMode::new(i as u32, info)
+            })
+            .collect();
+        let order = |policy: ModePolicy, native, modes: &mut [GraphicsMode]| {
+            policy.sort(modes, native);
+            modes.iter().map(|m| m.mode()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            order(ModePolicy::Highest, None, &mut modes),
+            [0, 2, 4, 1, 3]
+        );
+        assert_eq!(
+            order(ModePolicy::Closest(1000, 700), None, &mut modes),
+            [1, 4, 3, 2, 0]
+        );
+        let native = Some((1280, 720));
+        assert_eq!(
+            order(ModePolicy::Native, native, &mut modes),
+            [4, 1, 3, 2, 0]
+        );
+        assert_eq!(order(ModePolicy::Native, None, &mut modes), [0, 2, 4, 1, 3]);
+        assert_eq!(
+            order(ModePolicy::Aspect(16, 9), None, &mut modes),
+            [2, 4, 0, 1, 3]
+        );
+        assert_eq!(
+            order(ModePolicy::Aspect(4, 3), None, &mut modes),
+            [1, 3, 0, 2, 4]
+        );
+
+        let mut info = info(RawPixelFormat::BGR, NO_MASK);
+        let mut mode = RawGraphicsMode {
+            max_mode: res.len() as u32,
+            mode: 3,
+            info: addr_of_mut!(info),
+            info_size: size_of::<RawGraphicsInfo>(),
+            fb_base: 0,
+            fb_size: 0,
+        };
+        let mut raw = RawGraphicsOutput {
+            query_mode: None,
+            set_mode: Some(set_mode),
+            blt: None,
+            mode: addr_of_mut!(mode),
+        };
+        // Safety: `raw` outlives `gop`
+        let mut gop = unsafe { GraphicsOutput::new(addr_of_mut!(raw)) };
+
+        // Mode 0 fails, so falls back to the next best
+        order(ModePolicy::Highest, None, &mut modes);
+        assert_eq!(gop.set_first(&modes)?.mode(), 2);
+
+        // The current mode is kept if it's the best that works
+        assert_eq!(gop.set_first(&modes)?.mode(), 2);
+
+        assert!(gop
+            .set_first(&modes[..1])
+            .is_err_and(|e| e.status() == Status::DEVICE_ERROR));
+        assert!(gop
+            .set_first(&[])
+            .is_err_and(|e| e.status() == Status::NOT_FOUND));
+        assert_eq!(gop.mode().mode(), 2);
+        Ok(())
+    }
+
+    /// [`RawGraphicsOutput`] with a mode for every resolution
+    #[repr(C)]
+    struct ModesGop {
+        raw: RawGraphicsOutput,
+        mode: RawGraphicsMode,
+        modes: Vec<RawGraphicsInfo>,
+    }
+
+    impl ModesGop {
+        fn new(res: &[(u32, u32)], current: u32) -> Box<Self> {
+            let modes = res
+                .iter()
+                .map(|&(w, h)| {
+                    let mut info = info(RawPixelFormat::BGR, NO_MASK);
+                    (info.horizontal, info.vertical, info.stride) = (w, h, w);
+                    info
+                })
+                .collect();
+            let mut gop = Box::new(Self {
+                raw: RawGraphicsOutput {
+                    query_mode: Some(query_modes),
+                    set_mode: Some(set_modes),
+                    blt: None,
+                    mode: null_mut(),
+                },
+                mode: RawGraphicsMode {
+                    max_mode: res.len() as u32,
+                    mode: current,
+                    info: null_mut(),
+                    info_size: size_of::<RawGraphicsInfo>(),
+                    fb_base: 0,
+                    fb_size: 0,
+                },
+                modes,
+            });
+            gop.mode.info = addr_of_mut!(gop.modes[current as usize]);
+            gop.raw.mode = addr_of_mut!(gop.mode);
+            gop
+        }
+    }
+
+    unsafe extern "efiapi" fn query_modes(
+        this: *mut RawGraphicsOutput,
+        mode: u32,
+        size: *mut usize,
+        info: *mut *const RawGraphicsInfo,
+    ) -> Status {
+        // Safety: Only ever called through a `ModesGop`
+        let mock = unsafe { &*this.cast::<ModesGop>() };
+        match mock.modes.get(mode as usize) {
+            Some(m) => {
+                // Safety: Valid from `query_mode`
+                unsafe {
+                    *size = size_of::<RawGraphicsInfo>();
+                    *info = m;
+                }
+                Status::SUCCESS
+            }
+            None => Status::INVALID_PARAMETER,
+        }
+    }
+
+    unsafe extern "efiapi" fn set_modes(this: *mut RawGraphicsOutput, mode: u32) -> Status {
+        // Safety: Only ever called through a `ModesGop`
+        let mock = unsafe { &mut *this.cast::<ModesGop>() };
+        if mode as usize >= mock.modes.len() {
+            return Status::UNSUPPORTED;
+        }
+        mock.mode.mode = mode;
+        mock.mode.info = addr_of_mut!(mock.modes[mode as usize]);
+        Status::SUCCESS
+    }
+
+    /// Protocols on the two handles [`locate_gops`] finds
+    ///
+    /// Firmware gives boot services no context, so these are global.
+    /// Only [`native_mode`] uses them.
+    static GOPS: [AtomicPtr<c_void>; 2] = [const { AtomicPtr::new(null_mut()) }; 2];
+    static EDIDS: [AtomicPtr<c_void>; 2] = [const { AtomicPtr::new(null_mut()) }; 2];
+
+    unsafe extern "efiapi" fn locate_gops(
+        search: LocateSearch,
+        guid: *const Guid,
+        key: *const c_void,
+        size: *mut usize,
+        out: *mut EfiHandle,
+    ) -> Status {
+        // Safety: Valid from `locate_handle`
+        unsafe {
+            if search != LocateSearch::BY_PROTOCOL || *guid != GraphicsOutput::GUID {
+                return Status::NOT_FOUND;
+            }
+            let need = 2 * size_of::<EfiHandle>();
+            if *size < need {
+                *size = need;
+                return Status::BUFFER_TOO_SMALL;
+            }
+            *size = need;
+            for i in 0..2 {
+                *out.add(i) = EfiHandle::new((i + 1) as _);
+            }
+        }
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn gop_protocols(
+        handle: EfiHandle,
+        guid: *const Guid,
+        out: *mut *mut c_void,
+    ) -> Status {
+        // Safety: Valid from `handle_protocol`
+        let protos = match unsafe { *guid } {
+            g if g == GraphicsOutput::GUID => &GOPS,
+            g if g == ActiveEdid::GUID => &EDIDS,
+            _ => return Status::UNSUPPORTED,
+        };
+        let proto = match (handle.as_ptr() as usize).checked_sub(1) {
+            Some(i) if i < 2 => protos[i].load(Ordering::Acquire),
+            _ => null_mut(),
+        };
+        if proto.is_null() {
+            return Status::UNSUPPORTED;
+        }
+        // Safety: Valid from `handle_protocol`
+        unsafe { *out = proto };
+        Status::SUCCESS
+    }
+
+    /// A minimal EDID whose preferred timing is `timing`
+    fn edid(timing: [u8; 18]) -> Box<[u8; 128]> {
+        let mut e = Box::new([0u8; 128]);
+        e[..8].copy_from_slice(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]);
+        e[18..20].copy_from_slice(&[1, 4]);
+        e[54..72].copy_from_slice(&timing);
+        e[127] = 0u8.wrapping_sub(e.iter().fold(0u8, |s, b| s.wrapping_add(*b)));
+        e
+    }
+
+    #[test]
+    fn native_mode() -> Result<()> {
+        let res = [
+            (1920, 1200),
+            (1024, 768),
+            (1920, 1080),
+            (800, 600),
+            (1280, 720),
+        ];
+        let mut ours = ModesGop::new(&res, 3);
+        let mut other = ModesGop::new(&res, 3);
+        // 1920x1080@60 on another display, and 1280x720@60 on ours
+        let mut other_data = edid([
+            0x02, 0x3A, 0x80, 0x18, 0x71, 0x38, 0x2D, 0x40, 0x58, 0x2C, 0x45, 0x00, 0x13, 0x2B,
+            0x21, 0x00, 0x00, 0x1E,
+        ]);
+        let mut our_data = edid([
+            0x01, 0x1D, 0x00, 0x72, 0x51, 0xD0, 0x1E, 0x20, 0x6E, 0x28, 0x55, 0x00, 0xC4, 0x8E,
+            0x21, 0x00, 0x00, 0x1E,
+        ]);
+        let mut other_edid = RawEdidActive {
+            size: 128,
+            edid: other_data.as_mut_ptr(),
+        };
+        let mut our_edid = RawEdidActive {
+            size: 128,
+            edid: our_data.as_mut_ptr(),
+        };
+        GOPS[0].store(addr_of_mut!(other.raw).cast(), Ordering::Release);
+        GOPS[1].store(addr_of_mut!(ours.raw).cast(), Ordering::Release);
+        EDIDS[0].store(addr_of_mut!(other_edid).cast(), Ordering::Release);
+
+        // Safety: All zero is a valid, if empty, `RawBootServices`
+        let mut raw: RawBootServices = unsafe { zeroed() };
+        raw.locate_handle = Some(locate_gops);
+        raw.handle_protocol = Some(gop_protocols);
+        // Safety: `raw` outlives `boot`
+        let boot = unsafe { BootServices::new(addr_of_mut!(raw)) };
+        // Safety: `ours` outlives `gop`
+        let mut gop = unsafe { GraphicsOutput::new(addr_of_mut!(ours.raw)) };
+
+        // Our display has no EDID yet, so the highest mode is picked
+        assert_eq!(gop.native_res(&boot), None);
+        let mode = gop.select_mode_with(ModePolicy::Native, Some(&boot))?;
+        assert_eq!((mode.mode(), mode.res()), (0, (1920, 1200)));
+
+        EDIDS[1].store(addr_of_mut!(our_edid).cast(), Ordering::Release);
+        assert_eq!(gop.native_res(&boot), Some((1280, 720)));
+        let mode = gop.select_mode_with(ModePolicy::Native, Some(&boot))?;
+        assert_eq!((mode.mode(), mode.res()), (4, (1280, 720)));
+        assert_eq!(gop.mode().res(), (1280, 720));
+
+        // Without boot services there's no EDID either
+        let mode = gop.select_mode_with(ModePolicy::Native, None)?;
+        assert_eq!(mode.mode(), 0);
+
+        let mode = gop.select_mode_with(ModePolicy::Aspect(16, 9), Some(&boot))?;
+        assert_eq!((mode.mode(), mode.res()), (2, (1920, 1080)));
+        let mode = gop.select_mode_with(ModePolicy::Aspect(4, 3), Some(&boot))?;
+        assert_eq!((mode.mode(), mode.res()), (1, (1024, 768)));
+        let mode = gop.select_mode_with(ModePolicy::Aspect(21, 9), Some(&boot))?;
+        assert_eq!(mode.mode(), 0);
+
+        for proto in GOPS.iter().chain(&EDIDS) {
+            proto.store(null_mut(), Ordering::Release);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn blt_bounds() -> Result<()> {
+        let mut info = info(RawPixelFormat::BLT_ONLY, NO_MASK);
+        let mut mode = RawGraphicsMode {
+            max_mode: 1,
+            mode: 0,
+            info: addr_of_mut!(info),
+            info_size: size_of::<RawGraphicsInfo>(),
+            fb_base: 0,
+            fb_size: 0,
+        };
+        let mut mock = MockGop::new(addr_of_mut!(mode));
+        // Safety: `mock` outlives `gop`
+        let gop = unsafe { GraphicsOutput::new(addr_of_mut!(mock).cast()) };
+        let red = Pixel::new(255, 0, 0);
+        let invalid = |r: Result<()>| r.is_err_and(|e| e.status() == Status::INVALID_PARAMETER);
+
+        gop.fill(red, (0, 0), (640, 480))?;
+        gop.fill(red, (639, 479), (1, 1))?;
+        gop.fill(red, (640, 0), (0, 0))?;
+        assert!(invalid(gop.fill(red, (1, 0), (640, 1))));
+        assert!(invalid(gop.fill(red, (0, 480), (1, 1))));
+        assert!(invalid(gop.fill(red, (usize::MAX, 0), (2, 1))));
+
+        let mut buf = BltBuffer::new(16, 16);
+        gop.video_to_buffer(&mut buf, (624, 464), (0, 0), (16, 16))?;
+        gop.buffer_to_video(&buf, (8, 8), (0, 0), (8, 8))?;
+        assert!(invalid(gop.video_to_buffer(
+            &mut buf,
+            (0, 0),
+            (1, 0),
+            (16, 16)
+        )));
+        assert!(invalid(gop.buffer_to_video(
+            &buf,
+            (0, 0),
+            (630, 0),
+            (16, 16)
+        )));
+
+        gop.video_to_video((0, 0), (10, 10), (630, 470))?;
+        assert!(invalid(gop.video_to_video((0, 0), (11, 10), (630, 470))));
+
+        #[allow(deprecated)]
+        {
+            let pixels = [red; 16];
+            gop.blt(
+                &pixels,
+                BltOperation::BufferToVideo,
+                (0, 0),
+                (0, 0),
+                (4, 4),
+                0,
+            )?;
+            gop.blt(
+                &pixels,
+                BltOperation::BufferToVideo,
+                (2, 1),
+                (0, 0),
+                (2, 3),
+                4,
+            )?;
+            assert!(invalid(gop.blt(
+                &pixels,
+                BltOperation::BufferToVideo,
+                (2, 2),
+                (0, 0),
+                (2, 3),
+                4
+            )));
+            assert!(invalid(gop.blt(
+                &pixels,
+                BltOperation::VideoToBuffer,
+                (0, 0),
+                (0, 0),
+                (1, 1),
+                0
+            )));
+            assert!(invalid(gop.blt(
+                &[],
+                BltOperation::VideoFill,
+                (0, 0),
+                (0, 0),
+                (1, 1),
+                0
+            )));
+        }
+
+        // Safety: `gop` no longer uses `mock`
+        assert_eq!(unsafe { &*addr_of_mut!(mock) }.blts.borrow().len(), 7);
+        assert!(gop
+            .framebuffer()
+            .is_err_and(|e| e.status() == Status::UNSUPPORTED));
+        Ok(())
+    }
+
+    #[test]
+    fn blt_arguments() -> Result<()> {
+        let mut info = info(RawPixelFormat::BLT_ONLY, NO_MASK);
+        let mut mode = RawGraphicsMode {
+            max_mode: 1,
+            mode: 0,
+            info: addr_of_mut!(info),
+            info_size: size_of::<RawGraphicsInfo>(),
+            fb_base: 0,
+            fb_size: 0,
+        };
+        let mut mock = MockGop::new(addr_of_mut!(mode));
+        let mock = addr_of_mut!(mock);
+        // Safety: `mock` outlives `gop`
+        let gop = unsafe { GraphicsOutput::new(mock.cast()) };
+        // Safety: `gop` only uses `mock` during calls
+        let last = || unsafe { *(*mock).blts.borrow().last().unwrap() };
+
+        // `delta` is passed in bytes, from the buffer row width
+        let buf = BltBuffer::with_delta(5, 4, 7);
+        gop.buffer_to_video(&buf, (1, 2), (100, 200), (3, 2))?;
+        assert_eq!(
+            last(),
+            BltCall {
+                buffer: buf.pixels().as_ptr().cast_mut().cast(),
+                color: None,
+                op: RawBltOperation::BUFFER_TO_VIDEO,
+                src: (1, 2),
+                dest: (100, 200),
+                size: (3, 2),
+                delta: 7 * 4,
+            }
+        );
+
+        // Reading a sub-rect of the screen into part of the buffer
+        let mut buf = BltBuffer::new(16, 8);
+        gop.video_to_buffer(&mut buf, (320, 240), (4, 2), (8, 6))?;
+        assert_eq!(
+            last(),
+            BltCall {
+                buffer: buf.pixels_mut().as_mut_ptr().cast(),
+                color: None,
+                op: RawBltOperation::VIDEO_TO_BUFFER,
+                src: (320, 240),
+                dest: (4, 2),
+                size: (8, 6),
+                delta: 16 * 4,
+            }
+        );
+
+        // The color is the only pixel, and the source is unused
+        let color = Pixel::new(1, 2, 3);
+        gop.fill(color, (10, 20), (30, 40))?;
+        assert_eq!(
+            last(),
+            BltCall {
+                buffer: null_mut(),
+                color: Some(color),
+                op: RawBltOperation::VIDEO_FILL,
+                src: (0, 0),
+                dest: (10, 20),
+                size: (30, 40),
+                delta: 0,
+            }
+        );
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
 //! UEFI Loaded image Protocol
+use alloc::{format, string::String};
 use core::mem::size_of;
 
 use raw::RawLoadedImage;
@@ -51,6 +52,15 @@ impl<'table> LoadedImage<'table> {
         }
     }
 
+    /// A gdb `add-symbol-file` command loading `symbols` for this image
+    ///
+    /// `link_base` is the image base `symbols` was linked at,
+    /// all sections are offset by the difference from [`Self::image_base`].
+    pub fn gdb_add_symbol_file(&self, symbols: &str, link_base: u64) -> String {
+        let offset = (self.image_base() as u64).wrapping_sub(link_base);
+        format!("add-symbol-file {symbols} -o {offset:#x}")
+    }
+
     /// Set the LoadOptions for this loaded image
     ///
     /// # Panics
@@ -4,7 +4,7 @@ use core::{
     cell::Cell,
     iter::{from_fn, once},
     marker::PhantomData,
-    mem::{size_of, MaybeUninit},
+    mem::{offset_of, size_of, MaybeUninit},
     ptr::null_mut,
     slice::from_raw_parts,
 };
@@ -15,7 +15,8 @@ use raw::*;
 
 use crate::{
     error::{Result, Status},
-    proto::{Entity, Guid, Protocol},
+    nuefi_core::path::FilePath,
+    proto::{Entity, Guid, Protocol, Time},
     util::interface,
     Protocol,
 };
@@ -119,12 +120,13 @@ impl<'this, 'table> FsHandle<'this, 'table> {
     // Use a new lifetime because this is a new handle independent of ours.
     fn open_impl<'new_this>(
         &self,
-        name: &str,
+        name: &FilePath,
         mode: u64,
         flags: u64,
     ) -> Result<FsHandle<'new_this, 'table>> {
         let mut out = null_mut();
-        let name: Vec<u16> = name.encode_utf16().chain(once(0)).collect();
+        let name = name.to_path_buf();
+        let name: Vec<u16> = name.as_str().encode_utf16().chain(once(0)).collect();
 
         // Safety: `out` valid by definition, firmware
         let ret = unsafe {
@@ -143,6 +145,65 @@ impl<'this, 'table> FsHandle<'this, 'table> {
         }
     }
 
+    /// Get information identified by `guid` as bytes
+    fn get_info_impl(&self, guid: &Guid) -> Result<Vec<u8>> {
+        let mut size: usize = 0;
+        let mut out: Vec<u8> = Vec::new();
+        let fp = self.interface().get_info.ok_or(Status::UNSUPPORTED)?;
+
+        // Safety: Described within
+        unsafe {
+            // Get the buffer size
+
+            // All arguments are guaranteed valid
+            let info = (fp)(self.interface, guid, &mut size, null_mut());
+
+            // It should be `BUFFER_TOO_SMALL`
+            if info != Status::BUFFER_TOO_SMALL {
+                return Err(info.into());
+            }
+            // Sanity check
+            if size == 0 {
+                return Err(Status::INVALID_PARAMETER.into());
+            }
+
+            // Reserve enough memory for `size`, initializing to `0`.
+            out.resize(size, 0);
+
+            let ptr = out.as_mut_ptr();
+
+            // This time fill buffer
+
+            // All arguments are guaranteed valid
+            // `ptr` is valid for `size` bytes
+            let info = (fp)(self.interface, guid, &mut size, ptr);
+
+            if info.is_success() {
+                // Firmware may have used less than it asked for
+                out.truncate(size);
+                Ok(out)
+            } else {
+                Err(info.into())
+            }
+        }
+    }
+
+    /// Set information identified by `guid` to `data`
+    fn set_info_impl(&self, guid: &Guid, data: &[u8]) -> Result<()> {
+        let fp = self.interface().set_info.ok_or(Status::UNSUPPORTED)?;
+
+        // All arguments are guaranteed valid
+        // `data` is valid for its length
+        unsafe { (fp)(self.interface, guid, data.len(), data.as_ptr()) }.into()
+    }
+
+    /// Modify our current [`FsInfo`] with `f`, then set it
+    fn update_info(&self, f: impl FnOnce(&mut FsInfo)) -> Result<()> {
+        let mut info = self.info()?;
+        f(&mut info);
+        self.set_info_impl(&FsInfo::GUID, &info.to_bytes())
+    }
+
     /// Reads the buffer for [`FsHandle::read_impl`]
     fn read_impl_size(&self) -> Result<usize> {
         let rd = self.interface().read.unwrap();
@@ -257,17 +318,28 @@ impl<'this, 'table> FsHandle<'this, 'table> {
 impl<'this, 'table> FsHandle<'this, 'table> {
     /// Open a new [`FsHandle`] relative to this one
     ///
-    /// Remember that UEFI paths use `\`, not `/`
-    // FIXME: Provide a nice UEFI path type
-    pub fn open<'new_this>(&self, name: &str) -> Result<FsHandle<'new_this, 'table>> {
+    /// Remember that UEFI paths use `\`, not `/`,
+    /// though `/` is converted. See [`FilePath`]
+    pub fn open<'new_this, P: AsRef<FilePath>>(
+        &self,
+        name: P,
+    ) -> Result<FsHandle<'new_this, 'table>> {
         let mode = 0x1;
         let flags = 0;
-        self.open_impl(name, mode, flags)
+        self.open_impl(name.as_ref(), mode, flags)
     }
 
-    /// Create a new [`FsHandle`] relative to this one
-    pub fn create<'new_this>(&self, name: &str) -> Result<FsHandle<'new_this, 'table>> {
-        todo!()
+    /// Create a new [`FsHandle`] relative to this one,
+    /// or open it if it already exists.
+    ///
+    /// The file is opened for reading and writing.
+    pub fn create<'new_this, P: AsRef<FilePath>>(
+        &self,
+        name: P,
+    ) -> Result<FsHandle<'new_this, 'table>> {
+        let mode = 0x8000_0000_0000_0003;
+        let flags = 0;
+        self.open_impl(name.as_ref(), mode, flags)
     }
 
     /// Read the contents of the directory referred to by our handle
@@ -300,55 +372,116 @@ impl<'this, 'table> FsHandle<'this, 'table> {
         unsafe { self.read_impl_write(size, out) }
     }
 
-    /// Information about this [`FsHandle`]. See [`FsInfo`]
-    pub fn info(&self) -> Result<FsInfo> {
-        let guid = FsInfo::GUID;
-        let mut size: usize = 0;
-        let mut out: Vec<u8> = Vec::new();
-
-        // Safety: Described within
-        unsafe {
-            let fp = self.interface().get_info.unwrap();
+    /// Write bytes from `data`, returning how many were actually written.
+    ///
+    /// The files current [`FsHandle::position`] increases by the amount
+    /// written.
+    ///
+    /// Firmware may report [`Status::WARN_WRITE_FAILURE`] if the data was
+    /// not written properly. This is returned as an error,
+    /// as the contents of the file are then unknown.
+    pub fn write(&self, data: &[u8]) -> Result<usize> {
+        let wr = self.interface().write.ok_or(Status::UNSUPPORTED)?;
+        let mut size = data.len();
+        // Safety: `data` is valid for `size` bytes
+        let ret = unsafe { (wr)(self.interface, &mut size, data.as_ptr()) };
+        if ret.is_success() {
+            Ok(size)
+        } else {
+            Err(ret.into())
+        }
+    }
 
-            // Get the buffer size
+    /// Write all of `data`, calling [`FsHandle::write`] until it is done.
+    ///
+    /// Returns [`Status::WARN_WRITE_FAILURE`] if firmware stops making
+    /// progress.
+    pub fn write_all(&self, data: &[u8]) -> Result<()> {
+        let mut rest = data;
+        while !rest.is_empty() {
+            match self.write(rest)? {
+                0 => return Err(Status::WARN_WRITE_FAILURE.into()),
+                n => rest = rest.get(n..).ok_or(Status::DEVICE_ERROR)?,
+            }
+        }
+        Ok(())
+    }
 
-            // All arguments are guaranteed valid
-            let info = (fp)(self.interface, &guid, &mut size, null_mut());
+    /// Close the handle and delete the file or directory it refers to.
+    ///
+    /// The handle is always closed, but if the file could not be deleted
+    /// this returns [`Status::WARN_DELETE_FAILURE`].
+    pub fn delete(self) -> Result<()> {
+        let del = self.interface().delete.ok_or(Status::UNSUPPORTED)?;
+        // `delete` closes the handle, even on failure.
+        self.closed.set(true);
+        // Safety: statically valid, and never used again
+        unsafe { (del)(self.interface) }.into()
+    }
 
-            // It should be `BUFFER_TOO_SMALL`
-            if info != Status::BUFFER_TOO_SMALL {
-                return Err(info.into());
-            }
-            // Sanity check
-            if size == 0 {
-                return Err(Status::INVALID_PARAMETER.into());
-            }
+    /// Rename this entity to `name`,
+    /// relative to the directory containing it.
+    ///
+    /// Remember that UEFI paths use `\`, not `/`
+    pub fn rename(&self, name: &str) -> Result<()> {
+        self.update_info(|info| info.name = name.into())
+    }
 
-            // Reserve enough memory for `size`, initializing to `0`.
-            out.resize(size, 0);
+    /// Set the size of the file to `size` bytes,
+    /// truncating or extending it.
+    pub fn set_size(&self, size: u64) -> Result<()> {
+        self.update_info(|info| info.info.file_size = size)
+    }
 
-            // Just in case?
-            assert!(out.capacity() >= size, "File::info capacity bug");
+    /// Set the creation, last access, and modification times of this entity
+    ///
+    /// Times that are [`None`] are left unchanged.
+    pub fn set_times(
+        &self,
+        created: Option<Time>,
+        accessed: Option<Time>,
+        modified: Option<Time>,
+    ) -> Result<()> {
+        self.update_info(|info| {
+            let raw = &mut info.info;
+            raw.create_time = created.unwrap_or(raw.create_time);
+            raw.last_access_time = accessed.unwrap_or(raw.last_access_time);
+            raw.modification_time = modified.unwrap_or(raw.modification_time);
+        })
+    }
 
-            let ptr = out.as_mut_ptr();
+    /// Set the [`FileAttributes`] of this entity
+    ///
+    /// [`FileAttributes::DIRECTORY`] cannot be changed,
+    /// and is ignored in `attributes`.
+    pub fn set_attributes(&self, attributes: FileAttributes) -> Result<()> {
+        self.update_info(|info| {
+            let dir =
+                FileAttributes::from_bits_truncate(info.info.flags) & FileAttributes::DIRECTORY;
+            info.info.flags = ((attributes - FileAttributes::DIRECTORY) | dir).bits();
+        })
+    }
 
-            // This time fill buffer
+    /// Information about this [`FsHandle`]. See [`FsInfo`]
+    pub fn info(&self) -> Result<FsInfo> {
+        FsInfo::from_bytes(self.get_info_impl(&FsInfo::GUID)?)
+    }
 
-            // All arguments are guaranteed valid
-            // `ptr` is valid for `size` bytes
-            let info = (fp)(self.interface, &guid, &mut size, ptr);
+    /// Information about the filesystem this [`FsHandle`] is on.
+    /// See [`FileSystemInfo`]
+    pub fn fs_info(&self) -> Result<FileSystemInfo> {
+        FileSystemInfo::from_bytes(self.get_info_impl(&FileSystemInfo::GUID)?)
+    }
 
-            if info.is_success() {
-                // We only call this on success, and before returning.
-                // Out has been fully initialized, because we started initialized
-                out.set_len(size);
+    /// Label of the volume this [`FsHandle`] is on
+    pub fn volume_label(&self) -> Result<String> {
+        let label = self.get_info_impl(&VolumeLabel::GUID)?;
+        Ok(decode_name(&label))
+    }
 
-                let info = FsInfo::from_bytes(out).unwrap();
-                Ok(info)
-            } else {
-                Err(info.into())
-            }
-        }
+    /// Set the label of the volume this [`FsHandle`] is on
+    pub fn set_volume_label(&self, label: &str) -> Result<()> {
+        self.set_info_impl(&VolumeLabel::GUID, &encode_name(label))
     }
 
     /// Close the handle, flushing all data, waiting for any pending async I/O.
@@ -395,10 +528,32 @@ impl<'this, 'table> FsHandle<'this, 'table> {
 
 impl<'this, 'table> Drop for FsHandle<'this, 'table> {
     fn drop(&mut self) {
-        if !self.closed.get() {
-            self.closed.set(true);
-            let _ = self.close();
-        }
+        // Does nothing if already closed
+        let _ = self.close();
+    }
+}
+
+bitflags::bitflags! {
+    /// Attributes of an entity on the filesystem
+    #[repr(transparent)]
+    pub struct FileAttributes: u64 {
+        /// The file cannot be opened for writing
+        const READ_ONLY = 0x1;
+
+        /// The file is hidden
+        const HIDDEN = 0x2;
+
+        /// The file is a system file
+        const SYSTEM = 0x4;
+
+        /// Reserved, do not use
+        const RESERVED = 0x8;
+
+        /// The entity is a directory
+        const DIRECTORY = 0x10;
+
+        /// The file has been modified since it was last archived
+        const ARCHIVE = 0x20;
     }
 }
 
@@ -413,51 +568,54 @@ pub struct FsInfo {
 }
 
 impl FsInfo {
-    const DIRECTORY: u64 = 0x10;
-
     fn new(info: RawFsInfo, name: String) -> Self {
         Self { info, name }
     }
 
     /// Create `FsInfo` from bytes
     fn from_bytes(v: Vec<u8>) -> Result<FsInfo> {
-        // Safety: Described within
-        unsafe {
-            let mut info: MaybeUninit<RawFsInfo> = MaybeUninit::uninit();
-            let f_size = size_of::<RawFsInfo>();
+        let f_size = size_of::<RawFsInfo>();
 
-            // Split off the raw info struct from the name
-            let (raw, name) = v.split_at(f_size);
+        // Split off the raw info struct from the name
+        let (raw, name) = v.split_at_checked(f_size).ok_or(Status::BUFFER_TOO_SMALL)?;
 
-            // If `raw` is empty, error
-            if raw.len() < f_size {
-                return Err(Status::BUFFER_TOO_SMALL.into());
-            }
+        let mut info: MaybeUninit<RawFsInfo> = MaybeUninit::uninit();
 
-            // Initialize the new info struct
+        // Safety: `raw` is valid for `f_size` bytes,
+        // which initializes the new info struct
+        let info = unsafe {
             info.as_mut_ptr()
                 .cast::<u8>()
-                .copy_from_nonoverlapping(raw.as_ptr() as *const u8, f_size);
-            let info = info.assume_init();
+                .copy_from_nonoverlapping(raw.as_ptr(), f_size);
+            info.assume_init()
+        };
 
-            // The length of the filename in UTF-16, minus the nul terminator.
-            let name_len = (name.len() / 2) - 1;
+        Ok(FsInfo::new(info, decode_name(name)))
+    }
 
-            // Rebind `name` from a `&[u8]` slice
-            // to a `&[u16]` slice of half the length
-            let name = from_raw_parts(name.as_ptr() as *const u16, name_len);
+    /// Convert `FsInfo` to bytes, for [`FsHandle::set_info_impl`]
+    fn to_bytes(&self) -> Vec<u8> {
+        let name = encode_name(&self.name);
+        let mut info = self.info;
+        info.this_size = (size_of::<RawFsInfo>() + name.len()) as u64;
+
+        // Safety: `RawFsInfo` is `repr(C)` with no padding
+        let raw = unsafe {
+            from_raw_parts(
+                (&info as *const RawFsInfo).cast::<u8>(),
+                size_of::<RawFsInfo>(),
+            )
+        };
 
-            // Then decode it as UTF-16
-            let name = char::decode_utf16(name.iter().copied())
-                .map(|r| r.unwrap_or(char::REPLACEMENT_CHARACTER))
-                .collect::<String>();
-            Ok(FsInfo::new(info, name))
-        }
+        let mut out = Vec::with_capacity(info.this_size as usize);
+        out.extend_from_slice(raw);
+        out.extend(name);
+        out
     }
 
     /// Is this a directory or not?
     pub fn directory(&self) -> bool {
-        (self.info.flags & Self::DIRECTORY) == Self::DIRECTORY
+        FileAttributes::from_bits_truncate(self.info.flags).contains(FileAttributes::DIRECTORY)
     }
 
     /// Entity name
@@ -474,6 +632,122 @@ impl FsInfo {
     pub fn dev_size(&self) -> u64 {
         self.info.physical_size
     }
+
+    /// Entity [`FileAttributes`]
+    pub fn attributes(&self) -> FileAttributes {
+        FileAttributes::from_bits_truncate(self.info.flags)
+    }
+
+    /// Whether this entity is read-only
+    pub fn read_only(&self) -> bool {
+        self.attributes().contains(FileAttributes::READ_ONLY)
+    }
+
+    /// Time the entity was created
+    pub fn created(&self) -> Time {
+        self.info.create_time
+    }
+
+    /// Time the entity was last accessed
+    pub fn accessed(&self) -> Time {
+        self.info.last_access_time
+    }
+
+    /// Time the entity was last modified
+    pub fn modified(&self) -> Time {
+        self.info.modification_time
+    }
+}
+
+/// UEFI filesystem information
+///
+/// Represents information about the volume an [`FsHandle`] is on.
+/// See [`FsHandle::fs_info`]
+#[GUID("09576E93-6D3F-11D2-8E39-00A0C969723B", crate("crate"))]
+#[derive(Debug)]
+pub struct FileSystemInfo {
+    info: RawFileSystemInfo,
+    label: String,
+}
+
+impl FileSystemInfo {
+    /// Offset of the label, which overlaps the padding of [`RawFileSystemInfo`]
+    const LABEL: usize = offset_of!(RawFileSystemInfo, block_size) + size_of::<u32>();
+
+    /// Create `FileSystemInfo` from bytes
+    fn from_bytes(v: Vec<u8>) -> Result<FileSystemInfo> {
+        let (raw, label) = v
+            .split_at_checked(Self::LABEL)
+            .ok_or(Status::BUFFER_TOO_SMALL)?;
+
+        let mut info: MaybeUninit<RawFileSystemInfo> = MaybeUninit::zeroed();
+
+        // Safety: `raw` is valid for `LABEL` bytes, which is within
+        // `RawFileSystemInfo`. The remaining padding is already zeroed.
+        let info = unsafe {
+            info.as_mut_ptr()
+                .cast::<u8>()
+                .copy_from_nonoverlapping(raw.as_ptr(), Self::LABEL);
+            info.assume_init()
+        };
+
+        Ok(FileSystemInfo {
+            info,
+            label: decode_name(label),
+        })
+    }
+
+    /// Volume label
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Whether the volume is read-only
+    pub fn read_only(&self) -> bool {
+        self.info.read_only.into()
+    }
+
+    /// Volume size in bytes
+    pub fn volume_size(&self) -> u64 {
+        self.info.volume_size
+    }
+
+    /// Free space on the volume in bytes
+    pub fn free_space(&self) -> u64 {
+        self.info.free_space
+    }
+
+    /// Volume block size in bytes
+    pub fn block_size(&self) -> u32 {
+        self.info.block_size
+    }
+}
+
+/// UEFI volume label information, see [`FsHandle::volume_label`]
+#[GUID("DB47D7D3-FE81-11D3-9A35-0090273FC14D", crate("crate"))]
+#[derive(Debug)]
+struct VolumeLabel;
+
+/// Decode a nul terminated UTF-16 name from `bytes`
+///
+/// Stops at the first nul, or the end of `bytes` if there is none.
+fn decode_name(bytes: &[u8]) -> String {
+    let (name, _) = bytes.as_chunks::<2>();
+    let name = name
+        .iter()
+        .map(|c| u16::from_le_bytes(*c))
+        .take_while(|c| *c != 0);
+    char::decode_utf16(name)
+        .map(|r| r.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Encode `name` as nul terminated UTF-16 bytes
+fn encode_name(name: &str) -> Vec<u8> {
+    name.encode_utf16()
+        .chain(once(0))
+        .flat_map(u16::to_le_bytes)
+        .collect()
 }
 
 pub mod iter {
@@ -536,3 +810,266 @@ pub mod iter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use core::ptr::null;
+
+    use super::*;
+    use crate::proto::provider::Provider;
+
+    /// An in-memory file
+    #[derive(Debug, Default)]
+    struct Mock {
+        data: Vec<u8>,
+        info: Option<FsInfo>,
+        label: String,
+        fail: bool,
+        deleted: bool,
+        closes: usize,
+    }
+
+    type MockFile = Provider<RawFsHandle, Mock>;
+
+    /// Call `f` with the [`Mock`] for `this`
+    fn mock<U>(this: *const RawFsHandle, f: impl FnOnce(&mut Mock) -> U) -> U {
+        // Safety: `this` is only ever from a `MockFile`
+        unsafe { MockFile::with(this.cast_mut(), f) }.unwrap()
+    }
+
+    unsafe extern "efiapi" fn write(
+        this: *mut RawFsHandle,
+        size: *mut usize,
+        buf: *const u8,
+    ) -> Status {
+        mock(this, |m| {
+            if m.fail {
+                *size = 0;
+                return Status::WARN_WRITE_FAILURE;
+            }
+            // Only write a few bytes at a time
+            *size = (*size).min(4);
+            m.data.extend_from_slice(from_raw_parts(buf, *size));
+            Status::SUCCESS
+        })
+    }
+
+    unsafe extern "efiapi" fn get_info(
+        this: *mut RawFsHandle,
+        guid: *const Guid,
+        size: *mut usize,
+        buf: *mut u8,
+    ) -> Status {
+        let bytes = mock(this, |m| {
+            if *guid == FsInfo::GUID {
+                let info = m.info.as_mut().unwrap();
+                info.info.file_size = m.data.len() as u64;
+                Some(info.to_bytes())
+            } else if *guid == FileSystemInfo::GUID {
+                let mut bytes = Vec::new();
+                bytes.extend(0u64.to_le_bytes());
+                bytes.extend([1, 0, 0, 0, 0, 0, 0, 0]);
+                bytes.extend(0x10_0000u64.to_le_bytes());
+                bytes.extend(0x8000u64.to_le_bytes());
+                bytes.extend(512u32.to_le_bytes());
+                bytes.extend(encode_name(&m.label));
+                Some(bytes)
+            } else if *guid == VolumeLabel::GUID {
+                Some(encode_name(&m.label))
+            } else {
+                None
+            }
+        });
+        let Some(bytes) = bytes else {
+            return Status::UNSUPPORTED;
+        };
+        if *size < bytes.len() {
+            *size = bytes.len();
+            return Status::BUFFER_TOO_SMALL;
+        }
+        *size = bytes.len();
+        buf.copy_from_nonoverlapping(bytes.as_ptr(), bytes.len());
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn set_info(
+        this: *const RawFsHandle,
+        guid: *const Guid,
+        size: usize,
+        buf: *const u8,
+    ) -> Status {
+        let bytes = from_raw_parts(buf, size);
+        if *guid == VolumeLabel::GUID {
+            mock(this, |m| m.label = decode_name(bytes));
+            return Status::SUCCESS;
+        } else if *guid != FsInfo::GUID {
+            return Status::UNSUPPORTED;
+        }
+        let new = FsInfo::from_bytes(bytes.to_vec()).unwrap();
+        if new.info.this_size != size as u64 {
+            return Status::BAD_BUFFER_SIZE;
+        }
+        mock(this, |m| {
+            m.data.resize(new.info.file_size as usize, 0);
+            m.info = Some(new);
+            Status::SUCCESS
+        })
+    }
+
+    unsafe extern "efiapi" fn delete(this: *mut RawFsHandle) -> Status {
+        mock(this, |m| {
+            if m.fail {
+                return Status::WARN_DELETE_FAILURE;
+            }
+            m.deleted = true;
+            Status::SUCCESS
+        })
+    }
+
+    unsafe extern "efiapi" fn close(this: *mut RawFsHandle) -> Status {
+        mock(this, |m| m.closes += 1);
+        Status::SUCCESS
+    }
+
+    fn mock_file(name: &str) -> MockFile {
+        let raw = RawFsHandle {
+            revision: 0x00010000,
+            open: None,
+            close: Some(close),
+            delete: Some(delete),
+            read: None,
+            write: Some(write),
+            get_pos: None,
+            set_pos: None,
+            get_info: Some(get_info),
+            set_info: Some(set_info),
+            flush: None,
+            open_ex: null(),
+            read_ex: null(),
+            write_ex: null(),
+            flush_ex: null(),
+        };
+        let info = RawFsInfo {
+            this_size: 0,
+            file_size: 0,
+            physical_size: 0,
+            create_time: Time {
+                year: 2000,
+                ..Default::default()
+            },
+            last_access_time: Time::default(),
+            modification_time: Time::default(),
+            flags: FileAttributes::ARCHIVE.bits(),
+        };
+        let state = Mock {
+            info: Some(FsInfo::new(info, name.into())),
+            ..Default::default()
+        };
+        Provider::new(raw, state)
+    }
+
+    #[test]
+    fn write_and_update() -> Result<()> {
+        let provider = mock_file("a.txt");
+        // Safety: `provider` outlives `file`
+        let file = unsafe { FsHandle::new(provider.as_ptr()) };
+
+        file.write_all(b"hello world")?;
+        assert_eq!(
+            provider.with_state(|m| m.data.clone()).unwrap(),
+            b"hello world"
+        );
+
+        provider.with_state(|m| m.fail = true);
+        let e = file.write_all(b"!").unwrap_err();
+        assert_eq!(e.status(), Status::WARN_WRITE_FAILURE);
+        provider.with_state(|m| m.fail = false);
+
+        file.set_size(5)?;
+        assert_eq!(file.info()?.size(), 5);
+        assert_eq!(provider.with_state(|m| m.data.clone()).unwrap(), b"hello");
+
+        file.rename("b.txt")?;
+        assert_eq!(file.info()?.name(), "b.txt");
+
+        file.set_attributes(FileAttributes::READ_ONLY | FileAttributes::DIRECTORY)?;
+        let flags = provider.with_state(|m| m.info.as_ref().unwrap().info.flags);
+        assert_eq!(flags, Some(FileAttributes::READ_ONLY.bits()));
+
+        let time = Time {
+            year: 2023,
+            month: 4,
+            day: 1,
+            ..Default::default()
+        };
+        file.set_times(None, None, Some(time))?;
+        let info = provider
+            .with_state(|m| m.info.as_ref().unwrap().info)
+            .unwrap();
+        assert_eq!(info.create_time.year, 2000);
+        assert_eq!(info.last_access_time.year, 0);
+        assert_eq!(
+            (info.modification_time.year, info.modification_time.day),
+            (2023, 1)
+        );
+
+        let info = file.info()?;
+        assert_eq!(info.attributes(), FileAttributes::READ_ONLY);
+        assert!(info.read_only() && !info.directory());
+        assert_eq!(info.created().year, 2000);
+        assert_eq!(info.modified().month, 4);
+
+        file.delete()?;
+        let (deleted, closes) = provider.with_state(|m| (m.deleted, m.closes)).unwrap();
+        assert!(deleted);
+        assert_eq!(closes, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn close_once() -> Result<()> {
+        let provider = mock_file("a.txt");
+        // Safety: `provider` outlives `file`
+        let file = unsafe { FsHandle::new(provider.as_ptr()) };
+        drop(file);
+        assert_eq!(provider.with_state(|m| m.closes).unwrap(), 1);
+
+        // Safety: `provider` outlives `file`
+        let file = unsafe { FsHandle::new(provider.as_ptr()) };
+        file.close()?;
+        file.close()?;
+        drop(file);
+        assert_eq!(provider.with_state(|m| m.closes).unwrap(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_failure() {
+        let provider = mock_file("a.txt");
+        provider.with_state(|m| m.fail = true);
+        // Safety: `provider` outlives `file`
+        let file = unsafe { FsHandle::new(provider.as_ptr()) };
+
+        let e = file.delete().unwrap_err();
+        assert_eq!(e.status(), Status::WARN_DELETE_FAILURE);
+        assert_eq!(provider.with_state(|m| m.closes).unwrap(), 0);
+    }
+
+    #[test]
+    fn volume() -> Result<()> {
+        let provider = mock_file("a.txt");
+        provider.with_state(|m| m.label = "EFI".into());
+        // Safety: `provider` outlives `file`
+        let file = unsafe { FsHandle::new(provider.as_ptr()) };
+
+        let fs = file.fs_info()?;
+        assert_eq!(fs.label(), "EFI");
+        assert!(fs.read_only());
+        assert_eq!((fs.volume_size(), fs.free_space()), (0x10_0000, 0x8000));
+        assert_eq!(fs.block_size(), 512);
+
+        file.set_volume_label("BOOT")?;
+        assert_eq!(file.volume_label()?, "BOOT");
+        Ok(())
+    }
+}
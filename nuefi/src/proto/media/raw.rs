@@ -1,5 +1,5 @@
 use crate::{
-    nuefi_core::base::Status,
+    nuefi_core::base::{Boolean, Status},
     proto::{device_path::raw::RawDevicePath, Guid, Time},
 };
 
@@ -131,7 +131,7 @@ pub struct RawFsHandle {
 }
 
 /// UEFI [`RawFsInfo`] information
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct RawFsInfo {
     pub this_size: u64,
@@ -144,3 +144,17 @@ pub struct RawFsInfo {
     // This type is dynamically sized
     // pub filename: *mut u16,
 }
+
+/// UEFI [`RawFileSystemInfo`] information
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawFileSystemInfo {
+    pub this_size: u64,
+    pub read_only: Boolean,
+    pub volume_size: u64,
+    pub free_space: u64,
+    pub block_size: u32,
+    // This type is dynamically sized,
+    // starting directly after `block_size`
+    // pub volume_label: *mut u16,
+}
@@ -0,0 +1,118 @@
+//! Implementing UEFI Protocols in Rust
+//!
+//! Firmware calls a protocol through its [`Protocol::Raw`] table of function
+//! pointers, passing a pointer to the table as `this`.
+//!
+//! A [`Provider`] keeps Rust state next to that table, so the
+//! `extern "efiapi"` functions in it can get back to their state with
+//! [`Provider::with`], without any statics.
+//!
+//! See [`crate::graphics::console`] and [`crate::proto::edid::EdidOverride`]
+//! for examples.
+use alloc::boxed::Box;
+use core::{cell::RefCell, fmt, mem::forget, ptr::NonNull};
+
+use crate::{error::Result, proto::Protocol, table::BootServices, EfiHandle};
+
+/// The protocol table and its state
+///
+/// The table is first, so a pointer to it is a pointer to us.
+#[repr(C)]
+struct Inner<R, T> {
+    raw: R,
+    state: RefCell<T>,
+}
+
+/// An owned Rust implementation of the protocol table `R`, with state `T`
+///
+/// The table and state are never moved, so [`Provider::as_ptr`] is stable.
+pub struct Provider<R: 'static, T: 'static> {
+    inner: NonNull<Inner<R, T>>,
+}
+
+impl<R: 'static, T: 'static> Provider<R, T> {
+    /// Create a new provider for the protocol table `raw`,
+    /// whose functions use `state`
+    pub fn new(raw: R, state: T) -> Self {
+        let inner = Box::new(Inner {
+            raw,
+            state: RefCell::new(state),
+        });
+        Self {
+            inner: NonNull::from(Box::leak(inner)),
+        }
+    }
+
+    /// Pointer to the protocol table, for firmware
+    pub fn as_ptr(&self) -> *mut R {
+        self.inner.as_ptr().cast()
+    }
+
+    /// Call `f` with the state of the provider whose table is `this`
+    ///
+    /// Returns [`None`] if the state is already in use, such as if `f`
+    /// ends up calling the same protocol again.
+    ///
+    /// # Safety
+    ///
+    /// - `this` must be from [`Provider::as_ptr`] of a live `Provider<R, T>`
+    pub unsafe fn with<U, F>(this: *mut R, f: F) -> Option<U>
+    where
+        F: FnOnce(&mut T) -> U,
+    {
+        // Safety: Caller ensures `this` is the start of an `Inner`
+        let inner = unsafe { &*(this as *const Inner<R, T>) };
+        let mut state = inner.state.try_borrow_mut().ok()?;
+        Some(f(&mut state))
+    }
+
+    /// Call `f` with our state, see [`Provider::with`]
+    pub fn with_state<U, F>(&self, f: F) -> Option<U>
+    where
+        F: FnOnce(&mut T) -> U,
+    {
+        // Safety: We're alive
+        unsafe { Self::with(self.as_ptr(), f) }
+    }
+
+    /// Install the protocol `Proto` on `handle`, or a new handle if [`None`],
+    /// returning the handle.
+    ///
+    /// Installed protocols must live forever, so this leaks `self`,
+    /// unless installing fails.
+    pub fn install<'boot, Proto>(
+        self,
+        boot: &BootServices,
+        handle: Option<EfiHandle>,
+    ) -> Result<EfiHandle>
+    where
+        Proto: Protocol<'boot, Raw = R>,
+    {
+        // Safety: `as_ptr` is a valid `R`, and lives forever once leaked
+        let handle = unsafe { boot.install_protocol_on::<Proto>(handle, self.as_ptr())? };
+        self.leak();
+        Ok(handle)
+    }
+
+    /// Leak this provider, so it lives forever, returning its table
+    pub fn leak(self) -> *mut R {
+        let ptr = self.as_ptr();
+        forget(self);
+        ptr
+    }
+}
+
+impl<R: 'static, T: 'static> Drop for Provider<R, T> {
+    fn drop(&mut self) {
+        // Safety: `inner` was leaked from a `Box` in `new`
+        drop(unsafe { Box::from_raw(self.inner.as_ptr()) });
+    }
+}
+
+impl<R: 'static, T: 'static> fmt::Debug for Provider<R, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Provider")
+            .field("raw", &self.as_ptr())
+            .finish_non_exhaustive()
+    }
+}
@@ -2,25 +2,32 @@
 
 use alloc::{string::String, vec::Vec};
 use core::{
+    convert::Infallible,
     ffi::c_void,
     iter::from_fn,
     marker::PhantomData,
-    mem::{size_of, transmute},
+    mem::{size_of, transmute, MaybeUninit},
     ptr::{null_mut, NonNull},
     slice::from_raw_parts,
     time::Duration,
 };
 
-pub use nuefi_core::table::config;
+use nuefi_core::base::TaskPriorityLevel;
+pub use nuefi_core::{
+    base::Event,
+    table::{config, ResetType, Time, TimeCapabilities, TimerDelay},
+};
 
 use crate::{
     error::{Result, Status},
     get_image_handle,
-    mem::MemoryType,
+    mem::{MemoryMap, MemoryType},
     proto::{
         self,
-        console::SimpleTextOutput,
+        console::{raw::RawSimpleTextOutput, Consoles, SimpleTextInput, SimpleTextOutput},
         device_path::{raw::RawDevicePath, DevicePath},
+        loaded_image::LoadedImage,
+        Entity,
         Guid,
         Protocol,
         Scope,
@@ -35,7 +42,9 @@ pub mod raw {
     pub use nuefi_core::table::{
         boot_fn::*,
         config::ConfigurationTable as RawConfigurationTable,
+        run_fn::*,
         BootServices as RawBootServices,
+        EventType,
         Header,
         LocateSearch,
         Revision,
@@ -43,6 +52,7 @@ pub mod raw {
         SystemTable as RawSystemTable,
     };
 }
+use config::RuntimeServicesSupported;
 use raw::*;
 
 interface!(
@@ -327,14 +337,35 @@ impl<'table> BootServices<'table> {
         handle: EfiHandle,
         interface: *mut Proto::Raw,
     ) -> Result<()> {
+        self.install_protocol_on::<Proto>(Some(handle), interface)
+            .map(|_| ())
+    }
+
+    /// Install a `Protocol` on `handle`, or a new handle if [`None`],
+    /// returning the handle it was installed on
+    ///
+    /// # Safety
+    ///
+    /// - Pointer must be a valid instance of [proto::Protocol]
+    /// - Pointer must live long enough
+    pub unsafe fn install_protocol_on<'boot, Proto: proto::Protocol<'boot>>(
+        &self,
+        handle: Option<EfiHandle>,
+        interface: *mut Proto::Raw,
+    ) -> Result<EfiHandle> {
         let mut guid = Proto::GUID;
-        let mut h = handle;
+        let mut h = handle.unwrap_or(EfiHandle::null());
         let ipi = self
             .interface()
             .install_protocol_interface
             .ok_or(Status::UNSUPPORTED)?;
 
-        (ipi)(&mut h, &mut guid, 0, interface as *mut c_void).into()
+        let ret = (ipi)(&mut h, &mut guid, 0, interface as *mut c_void);
+        if ret.is_success() {
+            Ok(h)
+        } else {
+            Err(ret.into())
+        }
     }
 
     /// Query `handle` to determine if it supports `Protocol`
@@ -632,6 +663,56 @@ impl<'table> BootServices<'table> {
             .map(|n| n.cast())
     }
 
+    /// Get the current UEFI memory map
+    ///
+    /// The returned [`MemoryMap::key`] is only valid until the next memory
+    /// allocation.
+    pub fn memory_map(&self) -> Result<MemoryMap> {
+        let gmm = self.interface().get_memory_map.ok_or(Status::UNSUPPORTED)?;
+        let mut size = 0;
+        let mut key = 0;
+        let mut desc_size = 0;
+        let mut desc_ver = 0;
+
+        // Safety: Null map with a size of 0 is valid to query the size
+        let ret = unsafe {
+            (gmm)(
+                &mut size,
+                null_mut(),
+                &mut key,
+                &mut desc_size,
+                &mut desc_ver,
+            )
+        };
+        if ret != Status::BUFFER_TOO_SMALL {
+            return Err(ret.into());
+        }
+
+        loop {
+            // Allocating the buffer can grow the map, leave room for that.
+            size += desc_size * 4;
+            let mut buf: Vec<u64> = alloc::vec![0; size.div_ceil(size_of::<u64>())];
+
+            // Safety: `buf` is valid for `size` bytes and suitably aligned
+            let ret = unsafe {
+                (gmm)(
+                    &mut size,
+                    buf.as_mut_ptr().cast(),
+                    &mut key,
+                    &mut desc_size,
+                    &mut desc_ver,
+                )
+            };
+            if ret == Status::BUFFER_TOO_SMALL {
+                continue;
+            } else if ret.is_success() {
+                let map = MemoryMap::new(buf, size, key, desc_size, desc_ver);
+                return Ok(map.ok_or(Status::INVALID_PARAMETER)?);
+            }
+            return Err(ret.into());
+        }
+    }
+
     /// Free memory allocated by [BootServices::allocate_pool]
     ///
     /// # Safety
@@ -645,13 +726,428 @@ impl<'table> BootServices<'table> {
     }
 }
 
+/// Configuration tables
+impl<'table> BootServices<'table> {
+    /// Add, update, or remove with a null `table`,
+    /// the configuration table identified by `guid`
+    ///
+    /// # Safety
+    ///
+    /// - `table` must be valid for the table identified by `guid`
+    /// - `table` must live as long as the configuration table is installed,
+    ///   which usually means it must be allocated from pool.
+    pub unsafe fn install_configuration_table(
+        &self,
+        guid: &Guid,
+        table: *mut c_void,
+    ) -> Result<()> {
+        let ict = self
+            .interface()
+            .install_configuration_table
+            .ok_or(Status::UNSUPPORTED)?;
+        let mut guid = *guid;
+        (ict)(&mut guid, table).into()
+    }
+
+    /// Serialize `fdt` and install it as the [`config::DeviceTree`] table,
+    /// replacing any existing one.
+    ///
+    /// The blob is allocated from [`MemoryType::ACPI_RECLAIM`] as the
+    /// UEFI spec requires. The previous blob is not freed.
+    pub fn install_device_tree(&self, fdt: &config::fdt::OwnedFdt) -> Result<()> {
+        let blob = fdt.to_bytes();
+        let ptr = self.allocate_pool(MemoryType::ACPI_RECLAIM, blob.len())?;
+        let ptr = ptr.as_ptr();
+
+        // Safety:
+        // - `ptr` was just allocated for `blob.len()` bytes
+        // - `blob` is a valid DTB, and pool memory lives forever
+        unsafe {
+            ptr.cast::<u8>()
+                .copy_from_nonoverlapping(blob.as_ptr(), blob.len());
+            match self.install_configuration_table(&config::DeviceTree::GUID, ptr) {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    let _ = self.free_pool(ptr);
+                    Err(e)
+                }
+            }
+        }
+    }
+}
+
 /// Event/Timer/Task Priority
-impl<'table> BootServices<'table> {}
+impl<'table> BootServices<'table> {
+    /// Create a timer [`Event`], with no notification function
+    ///
+    /// Start it with [`BootServices::set_timer`], and close it with
+    /// [`BootServices::close_event`] when done.
+    pub fn create_timer(&self) -> Result<Event> {
+        let create = self.interface().create_event.ok_or(Status::UNSUPPORTED)?;
+        let mut event = MaybeUninit::uninit();
 
-interface!(
-    /// The UEFI Runtime Services
-    RuntimeServices(RawRuntimeServices),
-);
+        // Safety: Construction ensures safety
+        let ret = unsafe {
+            (create)(
+                EventType::TIMER,
+                TaskPriorityLevel::APPLICATION,
+                None,
+                null_mut(),
+                event.as_mut_ptr(),
+            )
+        };
+        if ret.is_success() {
+            // Safety: Firmware initialized `event` on success
+            return Ok(unsafe { event.assume_init() });
+        }
+        Err(ret.into())
+    }
+
+    /// Signal the timer `event` according to `ty`, after `trigger`
+    ///
+    /// Any previous setting is cancelled.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::INVALID_PARAMETER`] if `trigger` does not fit in 100ns units
+    pub fn set_timer(&self, event: Event, ty: TimerDelay, trigger: Duration) -> Result<()> {
+        let trigger =
+            u64::try_from(trigger.as_nanos() / 100).map_err(|_| Status::INVALID_PARAMETER)?;
+        let set = self.interface().set_timer.ok_or(Status::UNSUPPORTED)?;
+
+        // Safety: Construction ensures safety
+        unsafe { (set)(event, ty, trigger) }.into()
+    }
+
+    /// Close `event`, which must not be used again
+    pub fn close_event(&self, event: Event) -> Result<()> {
+        let close = self.interface().close_event.ok_or(Status::UNSUPPORTED)?;
+
+        // Safety: Construction ensures safety
+        unsafe { (close)(event) }.into()
+    }
+
+    /// Wait until any of `events` is signaled, returning its index
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::INVALID_PARAMETER`] if `events` is empty
+    /// - [`Status::UNSUPPORTED`] if not called at `TPL_APPLICATION`
+    pub fn wait_for_event(&self, events: &[Event]) -> Result<usize> {
+        if events.is_empty() {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let wait = self.interface().wait_for_event.ok_or(Status::UNSUPPORTED)?;
+        let mut index = 0;
+
+        // Safety: Construction ensures safety. `events` is valid for its length
+        let ret = unsafe { (wait)(events.len(), events.as_ptr(), &mut index) };
+        if ret.is_success() {
+            return Ok(index);
+        }
+        Err(ret.into())
+    }
+
+    /// Whether `event` is signaled, without waiting
+    ///
+    /// A signaled event is reset to the waiting state
+    pub fn check_event(&self, event: Event) -> Result<bool> {
+        let check = self.interface().check_event.ok_or(Status::UNSUPPORTED)?;
+
+        // Safety: Construction ensures safety
+        let ret = unsafe { (check)(event) };
+        if ret.is_success() {
+            Ok(true)
+        } else if ret == Status::NOT_READY {
+            Ok(false)
+        } else {
+            Err(ret.into())
+        }
+    }
+}
+
+/// The UEFI Runtime Services
+///
+/// After ExitBootServices, every call is checked against the
+/// [`RuntimeServicesSupported`] mask from the [`config::RuntimeProperties`]
+/// table, and fails with [`Status::UNSUPPORTED`] without calling firmware if
+/// it is not supported.
+///
+/// During boot services every service may be called.
+#[derive(Debug)]
+pub struct RuntimeServices<'table> {
+    /// Lifetime of this interface is conceptually tied to the
+    /// [`crate::SystemTable`]
+    interface: *mut RawRuntimeServices,
+
+    /// Services that may be called
+    supported: RuntimeServicesSupported,
+
+    phantom: PhantomData<&'table mut RawRuntimeServices>,
+}
+
+// Internal
+impl<'table> RuntimeServices<'table> {
+    /// Create a new interface
+    ///
+    /// # Safety
+    ///
+    /// - `interface` must be a valid non-null pointer
+    /// - Only called from [crate::SystemTable]
+    pub(crate) unsafe fn new(
+        interface: *mut RawRuntimeServices,
+        supported: RuntimeServicesSupported,
+    ) -> Self {
+        Self {
+            interface,
+            supported,
+            phantom: PhantomData,
+        }
+    }
+
+    fn interface(&self) -> &RawRuntimeServices {
+        // Safety: Ensured valid in construction.
+        unsafe { &*self.interface }
+    }
+
+    /// Fail with [`Status::UNSUPPORTED`] if `service` is not supported
+    fn check(&self, service: RuntimeServicesSupported) -> Result<()> {
+        if self.supported.contains(service) {
+            Ok(())
+        } else {
+            Err(Status::UNSUPPORTED.into())
+        }
+    }
+
+    /// Raw pointer to the runtime services table
+    ///
+    /// It is your responsibility to use it correctly.
+    pub fn as_ptr(&self) -> *mut RawRuntimeServices {
+        self.interface
+    }
+
+    /// Services that may currently be called
+    ///
+    /// This is every service during boot services.
+    pub fn supported(&self) -> RuntimeServicesSupported {
+        self.supported
+    }
+}
+
+/// Time
+impl<'table> RuntimeServices<'table> {
+    /// The current time
+    pub fn time(&self) -> Result<Time> {
+        self.check(RuntimeServicesSupported::GET_TIME)?;
+        let gt = self.interface().get_time.ok_or(Status::UNSUPPORTED)?;
+        let mut time = Time::default();
+
+        // Safety: Construction ensures safety. Capabilities are optional.
+        let ret = unsafe { (gt)(&mut time, null_mut()) };
+        if ret.is_success() {
+            return Ok(time);
+        }
+        Err(ret.into())
+    }
+
+    /// Capabilities of the real time clock
+    pub fn time_capabilities(&self) -> Result<TimeCapabilities> {
+        self.check(RuntimeServicesSupported::GET_TIME)?;
+        let gt = self.interface().get_time.ok_or(Status::UNSUPPORTED)?;
+        let mut time = Time::default();
+        let mut caps = TimeCapabilities::default();
+
+        // Safety: Construction ensures safety
+        let ret = unsafe { (gt)(&mut time, &mut caps) };
+        if ret.is_success() {
+            return Ok(caps);
+        }
+        Err(ret.into())
+    }
+
+    /// Set the current time
+    pub fn set_time(&self, time: &Time) -> Result<()> {
+        self.check(RuntimeServicesSupported::SET_TIME)?;
+        let st = self.interface().set_time.ok_or(Status::UNSUPPORTED)?;
+        let mut time = *time;
+
+        // Safety: Construction ensures safety
+        unsafe { (st)(&mut time) }.into()
+    }
+}
+
+/// Variables
+impl<'table> RuntimeServices<'table> {
+    /// Get the variable `name` from `vendor`, and its attributes
+    pub fn variable(&self, name: &str, vendor: &Guid) -> Result<(Vec<u8>, u32)> {
+        self.check(RuntimeServicesSupported::GET_VARIABLE)?;
+        let gv = self.interface().get_variable.ok_or(Status::UNSUPPORTED)?;
+        let name = to_utf16(name)?;
+        let mut attributes = 0;
+        let mut size = 0;
+        let mut data: Vec<u8> = Vec::new();
+
+        loop {
+            // Safety:
+            // - `name` is nul terminated
+            // - `data` is valid for `size` bytes
+            let ret = unsafe {
+                (gv)(
+                    name.as_ptr(),
+                    vendor,
+                    &mut attributes,
+                    &mut size,
+                    data.as_mut_ptr().cast(),
+                )
+            };
+            if ret == Status::BUFFER_TOO_SMALL {
+                data.resize(size, 0);
+                continue;
+            } else if ret.is_success() {
+                data.truncate(size);
+                return Ok((data, attributes));
+            }
+            return Err(ret.into());
+        }
+    }
+
+    /// Set the variable `name` from `vendor` to `data`, with `attributes`.
+    ///
+    /// Empty `data` deletes the variable
+    pub fn set_variable(
+        &self,
+        name: &str,
+        vendor: &Guid,
+        attributes: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        self.check(RuntimeServicesSupported::SET_VARIABLE)?;
+        let sv = self.interface().set_variable.ok_or(Status::UNSUPPORTED)?;
+        let name = to_utf16(name)?;
+
+        // Safety:
+        // - `name` is nul terminated
+        // - `data` is valid for its length
+        unsafe {
+            (sv)(
+                name.as_ptr(),
+                vendor,
+                attributes,
+                data.len(),
+                data.as_ptr().cast(),
+            )
+        }
+        .into()
+    }
+
+    /// The names and vendors of all variables
+    pub fn variable_names(&self) -> Result<Vec<(String, Guid)>> {
+        self.check(RuntimeServicesSupported::GET_NEXT_VARIABLE_NAME)?;
+        let gn = self
+            .interface()
+            .get_next_variable_name
+            .ok_or(Status::UNSUPPORTED)?;
+        let mut out = Vec::new();
+        // Start with an empty string
+        let mut name: Vec<u16> = alloc::vec![0; 64];
+        let mut vendor = Guid::new([0; 16]);
+
+        loop {
+            let mut size = name.len() * size_of::<u16>();
+            // Safety: `name` is valid for `size` bytes, and nul terminated.
+            let ret = unsafe { (gn)(&mut size, name.as_mut_ptr(), &mut vendor) };
+            if ret == Status::BUFFER_TOO_SMALL {
+                name.resize(size.div_ceil(size_of::<u16>()), 0);
+                continue;
+            } else if ret == Status::NOT_FOUND {
+                return Ok(out);
+            } else if ret.is_success() {
+                let len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+                out.push((String::from_utf16_lossy(&name[..len]), vendor));
+                continue;
+            }
+            return Err(ret.into());
+        }
+    }
+
+    /// Information about variable storage for variables with `attributes`
+    pub fn query_variable_info(&self, attributes: u32) -> Result<VariableStorageInfo> {
+        self.check(RuntimeServicesSupported::QUERY_VARIABLE_INFO)?;
+        let qv = self
+            .interface()
+            .query_variable_info
+            .ok_or(Status::UNSUPPORTED)?;
+        let mut info = VariableStorageInfo::default();
+
+        // Safety: Construction ensures safety
+        let ret = unsafe {
+            (qv)(
+                attributes,
+                &mut info.max_storage,
+                &mut info.remaining_storage,
+                &mut info.max_variable,
+            )
+        };
+        if ret.is_success() {
+            return Ok(info);
+        }
+        Err(ret.into())
+    }
+}
+
+/// Miscellaneous
+impl<'table> RuntimeServices<'table> {
+    /// The high 32-bits of the platforms monotonic counter
+    pub fn next_high_monotonic_count(&self) -> Result<u32> {
+        self.check(RuntimeServicesSupported::GET_NEXT_HIGH_MONOTONIC_COUNT)?;
+        let gn = self
+            .interface()
+            .get_next_high_monotonic_count
+            .ok_or(Status::UNSUPPORTED)?;
+        let mut out = 0;
+
+        // Safety: Construction ensures safety
+        let ret = unsafe { (gn)(&mut out) };
+        if ret.is_success() {
+            return Ok(out);
+        }
+        Err(ret.into())
+    }
+
+    /// Reset the system, with `status` as the reason.
+    ///
+    /// This only returns if resetting is not supported
+    pub fn reset(&self, ty: ResetType, status: Status) -> Result<Infallible> {
+        self.check(RuntimeServicesSupported::RESET_SYSTEM)?;
+        let rs = self.interface().reset_system.ok_or(Status::UNSUPPORTED)?;
+
+        // Safety: Construction ensures safety
+        unsafe { (rs)(ty, status, 0, null_mut()) }
+    }
+}
+
+/// Variable storage information
+///
+/// See [`RuntimeServices::query_variable_info`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VariableStorageInfo {
+    /// Maximum size of storage for variables
+    pub max_storage: u64,
+
+    /// Remaining size of storage for variables
+    pub remaining_storage: u64,
+
+    /// Maximum size of an individual variable
+    pub max_variable: u64,
+}
+
+/// Nul terminated UTF-16 `s`
+fn to_utf16(s: &str) -> Result<Vec<u16>> {
+    if s.contains('\0') {
+        return Err(Status::INVALID_PARAMETER.into());
+    }
+    Ok(s.encode_utf16().chain([0]).collect())
+}
 
 /// Type marker for [`SystemTable`] representing before ExitBootServices is
 /// called
@@ -751,6 +1247,28 @@ impl<T> SystemTable<T> {
         // - Remapping is not currently implemented, so it cannot safely be done.
         unsafe { &*self.table }
     }
+
+    /// The UEFI Runtime services, limited to `supported`
+    fn runtime_with(&self, supported: RuntimeServicesSupported) -> RuntimeServices<'_> {
+        let ptr = self.table().runtime_services;
+        assert!(!ptr.is_null(), "runtime_services handle was null");
+        // Safety: Construction ensures safety.
+        unsafe { RuntimeServices::new(ptr, supported) }
+    }
+
+    /// Iterator over the raw UEFI Configuration tables
+    fn raw_config_tables(&self) -> impl Iterator<Item = config::GenericConfig<'_>> + '_ {
+        let data = self.table().configuration_table;
+        let len = self.table().number_of_table_entries;
+        assert!(!data.is_null(), "UEFI Configuration table pointer was null");
+
+        // Safety: The pointer is valid for this many elements according
+        // to the UEFI spec
+        // The returned lifetime will be tied to `self`, which is valid.
+        let tables = unsafe { from_raw_parts(data, len).iter().copied() };
+
+        tables.map(config::GenericConfig::new)
+    }
 }
 
 // Internal, all
@@ -798,6 +1316,16 @@ impl SystemTable<Boot> {
         )
     }
 
+    /// Input from stdin.
+    ///
+    /// This is only valid for as long as the SystemTable is
+    pub fn stdin(&self) -> SimpleTextInput<'_> {
+        let ptr = self.table().con_in;
+        assert!(!ptr.is_null(), "con_in handle was null");
+        // Safety: Construction ensures safety.
+        unsafe { SimpleTextInput::new(ptr.cast()) }
+    }
+
     /// Output on stdout.
     ///
     /// This is only valid for as long as the SystemTable is
@@ -808,6 +1336,73 @@ impl SystemTable<Boot> {
         unsafe { SimpleTextOutput::new(ptr.cast()) }
     }
 
+    /// Replace [`SystemTable::stdout`] with `out`, which is installed on
+    /// `handle`, updating the table CRC.
+    ///
+    /// Such as with a
+    /// [`GraphicsConsole`][crate::graphics::console::GraphicsConsole].
+    ///
+    /// # Safety
+    ///
+    /// - `out` must be a valid [`SimpleTextOutput`] installed on `handle`
+    /// - `out` must live for as long as the system table does
+    /// - Nothing may be using the old [`SystemTable::stdout`]
+    pub unsafe fn set_stdout(&self, handle: EfiHandle, out: *mut RawSimpleTextOutput) {
+        let table = self.table;
+        // Safety:
+        // - Construction ensures `table` is valid
+        // - Caller ensures `out` is valid
+        unsafe {
+            (*table).console_out_handle = handle;
+            (*table).con_out = out.cast();
+            Header::update_crc(table.cast());
+        }
+    }
+
+    /// Every console output device, such as serial and GOP consoles.
+    ///
+    /// Unlike [`SystemTable::stdout`], which is whatever the firmware chose,
+    /// output to these is broadcast to every device.
+    ///
+    /// Only handles with a [`DevicePath`] are used, which excludes the
+    /// firmware's virtual splitter consoles so output is not duplicated.
+    /// If there are none, this falls back to [`SystemTable::stdout`].
+    ///
+    /// This is only valid for as long as the SystemTable is
+    pub fn consoles(&self) -> Result<Consoles<'_>> {
+        let boot = self.boot();
+        let handles = match boot.handles_for_protocol::<SimpleTextOutput>() {
+            Ok(h) => h,
+            Err(e) if e.status() == Status::NOT_FOUND || e.status() == Status::UNSUPPORTED => {
+                Vec::new()
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut outputs: Vec<(EfiHandle, SimpleTextOutput<'_>)> = Vec::new();
+        for handle in handles {
+            // Safety: Neither protocol is used beyond this loop, except
+            // by pointer, which firmware keeps valid during boot services.
+            unsafe {
+                if !matches!(boot.handle_protocol::<DevicePath>(handle), Ok(Some(_))) {
+                    continue;
+                }
+                let ptr = match boot.handle_protocol::<SimpleTextOutput>(handle) {
+                    Ok(Some(out)) => out.as_ptr(),
+                    _ => continue,
+                };
+                if outputs.iter().all(|(_, o)| o.as_ptr() != ptr) {
+                    outputs.push((handle, SimpleTextOutput::new(ptr)));
+                }
+            }
+        }
+
+        if outputs.is_empty() {
+            outputs.push((self.table().console_out_handle, self.stdout()));
+        }
+        Ok(Consoles::new(outputs))
+    }
+
     /// Output on stderr.
     ///
     /// This is only valid for as long as the SystemTable is
@@ -828,20 +1423,58 @@ impl SystemTable<Boot> {
         unsafe { BootServices::new(ptr) }
     }
 
+    /// Reference to the UEFI Runtime services.
+    ///
+    /// Every service may be called during boot services.
+    /// The [`config::RuntimeProperties`] table only describes support after
+    /// ExitBootServices, see [`SystemTable<Runtime>::runtime`].
+    pub fn runtime(&self) -> RuntimeServices<'_> {
+        self.runtime_with(RuntimeServicesSupported::all())
+    }
+
+    /// Every loaded image, from the [`config::DebugImageInfo`] table
+    ///
+    /// Returns [`Status::NOT_FOUND`] if the table doesn't exist,
+    /// and [`Status::NOT_READY`] if firmware is updating it.
+    ///
+    /// # Example
+    ///
+    /// Print a gdb command to load the symbols of every image
+    ///
+    /// ```rust,no_run
+    /// # use nuefi::{SystemTable, Boot, error::Result};
+    /// # fn example(table: SystemTable<Boot>) -> Result<()> {
+    /// for image in table.debug_images()? {
+    ///     let name = match image.file_path() {
+    ///         Some(p) => p.to_string_lossy()?,
+    ///         None => "unknown".into(),
+    ///     };
+    ///     log::info!("{}", image.gdb_add_symbol_file(&name, 0));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn debug_images(&self) -> Result<Vec<LoadedImage<'_>>> {
+        let table = self
+            .config_table::<config::DebugImageInfo>()
+            .ok_or(Status::NOT_FOUND)?;
+        let table = table.images();
+        if table.is_updating() {
+            return Err(Status::NOT_READY.into());
+        }
+        Ok(table
+            .images()
+            .filter(|i| !i.loaded_image().is_null())
+            // Safety: Non-null entries point to a valid `LoadedImage`
+            .map(|i| unsafe { LoadedImage::new(i.loaded_image().cast()) })
+            .collect())
+    }
+
     /// Iterator over UEFI Configuration tables
     ///
     /// See [`config`] and [`config::GenericConfig`] for details
     pub fn config_tables(&self) -> impl Iterator<Item = config::GenericConfig<'_>> + '_ {
-        let data = self.table().configuration_table;
-        let len = self.table().number_of_table_entries;
-        assert!(!data.is_null(), "UEFI Configuration table pointer was null");
-
-        // Safety: The pointer is valid for this many elements according
-        // to the UEFI spec
-        // The returned lifetime will be tied to `self`, which is valid.
-        let tables = unsafe { from_raw_parts(data, len).iter().copied() };
-
-        tables.map(config::GenericConfig::new)
+        self.raw_config_tables()
     }
 
     /// Get the configuration table specified by `T`, or [`None`]
@@ -856,3 +1489,22 @@ impl SystemTable<Boot> {
             .and_then(|t| t.as_table::<T>())
     }
 }
+
+/// Available after ExitBootServices
+impl SystemTable<Runtime> {
+    /// Reference to the UEFI Runtime services.
+    ///
+    /// Calls are limited to those firmware reports as supported by the
+    /// [`config::RuntimeProperties`] table, if it exists.
+    pub fn runtime(&self) -> RuntimeServices<'_> {
+        // The RuntimeProperties table is in runtime memory,
+        // so is still valid after ExitBootServices
+        let supported = self
+            .raw_config_tables()
+            .find(|t| t.guid() == config::RuntimeProperties::GUID)
+            .and_then(|t| t.as_table::<config::RuntimeProperties>())
+            .map(|t| t.supported())
+            .unwrap_or(RuntimeServicesSupported::all());
+        self.runtime_with(supported)
+    }
+}
@@ -0,0 +1,1302 @@
+//! Text mode user interface widgets
+//!
+//! Menus, message boxes, yes/no prompts, progress bars and a line editor,
+//! drawn on any [`Console`] and driven by any [`Input`].
+//!
+//! [`SimpleTextOutput`] and [`SimpleTextInput`] implement these for real
+//! firmware, while [`Buffer`] and [`Script`] allow rendering and testing
+//! widgets on the host.
+//!
+//! Layout adapts to [`Console::size`]. The bottom row is never drawn to,
+//! because writing its last cell scrolls many firmware consoles.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use nuefi::{entry, EfiHandle, SystemTable, Boot, error};
+//! use nuefi::tui::Menu;
+//! use core::time::Duration;
+//!
+//! #[entry()]
+//! fn efi_main(handle: EfiHandle, table: SystemTable<Boot>) -> error::Result<()> {
+//!     let mut stdout = table.stdout();
+//!     let mut stdin = table.stdin();
+//!     let choice = Menu::new("Boot", &["Linux", "Windows", "Firmware Setup"])
+//!         .timeout(Duration::from_secs(5))
+//!         .run(&mut stdout, &mut stdin)?;
+//!     Ok(())
+//! }
+//! #
+//! # fn main() {}
+//! ```
+use alloc::{collections::VecDeque, string::String, vec, vec::Vec};
+use core::{fmt::Write, iter::repeat_n, time::Duration};
+
+use crate::{
+    error::{Result, Status},
+    get_boot_table,
+    proto::console::{
+        Consoles,
+        Key,
+        ScanCode,
+        SimpleTextInput,
+        SimpleTextInputEx,
+        SimpleTextOutput,
+        TextBackground,
+        TextForeground,
+    },
+    table::{BootServices, Event, TimerDelay},
+};
+
+/// Text colors, foreground and background
+pub type Attr = (TextForeground, TextBackground);
+
+/// A text console widgets can draw on
+pub trait Console {
+    /// Size in (Cols, Rows)
+    fn size(&self) -> (usize, usize);
+
+    /// Move the cursor to column `col` and row `row`
+    fn set_cursor(&mut self, col: usize, row: usize) -> Result<()>;
+
+    /// Use `attr` for following writes and clears
+    fn set_attributes(&mut self, attr: Attr) -> Result<()>;
+
+    /// Write `s` at the cursor, advancing it
+    fn write(&mut self, s: &str) -> Result<()>;
+
+    /// Clear the console with the current attributes
+    fn clear(&mut self) -> Result<()>;
+
+    /// Show or hide the cursor
+    fn show_cursor(&mut self, visible: bool) -> Result<()>;
+}
+
+/// A source of keys for widgets
+pub trait Input {
+    /// The next [`Key`], waiting at most `timeout`, or forever if [`None`]
+    ///
+    /// Returns [`None`] if `timeout` passed without a key.
+    fn key(&mut self, timeout: Option<Duration>) -> Result<Option<Key>>;
+}
+
+impl<'table> Console for SimpleTextOutput<'table> {
+    fn size(&self) -> (usize, usize) {
+        self.mode().map(|m| m.size()).unwrap_or((80, 25))
+    }
+
+    fn set_cursor(&mut self, col: usize, row: usize) -> Result<()> {
+        SimpleTextOutput::set_cursor(self, col, row)
+    }
+
+    fn set_attributes(&mut self, attr: Attr) -> Result<()> {
+        SimpleTextOutput::set_attributes(self, attr.0, attr.1)
+    }
+
+    fn write(&mut self, s: &str) -> Result<()> {
+        self.write_str(s).map_err(|_| Status::DEVICE_ERROR.into())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        SimpleTextOutput::clear(self)
+    }
+
+    fn show_cursor(&mut self, visible: bool) -> Result<()> {
+        if visible {
+            self.enable_cursor()
+        } else {
+            self.disable_cursor()
+        }
+    }
+}
+
+impl<'table> Console for Consoles<'table> {
+    fn size(&self) -> (usize, usize) {
+        Consoles::size(self)
+    }
+
+    fn set_cursor(&mut self, col: usize, row: usize) -> Result<()> {
+        Consoles::set_cursor(self, col, row)
+    }
+
+    fn set_attributes(&mut self, attr: Attr) -> Result<()> {
+        Consoles::set_attributes(self, attr.0, attr.1)
+    }
+
+    fn write(&mut self, s: &str) -> Result<()> {
+        self.write_str(s).map_err(|_| Status::DEVICE_ERROR.into())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        Consoles::clear(self)
+    }
+
+    fn show_cursor(&mut self, visible: bool) -> Result<()> {
+        if visible {
+            self.enable_cursor()
+        } else {
+            self.disable_cursor()
+        }
+    }
+}
+
+/// Wait on `wait` until `read` returns a key or `timeout` passes
+///
+/// The timeout is a timer event waited on alongside `wait`.
+fn wait_key(
+    boot: &BootServices,
+    wait: Event,
+    timeout: Option<Duration>,
+    mut read: impl FnMut() -> Result<Option<Key>>,
+) -> Result<Option<Key>> {
+    let Some(timeout) = timeout else {
+        loop {
+            boot.wait_for_event(&[wait])?;
+            if let Some(key) = read()? {
+                return Ok(Some(key));
+            }
+        }
+    };
+    let timer = boot.create_timer()?;
+    let mut wait_timer = || {
+        boot.set_timer(timer, TimerDelay::RELATIVE, timeout)?;
+        loop {
+            if boot.wait_for_event(&[wait, timer])? == 1 {
+                return Ok(None);
+            }
+            if let Some(key) = read()? {
+                return Ok(Some(key));
+            }
+        }
+    };
+    let ret = wait_timer();
+    boot.close_event(timer)?;
+    ret
+}
+
+impl<'table> Input for SimpleTextInput<'table> {
+    fn key(&mut self, timeout: Option<Duration>) -> Result<Option<Key>> {
+        let table = get_boot_table().ok_or(Status::UNSUPPORTED)?;
+        wait_key(&table.boot(), self.wait_for_key(), timeout, || {
+            self.read_key_stroke()
+        })
+    }
+}
+
+impl<'table> Input for SimpleTextInputEx<'table> {
+    fn key(&mut self, timeout: Option<Duration>) -> Result<Option<Key>> {
+        let table = get_boot_table().ok_or(Status::UNSUPPORTED)?;
+        // Partial keystrokes have no key, skip them
+        wait_key(&table.boot(), self.wait_for_key(), timeout, || {
+            Ok(self.read_key_stroke()?.and_then(|k| k.key))
+        })
+    }
+}
+
+/// A rectangle on a [`Console`], in character cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub col: usize,
+    pub row: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    /// A `width` by `height` rectangle centered on a console of `size`,
+    /// shrunk to fit above the bottom row
+    pub fn centered(size: (usize, usize), width: usize, height: usize) -> Self {
+        let rows = size.1.saturating_sub(1);
+        let width = width.min(size.0);
+        let height = height.min(rows);
+        Self {
+            col: (size.0 - width) / 2,
+            row: (rows - height) / 2,
+            width,
+            height,
+        }
+    }
+}
+
+/// Colors used by widgets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Behind full screen widgets
+    pub screen: Attr,
+
+    /// Window frames and text
+    pub window: Attr,
+
+    /// The selected menu item or button
+    pub selected: Attr,
+
+    /// Line editor input fields
+    pub field: Attr,
+}
+
+impl Theme {
+    pub const fn new() -> Self {
+        Self {
+            screen: (TextForeground::LIGHT_GRAY, TextBackground::BLACK),
+            window: (TextForeground::WHITE, TextBackground::BLUE),
+            selected: (TextForeground::BLACK, TextBackground::LIGHT_GRAY),
+            field: (TextForeground::WHITE, TextBackground::BLACK),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Write `s` at (`col`, `row`), truncated to the console width
+fn put<C: Console + ?Sized>(
+    con: &mut C,
+    col: usize,
+    row: usize,
+    s: &str,
+    attr: Attr,
+) -> Result<()> {
+    let (cols, rows) = con.size();
+    if col >= cols || row + 1 >= rows {
+        return Ok(());
+    }
+    let s: String = s.chars().take(cols - col).collect();
+    con.set_cursor(col, row)?;
+    con.set_attributes(attr)?;
+    con.write(&s)
+}
+
+/// `s` padded with spaces or truncated to exactly `width` characters
+fn pad(s: &str, width: usize) -> String {
+    let mut out: String = s.chars().take(width).collect();
+    let len = out.chars().count();
+    out.extend(repeat_n(' ', width - len));
+    out
+}
+
+/// `s` centered in `width` characters
+fn center(s: &str, width: usize) -> String {
+    let len = s.chars().count().min(width);
+    let mut out = pad("", (width - len) / 2);
+    out.push_str(s);
+    pad(&out, width)
+}
+
+/// Draw a filled frame around `rect`, with `title` in the top border
+fn frame<C: Console + ?Sized>(con: &mut C, rect: Rect, title: &str, attr: Attr) -> Result<()> {
+    if rect.width < 2 || rect.height < 2 {
+        return Ok(());
+    }
+    let inner = rect.width - 2;
+    let mut top = String::from("┌");
+    let title: String = title.chars().take(inner.saturating_sub(2)).collect();
+    let title = if title.is_empty() {
+        title
+    } else {
+        alloc::format!(" {title} ")
+    };
+    top.push_str(&title);
+    top.extend(repeat_n('─', inner - title.chars().count()));
+    top.push('┐');
+    put(con, rect.col, rect.row, &top, attr)?;
+
+    let mut middle = String::from("│");
+    middle.push_str(&pad("", inner));
+    middle.push('│');
+    for row in 1..rect.height - 1 {
+        put(con, rect.col, rect.row + row, &middle, attr)?;
+    }
+
+    let mut bottom = String::from("└");
+    bottom.extend(repeat_n('─', inner));
+    bottom.push('┘');
+    put(con, rect.col, rect.row + rect.height - 1, &bottom, attr)
+}
+
+/// Word wrap `text` to `width` characters
+///
+/// Newlines in `text` are kept, and words longer than `width` are split.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for para in text.split('\n') {
+        let mut line = String::new();
+        let mut len = 0;
+        for word in para.split(' ').filter(|w| !w.is_empty()) {
+            let mut chars: Vec<char> = word.chars().collect();
+            if len != 0 && len + 1 + chars.len() > width {
+                lines.push(core::mem::take(&mut line));
+                len = 0;
+            }
+            if len != 0 {
+                line.push(' ');
+                len += 1;
+            }
+            while len + chars.len() > width {
+                let rest = chars.split_off(width - len);
+                line.extend(chars);
+                lines.push(core::mem::take(&mut line));
+                len = 0;
+                chars = rest;
+            }
+            len += chars.len();
+            line.extend(chars);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Dialog width for `title` and `text` on a console `cols` wide
+fn dialog_width(cols: usize, title: &str, text: &str) -> usize {
+    let longest = text
+        .split('\n')
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0);
+    let want = longest.max(title.chars().count() + 2).max(16) + 4;
+    want.min(cols.saturating_sub(4)).max(8.min(cols))
+}
+
+/// Draw a dialog with wrapped `text` and a row of `buttons`, returning its
+/// [`Rect`]
+fn dialog<C: Console + ?Sized>(
+    con: &mut C,
+    theme: &Theme,
+    title: &str,
+    text: &str,
+    buttons: &[&str],
+    selected: usize,
+) -> Result<Rect> {
+    let size = con.size();
+    let width = dialog_width(size.0, title, text);
+    let inner = width.saturating_sub(4);
+    let lines = wrap(text, inner);
+    let rect = Rect::centered(size, width, lines.len() + 4);
+    frame(con, rect, title, theme.window)?;
+
+    let visible = rect.height.saturating_sub(4);
+    for (i, line) in lines.iter().take(visible).enumerate() {
+        put(
+            con,
+            rect.col + 2,
+            rect.row + 1 + i,
+            &pad(line, inner),
+            theme.window,
+        )?;
+    }
+
+    let total: usize = buttons.iter().map(|b| b.chars().count() + 4).sum::<usize>()
+        + buttons.len().saturating_sub(1) * 2;
+    let mut col = rect.col + (rect.width.saturating_sub(total)) / 2;
+    let row = rect.row + rect.height.saturating_sub(2);
+    for (i, button) in buttons.iter().enumerate() {
+        let attr = if i == selected {
+            theme.selected
+        } else {
+            theme.window
+        };
+        let label = alloc::format!("< {button} >");
+        put(con, col, row, &label, attr)?;
+        col += label.chars().count() + 2;
+    }
+    Ok(rect)
+}
+
+/// A selectable menu, with an optional countdown that selects the default
+///
+/// See the [module documentation][self] for an example.
+#[derive(Debug, Clone)]
+pub struct Menu<'a> {
+    title: &'a str,
+    items: &'a [&'a str],
+    default: usize,
+    timeout: Option<Duration>,
+    theme: Theme,
+}
+
+impl<'a> Menu<'a> {
+    /// A menu titled `title` for `items`
+    pub const fn new(title: &'a str, items: &'a [&'a str]) -> Self {
+        Self {
+            title,
+            items,
+            default: 0,
+            timeout: None,
+            theme: Theme::new(),
+        }
+    }
+
+    /// Initially select item `default`
+    pub const fn default(self, default: usize) -> Self {
+        Self { default, ..self }
+    }
+
+    /// Select the default automatically after `timeout`
+    ///
+    /// Any key press stops the countdown.
+    pub const fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Use `theme`
+    pub const fn theme(self, theme: Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    /// Show the menu until an item is chosen
+    ///
+    /// Returns the index of the chosen item, or [`None`] if Escape was
+    /// pressed.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::INVALID_PARAMETER`] if there are no items
+    pub fn run<C, I>(&self, con: &mut C, input: &mut I) -> Result<Option<usize>>
+    where
+        C: Console + ?Sized,
+        I: Input + ?Sized,
+    {
+        if self.items.is_empty() {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let last = self.items.len() - 1;
+        let mut selected = self.default.min(last);
+        let mut top = 0;
+        let mut remaining = self.timeout.map(|t| t.as_secs().max(1));
+
+        con.show_cursor(false)?;
+        con.set_attributes(self.theme.screen)?;
+        con.clear()?;
+
+        let size = con.size();
+        let longest = self
+            .items
+            .iter()
+            .map(|i| i.chars().count())
+            .max()
+            .unwrap_or(0);
+        let width = longest.max(self.title.chars().count() + 2) + 6;
+        // Leave a row below the frame for the countdown
+        let rect = Rect::centered(
+            (size.0, size.1.saturating_sub(1)),
+            width,
+            self.items.len() + 2,
+        );
+        let visible = rect.height.saturating_sub(2).max(1);
+        let inner = rect.width.saturating_sub(4);
+        frame(con, rect, self.title, self.theme.window)?;
+
+        loop {
+            if selected < top {
+                top = selected;
+            } else if selected >= top + visible {
+                top = selected + 1 - visible;
+            }
+            for (i, item) in self.items.iter().enumerate().skip(top).take(visible) {
+                let attr = if i == selected {
+                    self.theme.selected
+                } else {
+                    self.theme.window
+                };
+                put(
+                    con,
+                    rect.col + 2,
+                    rect.row + 1 + i - top,
+                    &pad(item, inner),
+                    attr,
+                )?;
+            }
+
+            let status = match remaining {
+                Some(secs) => alloc::format!("Automatic selection in {secs}s"),
+                None => String::new(),
+            };
+            put(
+                con,
+                0,
+                rect.row + rect.height,
+                &center(&status, size.0),
+                self.theme.screen,
+            )?;
+
+            let key = match remaining {
+                Some(secs) => match input.key(Some(Duration::from_secs(1)))? {
+                    Some(key) => {
+                        remaining = None;
+                        key
+                    }
+                    None if secs <= 1 => return Ok(Some(selected)),
+                    None => {
+                        remaining = Some(secs - 1);
+                        continue;
+                    }
+                },
+                None => match input.key(None)? {
+                    Some(key) => key,
+                    None => continue,
+                },
+            };
+
+            match key {
+                Key::Scan(ScanCode::Up) => selected = selected.saturating_sub(1),
+                Key::Scan(ScanCode::Down) => selected = (selected + 1).min(last),
+                Key::Scan(ScanCode::Home) => selected = 0,
+                Key::Scan(ScanCode::End) => selected = last,
+                Key::Scan(ScanCode::PageUp) => selected = selected.saturating_sub(visible),
+                Key::Scan(ScanCode::PageDown) => selected = (selected + visible).min(last),
+                Key::Scan(ScanCode::Escape) => return Ok(None),
+                Key::Char('\r' | '\n') => return Ok(Some(selected)),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// A message box, closed by Enter, Space or Escape
+#[derive(Debug, Clone)]
+pub struct MessageBox<'a> {
+    title: &'a str,
+    text: &'a str,
+    theme: Theme,
+}
+
+impl<'a> MessageBox<'a> {
+    /// A message box titled `title` showing `text`
+    pub const fn new(title: &'a str, text: &'a str) -> Self {
+        Self {
+            title,
+            text,
+            theme: Theme::new(),
+        }
+    }
+
+    /// Use `theme`
+    pub const fn theme(self, theme: Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    /// Show the message box until it is dismissed
+    pub fn run<C, I>(&self, con: &mut C, input: &mut I) -> Result<()>
+    where
+        C: Console + ?Sized,
+        I: Input + ?Sized,
+    {
+        con.show_cursor(false)?;
+        dialog(con, &self.theme, self.title, self.text, &["OK"], 0)?;
+        loop {
+            match input.key(None)? {
+                Some(Key::Char('\r' | '\n' | ' ') | Key::Scan(ScanCode::Escape)) => return Ok(()),
+                _ => continue,
+            }
+        }
+    }
+}
+
+/// A yes/no prompt
+#[derive(Debug, Clone)]
+pub struct Confirm<'a> {
+    title: &'a str,
+    text: &'a str,
+    default: bool,
+    theme: Theme,
+}
+
+impl<'a> Confirm<'a> {
+    /// A prompt titled `title` asking `text`, defaulting to no
+    pub const fn new(title: &'a str, text: &'a str) -> Self {
+        Self {
+            title,
+            text,
+            default: false,
+            theme: Theme::new(),
+        }
+    }
+
+    /// Initially select yes if `default` is true
+    pub const fn default(self, default: bool) -> Self {
+        Self { default, ..self }
+    }
+
+    /// Use `theme`
+    pub const fn theme(self, theme: Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    /// Show the prompt until it is answered
+    ///
+    /// `y` and `n` answer immediately, Escape answers no.
+    pub fn run<C, I>(&self, con: &mut C, input: &mut I) -> Result<bool>
+    where
+        C: Console + ?Sized,
+        I: Input + ?Sized,
+    {
+        let mut yes = self.default;
+        con.show_cursor(false)?;
+        loop {
+            dialog(
+                con,
+                &self.theme,
+                self.title,
+                self.text,
+                &["Yes", "No"],
+                usize::from(!yes),
+            )?;
+            match input.key(None)? {
+                Some(Key::Char('y' | 'Y')) => return Ok(true),
+                Some(Key::Char('n' | 'N') | Key::Scan(ScanCode::Escape)) => return Ok(false),
+                Some(Key::Char('\r' | '\n')) => return Ok(yes),
+                Some(Key::Char('\t') | Key::Scan(ScanCode::Left | ScanCode::Right)) => yes = !yes,
+                _ => (),
+            }
+        }
+    }
+}
+
+/// A progress bar dialog
+#[derive(Debug, Clone)]
+pub struct ProgressBar<'a> {
+    title: &'a str,
+    theme: Theme,
+}
+
+impl<'a> ProgressBar<'a> {
+    /// A progress bar titled `title`
+    pub const fn new(title: &'a str) -> Self {
+        Self {
+            title,
+            theme: Theme::new(),
+        }
+    }
+
+    /// Use `theme`
+    pub const fn theme(self, theme: Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    /// Draw the progress bar at `done` out of `total`
+    pub fn draw<C: Console + ?Sized>(&self, con: &mut C, done: u64, total: u64) -> Result<()> {
+        let size = con.size();
+        let rect = Rect::centered(size, 60.min(size.0.saturating_sub(4)), 3);
+        frame(con, rect, self.title, self.theme.window)?;
+
+        // Wide enough that `done * 100` can't overflow
+        let percent = (u128::from(done.min(total)) * 100)
+            .checked_div(u128::from(total))
+            .unwrap_or(100) as u64;
+        // Frame, padding, and " 100%"
+        let width = rect.width.saturating_sub(4 + 5);
+        let filled = (width as u64 * percent / 100) as usize;
+        let mut bar = String::new();
+        bar.extend(repeat_n('█', filled));
+        bar.extend(repeat_n('░', width - filled));
+        let _ = write!(bar, " {percent:>3}%");
+        put(con, rect.col + 2, rect.row + 1, &bar, self.theme.window)
+    }
+}
+
+/// A single line text editor
+#[derive(Debug, Clone)]
+pub struct LineEditor<'a> {
+    title: &'a str,
+    prompt: &'a str,
+    initial: &'a str,
+    max_len: usize,
+    masked: bool,
+    theme: Theme,
+}
+
+impl<'a> LineEditor<'a> {
+    /// An editor titled `title`, showing `prompt` above the input field
+    pub const fn new(title: &'a str, prompt: &'a str) -> Self {
+        Self {
+            title,
+            prompt,
+            initial: "",
+            max_len: usize::MAX,
+            masked: false,
+            theme: Theme::new(),
+        }
+    }
+
+    /// Start with `initial` text
+    pub const fn initial(self, initial: &'a str) -> Self {
+        Self { initial, ..self }
+    }
+
+    /// Accept at most `max_len` characters
+    pub const fn max_len(self, max_len: usize) -> Self {
+        Self { max_len, ..self }
+    }
+
+    /// Show `*` instead of the text, for passwords
+    pub const fn masked(self, masked: bool) -> Self {
+        Self { masked, ..self }
+    }
+
+    /// Use `theme`
+    pub const fn theme(self, theme: Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    /// Edit until Enter, returning the text, or Escape, returning [`None`]
+    pub fn run<C, I>(&self, con: &mut C, input: &mut I) -> Result<Option<String>>
+    where
+        C: Console + ?Sized,
+        I: Input + ?Sized,
+    {
+        let mut text: Vec<char> = self.initial.chars().take(self.max_len).collect();
+        let mut cursor = text.len();
+        let mut offset = 0;
+
+        let size = con.size();
+        let width = (self.prompt.chars().count().max(40) + 4).min(size.0.saturating_sub(4));
+        let rect = Rect::centered(size, width, 4);
+        let inner = rect.width.saturating_sub(4);
+        frame(con, rect, self.title, self.theme.window)?;
+        put(
+            con,
+            rect.col + 2,
+            rect.row + 1,
+            &pad(self.prompt, inner),
+            self.theme.window,
+        )?;
+        let row = rect.row + 2;
+
+        let ret = loop {
+            // Keep the cursor inside the field, which has room for it at the
+            // end
+            let field = inner.max(1);
+            if cursor < offset {
+                offset = cursor;
+            } else if cursor >= offset + field {
+                offset = cursor + 1 - field;
+            }
+            let shown: String = text
+                .iter()
+                .skip(offset)
+                .take(field)
+                .map(|&c| if self.masked { '*' } else { c })
+                .collect();
+            put(
+                con,
+                rect.col + 2,
+                row,
+                &pad(&shown, field),
+                self.theme.field,
+            )?;
+            con.set_cursor(rect.col + 2 + cursor - offset, row)?;
+            con.show_cursor(true)?;
+
+            let Some(key) = input.key(None)? else {
+                continue;
+            };
+            match key {
+                Key::Char('\r' | '\n') => break Some(text.iter().collect()),
+                Key::Scan(ScanCode::Escape) => break None,
+                Key::Scan(ScanCode::Left) => cursor = cursor.saturating_sub(1),
+                Key::Scan(ScanCode::Right) => cursor = (cursor + 1).min(text.len()),
+                Key::Scan(ScanCode::Home) => cursor = 0,
+                Key::Scan(ScanCode::End) => cursor = text.len(),
+                Key::Scan(ScanCode::Delete) if cursor < text.len() => {
+                    text.remove(cursor);
+                }
+                Key::Char('\u{8}') if cursor > 0 => {
+                    cursor -= 1;
+                    text.remove(cursor);
+                }
+                Key::Char(c) if !c.is_control() && text.len() < self.max_len => {
+                    text.insert(cursor, c);
+                    cursor += 1;
+                }
+                _ => (),
+            }
+        };
+        con.show_cursor(false)?;
+        Ok(ret)
+    }
+}
+
+/// A cell in a [`Buffer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub attr: Attr,
+}
+
+/// An in-memory [`Console`], for rendering widgets on the host
+///
+/// Writes wrap at the end of a row and scroll at the bottom,
+/// like a firmware console.
+#[derive(Debug, Clone)]
+pub struct Buffer {
+    size: (usize, usize),
+    cells: Vec<Cell>,
+    cursor: (usize, usize),
+    attr: Attr,
+    cursor_visible: bool,
+}
+
+impl Buffer {
+    /// A blank `cols` by `rows` buffer
+    pub fn new(cols: usize, rows: usize) -> Self {
+        let attr = Theme::new().screen;
+        Self {
+            size: (cols, rows),
+            cells: vec![Cell { ch: ' ', attr }; cols * rows],
+            cursor: (0, 0),
+            attr,
+            cursor_visible: true,
+        }
+    }
+
+    /// The [`Cell`] at (`col`, `row`)
+    ///
+    /// # Panics
+    ///
+    /// - If (`col`, `row`) is outside the buffer
+    pub fn cell(&self, col: usize, row: usize) -> Cell {
+        assert!(col < self.size.0 && row < self.size.1, "Cell out of bounds");
+        self.cells[row * self.size.0 + col]
+    }
+
+    /// The text of `row`
+    pub fn line(&self, row: usize) -> String {
+        let cols = self.size.0;
+        self.cells[row * cols..][..cols]
+            .iter()
+            .map(|c| c.ch)
+            .collect()
+    }
+
+    /// The text of every row
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        (0..self.size.1).map(|row| self.line(row))
+    }
+
+    /// Whether any row contains `s`
+    pub fn contains(&self, s: &str) -> bool {
+        self.lines().any(|l| l.contains(s))
+    }
+
+    /// The row containing `s`, if any
+    pub fn find(&self, s: &str) -> Option<(usize, usize)> {
+        self.lines()
+            .enumerate()
+            .find_map(|(row, l)| l.find(s).map(|i| (l[..i].chars().count(), row)))
+    }
+
+    /// Cursor position, (Col, Row)
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// Whether the cursor is visible
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    fn scroll(&mut self) {
+        let cols = self.size.0;
+        self.cells.drain(..cols);
+        let blank = Cell {
+            ch: ' ',
+            attr: self.attr,
+        };
+        self.cells.extend(repeat_n(blank, cols));
+    }
+}
+
+impl Console for Buffer {
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn set_cursor(&mut self, col: usize, row: usize) -> Result<()> {
+        if col >= self.size.0 || row >= self.size.1 {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        self.cursor = (col, row);
+        Ok(())
+    }
+
+    fn set_attributes(&mut self, attr: Attr) -> Result<()> {
+        self.attr = attr;
+        Ok(())
+    }
+
+    fn write(&mut self, s: &str) -> Result<()> {
+        let (cols, rows) = self.size;
+        for ch in s.chars() {
+            match ch {
+                '\r' => self.cursor.0 = 0,
+                '\n' => self.cursor.1 += 1,
+                _ => {
+                    let (col, row) = self.cursor;
+                    self.cells[row * cols + col] = Cell {
+                        ch,
+                        attr: self.attr,
+                    };
+                    self.cursor.0 += 1;
+                    if self.cursor.0 == cols {
+                        self.cursor = (0, row + 1);
+                    }
+                }
+            }
+            if self.cursor.1 == rows {
+                self.scroll();
+                self.cursor.1 -= 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        let attr = self.attr;
+        self.cells.fill(Cell { ch: ' ', attr });
+        self.cursor = (0, 0);
+        Ok(())
+    }
+
+    fn show_cursor(&mut self, visible: bool) -> Result<()> {
+        self.cursor_visible = visible;
+        Ok(())
+    }
+}
+
+/// Scripted [`Input`], for driving widgets on the host
+///
+/// [`None`] entries are a timeout passing without a key.
+/// Once the script runs out, [`Input::key`] fails with [`Status::NOT_READY`].
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    keys: VecDeque<Option<Key>>,
+}
+
+impl Script {
+    /// A script of `keys`
+    pub fn new(keys: impl IntoIterator<Item = Option<Key>>) -> Self {
+        Self {
+            keys: keys.into_iter().collect(),
+        }
+    }
+
+    /// A script typing `s`, followed by Enter
+    pub fn typed(s: &str) -> Self {
+        Self::new(s.chars().chain(['\r']).map(|c| Some(Key::Char(c))))
+    }
+}
+
+impl Input for Script {
+    fn key(&mut self, timeout: Option<Duration>) -> Result<Option<Key>> {
+        loop {
+            match self.keys.pop_front() {
+                Some(Some(key)) => return Ok(Some(key)),
+                Some(None) if timeout.is_some() => return Ok(None),
+                // Time passing doesn't matter without a timeout
+                Some(None) => continue,
+                None => return Err(Status::NOT_READY.into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UP: Option<Key> = Some(Key::Scan(ScanCode::Up));
+    const DOWN: Option<Key> = Some(Key::Scan(ScanCode::Down));
+    const ENTER: Option<Key> = Some(Key::Char('\r'));
+    const ESC: Option<Key> = Some(Key::Scan(ScanCode::Escape));
+
+    #[test]
+    fn menu() -> Result<()> {
+        let items = ["Linux", "Windows", "Setup"];
+        let menu = Menu::new("Boot", &items);
+        let mut con = Buffer::new(80, 25);
+
+        let mut keys = Script::new([DOWN, DOWN, DOWN, UP, ENTER]);
+        assert_eq!(menu.run(&mut con, &mut keys)?, Some(1));
+        assert!(con.contains("Boot"));
+        let (col, row) = con.find("Windows").unwrap();
+        assert_eq!(con.cell(col, row).attr, Theme::new().selected);
+        assert_eq!(con.cell(col, row - 1).attr, Theme::new().window);
+
+        let mut keys = Script::new([ESC]);
+        assert_eq!(menu.run(&mut con, &mut keys)?, None);
+        Ok(())
+    }
+
+    /// [`Input`] with keys arriving at fixed times on a mock clock
+    struct Clock {
+        now: Duration,
+        keys: VecDeque<(Duration, Key)>,
+    }
+
+    impl Clock {
+        fn new(keys: impl IntoIterator<Item = (u64, Key)>) -> Self {
+            Self {
+                now: Duration::ZERO,
+                keys: keys
+                    .into_iter()
+                    .map(|(ms, k)| (Duration::from_millis(ms), k))
+                    .collect(),
+            }
+        }
+    }
+
+    impl Input for Clock {
+        fn key(&mut self, timeout: Option<Duration>) -> Result<Option<Key>> {
+            let deadline = timeout.map(|t| self.now + t);
+            match self.keys.front() {
+                Some(&(at, key)) if deadline.is_none_or(|d| at <= d) => {
+                    self.keys.pop_front();
+                    self.now = self.now.max(at);
+                    Ok(Some(key))
+                }
+                _ => match deadline {
+                    Some(d) => {
+                        self.now = d;
+                        Ok(None)
+                    }
+                    // Would wait forever
+                    None => Err(Status::NOT_READY.into()),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn menu_timeout() -> Result<()> {
+        let items = ["Linux", "Windows", "Setup"];
+        let menu = Menu::new("Boot", &items)
+            .default(2)
+            .timeout(Duration::from_secs(3));
+        let mut con = Buffer::new(80, 25);
+
+        // Counts down to the default
+        let mut clock = Clock::new([]);
+        assert_eq!(menu.run(&mut con, &mut clock)?, Some(2));
+        assert_eq!(clock.now, Duration::from_secs(3));
+        assert!(con.contains("Automatic selection in 1s"));
+
+        // A key stops the countdown
+        let mut clock = Clock::new([(1500, Key::Scan(ScanCode::Up)), (60_000, Key::Char('\r'))]);
+        assert_eq!(menu.run(&mut con, &mut clock)?, Some(1));
+        assert_eq!(clock.now, Duration::from_secs(60));
+        assert!(!con.contains("Automatic"));
+        Ok(())
+    }
+
+    /// [`wait_key`] waits on the key event and a timer, not by polling
+    #[test]
+    fn wait_key_timer() -> Result<()> {
+        use core::{
+            ffi::c_void,
+            mem::{transmute, zeroed},
+            ptr::addr_of_mut,
+            sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        };
+
+        use nuefi_core::{
+            base::TaskPriorityLevel,
+            table::{boot_fn::EventNotify, EventType},
+        };
+
+        use crate::table::raw::RawBootServices;
+
+        static TRIGGER: AtomicU64 = AtomicU64::new(0);
+        static SIGNALED: AtomicUsize = AtomicUsize::new(0);
+        static WAITED: AtomicUsize = AtomicUsize::new(0);
+        static CLOSED: AtomicBool = AtomicBool::new(false);
+        // Safety: `Event` is a transparent pointer
+        let key_event: Event = unsafe { transmute(0x10usize as *mut c_void) };
+        // Safety: See above
+        let timer: Event = unsafe { transmute(0x20usize as *mut c_void) };
+
+        unsafe extern "efiapi" fn create_event(
+            ty: EventType,
+            _: TaskPriorityLevel,
+            notify: Option<EventNotify>,
+            _: *mut c_void,
+            event: *mut Event,
+        ) -> Status {
+            assert_eq!(ty, EventType::TIMER);
+            assert!(notify.is_none());
+            event.cast::<*mut c_void>().write(0x20 as *mut c_void);
+            CLOSED.store(false, Ordering::Relaxed);
+            Status::SUCCESS
+        }
+
+        unsafe extern "efiapi" fn set_timer(_: Event, ty: TimerDelay, trigger: u64) -> Status {
+            assert_eq!(ty, TimerDelay::RELATIVE);
+            TRIGGER.store(trigger, Ordering::Relaxed);
+            Status::SUCCESS
+        }
+
+        unsafe extern "efiapi" fn wait_for_event(
+            len: usize,
+            events: *const Event,
+            index: *mut usize,
+        ) -> Status {
+            WAITED.store(len, Ordering::Relaxed);
+            let events = core::slice::from_raw_parts(events.cast::<usize>(), len);
+            assert_eq!(events[0], 0x10);
+            if len == 2 {
+                assert_eq!(events[1], 0x20);
+            }
+            index.write(SIGNALED.load(Ordering::Relaxed));
+            Status::SUCCESS
+        }
+
+        unsafe extern "efiapi" fn close_event(event: Event) -> Status {
+            assert_eq!(event.as_ptr() as usize, 0x20);
+            CLOSED.store(true, Ordering::Relaxed);
+            Status::SUCCESS
+        }
+
+        // Safety: All zero is a valid, if empty, `RawBootServices`
+        let mut raw: RawBootServices = unsafe { zeroed() };
+        raw.create_event = Some(create_event);
+        raw.set_timer = Some(set_timer);
+        raw.wait_for_event = Some(wait_for_event);
+        raw.close_event = Some(close_event);
+        // Safety: `raw` outlives `boot`
+        let boot = unsafe { BootServices::new(addr_of_mut!(raw)) };
+        assert_eq!(boot.create_timer()?, timer);
+
+        // The timer fires first
+        SIGNALED.store(1, Ordering::Relaxed);
+        let mut reads = 0;
+        let key = wait_key(&boot, key_event, Some(Duration::from_secs(1)), || {
+            reads += 1;
+            Ok(None)
+        })?;
+        assert_eq!(key, None);
+        assert_eq!(reads, 0);
+        assert_eq!(WAITED.load(Ordering::Relaxed), 2);
+        // 100ns units
+        assert_eq!(TRIGGER.load(Ordering::Relaxed), 10_000_000);
+        assert!(CLOSED.load(Ordering::Relaxed));
+
+        // A key, after a partial keystroke
+        SIGNALED.store(0, Ordering::Relaxed);
+        let mut keys = [None, Some(Key::Char('a'))].into_iter();
+        let key = wait_key(&boot, key_event, Some(Duration::from_millis(5)), || {
+            Ok(keys.next().flatten())
+        })?;
+        assert_eq!(key, Some(Key::Char('a')));
+        assert_eq!(TRIGGER.load(Ordering::Relaxed), 50_000);
+        assert!(CLOSED.load(Ordering::Relaxed));
+
+        // No timer without a timeout
+        CLOSED.store(false, Ordering::Relaxed);
+        TRIGGER.store(0, Ordering::Relaxed);
+        let key = wait_key(&boot, key_event, None, || Ok(Some(Key::Char('b'))))?;
+        assert_eq!(key, Some(Key::Char('b')));
+        assert_eq!(WAITED.load(Ordering::Relaxed), 1);
+        assert_eq!(TRIGGER.load(Ordering::Relaxed), 0);
+        assert!(!CLOSED.load(Ordering::Relaxed));
+        Ok(())
+    }
+
+    #[test]
+    fn menu_scroll() -> Result<()> {
+        let items = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+        let menu = Menu::new("Many", &items);
+        let mut con = Buffer::new(40, 8);
+        let mut keys = Script::new([Some(Key::Scan(ScanCode::End)), ENTER]);
+        assert_eq!(menu.run(&mut con, &mut keys)?, Some(9));
+        assert!(con.find(" 9 ").is_some());
+        assert!(con.find(" 0 ").is_none());
+        // Bottom row is untouched
+        assert_eq!(con.line(7).trim(), "");
+        Ok(())
+    }
+
+    #[test]
+    fn confirm() -> Result<()> {
+        let prompt = Confirm::new("Reset", "Really reset the system?");
+        let mut con = Buffer::new(80, 25);
+        assert!(!prompt.run(&mut con, &mut Script::new([ENTER]))?);
+        assert!(con.contains("Really reset the system?"));
+        assert!(con.contains("< Yes >"));
+        assert!(prompt.run(&mut con, &mut Script::new([Some(Key::Char('y'))]))?);
+        assert!(prompt.run(
+            &mut con,
+            &mut Script::new([Some(Key::Scan(ScanCode::Left)), ENTER])
+        )?);
+        assert!(!prompt
+            .default(true)
+            .run(&mut con, &mut Script::new([ESC]))?);
+        Ok(())
+    }
+
+    #[test]
+    fn message_box() -> Result<()> {
+        let text = "The quick brown fox jumps over the lazy dog, again and again and again.";
+        let mut con = Buffer::new(40, 12);
+        MessageBox::new("Note", text).run(&mut con, &mut Script::new([ENTER]))?;
+        assert!(con.contains("< OK >"));
+        assert!(con.contains("The quick brown fox jumps over"));
+        assert!(con.contains("the lazy dog, again and again"));
+        Ok(())
+    }
+
+    #[test]
+    fn progress() -> Result<()> {
+        let mut con = Buffer::new(80, 25);
+        let bar = ProgressBar::new("Loading");
+        bar.draw(&mut con, 1, 4)?;
+        assert!(con.contains(" 25%"));
+        bar.draw(&mut con, 10, 4)?;
+        assert!(con.contains("100%"));
+        assert!(!con.contains("░"));
+        // Large totals don't overflow
+        bar.draw(&mut con, u64::MAX / 2, u64::MAX)?;
+        assert!(con.contains(" 49%"));
+        Ok(())
+    }
+
+    #[test]
+    fn line_editor() -> Result<()> {
+        let mut con = Buffer::new(80, 25);
+        let editor = LineEditor::new("Edit", "Kernel command line").initial("quiet");
+
+        let mut keys = Script::new([
+            Some(Key::Scan(ScanCode::Home)),
+            Some(Key::Scan(ScanCode::Delete)),
+            Some(Key::Char('Q')),
+            Some(Key::Scan(ScanCode::End)),
+            Some(Key::Char('\u{8}')),
+            Some(Key::Char('!')),
+            ENTER,
+        ]);
+        assert_eq!(editor.run(&mut con, &mut keys)?.as_deref(), Some("Quie!"));
+        assert!(con.contains("Kernel command line"));
+        assert!(!con.cursor_visible());
+
+        assert_eq!(editor.run(&mut con, &mut Script::new([ESC]))?, None);
+
+        let password = LineEditor::new("Login", "Password").masked(true).max_len(4);
+        let out = password.run(&mut con, &mut Script::typed("hunter2"))?;
+        assert_eq!(out.as_deref(), Some("hunt"));
+        assert!(con.contains("****"));
+        assert!(!con.contains("hunt"));
+        Ok(())
+    }
+
+    #[test]
+    fn wrapping() {
+        assert_eq!(wrap("a bb ccc", 4), ["a bb", "ccc"]);
+        assert_eq!(wrap("abcdefgh", 3), ["abc", "def", "gh"]);
+        assert_eq!(wrap("a\nb", 10), ["a", "b"]);
+    }
+}